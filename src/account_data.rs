@@ -0,0 +1,159 @@
+use anyhow::anyhow;
+use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
+use solana_client::{rpc_client::RpcClient, rpc_config::RpcAccountInfoConfig};
+use solana_sdk::{
+    bpf_loader_upgradeable::UpgradeableLoaderState, pubkey::Pubkey,
+    system_instruction::MAX_PERMITTED_DATA_LENGTH,
+};
+
+/// Size of the slices large accounts are fetched in, small enough for constrained RPCs
+const CHUNK_SIZE: usize = 256 * 1024;
+
+/// Reads an account of unknown length through `read_slice(offset, length)`, until a slice
+/// comes back shorter than requested.
+fn read_in_chunks(
+    chunk_size: usize,
+    max_len: usize,
+    mut read_slice: impl FnMut(usize, usize) -> anyhow::Result<Vec<u8>>,
+) -> anyhow::Result<Vec<u8>> {
+    let mut data = vec![];
+    loop {
+        let chunk = read_slice(data.len(), chunk_size)?;
+        if chunk.len() > chunk_size {
+            return Err(anyhow!(
+                "RPC returned {} bytes for a slice of {} bytes",
+                chunk.len(),
+                chunk_size
+            ));
+        }
+        let last = chunk.len() < chunk_size;
+        data.extend(chunk);
+        if data.len() > max_len {
+            return Err(anyhow!("Account data exceeds {} bytes", max_len));
+        }
+        if last {
+            return Ok(data);
+        }
+    }
+}
+
+/// Fetches account data in slices. Every slice is read at or after the slot of the first one,
+/// and the leading bytes are read again at the end so a redeploy during the download, which
+/// rewrites the program data header, is detected instead of producing a mixed program.
+pub fn get_account_data_chunked(client: &RpcClient, address: &Pubkey) -> anyhow::Result<Vec<u8>> {
+    let mut min_context_slot = None;
+    let mut read_slice = |offset: usize, length: usize| -> anyhow::Result<Vec<u8>> {
+        let config = RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            data_slice: Some(UiDataSliceConfig { offset, length }),
+            commitment: Some(client.commitment()),
+            min_context_slot,
+        };
+        let response = client.get_account_with_config(address, config)?;
+        min_context_slot = Some(min_context_slot.unwrap_or(0).max(response.context.slot));
+        response
+            .value
+            .map(|account| account.data)
+            .ok_or_else(|| anyhow!("Account {} not found", address))
+    };
+
+    let data = read_in_chunks(
+        CHUNK_SIZE,
+        MAX_PERMITTED_DATA_LENGTH as usize,
+        &mut read_slice,
+    )?;
+    let header_len = data
+        .len()
+        .min(UpgradeableLoaderState::size_of_programdata_metadata());
+    if read_slice(0, header_len)? != data[..header_len] {
+        return Err(anyhow!(
+            "Account {} changed while it was being fetched, try again",
+            address
+        ));
+    }
+    Ok(data)
+}
+
+/// Fetches account data in one request, falling back to slices when the RPC can't return
+/// the whole account at once.
+pub fn get_large_account_data(client: &RpcClient, address: &Pubkey) -> anyhow::Result<Vec<u8>> {
+    match client.get_account_data(address) {
+        Ok(data) => Ok(data),
+        Err(err) => {
+            println!(
+                "Unable to fetch account {} at once ({}), fetching it in chunks",
+                address, err
+            );
+            get_account_data_chunked(client, address)
+        }
+    }
+}
+
+/// Executable stored at `offset` of an account that is not a loader buffer or ProgramData
+/// account, e.g. a loader-v4 program or a copy kept by a registry. Without `length`, the
+/// executable runs to the end of the account.
+pub fn slice_program_data(
+    data: &[u8],
+    offset: usize,
+    length: Option<usize>,
+) -> anyhow::Result<&[u8]> {
+    let end = match length {
+        Some(length) => offset
+            .checked_add(length)
+            .ok_or_else(|| anyhow!("--data-offset and --data-length overflow"))?,
+        None => data.len(),
+    };
+    let executable = data.get(offset..end).ok_or_else(|| {
+        anyhow!(
+            "Account holds {} bytes, which does not cover bytes {} to {}",
+            data.len(),
+            offset,
+            end
+        )
+    })?;
+    if !executable.starts_with(b"\x7fELF") {
+        println!(
+            "Warning: the data at offset {} is not an ELF executable, check --data-offset",
+            offset
+        );
+    }
+    Ok(executable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slice_program_data() {
+        let mut data = vec![0u8; 48];
+        data.extend(b"\x7fELF");
+        data.extend([1, 2, 3, 0, 0]);
+        assert_eq!(
+            slice_program_data(&data, 48, None).unwrap(),
+            b"\x7fELF\x01\x02\x03\x00\x00"
+        );
+        assert_eq!(slice_program_data(&data, 48, Some(4)).unwrap(), b"\x7fELF");
+        assert!(slice_program_data(&data, 48, Some(100)).is_err());
+        assert!(slice_program_data(&data, 100, None).is_err());
+    }
+
+    #[test]
+    fn test_read_in_chunks() {
+        for len in [0, 1, 9, 10, 11, 35] {
+            let account = (0..len).map(|i| i as u8).collect::<Vec<_>>();
+            let data = read_in_chunks(10, 100, |offset, length| {
+                Ok(account[offset.min(len)..(offset + length).min(len)].to_vec())
+            })
+            .unwrap();
+            assert_eq!(data, account);
+        }
+
+        let account = [1u8; 35];
+        assert!(read_in_chunks(10, 20, |offset, length| {
+            Ok(account[offset.min(35)..(offset + length).min(35)].to_vec())
+        })
+        .is_err());
+        assert!(read_in_chunks(10, 100, |_, _| Ok(vec![0; 11])).is_err());
+    }
+}
@@ -1,67 +1,75 @@
 use anyhow::anyhow;
-use crossbeam_channel::{unbounded, Receiver};
-use indicatif::{HumanDuration, ProgressBar, ProgressStyle};
+use indicatif::{ProgressBar, ProgressStyle};
+use log::{error, info};
 use reqwest::{Client, Response};
 use serde_json::json;
+use sha2::{Digest, Sha256};
 use solana_client::rpc_client::RpcClient;
 use solana_sdk::pubkey::Pubkey;
-use std::sync::atomic::Ordering;
-use std::thread;
 use std::time::{Duration, Instant};
 
 use crate::api::models::{
-    ErrorResponse, JobResponse, JobStatus, JobVerificationResponse, RemoteStatusResponseWrapper,
-    VerifyResponse,
+    ErrorResponse, JobStatus, JobVerificationResponse, RemoteStatusResponse,
+    RemoteStatusResponseWrapper, StatusResponse, VerifyResponse,
 };
-use crate::solana_program::get_program_pda;
-use crate::SIGNAL_RECEIVED;
+use crate::api::polling::{wait_for_job, JobProgress};
+use crate::api::remediation::{fetch_job_logs, KnownFailure, RemoteJobFailed};
+use crate::api::schema::{api_client_builder, negotiate_schema_version, parse_api_response};
+use crate::cleanup::interrupted;
+use crate::endpoints::remote_api_url;
+use crate::humanize::format_duration;
+use crate::logging::is_quiet;
+use crate::solana_program::{get_program_pda, OtterBuildParams};
 use crate::{get_genesis_hash, MAINNET_GENESIS_HASH};
 
-// URL for the remote server
-pub const REMOTE_SERVER_URL: &str = "https://verify.osec.io";
-
-fn loading_animation(receiver: Receiver<bool>) {
-    let started = Instant::now();
-    let spinner_style =
-        ProgressStyle::with_template("[{elapsed_precise}] {prefix:.bold.dim} {spinner} {wide_msg}")
-            .unwrap()
-            .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ ");
+/// Spinner shown while the CLI waits for a remote job.
+struct SpinnerProgress {
+    pb: ProgressBar,
+    started: Instant,
+}
 
-    let pb = ProgressBar::new_spinner();
-    pb.set_style(spinner_style);
-    pb.enable_steady_tick(Duration::from_millis(100));
-    pb.set_message("Request sent. Awaiting server response. This may take a moment... ⏳");
+impl SpinnerProgress {
+    fn new() -> SpinnerProgress {
+        let spinner_style = ProgressStyle::with_template(
+            "[{elapsed_precise}] {prefix:.bold.dim} {spinner} {wide_msg}",
+        )
+        .unwrap()
+        .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ ");
 
-    loop {
-        // Check if interrupt signal was received
-        if SIGNAL_RECEIVED.load(Ordering::Relaxed) {
-            pb.finish_with_message("❌ Operation interrupted by user.");
-            break;
+        let pb = if is_quiet() {
+            ProgressBar::hidden()
+        } else {
+            ProgressBar::new_spinner()
+        };
+        pb.set_style(spinner_style);
+        pb.enable_steady_tick(Duration::from_millis(100));
+        pb.set_message("Request sent. Awaiting server response. This may take a moment... ⏳");
+        SpinnerProgress {
+            pb,
+            started: Instant::now(),
         }
+    }
+}
 
-        match receiver.try_recv() {
-            Ok(result) => {
-                if result {
-                    pb.finish_with_message(format!(
-                        "✅ Process completed. (Done in {})\n",
-                        HumanDuration(started.elapsed())
-                    ));
-                } else {
-                    pb.finish_with_message("❌ Request processing failed.");
-                    println!("❌ Time elapsed : {}", HumanDuration(started.elapsed()));
-                }
-                break;
-            }
-            Err(_) => {
-                if SIGNAL_RECEIVED.load(Ordering::Relaxed) {
-                    pb.finish_with_message("❌ Operation interrupted by user.");
-                    break;
-                }
-                thread::sleep(Duration::from_millis(10));
-            }
+impl JobProgress for SpinnerProgress {
+    fn finish(&mut self, success: bool) {
+        if interrupted() {
+            self.pb
+                .finish_with_message("❌ Operation interrupted by user.");
+        } else if success {
+            self.pb.finish_with_message(format!(
+                "✅ Process completed. (Done in {})\n",
+                format_duration(self.started.elapsed())
+            ));
+        } else {
+            self.pb.finish_with_message("❌ Request processing failed.");
+            info!(
+                "❌ Time elapsed : {}",
+                format_duration(self.started.elapsed())
+            );
         }
+        self.pb.abandon(); // Ensure the progress bar is cleaned up
     }
-    pb.abandon(); // Ensure the progress bar is cleaned up
 }
 
 fn print_verification_status(
@@ -99,15 +107,18 @@ pub async fn send_job_with_uploader_to_remote(
     if genesis_hash != MAINNET_GENESIS_HASH {
         return Err(anyhow!("Remote verification only works with mainnet. Please omit the --remote flag to verify locally."));
     }
-    get_program_pda(connection, program_id, Some(uploader.to_string())).await?;
+    let (_, build_params) =
+        get_program_pda(connection, program_id, Some(uploader.to_string())).await?;
 
-    let client = Client::builder()
+    negotiate_schema_version().await?;
+    let client = api_client_builder()
         .timeout(Duration::from_secs(18000))
         .build()?;
 
     // Send the POST request
     let response = client
-        .post(format!("{}/verify-with-signer", REMOTE_SERVER_URL))
+        .post(format!("{}/verify-with-signer", remote_api_url()))
+        .header("Idempotency-Key", idempotency_key(&build_params))
         .json(&json!({
             "program_id": program_id.to_string(),
             "signer": uploader.to_string(),
@@ -117,145 +128,126 @@ pub async fn send_job_with_uploader_to_remote(
         .send()
         .await?;
 
-    handle_submission_response(&client, response, program_id).await
+    handle_submission_response(response, program_id).await
+}
+
+/// Key identifying a submission, so that a retried run (e.g. a rerun CI job) resubmitting the
+/// same PDA is attached to the job already processing it instead of starting another. It is
+/// derived from the PDA rather than generated randomly so it survives across processes.
+fn idempotency_key(build_params: &OtterBuildParams) -> String {
+    let mut hasher = Sha256::new();
+    for part in [
+        build_params.address.to_string(),
+        build_params.signer.to_string(),
+        build_params.git_url.clone(),
+        build_params.commit.clone(),
+        build_params.args.join(" "),
+        build_params.deployed_slot.to_string(),
+    ] {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+    hex::encode(hasher.finalize())
 }
 
 pub async fn handle_submission_response(
-    client: &Client,
     response: Response,
     program_id: &Pubkey,
 ) -> anyhow::Result<()> {
     if response.status().is_success() {
-        // First get the raw text to preserve it in case of parsing failure
-        let response_text = response.text().await?;
-        let status_response =
-            serde_json::from_str::<VerifyResponse>(&response_text).map_err(|e| {
-                eprintln!("Failed to parse response as VerifyResponse: {}", e);
-                eprintln!("Raw response: {}", response_text);
-                anyhow!("Failed to parse server response")
-            })?;
+        let status_response = parse_api_response::<VerifyResponse>(response).await?;
         let request_id = status_response.request_id;
-        println!("Verification request sent with request id: {}", request_id);
-        println!("Verification in progress... ⏳");
-
-        // Span new thread for polling the server for status
-        // Create a channel for communication between threads
-        let (sender, receiver) = unbounded();
-        let handle = thread::spawn(move || loading_animation(receiver));
-
-        loop {
-            // Check for interrupt signal before polling
-            if SIGNAL_RECEIVED.load(Ordering::Relaxed) {
-                let _ = sender.send(false);
-                handle.join().unwrap();
-                break; // Exit the loop and continue with normal error handling
+        info!("Verification request sent with request id: {}", request_id);
+        follow_job(&request_id, program_id).await
+    } else if response.status() == 409 {
+        let response = parse_api_response::<ErrorResponse>(response).await?;
+        match response.request_id {
+            Some(request_id) => {
+                info!(
+                    "Verification is already being processed with request id: {}",
+                    request_id
+                );
+                follow_job(&request_id, program_id).await
             }
-
-            let status = check_job_status(client, &request_id).await?;
-            match status.status {
-                JobStatus::InProgress => {
-                    if SIGNAL_RECEIVED.load(Ordering::Relaxed) {
-                        let _ = sender.send(false);
-                        handle.join().unwrap();
-                        break;
-                    }
-                    thread::sleep(Duration::from_secs(10));
-                }
-                JobStatus::Completed => {
-                    let _ = sender.send(true);
-                    handle.join().unwrap();
-                    let status_response = status.respose.unwrap();
-
-                    if status_response.executable_hash == status_response.on_chain_hash {
-                        print_verification_status(
-                            program_id.to_string().as_str(),
-                            true,
-                            &status_response,
-                        );
-                    } else {
-                        print_verification_status(
-                            program_id.to_string().as_str(),
-                            false,
-                            &status_response,
-                        );
-                    }
-                    break;
-                }
-                JobStatus::Failed => {
-                    let _ = sender.send(false);
-                    handle.join().unwrap();
-                    let status_response: JobVerificationResponse = status.respose.unwrap();
-                    println!("Program {} has not been verified. ❌", program_id);
-                    eprintln!("Error message: {}", status_response.message.as_str());
-                    println!(
-                        "You can check the logs for more details here: {}/logs/{}",
-                        REMOTE_SERVER_URL, request_id
-                    );
-                    break;
-                }
-                JobStatus::Unknown => {
-                    let _ = sender.send(false);
-                    handle.join().unwrap();
-                    println!("Program {} has not been verified. ❌", program_id);
-                    break;
-                }
+            None => {
+                error!("{}", response.error.as_str());
+                let url = format!("{}/status/{}", remote_api_url(), program_id);
+                info!("Check the status at: {}", url);
+                Ok(())
             }
         }
-        let url = format!("https://verify.osec.io/status/{}", program_id);
-        println!("Check the verification status at: {}", url);
-        println!(
-            "Job url: {}",
-            &format!("{}/job/{}", REMOTE_SERVER_URL, request_id)
-        );
-
-        Ok(())
-    } else if response.status() == 409 {
-        let response = response.json::<ErrorResponse>().await?;
-        eprintln!("Error: {}", response.error.as_str());
-        let url = format!("{}/status/{}", REMOTE_SERVER_URL, program_id);
-        println!("Check the status at: {}", url);
-        Ok(())
     } else {
-        eprintln!("Encountered an error while attempting to send the job to remote");
+        error!("Encountered an error while attempting to send the job to remote");
         Err(anyhow!("{:?}", response.text().await?))?;
-        let url = format!("{}/status/{}", REMOTE_SERVER_URL, program_id);
-        println!("Check the verification status at: {}", url);
+        let url = format!("{}/status/{}", remote_api_url(), program_id);
+        info!("Check the verification status at: {}", url);
         Ok(())
     }
 }
 
-async fn check_job_status(client: &Client, request_id: &str) -> anyhow::Result<JobResponse> {
+/// Polls a submitted job until it completes and prints its outcome.
+async fn follow_job(request_id: &str, program_id: &Pubkey) -> anyhow::Result<()> {
+    info!("Verification in progress... ⏳");
+
+    let status_response = wait_for_job(request_id, &mut SpinnerProgress::new()).await?;
+    match status_response {
+        // Interrupted, continue with normal error handling
+        None => {}
+        Some(status_response) => match status_response.status {
+            JobStatus::Completed => {
+                print_verification_status(
+                    program_id.to_string().as_str(),
+                    status_response.executable_hash == status_response.on_chain_hash,
+                    &status_response,
+                );
+            }
+            JobStatus::Failed => {
+                println!("Program {} has not been verified. ❌", program_id);
+                error!("{}", status_response.message.as_str());
+                info!(
+                    "You can check the logs for more details here: {}/logs/{}",
+                    remote_api_url(),
+                    request_id
+                );
+                let logs = fetch_job_logs(request_id).await.unwrap_or_default();
+                if let Some(failure) =
+                    KnownFailure::detect(&format!("{}\n{}", status_response.message, logs))
+                {
+                    failure.print_remediation();
+                    return Err(RemoteJobFailed {
+                        request_id: request_id.to_string(),
+                        failure,
+                    }
+                    .into());
+                }
+            }
+            JobStatus::InProgress | JobStatus::Unknown => {
+                println!("Program {} has not been verified. ❌", program_id);
+            }
+        },
+    }
+    let url = format!("{}/status/{}", remote_api_url(), program_id);
+    info!("Check the verification status at: {}", url);
+    info!(
+        "Job url: {}",
+        &format!("{}/job/{}", remote_api_url(), request_id)
+    );
+
+    Ok(())
+}
+
+async fn check_job_status(
+    client: &Client,
+    request_id: &str,
+) -> anyhow::Result<JobVerificationResponse> {
     // Get /job/:id
     let response = client
-        .get(format!("{}/job/{}", REMOTE_SERVER_URL, request_id))
+        .get(format!("{}/job/{}", remote_api_url(), request_id))
         .send()
-        .await
-        .unwrap();
+        .await?;
 
     if response.status().is_success() {
-        // Parse the response
-        let response: JobVerificationResponse = response.json().await?;
-        match response.status {
-            JobStatus::InProgress => {
-                thread::sleep(Duration::from_secs(5));
-                Ok(JobResponse {
-                    status: JobStatus::InProgress,
-                    respose: None,
-                })
-            }
-            JobStatus::Completed => Ok(JobResponse {
-                status: JobStatus::Completed,
-                respose: Some(response),
-            }),
-            JobStatus::Failed => Ok(JobResponse {
-                status: JobStatus::Failed,
-                respose: Some(response),
-            }),
-            JobStatus::Unknown => Ok(JobResponse {
-                status: JobStatus::Unknown,
-                respose: Some(response),
-            }),
-        }
+        parse_api_response(response).await
     } else {
         Err(anyhow!(
             "Encountered an error while attempting to check job status : {:?}",
@@ -264,27 +256,120 @@ async fn check_job_status(client: &Client, request_id: &str) -> anyhow::Result<J
     }
 }
 
-pub async fn get_remote_status(program_id: Pubkey) -> anyhow::Result<()> {
-    let client = Client::builder()
+/// Fetches the verification status of every PDA of a program the remote verifier knows of.
+pub async fn fetch_remote_statuses(
+    program_id: Pubkey,
+    signer: Option<Pubkey>,
+    only_verified: bool,
+) -> anyhow::Result<Vec<RemoteStatusResponse>> {
+    negotiate_schema_version().await?;
+    let client = api_client_builder()
         .timeout(Duration::from_secs(18000))
         .build()?;
 
+    let mut query: Vec<(&str, String)> = vec![];
+    if let Some(signer) = signer {
+        query.push(("signer", signer.to_string()));
+    }
+    if only_verified {
+        query.push(("only_verified", "true".to_string()));
+    }
+
     let response = client
-        .get(format!("{}/status-all/{}", REMOTE_SERVER_URL, program_id,))
+        .get(format!("{}/status-all/{}", remote_api_url(), program_id,))
+        .query(&query)
         .send()
         .await?;
 
-    let status: RemoteStatusResponseWrapper = response.json().await?;
+    let mut status: RemoteStatusResponseWrapper = parse_api_response(response).await?;
+
+    // Apply the filters locally as well in case the server does not support them
+    status.0.retain(|response| {
+        let signer_matches = match signer {
+            Some(signer) => response.signer == signer.to_string(),
+            None => true,
+        };
+        signer_matches && (!only_verified || response.is_verified)
+    });
+    Ok(status.0)
+}
+
+pub async fn get_remote_status(
+    program_id: Pubkey,
+    signer: Option<Pubkey>,
+    only_verified: bool,
+    json: bool,
+) -> anyhow::Result<()> {
+    let status = RemoteStatusResponseWrapper(
+        fetch_remote_statuses(program_id, signer, only_verified).await?,
+    );
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&status)?);
+    } else if status.0.is_empty() {
+        println!("No verification status found for program {}", program_id);
+    } else {
+        println!("{}", status);
+    }
+    Ok(())
+}
+
+/// Fetches the verification status the API reports for a program, without using an RPC.
+pub async fn fetch_api_status(program_id: Pubkey) -> anyhow::Result<StatusResponse> {
+    negotiate_schema_version().await?;
+    let client = api_client_builder()
+        .timeout(Duration::from_secs(30))
+        .build()?;
+
+    let response = client
+        .get(format!("{}/status/{}", remote_api_url(), program_id))
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!(
+            "Failed to get the status of {} from {}: {}",
+            program_id,
+            remote_api_url(),
+            response.text().await?
+        ));
+    }
+
+    parse_api_response(response).await
+}
+
+/// Prints the verification status the API reports for a program, without using an RPC.
+pub async fn get_api_status(program_id: Pubkey) -> anyhow::Result<()> {
+    let status = fetch_api_status(program_id).await?;
+    println!("Program Id: {}", program_id);
     println!("{}", status);
     Ok(())
 }
 
-pub async fn get_remote_job(job_id: &str) -> anyhow::Result<()> {
-    let client = Client::builder()
+pub async fn get_remote_job(job_id: &str, json: bool) -> anyhow::Result<()> {
+    negotiate_schema_version().await?;
+    let client = api_client_builder()
         .timeout(Duration::from_secs(18000))
         .build()?;
 
-    let job = check_job_status(&client, job_id).await?;
-    println!("{}", job);
+    let mut job = check_job_status(&client, job_id).await?;
+    let logs_url = format!("{}/logs/{}", remote_api_url(), job_id);
+    if json {
+        let now = chrono::Utc::now();
+        let queue_seconds = job.queue_time(now).map(|time| time.num_seconds());
+        let build_seconds = job.build_duration(now).map(|time| time.num_seconds());
+        job.failure_category = job.failure();
+        let mut report = serde_json::to_value(&job)?;
+        report["request_id"] = json!(job_id);
+        report["queue_seconds"] = json!(queue_seconds);
+        report["build_seconds"] = json!(build_seconds);
+        report["logs_url"] = json!(logs_url);
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("{}", job);
+        if job.failure().is_some() {
+            println!("Logs: {}", logs_url);
+        }
+    }
     Ok(())
 }
@@ -1,8 +1,22 @@
 mod client;
 mod models;
+mod polling;
+mod remediation;
+mod schema;
 mod solana;
 
+pub use client::fetch_api_status;
+pub use client::fetch_remote_statuses;
+pub use client::get_api_status;
 pub use client::get_remote_job;
 pub use client::get_remote_status;
 pub use client::send_job_with_uploader_to_remote;
+pub use models::{
+    FailureCategory, JobStatus, JobVerificationResponse, RemoteStatusResponse, StatusResponse,
+};
+pub use polling::{sleep_unless_interrupted, wait_for_job, Backoff, JobProgress, NoProgress};
+pub use remediation::{
+    lockfile_compatible_base_image, with_base_image, KnownFailure, RemoteJobFailed,
+};
+pub use schema::{negotiate_schema_version, SUPPORTED_SCHEMA_VERSIONS};
 pub use solana::get_last_deployed_slot;
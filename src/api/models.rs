@@ -1,5 +1,8 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::humanize;
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Status {
@@ -20,31 +23,46 @@ pub struct StatusResponse {
     pub message: String,
     pub on_chain_hash: String,
     pub executable_hash: String,
+    #[serde(default)]
+    pub repo_url: Option<String>,
+    #[serde(default)]
+    pub commit: Option<String>,
+    #[serde(default)]
+    pub last_verified_at: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ErrorResponse {
-    pub status: Status,
-    pub error: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct JobResponse {
-    pub status: JobStatus,
-    pub respose: Option<JobVerificationResponse>,
-}
-
-impl std::fmt::Display for JobResponse {
+impl std::fmt::Display for StatusResponse {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if let Some(response) = &self.respose {
-            writeln!(f, "{}", response)?;
-        } else {
-            writeln!(f, "Status: {:?}", self.status)?;
+        writeln!(
+            f,
+            "Verified: {}",
+            if self.is_verified { "✅" } else { "❌" }
+        )?;
+        writeln!(f, "Message: {}", self.message)?;
+        writeln!(f, "On-chain Hash: {}", self.on_chain_hash)?;
+        write!(f, "Executable Hash: {}", self.executable_hash)?;
+        if let Some(repo_url) = &self.repo_url {
+            write!(f, "\nRepository URL: {}", repo_url)?;
+        }
+        if let Some(commit) = &self.commit {
+            write!(f, "\nCommit: {}", commit)?;
+        }
+        if let Some(last_verified_at) = &self.last_verified_at {
+            write!(f, "\nLast Verified: {}", last_verified_at)?;
         }
         Ok(())
     }
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub status: Status,
+    pub error: String,
+    /// Job already processing the submission, returned with 409 responses
+    #[serde(default)]
+    pub request_id: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub enum JobStatus {
     #[serde(rename = "in_progress")]
@@ -57,6 +75,74 @@ pub enum JobStatus {
     Unknown,
 }
 
+/// Stage of a remote job that failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureCategory {
+    /// The repository or commit could not be fetched
+    Clone,
+    /// The program did not build
+    Build,
+    /// The build does not match the on-chain program
+    #[serde(alias = "hash-mismatch")]
+    HashMismatch,
+    /// The server failed, retrying may help
+    #[serde(other)]
+    Internal,
+}
+
+impl FailureCategory {
+    /// Infers the category from the message of servers that do not report one.
+    pub fn classify(message: &str) -> FailureCategory {
+        let message = message.to_lowercase();
+        if [
+            "clone",
+            "checkout",
+            "repository not found",
+            "could not find commit",
+        ]
+        .iter()
+        .any(|pattern| message.contains(pattern))
+        {
+            FailureCategory::Clone
+        } else if ["hash mismatch", "does not match", "do not match"]
+            .iter()
+            .any(|pattern| message.contains(pattern))
+        {
+            FailureCategory::HashMismatch
+        } else if ["build", "compil", "cargo"]
+            .iter()
+            .any(|pattern| message.contains(pattern))
+        {
+            FailureCategory::Build
+        } else {
+            FailureCategory::Internal
+        }
+    }
+}
+
+impl std::fmt::Display for FailureCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FailureCategory::Clone => write!(f, "clone"),
+            FailureCategory::Build => write!(f, "build"),
+            FailureCategory::HashMismatch => write!(f, "hash mismatch"),
+            FailureCategory::Internal => write!(f, "internal"),
+        }
+    }
+}
+
+fn parse_timestamp(timestamp: &Option<String>) -> Option<DateTime<Utc>> {
+    timestamp
+        .as_deref()
+        .and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok())
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+}
+
+fn format_duration(duration: chrono::Duration) -> String {
+    humanize::format_duration(duration.to_std().unwrap_or_default())
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct JobVerificationResponse {
     pub status: JobStatus,
@@ -64,15 +150,72 @@ pub struct JobVerificationResponse {
     pub on_chain_hash: String,
     pub executable_hash: String,
     pub repo_url: String,
+    /// RFC 3339 timestamps of the job's submission, start and end
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure_category: Option<FailureCategory>,
+}
+
+impl JobVerificationResponse {
+    /// Time the job waited before a builder picked it up, so far if it is still queued.
+    pub fn queue_time(&self, now: DateTime<Utc>) -> Option<chrono::Duration> {
+        let created_at = parse_timestamp(&self.created_at)?;
+        Some(parse_timestamp(&self.started_at).unwrap_or(now) - created_at)
+    }
+
+    /// Time spent building, so far if the job is still running.
+    pub fn build_duration(&self, now: DateTime<Utc>) -> Option<chrono::Duration> {
+        let started_at = parse_timestamp(&self.started_at)?;
+        Some(parse_timestamp(&self.completed_at).unwrap_or(now) - started_at)
+    }
+
+    /// Why the job failed, as reported by the server or inferred from its message.
+    pub fn failure(&self) -> Option<FailureCategory> {
+        match self.status {
+            JobStatus::Failed => Some(
+                self.failure_category
+                    .unwrap_or_else(|| FailureCategory::classify(&self.message)),
+            ),
+            JobStatus::Completed
+                if !self.executable_hash.is_empty()
+                    && self.executable_hash != self.on_chain_hash =>
+            {
+                Some(FailureCategory::HashMismatch)
+            }
+            _ => None,
+        }
+    }
 }
 
 impl std::fmt::Display for JobVerificationResponse {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "Status: {:?}", self.status)?;
-        writeln!(f, "Message: {}", self.message)?;
-        writeln!(f, "On-chain Hash: {}", self.on_chain_hash)?;
-        writeln!(f, "Executable Hash: {}", self.executable_hash)?;
-        write!(f, "Repository URL: {}", self.repo_url)
+        let now = Utc::now();
+        write!(f, "Status: {:?}", self.status)?;
+        if let Some(failure) = self.failure() {
+            write!(f, "\nFailure: {}", failure)?;
+        }
+        if let Some(queue_time) = self.queue_time(now) {
+            write!(f, "\nQueue Time: {}", format_duration(queue_time))?;
+        }
+        if let Some(build_duration) = self.build_duration(now) {
+            write!(f, "\nBuild Duration: {}", format_duration(build_duration))?;
+        }
+        write!(f, "\nMessage: {}", self.message)?;
+        if !self.on_chain_hash.is_empty() {
+            write!(f, "\nOn-chain Hash: {}", self.on_chain_hash)?;
+        }
+        if !self.executable_hash.is_empty() {
+            write!(f, "\nExecutable Hash: {}", self.executable_hash)?;
+        }
+        if !self.repo_url.is_empty() {
+            write!(f, "\nRepository URL: {}", self.repo_url)?;
+        }
+        Ok(())
     }
 }
 
@@ -104,7 +247,7 @@ impl std::fmt::Display for RemoteStatusResponse {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct RemoteStatusResponseWrapper(Vec<RemoteStatusResponse>);
+pub struct RemoteStatusResponseWrapper(pub Vec<RemoteStatusResponse>);
 
 impl std::fmt::Display for RemoteStatusResponseWrapper {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -121,3 +264,62 @@ impl std::fmt::Display for RemoteStatusResponseWrapper {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failure_classification() {
+        assert_eq!(
+            FailureCategory::classify("Failed to clone repository"),
+            FailureCategory::Clone
+        );
+        assert_eq!(
+            FailureCategory::classify("cargo build-sbf exited with status 101"),
+            FailureCategory::Build
+        );
+        assert_eq!(
+            FailureCategory::classify("Executable hash does not match the on-chain hash"),
+            FailureCategory::HashMismatch
+        );
+        assert_eq!(
+            FailureCategory::classify("worker crashed"),
+            FailureCategory::Internal
+        );
+    }
+
+    #[test]
+    fn test_job_timings_and_failure() {
+        let job: JobVerificationResponse = serde_json::from_str(
+            r#"{
+                "status": "failed",
+                "message": "Verification failed",
+                "on_chain_hash": "",
+                "executable_hash": "",
+                "repo_url": "https://github.com/Ellipsis-Labs/phoenix-v1",
+                "created_at": "2024-05-01T10:00:00Z",
+                "started_at": "2024-05-01T10:02:30Z",
+                "completed_at": "2024-05-01T10:12:30Z",
+                "failure_category": "hash-mismatch"
+            }"#,
+        )
+        .unwrap();
+        let now = Utc::now();
+        assert_eq!(job.queue_time(now).unwrap().num_seconds(), 150);
+        assert_eq!(job.build_duration(now).unwrap().num_seconds(), 600);
+        assert_eq!(job.failure(), Some(FailureCategory::HashMismatch));
+        let output = job.to_string();
+        assert!(output.contains("Failure: hash mismatch"));
+        assert!(output.contains("Build Duration: 10m"));
+        assert!(!output.contains("On-chain Hash"));
+
+        let queued: JobVerificationResponse = serde_json::from_str(
+            r#"{"status": "in_progress", "message": "", "on_chain_hash": "", "executable_hash": "", "repo_url": "", "failure_category": "disk_full"}"#,
+        )
+        .unwrap();
+        assert_eq!(queued.failure_category, Some(FailureCategory::Internal));
+        assert_eq!(queued.failure(), None);
+        assert_eq!(queued.queue_time(now), None);
+    }
+}
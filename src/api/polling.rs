@@ -0,0 +1,219 @@
+use anyhow::anyhow;
+use log::warn;
+use reqwest::{header, Client, StatusCode};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::api::models::{JobStatus, JobVerificationResponse};
+use crate::api::schema::{api_client_builder, parse_api_body, response_schema_version};
+use crate::cleanup::interrupted;
+use crate::endpoints::remote_api_url;
+
+/// Polls giving up after this many consecutive failed requests
+const MAX_CONSECUTIVE_FAILURES: u32 = 8;
+
+/// Delay between polls of a job. It grows while the job reports nothing new and goes back
+/// to the minimum as soon as it does. Delays are jittered so clients don't poll in lockstep.
+pub struct Backoff {
+    min: Duration,
+    max: Duration,
+    current: Duration,
+}
+
+impl Backoff {
+    pub fn new(min: Duration, max: Duration) -> Backoff {
+        Backoff {
+            min,
+            max,
+            current: min,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.current = self.min;
+    }
+
+    /// Delay to wait before the next poll. Each call makes the following delay longer.
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = jitter(self.current);
+        self.current = self.current.mul_f64(1.5).min(self.max);
+        delay
+    }
+}
+
+/// Spreads `delay` over ±20%.
+fn jitter(delay: Duration) -> Duration {
+    let random = (Uuid::new_v4().as_u128() % 1000) as f64 / 1000.0;
+    delay.mul_f64(0.8 + 0.4 * random)
+}
+
+/// Sleeps for `delay`, returning early if an interrupt signal is received.
+pub async fn sleep_unless_interrupted(delay: Duration) {
+    let deadline = Instant::now() + delay;
+    while !interrupted() {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        tokio::time::sleep(remaining.min(Duration::from_millis(200))).await;
+    }
+}
+
+/// Polls `/job/<id>` with conditional requests over a single kept-alive connection.
+pub struct JobPoller {
+    client: Client,
+    url: String,
+    etag: Option<String>,
+    last_body: Option<String>,
+    /// Schema version the server reported for the last body
+    schema_version: Option<u32>,
+    consecutive_failures: u32,
+}
+
+impl JobPoller {
+    pub fn new(url: String) -> anyhow::Result<JobPoller> {
+        let client = api_client_builder()
+            .timeout(Duration::from_secs(30))
+            .pool_idle_timeout(Duration::from_secs(300))
+            .tcp_keepalive(Duration::from_secs(60))
+            .build()?;
+        Ok(JobPoller {
+            client,
+            url,
+            etag: None,
+            last_body: None,
+            schema_version: None,
+            consecutive_failures: 0,
+        })
+    }
+
+    async fn fetch(&mut self) -> anyhow::Result<String> {
+        let mut request = self.client.get(&self.url);
+        if let Some(etag) = &self.etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        let response = request.send().await?;
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some(body) = &self.last_body {
+                return Ok(body.clone());
+            }
+        }
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "Encountered an error while attempting to check job status : {:?}",
+                response.text().await?
+            ));
+        }
+
+        self.schema_version = response_schema_version(&response);
+        self.etag = response
+            .headers()
+            .get(header::ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(|etag| etag.to_string());
+        Ok(response.text().await?)
+    }
+
+    /// Returns the job status and whether it changed since the previous poll. Brief outages
+    /// are reported as errors only once they last for several polls in a row; until then
+    /// `None` is returned and the caller should simply poll again later.
+    pub async fn poll(&mut self) -> anyhow::Result<Option<(JobVerificationResponse, bool)>> {
+        let body = match self.fetch().await {
+            Ok(body) => body,
+            Err(err) => {
+                self.consecutive_failures += 1;
+                if self.consecutive_failures >= MAX_CONSECUTIVE_FAILURES {
+                    return Err(err);
+                }
+                warn!("failed to check the job status, retrying: {}", err);
+                return Ok(None);
+            }
+        };
+        self.consecutive_failures = 0;
+
+        let response = parse_api_body(&body, self.schema_version)?;
+        let changed = self.last_body.as_ref() != Some(&body);
+        self.last_body = Some(body);
+        Ok(Some((response, changed)))
+    }
+}
+
+/// Receives the progress of a remote verification job. The CLI draws a spinner, services
+/// embedding the client can forward the updates wherever they need.
+pub trait JobProgress {
+    /// Called with every status received while polling.
+    fn update(&mut self, _status: &JobVerificationResponse) {}
+
+    /// Called once polling stops. `success` is false if the job failed, or polling failed or
+    /// was interrupted.
+    fn finish(&mut self, _success: bool) {}
+}
+
+/// Progress that is not reported anywhere.
+pub struct NoProgress;
+
+impl JobProgress for NoProgress {}
+
+/// Polls a remote job until it is no longer in progress, without blocking the runtime.
+/// Returns `None` if an interrupt signal was received first.
+pub async fn wait_for_job(
+    request_id: &str,
+    progress: &mut dyn JobProgress,
+) -> anyhow::Result<Option<JobVerificationResponse>> {
+    let mut poller = JobPoller::new(format!("{}/job/{}", remote_api_url(), request_id))?;
+    let mut backoff = Backoff::new(Duration::from_secs(5), Duration::from_secs(60));
+    loop {
+        if interrupted() {
+            progress.finish(false);
+            return Ok(None);
+        }
+
+        let status = match poller.poll().await {
+            Ok(Some((status, changed))) => {
+                if changed {
+                    backoff.reset();
+                }
+                status
+            }
+            Ok(None) => {
+                sleep_unless_interrupted(backoff.next_delay()).await;
+                continue;
+            }
+            Err(err) => {
+                progress.finish(false);
+                return Err(err);
+            }
+        };
+        progress.update(&status);
+
+        match status.status {
+            JobStatus::InProgress => sleep_unless_interrupted(backoff.next_delay()).await,
+            JobStatus::Completed => {
+                progress.finish(true);
+                return Ok(Some(status));
+            }
+            JobStatus::Failed | JobStatus::Unknown => {
+                progress.finish(false);
+                return Ok(Some(status));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let mut backoff = Backoff::new(Duration::from_secs(4), Duration::from_secs(10));
+        let delays = (0..4).map(|_| backoff.next_delay()).collect::<Vec<_>>();
+        for (delay, expected) in delays.iter().zip([4.0, 6.0, 9.0, 10.0]) {
+            assert!(delay.as_secs_f64() >= expected * 0.8 && delay.as_secs_f64() <= expected * 1.2);
+        }
+
+        backoff.reset();
+        assert!(backoff.next_delay() <= Duration::from_secs_f64(4.8));
+    }
+}
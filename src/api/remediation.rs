@@ -0,0 +1,141 @@
+use std::time::Duration;
+
+use crate::api::schema::api_client_builder;
+use crate::endpoints::remote_api_url;
+use crate::image_config::IMAGE_MAP;
+use crate::lockfile::LOCKFILE_V4_SOLANA_VERSION;
+
+/// Output of remote builds whose cargo cannot read the program's Cargo.lock, usually a
+/// version 4 lockfile generated by a recent cargo. Other `cargo metadata` failures have other
+/// causes, so only the lockfile version errors are matched.
+const LOCKFILE_PATTERNS: [&str; 2] = [
+    "lock file version 4 requires `-Znext-lockfile-bump`",
+    "but this version of Cargo does not understand this lock file",
+];
+
+/// A remote build failure with a known remediation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KnownFailure {
+    LockfileVersion,
+}
+
+impl KnownFailure {
+    /// Recognizes a known failure in the message or logs of a failed job.
+    pub fn detect(output: &str) -> Option<KnownFailure> {
+        LOCKFILE_PATTERNS
+            .iter()
+            .any(|pattern| output.contains(pattern))
+            .then_some(KnownFailure::LockfileVersion)
+    }
+
+    pub fn print_remediation(&self) {
+        match self {
+            KnownFailure::LockfileVersion => {
+                println!("The remote build could not read the program's Cargo.lock. Lockfiles generated by cargo 1.78 or later use version 4, which the cargo of older build images does not understand. Either:");
+                println!("  - set `version = 3` at the top of Cargo.lock, commit, push and verify the new commit");
+                println!(
+                    "  - or build with a newer image by uploading the verification again with `--base-image {}`",
+                    lockfile_compatible_base_image()
+                );
+            }
+        }
+    }
+}
+
+/// Returned when a remote job failed in a way `KnownFailure` recognizes.
+#[derive(Debug)]
+pub struct RemoteJobFailed {
+    pub request_id: String,
+    pub failure: KnownFailure,
+}
+
+impl std::fmt::Display for RemoteJobFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Remote job {} failed: {:?}. See {}/logs/{}",
+            self.request_id,
+            self.failure,
+            remote_api_url(),
+            self.request_id
+        )
+    }
+}
+
+impl std::error::Error for RemoteJobFailed {}
+
+/// Build image of the first Solana release able to read version 4 lockfiles.
+pub fn lockfile_compatible_base_image() -> String {
+    let digest = IMAGE_MAP
+        .range(LOCKFILE_V4_SOLANA_VERSION..)
+        .next()
+        .map(|(_, digest)| *digest)
+        .unwrap_or_default();
+    format!("solanafoundation/solana-verifiable-build@{}", digest)
+}
+
+/// Build arguments with the base image replaced by `image`.
+pub fn with_base_image(args: &[String], image: &str) -> Vec<String> {
+    let mut replaced = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--base-image" || arg == "-b" {
+            args.next();
+        } else {
+            replaced.push(arg.clone());
+        }
+    }
+    replaced.extend(["--base-image".to_string(), image.to_string()]);
+    replaced
+}
+
+/// Logs of a remote job, if the server still has them.
+pub async fn fetch_job_logs(request_id: &str) -> Option<String> {
+    let client = api_client_builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+        .ok()?;
+    let response = client
+        .get(format!("{}/logs/{}", remote_api_url(), request_id))
+        .send()
+        .await
+        .ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    response.text().await.ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_lockfile_failure() {
+        let logs = "error: failed to parse lock file at: /build/Cargo.lock\n\nCaused by:\n  lock file version 4 requires `-Znext-lockfile-bump`\nError: Failed to obtain package metadata: `cargo metadata` exited with an error";
+        assert_eq!(
+            KnownFailure::detect(logs),
+            Some(KnownFailure::LockfileVersion)
+        );
+        assert_eq!(
+            KnownFailure::detect("error[E0425]: cannot find value"),
+            None
+        );
+        assert_eq!(
+            KnownFailure::detect("Error: Failed to obtain package metadata: `cargo metadata` exited with an error: failed to select a version for `anchor-lang`"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_with_base_image() {
+        let args = ["--library-name", "phoenix", "-b", "old", "--bpf"]
+            .map(String::from)
+            .to_vec();
+        assert_eq!(
+            with_base_image(&args, "new"),
+            ["--library-name", "phoenix", "--bpf", "--base-image", "new"].map(String::from)
+        );
+        assert!(lockfile_compatible_base_image().contains("@sha256:"));
+    }
+}
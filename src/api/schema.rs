@@ -0,0 +1,161 @@
+use anyhow::anyhow;
+use reqwest::{header, Client, ClientBuilder, Response, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::time::Duration;
+use tokio::sync::OnceCell;
+
+use crate::endpoints::remote_api_url;
+
+/// Header carrying the schema version of requests and responses
+pub const SCHEMA_VERSION_HEADER: &str = "X-Schema-Version";
+
+/// Versions of the API schema this client can parse, oldest first. Servers that predate the
+/// handshake speak version 1.
+pub const SUPPORTED_SCHEMA_VERSIONS: [u32; 1] = [1];
+
+/// Schema version agreed with the server, negotiated once per run
+static NEGOTIATED_VERSION: OnceCell<u32> = OnceCell::const_new();
+
+/// Response of `GET /version`.
+#[derive(Debug, Deserialize)]
+struct VersionResponse {
+    schema_versions: Vec<u32>,
+    /// Oldest solana-verify release the server still works with
+    #[serde(default)]
+    min_client_version: Option<String>,
+}
+
+fn latest_supported() -> u32 {
+    SUPPORTED_SCHEMA_VERSIONS[SUPPORTED_SCHEMA_VERSIONS.len() - 1]
+}
+
+fn upgrade_message(server_versions: &[u32]) -> String {
+    format!(
+        "{} speaks API schema version(s) {:?} but this solana-verify {} only supports {:?}. Please upgrade with `cargo install solana-verify`",
+        remote_api_url(),
+        server_versions,
+        env!("CARGO_PKG_VERSION"),
+        SUPPORTED_SCHEMA_VERSIONS
+    )
+}
+
+/// Highest schema version both sides support.
+fn select_version(server: &VersionResponse) -> anyhow::Result<u32> {
+    SUPPORTED_SCHEMA_VERSIONS
+        .iter()
+        .rev()
+        .find(|version| server.schema_versions.contains(version))
+        .copied()
+        .ok_or_else(|| {
+            let mut message = upgrade_message(&server.schema_versions);
+            if let Some(min_client_version) = &server.min_client_version {
+                message.push_str(&format!(" (requires {} or later)", min_client_version));
+            }
+            anyhow!(message)
+        })
+}
+
+async fn fetch_schema_version() -> anyhow::Result<u32> {
+    let client = Client::builder().timeout(Duration::from_secs(30)).build()?;
+    let response = match client
+        .get(format!("{}/version", remote_api_url()))
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        // The request that follows reports the server being unreachable
+        Err(_) => return Ok(latest_supported()),
+    };
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(SUPPORTED_SCHEMA_VERSIONS[0]);
+    }
+    match response.json::<VersionResponse>().await {
+        Ok(server) => select_version(&server),
+        Err(_) => Ok(SUPPORTED_SCHEMA_VERSIONS[0]),
+    }
+}
+
+/// Agrees on a schema version with the server, failing with an upgrade message if the
+/// server no longer speaks any version this client supports.
+pub async fn negotiate_schema_version() -> anyhow::Result<u32> {
+    NEGOTIATED_VERSION
+        .get_or_try_init(fetch_schema_version)
+        .await
+        .copied()
+}
+
+/// Client for the remote API, sending the negotiated schema version with every request.
+pub fn api_client_builder() -> ClientBuilder {
+    let version = NEGOTIATED_VERSION
+        .get()
+        .copied()
+        .unwrap_or_else(latest_supported);
+    let mut headers = header::HeaderMap::new();
+    headers.insert(SCHEMA_VERSION_HEADER, header::HeaderValue::from(version));
+    Client::builder().default_headers(headers)
+}
+
+/// Schema version of a response, if the server reported one.
+pub fn response_schema_version(response: &Response) -> Option<u32> {
+    response
+        .headers()
+        .get(SCHEMA_VERSION_HEADER)
+        .and_then(|version| version.to_str().ok())
+        .and_then(|version| version.trim().parse().ok())
+}
+
+/// Parses a response body, reporting a body in an unsupported schema as such rather than as
+/// a parse error.
+pub fn parse_api_body<T: DeserializeOwned>(
+    body: &str,
+    schema_version: Option<u32>,
+) -> anyhow::Result<T> {
+    if let Some(version) = schema_version {
+        if !SUPPORTED_SCHEMA_VERSIONS.contains(&version) {
+            return Err(anyhow!(upgrade_message(&[version])));
+        }
+    }
+    serde_json::from_str(body).map_err(|e| {
+        anyhow!(
+            "Unexpected response from {}: {}\n{}",
+            remote_api_url(),
+            e,
+            body
+        )
+    })
+}
+
+pub async fn parse_api_response<T: DeserializeOwned>(response: Response) -> anyhow::Result<T> {
+    let schema_version = response_schema_version(&response);
+    parse_api_body(&response.text().await?, schema_version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::models::VerifyResponse;
+
+    #[test]
+    fn test_select_version() {
+        let server = |schema_versions: Vec<u32>| VersionResponse {
+            schema_versions,
+            min_client_version: Some("0.5.0".to_string()),
+        };
+        assert_eq!(select_version(&server(vec![1, 2])).unwrap(), 1);
+        let err = select_version(&server(vec![2, 3])).unwrap_err().to_string();
+        assert!(err.contains("Please upgrade"));
+        assert!(err.contains("0.5.0"));
+    }
+
+    #[test]
+    fn test_parse_api_body() {
+        let body = r#"{"status": "in_progress", "request_id": "1234", "message": "queued"}"#;
+        assert!(parse_api_body::<VerifyResponse>(body, Some(1)).is_ok());
+        assert!(parse_api_body::<VerifyResponse>(body, None).is_ok());
+        let err = parse_api_body::<VerifyResponse>(body, Some(99))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Please upgrade"));
+        assert!(parse_api_body::<VerifyResponse>("{}", None).is_err());
+    }
+}
@@ -1,15 +1,11 @@
 #![allow(dead_code)]
 
-use reqwest::Client;
 use serde::Deserialize;
-use solana_client::rpc_client::RpcClient;
-use std::error::Error;
+use solana_client::{rpc_client::RpcClient, rpc_request::RpcRequest};
+use solana_sdk::{commitment_config::CommitmentLevel, pubkey::Pubkey};
+use std::{error::Error, str::FromStr};
 
-#[derive(Deserialize)]
-struct RpcResponse {
-    result: Option<AccountInfoResponse>,
-    id: u64,
-}
+use crate::is_legacy_loader;
 
 #[derive(Deserialize)]
 struct AccountInfoResponse {
@@ -34,10 +30,24 @@ struct AccountValue {
 }
 
 #[derive(Deserialize)]
-struct AccountData {
-    parsed: ParsedData,
-    program: String,
-    space: u64,
+#[serde(untagged)]
+enum AccountData {
+    Parsed {
+        parsed: ParsedData,
+        program: String,
+        space: u64,
+    },
+    /// Data of accounts the RPC cannot parse, such as programs of the legacy BPF loaders
+    Raw(Vec<String>),
+}
+
+impl AccountData {
+    fn info(&self) -> Option<&ProgramInfo> {
+        match self {
+            AccountData::Parsed { parsed, .. } => Some(&parsed.info),
+            AccountData::Raw(_) => None,
+        }
+    }
 }
 
 #[derive(Deserialize)]
@@ -55,27 +65,24 @@ struct ProgramInfo {
     slot: Option<u64>,
 }
 
+/// Fetches the account through `connection`, so the request is metered and recorded like
+/// every other RPC call.
 async fn get_account_info(
-    client: &Client,
-    rpc_url: &str,
+    connection: &RpcClient,
     address: &str,
+    commitment: CommitmentLevel,
 ) -> anyhow::Result<AccountValue> {
-    let body = serde_json::json!({
-        "jsonrpc": "2.0",
-        "id": 1,
-        "method": "getAccountInfo",
-        "params": [
-            address,
-            {
-                "encoding": "jsonParsed"
-            }
-        ]
-    });
-
-    let response = client.post(rpc_url).json(&body).send().await?;
-
-    let response: RpcResponse = response.json().await?;
-    if let Some(value) = response.result {
+    let params = serde_json::json!([
+        address,
+        {
+            "encoding": "jsonParsed",
+            "commitment": commitment
+        }
+    ]);
+
+    let response: Option<AccountInfoResponse> =
+        connection.send(RpcRequest::GetAccountInfo, params)?;
+    if let Some(value) = response {
         return value
             .value
             .ok_or_else(|| anyhow::anyhow!("No value found in account info response"));
@@ -87,25 +94,26 @@ pub async fn get_last_deployed_slot(
     connection: &RpcClient,
     program_address: &str,
 ) -> Result<u64, Box<dyn Error>> {
-    let client = Client::new();
-
     // Step 1: Get account info for the program address
-    let account_info = get_account_info(&client, &connection.url(), program_address).await?;
+    let commitment = connection.commitment().commitment;
+    let account_info = get_account_info(connection, program_address, commitment).await?;
+    // Programs of the legacy loaders are immutable and have no program data recording a
+    // deployment slot, 0 stands for an unrecorded slot
+    if is_legacy_loader(&Pubkey::from_str(&account_info.owner)?) {
+        return Ok(0);
+    }
     let program_data_address = account_info
         .data
-        .parsed
-        .info
-        .program_data
+        .info()
+        .and_then(|info| info.program_data.clone())
         .ok_or("No programData found in program account response")?;
 
     // Step 2: Get account info for the program data address
-    let program_data_info =
-        get_account_info(&client, &connection.url(), &program_data_address).await?;
+    let program_data_info = get_account_info(connection, &program_data_address, commitment).await?;
     let last_deployed_slot = program_data_info
         .data
-        .parsed
-        .info
-        .slot
+        .info()
+        .and_then(|info| info.slot)
         .ok_or("No slot found in program data account response")?;
 
     Ok(last_deployed_slot)
@@ -123,4 +131,43 @@ mod tests {
             get_last_deployed_slot(&RpcClient::new(rpc_url), program_address).await;
         assert!(last_deployed_slot.is_ok());
     }
+
+    #[test]
+    fn test_parse_legacy_program_account() {
+        let account: AccountValue = serde_json::from_value(serde_json::json!({
+            "data": ["f0VMRgIBAQ==", "base64"],
+            "executable": true,
+            "lamports": 1141440,
+            "owner": "BPFLoader2111111111111111111111111111111111",
+            "rentEpoch": 0,
+            "space": 36
+        }))
+        .unwrap();
+        assert!(account.data.info().is_none());
+        assert!(is_legacy_loader(&Pubkey::from_str(&account.owner).unwrap()));
+
+        let upgradeable: AccountValue = serde_json::from_value(serde_json::json!({
+            "data": {
+                "parsed": {
+                    "info": {"programData": "4XhdFCbECXJvnm6GSjt4wWrxCA6FVXFnfTFoTwXjdAUd"},
+                    "type": "program"
+                },
+                "program": "bpf-upgradeable-loader",
+                "space": 36
+            },
+            "executable": true,
+            "lamports": 1141440,
+            "owner": "BPFLoaderUpgradeab1e11111111111111111111111",
+            "rentEpoch": 0,
+            "space": 36
+        }))
+        .unwrap();
+        assert_eq!(
+            upgradeable.data.info().unwrap().program_data.as_deref(),
+            Some("4XhdFCbECXJvnm6GSjt4wWrxCA6FVXFnfTFoTwXjdAUd")
+        );
+        assert!(!is_legacy_loader(
+            &Pubkey::from_str(&upgradeable.owner).unwrap()
+        ));
+    }
 }
@@ -0,0 +1,129 @@
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use solana_sdk::{pubkey::Pubkey, signature::Signature, signer::Signer};
+use std::{path::Path, str::FromStr};
+
+/// First line of every attested message, so a signature over one cannot be mistaken for a
+/// signature over anything else
+const ATTESTATION_DOMAIN: &str = "solana-verify attestation v1";
+
+/// A signed claim that the build of `commit` matched the program deployed at `program_id`,
+/// whose hash was `on_chain_hash` at `slot`. Unlike a PDA it is not written on chain, anyone
+/// holding it can check the signature offline.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attestation {
+    pub program_id: String,
+    pub on_chain_hash: String,
+    pub slot: u64,
+    pub commit: String,
+    pub signer: String,
+    pub signature: String,
+}
+
+fn attested_message(program_id: &str, on_chain_hash: &str, slot: u64, commit: &str) -> String {
+    format!(
+        "{}\nprogram: {}\nhash: {}\nslot: {}\ncommit: {}",
+        ATTESTATION_DOMAIN, program_id, on_chain_hash, slot, commit
+    )
+}
+
+impl Attestation {
+    pub fn sign(
+        signer: &dyn Signer,
+        program_id: &Pubkey,
+        on_chain_hash: &str,
+        slot: u64,
+        commit: &str,
+    ) -> anyhow::Result<Attestation> {
+        let program_id = program_id.to_string();
+        let message = attested_message(&program_id, on_chain_hash, slot, commit);
+        let signature = signer
+            .try_sign_message(message.as_bytes())
+            .map_err(|e| anyhow!("Failed to sign the attestation: {}", e))?;
+        Ok(Attestation {
+            program_id,
+            on_chain_hash: on_chain_hash.to_string(),
+            slot,
+            commit: commit.to_string(),
+            signer: signer.try_pubkey()?.to_string(),
+            signature: signature.to_string(),
+        })
+    }
+
+    /// Message the signature covers.
+    pub fn message(&self) -> String {
+        attested_message(
+            &self.program_id,
+            &self.on_chain_hash,
+            self.slot,
+            &self.commit,
+        )
+    }
+
+    /// Checks that `signer` signed the attested tuple.
+    pub fn verify(&self) -> anyhow::Result<()> {
+        let signer = Pubkey::from_str(&self.signer)
+            .map_err(|e| anyhow!("Invalid signer {}: {}", self.signer, e))?;
+        let signature = Signature::from_str(&self.signature)
+            .map_err(|e| anyhow!("Invalid signature {}: {}", self.signature, e))?;
+        if !signature.verify(signer.as_ref(), self.message().as_bytes()) {
+            return Err(anyhow!(
+                "The signature does not match the attestation, it was altered or signed by another key than {}",
+                self.signer
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Attestation> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| anyhow!("Invalid attestation {}: {}", path.display(), e))
+    }
+
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))
+    }
+
+    pub fn print(&self) {
+        println!("Attestation signed by {}:", self.signer);
+        println!("  Program ID: {}", self.program_id);
+        println!("  On-chain hash: {}", self.on_chain_hash);
+        println!("  Slot: {}", self.slot);
+        println!("  Commit: {}", self.commit);
+        println!("  Signature: {}", self.signature);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::Keypair;
+
+    #[test]
+    fn test_attestation() {
+        let keypair = Keypair::new();
+        let program_id = Pubkey::new_unique();
+        let attestation =
+            Attestation::sign(&keypair, &program_id, "abcd", 250_000_000, "deadbeef").unwrap();
+        assert_eq!(attestation.signer, keypair.pubkey().to_string());
+        attestation.verify().unwrap();
+
+        let json = serde_json::to_string(&attestation).unwrap();
+        let parsed: Attestation = serde_json::from_str(&json).unwrap();
+        parsed.verify().unwrap();
+
+        let tampered = Attestation {
+            slot: 250_000_001,
+            ..attestation.clone()
+        };
+        assert!(tampered.verify().is_err());
+        let other_signer = Attestation {
+            signer: Pubkey::new_unique().to_string(),
+            ..attestation
+        };
+        assert!(other_signer.verify().is_err());
+    }
+}
@@ -0,0 +1,198 @@
+use anyhow::anyhow;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+
+/// An advisory of the RustSec database matching a crate of the lockfile.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AdvisoryFinding {
+    /// `vulnerability`, or the kind of warning, e.g. `unmaintained` or `yanked`
+    pub kind: String,
+    pub package: String,
+    pub version: String,
+    /// RustSec id, e.g. RUSTSEC-2022-0093. Yanked crates have none
+    pub id: Option<String>,
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub patched_versions: Vec<String>,
+}
+
+/// Result of checking a lockfile against the RustSec advisory database with `cargo audit`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DependencyAudit {
+    /// Last update of the advisory database the check ran against
+    pub database_updated_at: Option<String>,
+    pub vulnerabilities: Vec<AdvisoryFinding>,
+    pub warnings: Vec<AdvisoryFinding>,
+}
+
+#[derive(Deserialize)]
+struct AuditOutput {
+    database: Option<AuditDatabase>,
+    vulnerabilities: AuditVulnerabilities,
+    #[serde(default)]
+    warnings: std::collections::BTreeMap<String, Vec<AuditEntry>>,
+}
+
+#[derive(Deserialize)]
+struct AuditDatabase {
+    #[serde(rename = "last-updated")]
+    last_updated: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AuditVulnerabilities {
+    list: Vec<AuditEntry>,
+}
+
+#[derive(Deserialize)]
+struct AuditEntry {
+    kind: Option<String>,
+    advisory: Option<AuditAdvisory>,
+    versions: Option<AuditVersions>,
+    package: AuditPackage,
+}
+
+#[derive(Deserialize)]
+struct AuditAdvisory {
+    id: String,
+    title: String,
+    url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AuditVersions {
+    #[serde(default)]
+    patched: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct AuditPackage {
+    name: String,
+    version: String,
+}
+
+impl AuditEntry {
+    fn into_finding(self, kind: &str) -> AdvisoryFinding {
+        let advisory = self.advisory;
+        AdvisoryFinding {
+            kind: self.kind.unwrap_or_else(|| kind.to_string()),
+            package: self.package.name,
+            version: self.package.version,
+            id: advisory.as_ref().map(|advisory| advisory.id.clone()),
+            title: advisory.as_ref().map(|advisory| advisory.title.clone()),
+            url: advisory.and_then(|advisory| advisory.url),
+            patched_versions: self
+                .versions
+                .map(|versions| versions.patched)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl DependencyAudit {
+    /// Parses the output of `cargo audit --json`.
+    pub fn parse(output: &str) -> anyhow::Result<DependencyAudit> {
+        let output: AuditOutput = serde_json::from_str(output)
+            .map_err(|e| anyhow!("Unexpected output from cargo audit: {}", e))?;
+        Ok(DependencyAudit {
+            database_updated_at: output.database.and_then(|database| database.last_updated),
+            vulnerabilities: output
+                .vulnerabilities
+                .list
+                .into_iter()
+                .map(|entry| entry.into_finding("vulnerability"))
+                .collect(),
+            warnings: output
+                .warnings
+                .into_iter()
+                .flat_map(|(kind, entries)| {
+                    entries
+                        .into_iter()
+                        .map(move |entry| entry.into_finding(&kind))
+                })
+                .collect(),
+        })
+    }
+
+    pub fn print_summary(&self) {
+        info!(
+            "Dependency audit: {} vulnerabilit{}, {} warning(s)",
+            self.vulnerabilities.len(),
+            if self.vulnerabilities.len() == 1 {
+                "y"
+            } else {
+                "ies"
+            },
+            self.warnings.len()
+        );
+        for finding in self.vulnerabilities.iter().chain(&self.warnings) {
+            info!(
+                "  {} {} {}: {}",
+                finding.id.as_deref().unwrap_or(&finding.kind),
+                finding.package,
+                finding.version,
+                finding.title.as_deref().unwrap_or(&finding.kind)
+            );
+        }
+    }
+}
+
+/// Checks a Cargo.lock against the RustSec advisory database. Findings are reported, they do
+/// not fail the check.
+pub fn audit_lockfile(lockfile: &Path) -> anyhow::Result<DependencyAudit> {
+    let output = Command::new("cargo")
+        .args(["audit", "--json", "--file"])
+        .arg(lockfile)
+        .output()
+        .map_err(|e| anyhow!("Failed to run cargo audit: {}", e))?;
+    // cargo audit exits with an error when it finds vulnerabilities, so only output that is
+    // not a report is a failure
+    DependencyAudit::parse(&String::from_utf8_lossy(&output.stdout)).map_err(|e| {
+        anyhow!(
+            "cargo audit failed, install it with `cargo install cargo-audit`: {}\n{}",
+            e,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_audit_output() {
+        let audit = DependencyAudit::parse(
+            r#"{
+                "database": {"advisory-count": 600, "last-updated": "2024-10-01T12:00:00+02:00"},
+                "vulnerabilities": {"found": true, "count": 1, "list": [{
+                    "advisory": {"id": "RUSTSEC-2022-0093", "package": "ed25519-dalek", "title": "Double Public Key Signing Function Oracle Attack on `ed25519-dalek`", "url": "https://github.com/dalek-cryptography/ed25519-dalek/pull/205"},
+                    "versions": {"patched": [">=2"], "unaffected": []},
+                    "package": {"name": "ed25519-dalek", "version": "1.0.1"}
+                }]},
+                "warnings": {"yanked": [{
+                    "kind": "yanked",
+                    "advisory": null,
+                    "versions": null,
+                    "package": {"name": "ahash", "version": "0.7.6"}
+                }]}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            audit.database_updated_at.as_deref(),
+            Some("2024-10-01T12:00:00+02:00")
+        );
+        assert_eq!(audit.vulnerabilities.len(), 1);
+        let vulnerability = &audit.vulnerabilities[0];
+        assert_eq!(vulnerability.kind, "vulnerability");
+        assert_eq!(vulnerability.id.as_deref(), Some("RUSTSEC-2022-0093"));
+        assert_eq!(vulnerability.patched_versions, vec![">=2".to_string()]);
+        assert_eq!(audit.warnings[0].kind, "yanked");
+        assert_eq!(audit.warnings[0].id, None);
+
+        assert!(DependencyAudit::parse("error: not found").is_err());
+    }
+}
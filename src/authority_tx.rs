@@ -0,0 +1,214 @@
+use anyhow::anyhow;
+use log::{info, warn};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use std::time::Duration;
+
+use crate::api::{fetch_api_status, send_job_with_uploader_to_remote, sleep_unless_interrupted};
+use crate::cleanup::interrupted;
+use crate::humanize::format_duration;
+use crate::solana_program::{find_build_params_pda, get_program_upgrade_authority};
+
+const SQUADS_V4_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("SQDS4ep65T869zMMBKyuUq6aD6EgTu8psMjkvj52pCf");
+
+/// `GovernanceAccountType::ProposalV2` of spl-governance, which is deployed under many ids
+const SPL_GOV_PROPOSAL_V2: u8 = 14;
+
+/// How long to wait for the API to report the program as verified after the job is sent
+const API_STATUS_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ProposalStatus {
+    Pending(&'static str),
+    Executed,
+    /// The proposal can no longer be executed
+    Abandoned(&'static str),
+    /// Not a Squads v4 or spl-governance proposal
+    Unknown,
+}
+
+/// Reads the status of a Squads v4 or spl-governance proposal from its account.
+pub fn proposal_status(owner: &Pubkey, data: &[u8]) -> ProposalStatus {
+    if *owner == SQUADS_V4_PROGRAM_ID {
+        // Anchor discriminator, multisig, transaction index, then the status enum
+        return match data.get(8 + 32 + 8) {
+            Some(0) => ProposalStatus::Pending("draft"),
+            Some(1) => ProposalStatus::Pending("active"),
+            Some(2) => ProposalStatus::Abandoned("rejected"),
+            Some(3) => ProposalStatus::Pending("approved"),
+            Some(4) => ProposalStatus::Pending("executing"),
+            Some(5) => ProposalStatus::Executed,
+            Some(6) => ProposalStatus::Abandoned("cancelled"),
+            _ => ProposalStatus::Unknown,
+        };
+    }
+    if data.first() == Some(&SPL_GOV_PROPOSAL_V2) {
+        // Account type, governance, governing token mint, then the proposal state
+        return match data.get(1 + 32 + 32) {
+            Some(0) => ProposalStatus::Pending("draft"),
+            Some(1) => ProposalStatus::Pending("signing off"),
+            Some(2) => ProposalStatus::Pending("voting"),
+            Some(3) => ProposalStatus::Pending("succeeded"),
+            Some(4) => ProposalStatus::Pending("executing"),
+            Some(5) => ProposalStatus::Executed,
+            Some(6) => ProposalStatus::Abandoned("cancelled"),
+            Some(7) => ProposalStatus::Abandoned("defeated"),
+            Some(8) => ProposalStatus::Abandoned("executing with errors"),
+            Some(9) => ProposalStatus::Abandoned("vetoed"),
+            _ => ProposalStatus::Unknown,
+        };
+    }
+    ProposalStatus::Unknown
+}
+
+fn get_pda_data(connection: &RpcClient, pda: &Pubkey) -> anyhow::Result<Option<Vec<u8>>> {
+    Ok(connection
+        .get_account_with_commitment(pda, CommitmentConfig::finalized())
+        .map_err(|e| anyhow!("Unable to fetch PDA {}: {}", pda, e))?
+        .value
+        .map(|account| account.data))
+}
+
+/// Waits for the governance proposal uploading the verification of `program_id` to be
+/// executed, then submits the remote verification job for the uploader and waits for the
+/// API to report the program as verified.
+pub async fn wait_for_authority_tx(
+    connection: &RpcClient,
+    program_id: Pubkey,
+    proposal: Pubkey,
+    uploader: Option<Pubkey>,
+    poll_interval: Duration,
+) -> anyhow::Result<()> {
+    let uploader = match uploader {
+        Some(uploader) => uploader,
+        None => get_program_upgrade_authority(connection, &program_id)?
+            .ok_or_else(|| anyhow!("Program {} is immutable, pass --uploader", program_id))?,
+    };
+    let pda = find_build_params_pda(&program_id, &uploader).0;
+    println!("Uploader: {}", uploader);
+    info!("Waiting for proposal {} to write PDA {}", proposal, pda);
+
+    // The PDA may hold params from an earlier upload, only a change means the proposal ran
+    let initial_pda_data = get_pda_data(connection, &pda)?;
+    let mut last_status = None;
+    loop {
+        if interrupted() {
+            return Err(anyhow!("Interrupted while waiting for the proposal"));
+        }
+
+        let status = match connection
+            .get_account_with_commitment(&proposal, CommitmentConfig::finalized())
+            .map_err(|e| anyhow!("Unable to fetch proposal {}: {}", proposal, e))?
+            .value
+        {
+            Some(account) => proposal_status(&account.owner, &account.data),
+            // Proposals may be closed once executed to reclaim rent
+            None => ProposalStatus::Unknown,
+        };
+        if last_status.as_ref() != Some(&status) {
+            match &status {
+                ProposalStatus::Pending(state) => println!("Proposal is {}", state),
+                ProposalStatus::Executed => println!("Proposal executed"),
+                ProposalStatus::Abandoned(state) => {
+                    return Err(anyhow!("Proposal {} was {}", proposal, state));
+                }
+                ProposalStatus::Unknown => {
+                    warn!("unable to read the proposal status, waiting for the PDA instead")
+                }
+            }
+        }
+
+        // An executed proposal may have written the PDA before this command started
+        let pda_data = get_pda_data(connection, &pda)?;
+        if pda_data.is_some()
+            && (pda_data != initial_pda_data || status == ProposalStatus::Executed)
+        {
+            println!("PDA {} written", pda);
+            break;
+        }
+        if status == ProposalStatus::Executed && last_status == Some(ProposalStatus::Executed) {
+            return Err(anyhow!(
+                "Proposal {} was executed but did not write PDA {}. Check that it uploads the verification with {}",
+                proposal,
+                pda,
+                uploader
+            ));
+        }
+        last_status = Some(status);
+        sleep_unless_interrupted(poll_interval).await;
+    }
+
+    send_job_with_uploader_to_remote(connection, &program_id, &uploader).await?;
+
+    info!("Waiting for the verification status to be updated...");
+    let started = std::time::Instant::now();
+    while started.elapsed() < API_STATUS_TIMEOUT {
+        if interrupted() {
+            return Err(anyhow!(
+                "Interrupted while waiting for the verification status"
+            ));
+        }
+        match fetch_api_status(program_id).await {
+            Ok(status) if status.is_verified => {
+                println!("Program {} is verified ✅", program_id);
+                return Ok(());
+            }
+            Ok(_) => {}
+            Err(err) => warn!("failed to get the verification status, retrying: {}", err),
+        }
+        sleep_unless_interrupted(poll_interval).await;
+    }
+    Err(anyhow!(
+        "Program {} is still not reported as verified after {}",
+        program_id,
+        format_duration(API_STATUS_TIMEOUT)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_squads_proposal_status() {
+        let mut data = vec![0u8; 8 + 32 + 8 + 9];
+        data[48] = 3;
+        assert_eq!(
+            proposal_status(&SQUADS_V4_PROGRAM_ID, &data),
+            ProposalStatus::Pending("approved")
+        );
+        data[48] = 5;
+        assert_eq!(
+            proposal_status(&SQUADS_V4_PROGRAM_ID, &data),
+            ProposalStatus::Executed
+        );
+        assert_eq!(
+            proposal_status(&Pubkey::new_unique(), &data),
+            ProposalStatus::Unknown
+        );
+    }
+
+    #[test]
+    fn test_spl_gov_proposal_status() {
+        let governance = Pubkey::new_unique();
+        let mut data = vec![0u8; 1 + 32 + 32 + 1];
+        data[0] = SPL_GOV_PROPOSAL_V2;
+        data[65] = 2;
+        assert_eq!(
+            proposal_status(&governance, &data),
+            ProposalStatus::Pending("voting")
+        );
+        data[65] = 5;
+        assert_eq!(
+            proposal_status(&governance, &data),
+            ProposalStatus::Executed
+        );
+        data[65] = 7;
+        assert_eq!(
+            proposal_status(&governance, &data),
+            ProposalStatus::Abandoned("defeated")
+        );
+        assert_eq!(proposal_status(&governance, &[]), ProposalStatus::Unknown);
+    }
+}
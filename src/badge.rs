@@ -0,0 +1,151 @@
+use serde_json::json;
+use solana_sdk::pubkey::Pubkey;
+use std::path::Path;
+
+use crate::{
+    api::{fetch_api_status, StatusResponse},
+    compression::write_artifact,
+};
+
+const LABEL: &str = "verified build";
+const VERIFIED_COLOR: &str = "#4c1";
+const UNVERIFIED_COLOR: &str = "#e05d44";
+
+/// Text and color of a verification badge.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Badge {
+    pub message: String,
+    pub color: &'static str,
+}
+
+impl Badge {
+    pub fn from_status(status: &StatusResponse) -> Badge {
+        if !status.is_verified {
+            return Badge {
+                message: "unverified".to_string(),
+                color: UNVERIFIED_COLOR,
+            };
+        }
+        let mut parts = vec!["verified".to_string()];
+        if let Some(commit) = &status.commit {
+            parts.push(commit.chars().take(7).collect());
+        }
+        // Timestamps are RFC 3339, the day is enough for a badge
+        if let Some(date) = &status.last_verified_at {
+            parts.push(date.chars().take(10).collect());
+        }
+        Badge {
+            message: parts.join(" · "),
+            color: VERIFIED_COLOR,
+        }
+    }
+
+    /// Endpoint JSON for `https://img.shields.io/endpoint?url=...`
+    pub fn to_shields_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(&json!({
+            "schemaVersion": 1,
+            "label": LABEL,
+            "message": self.message,
+            "color": self.color,
+        }))?)
+    }
+
+    /// Flat badge in the style of shields.io
+    pub fn to_svg(&self) -> String {
+        let label_width = text_width(LABEL);
+        let message_width = text_width(&self.message);
+        let width = label_width + message_width;
+        let label = escape_xml(LABEL);
+        let message = escape_xml(&self.message);
+        format!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{label}: {message}">
+  <title>{label}: {message}</title>
+  <linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient>
+  <clipPath id="r"><rect width="{width}" height="20" rx="3" fill="#fff"/></clipPath>
+  <g clip-path="url(#r)">
+    <rect width="{label_width}" height="20" fill="#555"/>
+    <rect x="{label_width}" width="{message_width}" height="20" fill="{color}"/>
+    <rect width="{width}" height="20" fill="url(#s)"/>
+  </g>
+  <g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">
+    <text x="{label_x}" y="14">{label}</text>
+    <text x="{message_x}" y="14">{message}</text>
+  </g>
+</svg>
+"##,
+            color = self.color,
+            label_x = label_width / 2,
+            message_x = label_width + message_width / 2,
+        )
+    }
+}
+
+/// Approximate width of `text` in 11px Verdana, plus padding.
+fn text_width(text: &str) -> usize {
+    text.chars().count() * 7 + 10
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Writes a badge for the current verification status of a program to `out`, and the
+/// matching shields.io endpoint JSON next to it.
+pub async fn generate_badge(program_id: Pubkey, out: &Path) -> anyhow::Result<()> {
+    let status = fetch_api_status(program_id).await?;
+    let badge = Badge::from_status(&status);
+
+    let svg_out = write_artifact(out, badge.to_svg().as_bytes())?;
+    let json_out = write_artifact(
+        out.with_extension("json"),
+        badge.to_shields_json()?.as_bytes(),
+    )?;
+    println!("{}: {}", LABEL, badge.message);
+    println!("Badge written to {}", svg_out.display());
+    println!("Shields.io endpoint JSON written to {}", json_out.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(is_verified: bool) -> StatusResponse {
+        StatusResponse {
+            is_verified,
+            message: String::new(),
+            on_chain_hash: String::new(),
+            executable_hash: String::new(),
+            repo_url: None,
+            commit: Some("5a7a8fd2b5a4c3a7f1b0e9d8c7b6a5f4e3d2c1b0".to_string()),
+            last_verified_at: Some("2024-10-01T12:34:56Z".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_badge_from_status() {
+        assert_eq!(
+            Badge::from_status(&status(true)),
+            Badge {
+                message: "verified · 5a7a8fd · 2024-10-01".to_string(),
+                color: VERIFIED_COLOR,
+            }
+        );
+        assert_eq!(Badge::from_status(&status(false)).message, "unverified");
+    }
+
+    #[test]
+    fn test_badge_svg_escapes_text() {
+        let badge = Badge {
+            message: "<a & b>".to_string(),
+            color: VERIFIED_COLOR,
+        };
+        let svg = badge.to_svg();
+        assert!(svg.contains("&lt;a &amp; b&gt;"));
+        assert!(!svg.contains("<a & b>"));
+    }
+}
@@ -0,0 +1,190 @@
+use anyhow::anyhow;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{bpf_loader_upgradeable::UpgradeableLoaderState, pubkey::Pubkey};
+use std::{fmt, ops::Range};
+
+use crate::account_data::get_large_account_data;
+use crate::{get_binary_hash, get_file_hash, humanize::format_number};
+
+/// Maximum number of missing or conflicting ranges printed in a report
+const MAX_RANGES_PRINTED: usize = 10;
+
+/// Result of comparing the content of a (possibly partially written) buffer account against
+/// a local build.
+#[derive(Debug, Default, PartialEq)]
+pub struct BufferComparison {
+    pub program_len: usize,
+    /// Bytes already written with the same content as the local build
+    pub matching_bytes: usize,
+    /// Byte ranges not written yet, i.e. still zero in the buffer
+    pub missing: Vec<Range<usize>>,
+    /// Byte ranges written with content different from the local build
+    pub conflicting: Vec<Range<usize>>,
+}
+
+impl BufferComparison {
+    pub fn missing_bytes(&self) -> usize {
+        self.missing.iter().map(|range| range.len()).sum()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty() && self.conflicting.is_empty()
+    }
+
+    pub fn completeness(&self) -> f64 {
+        if self.program_len == 0 {
+            return 100.0;
+        }
+        100.0 * (self.program_len - self.missing_bytes()) as f64 / self.program_len as f64
+    }
+}
+
+fn push_range(ranges: &mut Vec<Range<usize>>, index: usize) {
+    match ranges.last_mut() {
+        Some(range) if range.end == index => range.end += 1,
+        _ => ranges.push(index..index + 1),
+    }
+}
+
+/// Compares buffer program data against a local executable byte by byte. A zero byte in the
+/// buffer where the executable is non-zero is considered not written yet.
+pub fn compare_buffer_data(buffer_data: &[u8], executable: &[u8]) -> BufferComparison {
+    let program_len = executable
+        .iter()
+        .rposition(|&b| b != 0)
+        .map_or(0, |i| i + 1);
+    let mut comparison = BufferComparison {
+        program_len,
+        ..Default::default()
+    };
+
+    for (index, &expected) in executable[..program_len].iter().enumerate() {
+        match buffer_data.get(index).copied() {
+            Some(actual) if actual == expected => comparison.matching_bytes += 1,
+            Some(0) => push_range(&mut comparison.missing, index),
+            Some(_) | None => push_range(&mut comparison.conflicting, index),
+        }
+    }
+
+    // Anything written past the end of the program would change its hash
+    for (index, &actual) in buffer_data.iter().enumerate().skip(program_len) {
+        if actual != 0 {
+            push_range(&mut comparison.conflicting, index);
+        }
+    }
+
+    comparison
+}
+
+fn format_ranges(ranges: &[Range<usize>]) -> String {
+    let mut formatted = ranges
+        .iter()
+        .take(MAX_RANGES_PRINTED)
+        .map(|range| format!("{}..{}", range.start, range.end))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if ranges.len() > MAX_RANGES_PRINTED {
+        formatted.push_str(&format!(" and {} more", ranges.len() - MAX_RANGES_PRINTED));
+    }
+    formatted
+}
+
+impl fmt::Display for BufferComparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Written: {}/{} bytes ({:.2}%)",
+            format_number((self.program_len - self.missing_bytes()) as u64),
+            format_number(self.program_len as u64),
+            self.completeness()
+        )?;
+        writeln!(
+            f,
+            "Matching: {} bytes",
+            format_number(self.matching_bytes as u64)
+        )?;
+        if !self.missing.is_empty() {
+            writeln!(f, "Not written yet: {}", format_ranges(&self.missing))?;
+        }
+        if !self.conflicting.is_empty() {
+            writeln!(
+                f,
+                "Differs from local build: {}",
+                format_ranges(&self.conflicting)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares a buffer, which may still be receiving write transactions, against a local build.
+pub fn compare_buffer(
+    client: &RpcClient,
+    buffer_address: &Pubkey,
+    executable_path: &str,
+) -> anyhow::Result<BufferComparison> {
+    let account_data = get_large_account_data(client, buffer_address)
+        .map_err(|e| anyhow!("Failed to fetch buffer {}: {}", buffer_address, e))?;
+
+    let offset = UpgradeableLoaderState::size_of_buffer_metadata();
+    if account_data.len() < offset {
+        return Err(anyhow!("{} is not a buffer account", buffer_address));
+    }
+    match bincode::deserialize::<UpgradeableLoaderState>(&account_data[..offset]) {
+        Ok(UpgradeableLoaderState::Buffer { authority_address }) => match authority_address {
+            Some(authority) => println!("Buffer authority: {}", authority),
+            None => println!("Buffer authority: none"),
+        },
+        _ => return Err(anyhow!("{} is not a buffer account", buffer_address)),
+    }
+
+    let executable = std::fs::read(executable_path)
+        .map_err(|e| anyhow!("Failed to read {}: {}", executable_path, e))?;
+    let buffer_data = &account_data[offset..];
+    let comparison = compare_buffer_data(buffer_data, &executable);
+
+    println!("Buffer Hash: {}", get_binary_hash(buffer_data.to_vec()));
+    println!("Executable Hash: {}", get_file_hash(executable_path)?);
+    print!("{}", comparison);
+
+    if !comparison.conflicting.is_empty() {
+        println!("Buffer content does not match the local build ❌");
+    } else if comparison.is_complete() {
+        println!("Buffer is fully written and matches the local build ✅");
+    } else {
+        println!(
+            "Buffer is partially written, everything written so far matches the local build ⏳"
+        );
+    }
+    Ok(comparison)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_buffer_data() {
+        let executable = [1, 2, 3, 0, 5, 6, 7, 8];
+
+        // Fully written, with trailing space left in the buffer
+        let comparison = compare_buffer_data(&[1, 2, 3, 0, 5, 6, 7, 8, 0, 0], &executable);
+        assert!(comparison.is_complete());
+        assert_eq!(comparison.matching_bytes, 8);
+
+        // Last write transaction still missing
+        let comparison = compare_buffer_data(&[1, 2, 3, 0, 5, 0, 0, 0, 0, 0], &executable);
+        assert_eq!(comparison.missing, vec![5..8]);
+        assert!(comparison.conflicting.is_empty());
+        assert_eq!(comparison.missing_bytes(), 3);
+
+        // Written with different content and garbage past the end
+        let comparison = compare_buffer_data(&[1, 9, 3, 0, 5, 6, 7, 8, 4], &executable);
+        assert!(comparison.missing.is_empty());
+        assert_eq!(comparison.conflicting, vec![1..2, 8..9]);
+
+        // Buffer too small for the program
+        let comparison = compare_buffer_data(&[1, 2, 3, 0, 5, 6], &executable);
+        assert_eq!(comparison.conflicting, vec![6..8]);
+    }
+}
@@ -0,0 +1,127 @@
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::{process::Stdio, sync::OnceLock};
+
+use crate::docker::docker_command;
+
+/// Where the cache volume is mounted in the build container
+const CACHE_MOUNT: &str = "/build-cache";
+
+/// Docker volume given with `--build-cache`
+static BUILD_CACHE: OnceLock<String> = OnceLock::new();
+
+pub fn set_build_cache(volume: &str) {
+    let _ = BUILD_CACHE.set(volume.to_string());
+}
+
+pub fn get_build_cache() -> Option<&'static str> {
+    BUILD_CACHE.get().map(|volume| volume.as_str())
+}
+
+/// Compile cache a build ran with, recorded in the verification report. Reusing compiled
+/// dependencies does not change the executable, so a cached build verifies the same as a
+/// clean one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuildCacheUse {
+    pub volume: String,
+    /// False if the image has no sccache, in which case the build ran uncached
+    pub used: bool,
+    /// `sccache --show-stats` after the build
+    pub stats: Option<String>,
+}
+
+/// Creates the cache volume if it does not exist yet. Returns whether it was created, in
+/// which case it is only kept once a build has filled it.
+pub fn create_volume(volume: &str) -> anyhow::Result<bool> {
+    let exists = docker_command()
+        .args(["volume", "inspect", volume])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if exists {
+        return Ok(false);
+    }
+    let output = docker_command()
+        .args(["volume", "create", volume])
+        .stdout(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "Failed to create the cache volume {}: {}",
+            volume,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    debug!("Created the cache volume {}", volume);
+    Ok(true)
+}
+
+/// `docker run` arguments mounting the cache volume and pointing sccache at it.
+pub fn docker_run_args(volume: &str) -> Vec<String> {
+    vec![
+        "--mount".to_string(),
+        format!("type=volume,source={},target={}", volume, CACHE_MOUNT),
+        "-e".to_string(),
+        format!("SCCACHE_DIR={}/sccache", CACHE_MOUNT),
+    ]
+}
+
+/// `docker exec` arguments that make cargo compile through sccache, if the image has it.
+pub fn docker_exec_args(container_id: &str, volume: &str) -> Vec<String> {
+    let has_sccache = docker_command()
+        .args(["exec", container_id, "sh", "-c", "command -v sccache"])
+        .stderr(Stdio::null())
+        .output()
+        .is_ok_and(|output| output.status.success());
+    if !has_sccache {
+        warn!(
+            "the image has no sccache, building without the cache volume {}",
+            volume
+        );
+        return vec![];
+    }
+    info!("Compiling through sccache with the cache volume {}", volume);
+    vec!["-e".to_string(), "RUSTC_WRAPPER=sccache".to_string()]
+}
+
+/// Records whether the build went through the cache, with its hit rates if it did.
+pub fn build_cache_use(container_id: &str, volume: &str, used: bool) -> BuildCacheUse {
+    let stats = used
+        .then(|| {
+            docker_command()
+                .args(["exec", container_id, "sccache", "--show-stats"])
+                .stderr(Stdio::null())
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+        })
+        .flatten();
+    if let Some(stats) = &stats {
+        debug!("{}", stats.trim_end());
+    }
+    BuildCacheUse {
+        volume: volume.to_string(),
+        used,
+        stats,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_docker_run_args() {
+        assert_eq!(
+            docker_run_args("phoenix-cache"),
+            vec![
+                "--mount",
+                "type=volume,source=phoenix-cache,target=/build-cache",
+                "-e",
+                "SCCACHE_DIR=/build-cache/sccache",
+            ]
+        );
+    }
+}
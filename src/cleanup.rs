@@ -0,0 +1,202 @@
+use log::{info, warn};
+use std::{
+    path::Path,
+    path::PathBuf,
+    process::Command,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use crate::docker::docker_command;
+use crate::support_bundle::save_container_logs;
+
+/// Something the CLI created that must not outlive an interrupted run.
+#[derive(Debug, Clone, PartialEq)]
+enum Resource {
+    Container(String),
+    Volume(String),
+    TempDir(PathBuf),
+    Process(u32),
+}
+
+/// Resources currently alive, across every code path and thread. Each is removed from the
+/// registry once it has been cleaned up normally.
+static REGISTRY: Mutex<Vec<Resource>> = Mutex::new(Vec::new());
+
+fn track(resource: Resource) {
+    let mut registry = REGISTRY.lock().unwrap();
+    if !registry.contains(&resource) {
+        registry.push(resource);
+    }
+}
+
+fn untrack(resource: &Resource) {
+    REGISTRY
+        .lock()
+        .unwrap()
+        .retain(|tracked| tracked != resource);
+}
+
+/// Set once an interrupt signal is received, for every thread to stop at its next step.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Marks the run as interrupted, from the signal handler of the binary.
+pub fn interrupt() {
+    INTERRUPTED.store(true, Ordering::Relaxed);
+}
+
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+/// Returned once the run is interrupted, so that it stops before its next step. The
+/// registered resources are cleaned up by the caller handling the interrupt.
+#[derive(Debug)]
+pub struct Interrupted;
+
+impl std::fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Interrupted")
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+/// Fails with [`Interrupted`] if an interrupt signal was received.
+pub fn check_interrupted() -> anyhow::Result<()> {
+    if interrupted() {
+        return Err(Interrupted.into());
+    }
+    Ok(())
+}
+
+/// Registers a docker container to kill if the run is interrupted.
+pub fn track_container(container_id: &str) {
+    track(Resource::Container(container_id.to_string()));
+}
+
+/// Registers a docker volume created by the run, to remove if the run is interrupted.
+pub fn track_volume(volume: &str) {
+    track(Resource::Volume(volume.to_string()));
+}
+
+/// Stops registration of a docker volume that is kept on purpose.
+pub fn untrack_volume(volume: &str) {
+    untrack(&Resource::Volume(volume.to_string()));
+}
+
+/// Registers a temporary file or directory to remove if the run is interrupted.
+pub fn track_temp_dir(path: &Path) {
+    track(Resource::TempDir(path.to_path_buf()));
+}
+
+/// Registers a child process, such as a local validator, to kill if the run is interrupted.
+pub fn track_process(pid: u32) {
+    track(Resource::Process(pid));
+}
+
+pub fn untrack_process(pid: u32) {
+    untrack(&Resource::Process(pid));
+}
+
+/// Stops registration of a temporary directory that is kept on purpose.
+pub fn untrack_temp_dir(path: &Path) {
+    untrack(&Resource::TempDir(path.to_path_buf()));
+}
+
+/// Kills a container and removes it from the registry.
+pub fn kill_container(container_id: &str) -> std::io::Result<std::process::Output> {
+    save_container_logs(container_id);
+    let output = docker_command().args(["kill", container_id]).output();
+    untrack(&Resource::Container(container_id.to_string()));
+    output
+}
+
+/// Removes a temporary file or directory and removes it from the registry.
+pub fn remove_dir(path: &Path) -> std::io::Result<()> {
+    let output = Command::new("rm").arg("-rf").arg(path).output();
+    untrack(&Resource::TempDir(path.to_path_buf()));
+    output.map(|_| ())
+}
+
+/// Cleans up every registered resource: processes first, then containers, then the
+/// volumes and directories they may have been using.
+pub fn cleanup_all() {
+    let mut resources = std::mem::take(&mut *REGISTRY.lock().unwrap());
+    resources.sort_by_key(|resource| match resource {
+        Resource::Process(_) => 0,
+        Resource::Container(_) => 1,
+        Resource::Volume(_) => 2,
+        Resource::TempDir(_) => 3,
+    });
+    for resource in resources {
+        match resource {
+            Resource::Process(pid) => {
+                if Command::new("kill").arg(pid.to_string()).output().is_err() {
+                    warn!("Failed to stop process {}", pid);
+                } else {
+                    info!("Stopped process {}", pid);
+                }
+            }
+            Resource::Container(container_id) => {
+                save_container_logs(&container_id);
+                if docker_command()
+                    .args(["kill", &container_id])
+                    .output()
+                    .is_err()
+                {
+                    warn!("Failed to close docker container");
+                } else {
+                    info!("Stopped container {}", container_id)
+                }
+            }
+            Resource::Volume(volume) => {
+                if docker_command()
+                    .args(["volume", "rm", "--force", &volume])
+                    .output()
+                    .is_err()
+                {
+                    warn!("Failed to remove docker volume {}", volume);
+                } else {
+                    info!("Removed docker volume {}", volume);
+                }
+            }
+            Resource::TempDir(path) => {
+                if Command::new("rm").arg("-rf").arg(&path).output().is_err() {
+                    warn!("Failed to remove temporary directory");
+                } else {
+                    info!("Removed temporary directory {}", path.display());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("checkout");
+        std::fs::create_dir_all(dir.join("repo")).unwrap();
+        track_temp_dir(&dir);
+        track_temp_dir(&dir);
+        let tracked = |path: &Path| {
+            REGISTRY
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|resource| **resource == Resource::TempDir(path.to_path_buf()))
+                .count()
+        };
+        assert_eq!(tracked(&dir), 1);
+
+        remove_dir(&dir).unwrap();
+        assert!(!dir.exists());
+        assert_eq!(tracked(&dir), 0);
+    }
+}
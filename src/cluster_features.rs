@@ -0,0 +1,164 @@
+use anyhow::anyhow;
+use log::warn;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{feature, feature_set, pubkey::Pubkey};
+
+use crate::elf::get_syscalls;
+use crate::secrets::redact_rpc_url;
+
+/// Syscalls that only work once the feature gate enabling them is active on the cluster
+const FEATURE_GATED_SYSCALLS: &[(&str, Pubkey)] = &[
+    ("sol_blake3", feature_set::blake3_syscall_enabled::ID),
+    (
+        "sol_curve_validate_point",
+        feature_set::curve25519_syscall_enabled::ID,
+    ),
+    (
+        "sol_curve_group_op",
+        feature_set::curve25519_syscall_enabled::ID,
+    ),
+    (
+        "sol_curve_multiscalar_mul",
+        feature_set::curve25519_syscall_enabled::ID,
+    ),
+    (
+        "sol_curve_pairing_map",
+        feature_set::curve25519_syscall_enabled::ID,
+    ),
+    (
+        "sol_alt_bn128_group_op",
+        feature_set::enable_alt_bn128_syscall::ID,
+    ),
+    (
+        "sol_alt_bn128_compression",
+        feature_set::enable_alt_bn128_compression_syscall::ID,
+    ),
+    (
+        "sol_big_mod_exp",
+        feature_set::enable_big_mod_exp_syscall::ID,
+    ),
+    ("sol_poseidon", feature_set::enable_poseidon_syscall::ID),
+    (
+        "sol_remaining_compute_units",
+        feature_set::remaining_compute_units_syscall_enabled::ID,
+    ),
+    (
+        "sol_get_last_restart_slot",
+        feature_set::last_restart_slot_sysvar::ID,
+    ),
+    (
+        "sol_get_epoch_rewards_sysvar",
+        feature_set::enable_partitioned_epoch_reward::ID,
+    ),
+];
+
+#[derive(Debug)]
+pub struct GatedSyscall {
+    pub syscall: String,
+    pub feature: Pubkey,
+    /// Slot the feature was activated at, `None` if it is not active on the cluster
+    pub activated_at: Option<u64>,
+}
+
+fn gated_syscalls(syscalls: &[String]) -> Vec<(String, Pubkey)> {
+    syscalls
+        .iter()
+        .filter_map(|syscall| {
+            FEATURE_GATED_SYSCALLS
+                .iter()
+                .find(|(name, _)| name == syscall)
+                .map(|(_, feature)| (syscall.clone(), *feature))
+        })
+        .collect()
+}
+
+/// Looks up the activation of the feature gates of every syscall used by the program.
+pub fn check_cluster_features(
+    client: &RpcClient,
+    program: &[u8],
+) -> anyhow::Result<Vec<GatedSyscall>> {
+    let gated = gated_syscalls(&get_syscalls(program)?);
+    let features = gated
+        .iter()
+        .map(|(_, feature)| *feature)
+        .collect::<Vec<_>>();
+    let accounts = client
+        .get_multiple_accounts(&features)
+        .map_err(|e| anyhow!("Failed to fetch feature accounts: {}", e))?;
+
+    Ok(gated
+        .into_iter()
+        .zip(accounts)
+        .map(|((syscall, feature), account)| GatedSyscall {
+            syscall,
+            feature,
+            activated_at: account
+                .as_ref()
+                .and_then(feature::from_account)
+                .and_then(|feature| feature.activated_at),
+        })
+        .collect())
+}
+
+/// Prints the feature gated syscalls used by the program and returns whether all of them are
+/// active on the cluster.
+pub fn print_cluster_features(client: &RpcClient, program: &[u8]) -> anyhow::Result<bool> {
+    let gated = check_cluster_features(client, program)?;
+    if gated.is_empty() {
+        println!("Program does not use feature gated syscalls");
+        return Ok(true);
+    }
+
+    for syscall in &gated {
+        match syscall.activated_at {
+            Some(slot) => println!(
+                "✅ {} (feature {} active since slot {})",
+                syscall.syscall, syscall.feature, slot
+            ),
+            None => println!(
+                "❌ {} (feature {} is not active on {})",
+                syscall.syscall,
+                syscall.feature,
+                redact_rpc_url(&client.url())
+            ),
+        }
+    }
+
+    let all_active = gated.iter().all(|syscall| syscall.activated_at.is_some());
+    if !all_active {
+        warn!("the program uses syscalls that are not enabled on this cluster yet and will fail when calling them");
+    }
+    Ok(all_active)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gated_syscalls() {
+        let syscalls = vec![
+            "sol_log_".to_string(),
+            "sol_poseidon".to_string(),
+            "sol_curve_group_op".to_string(),
+            "sol_curve_pairing_map".to_string(),
+        ];
+        assert_eq!(
+            gated_syscalls(&syscalls),
+            vec![
+                (
+                    "sol_poseidon".to_string(),
+                    feature_set::enable_poseidon_syscall::ID
+                ),
+                (
+                    "sol_curve_group_op".to_string(),
+                    feature_set::curve25519_syscall_enabled::ID
+                ),
+                (
+                    "sol_curve_pairing_map".to_string(),
+                    feature_set::curve25519_syscall_enabled::ID
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,162 @@
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::clock::{Slot, UnixTimestamp};
+use std::time::Duration;
+
+use crate::humanize::{format_age, format_duration};
+
+/// Target slot duration, used when the cluster reports no performance samples
+const DEFAULT_SLOT_SECONDS: f64 = 0.4;
+
+/// Number of recent performance samples (one per minute) averaged to estimate slot time
+const PERFORMANCE_SAMPLES: usize = 60;
+
+/// Wall-clock time of a slot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SlotTime {
+    pub timestamp: UnixTimestamp,
+    /// The block is no longer available from the RPC node, so the time was extrapolated from
+    /// the average slot duration
+    pub estimated: bool,
+}
+
+impl SlotTime {
+    /// The time and how long before `now` it was, such as `2024-10-01T00:00:00+00:00, 3d 4h ago`.
+    pub fn describe(&self, now: UnixTimestamp) -> String {
+        match chrono::DateTime::from_timestamp(self.timestamp, 0) {
+            Some(time) if self.estimated => format!(
+                "~{} (estimated), ~{}",
+                time.to_rfc3339(),
+                format_age(self.timestamp, now)
+            ),
+            Some(time) => format!("{}, {}", time.to_rfc3339(), format_age(self.timestamp, now)),
+            None => "unknown".to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for SlotTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.describe(chrono::Utc::now().timestamp()))
+    }
+}
+
+/// Converts slots to timestamps. Slots whose blocks have been pruned by the RPC node are
+/// extrapolated from a recent slot with a known time.
+pub struct ClusterClock<'a> {
+    client: &'a RpcClient,
+    reference: Option<(Slot, UnixTimestamp)>,
+    seconds_per_slot: f64,
+}
+
+impl<'a> ClusterClock<'a> {
+    pub fn new(client: &'a RpcClient) -> ClusterClock<'a> {
+        let reference = client
+            .get_slot()
+            .ok()
+            .and_then(|slot| Some((slot, client.get_block_time(slot).ok()?)));
+        let seconds_per_slot = client
+            .get_recent_performance_samples(Some(PERFORMANCE_SAMPLES))
+            .ok()
+            .and_then(|samples| {
+                average_slot_seconds(
+                    samples
+                        .iter()
+                        .map(|sample| (sample.num_slots, sample.sample_period_secs)),
+                )
+            })
+            .unwrap_or(DEFAULT_SLOT_SECONDS);
+        ClusterClock {
+            client,
+            reference,
+            seconds_per_slot,
+        }
+    }
+
+    /// Time of `slot`, from its block if the node has it, estimated otherwise.
+    pub fn slot_time(&self, slot: Slot) -> Option<SlotTime> {
+        if let Ok(timestamp) = self.client.get_block_time(slot) {
+            return Some(SlotTime {
+                timestamp,
+                estimated: false,
+            });
+        }
+        let (reference_slot, reference_time) = self.reference?;
+        Some(SlotTime {
+            timestamp: estimate_timestamp(
+                reference_slot,
+                reference_time,
+                self.seconds_per_slot,
+                slot,
+            ),
+            estimated: true,
+        })
+    }
+}
+
+fn average_slot_seconds(samples: impl Iterator<Item = (u64, u16)>) -> Option<f64> {
+    let (slots, seconds) = samples.fold((0u64, 0u64), |(slots, seconds), (n, period)| {
+        (slots + n, seconds + period as u64)
+    });
+    (slots > 0).then(|| seconds as f64 / slots as f64)
+}
+
+fn estimate_timestamp(
+    reference_slot: Slot,
+    reference_time: UnixTimestamp,
+    seconds_per_slot: f64,
+    slot: Slot,
+) -> UnixTimestamp {
+    let slots = slot as f64 - reference_slot as f64;
+    reference_time + (slots * seconds_per_slot).round() as UnixTimestamp
+}
+
+/// Describes how long after a deployment its verification was uploaded.
+pub fn describe_verification_delay(deployed: SlotTime, verified: UnixTimestamp) -> String {
+    let delay = format_duration(Duration::from_secs(deployed.timestamp.abs_diff(verified)));
+    let approximate = if deployed.estimated { "~" } else { "" };
+    if verified < deployed.timestamp {
+        format!("verified {}{} before deployment", approximate, delay)
+    } else {
+        format!("verified {}{} after deployment", approximate, delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_timestamp() {
+        let seconds_per_slot = average_slot_seconds([(150, 60), (170, 60)].into_iter()).unwrap();
+        assert_eq!(seconds_per_slot, 0.375);
+        assert_eq!(
+            estimate_timestamp(1_000_000, 1_700_000_000, seconds_per_slot, 1_000_000 - 800),
+            1_700_000_000 - 300
+        );
+        assert_eq!(average_slot_seconds(std::iter::empty()), None);
+    }
+
+    #[test]
+    fn test_describe_verification_delay() {
+        let deployed = SlotTime {
+            timestamp: 1_700_000_000,
+            estimated: false,
+        };
+        assert_eq!(
+            describe_verification_delay(deployed, 1_700_000_000 + 3 * 86_400 + 43_200),
+            "verified 3d 12h after deployment"
+        );
+        let estimated = SlotTime {
+            estimated: true,
+            ..deployed
+        };
+        assert_eq!(
+            describe_verification_delay(estimated, 1_700_000_000 - 86_400),
+            "verified ~1d before deployment"
+        );
+        assert_eq!(
+            estimated.describe(1_700_000_000 + 7_200),
+            "~2023-11-14T22:13:20+00:00 (estimated), ~2h ago"
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use solana_client::rpc_client::RpcClient;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use crate::cleanup::check_interrupted;
+use crate::{
+    cleanup::remove_dir,
+    clone_repo_and_checkout,
+    docker::check_docker_available,
+    get_basename, get_commit_hash,
+    hash::get_raw_file_sha256,
+    paths::list_source_files,
+    verify_all::{verify_program, ManifestProgram, ProgramVerification},
+};
+
+/// Files listed per category in the printed summary, the rest are counted
+const LISTED_FILES: usize = 20;
+
+/// Source files of two checkouts, compared by content.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceDiff {
+    pub only_a: Vec<PathBuf>,
+    pub only_b: Vec<PathBuf>,
+    pub modified: Vec<PathBuf>,
+    pub identical: usize,
+}
+
+impl SourceDiff {
+    /// Compares the files both checkouts would build from, the files git tracks.
+    pub fn new(root_a: &Path, root_b: &Path) -> anyhow::Result<SourceDiff> {
+        let hashes = |root: &Path| -> anyhow::Result<BTreeMap<PathBuf, String>> {
+            list_source_files(root)?
+                .into_iter()
+                .map(|file| Ok((file.clone(), get_raw_file_sha256(root.join(&file))?)))
+                .collect()
+        };
+        Ok(SourceDiff::from_hashes(&hashes(root_a)?, &hashes(root_b)?))
+    }
+
+    fn from_hashes(a: &BTreeMap<PathBuf, String>, b: &BTreeMap<PathBuf, String>) -> SourceDiff {
+        let mut diff = SourceDiff::default();
+        for (file, hash) in a {
+            match b.get(file) {
+                Some(other) if other == hash => diff.identical += 1,
+                Some(_) => diff.modified.push(file.clone()),
+                None => diff.only_a.push(file.clone()),
+            }
+        }
+        diff.only_b = b
+            .keys()
+            .filter(|file| !a.contains_key(*file))
+            .cloned()
+            .collect();
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.only_a.is_empty() && self.only_b.is_empty() && self.modified.is_empty()
+    }
+}
+
+/// One side of the comparison: a repository at a commit and what its build produced.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepoBuild {
+    pub repo_url: String,
+    pub commit: String,
+    pub verification: ProgramVerification,
+}
+
+/// Which of two repositories, typically an upstream protocol and a fork of it, reproduces
+/// a deployed program, and how their sources differ.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepoComparison {
+    pub a: RepoBuild,
+    pub b: RepoBuild,
+    /// None if either repository could not be cloned
+    pub diff: Option<SourceDiff>,
+}
+
+impl RepoComparison {
+    pub fn verdict(&self) -> &'static str {
+        match (self.a.verification.matches(), self.b.verification.matches()) {
+            (true, true) => "Both repositories reproduce the on-chain program",
+            (true, false) => "Only repo A reproduces the on-chain program",
+            (false, true) => "Only repo B reproduces the on-chain program",
+            (false, false) => "Neither repository reproduces the on-chain program",
+        }
+    }
+}
+
+fn print_files(label: &str, files: &[PathBuf]) {
+    if files.is_empty() {
+        return;
+    }
+    println!("{} ({}):", label, files.len());
+    for file in files.iter().take(LISTED_FILES) {
+        println!("  {}", file.display());
+    }
+    if files.len() > LISTED_FILES {
+        println!("  ... and {} more", files.len() - LISTED_FILES);
+    }
+}
+
+pub fn print_comparison(comparison: &RepoComparison) {
+    for (label, build) in [("A", &comparison.a), ("B", &comparison.b)] {
+        let result = match &build.verification.hashes {
+            _ if build.verification.matches() => "match".to_string(),
+            Ok((build_hash, program_hash)) => {
+                format!("mismatch, built {} (on chain {})", build_hash, program_hash)
+            }
+            Err(err) => format!("error, {}", err.lines().next().unwrap_or_default()),
+        };
+        println!(
+            "Repo {}: {} at {}: {}",
+            label, build.repo_url, build.commit, result
+        );
+    }
+    println!("{}", comparison.verdict());
+
+    match &comparison.diff {
+        Some(diff) if diff.is_empty() => {
+            println!("Sources are identical ({} files)", diff.identical)
+        }
+        Some(diff) => {
+            println!(
+                "Sources differ: {} modified, {} only in A, {} only in B, {} identical",
+                diff.modified.len(),
+                diff.only_a.len(),
+                diff.only_b.len(),
+                diff.identical
+            );
+            print_files("Modified", &diff.modified);
+            print_files("Only in A", &diff.only_a);
+            print_files("Only in B", &diff.only_b);
+        }
+        None => println!("Sources not compared, a repository could not be cloned"),
+    }
+}
+
+/// Builds the program from both repositories, compares each build with the deployed
+/// program and diffs their sources. Nothing is uploaded.
+#[allow(clippy::too_many_arguments)]
+pub async fn compare_repos(
+    program_id: &str,
+    repo_a: (&str, Option<&str>),
+    repo_b: (&str, Option<&str>),
+    program: &ManifestProgram,
+    connection: &RpcClient,
+    current_dir: bool,
+) -> anyhow::Result<RepoComparison> {
+    check_docker_available()?;
+
+    let mut builds = vec![];
+    let mut checkouts = vec![];
+    for (repo_url, commit) in [repo_a, repo_b] {
+        check_interrupted()?;
+        let commit = get_commit_hash(commit, repo_url).await?;
+        let verification = match clone_repo_and_checkout(
+            repo_url,
+            current_dir,
+            &get_basename(repo_url)?,
+            Some(commit.clone()),
+        ) {
+            Ok((root, verify_dir)) => {
+                let verification = verify_program(&root, program_id, program, connection);
+                checkouts.push((root, verify_dir));
+                verification
+            }
+            Err(err) => ProgramVerification {
+                program_id: program_id.to_string(),
+                library_name: program.library_name.clone(),
+                hashes: Err(format!("{:#}", err)),
+            },
+        };
+        builds.push(RepoBuild {
+            repo_url: repo_url.to_string(),
+            commit,
+            verification,
+        });
+    }
+
+    let diff = match checkouts.as_slice() {
+        [(root_a, _), (root_b, _)] => Some(SourceDiff::new(root_a, root_b)),
+        _ => None,
+    };
+    for (_, verify_dir) in &checkouts {
+        remove_dir(verify_dir)?;
+    }
+    let diff = diff.transpose()?;
+
+    let b = builds.pop().unwrap();
+    let a = builds.pop().unwrap();
+    Ok(RepoComparison { a, b, diff })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_diff() {
+        let files = |entries: &[(&str, &str)]| {
+            entries
+                .iter()
+                .map(|(file, hash)| (PathBuf::from(file), hash.to_string()))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let diff = SourceDiff::from_hashes(
+            &files(&[
+                ("Cargo.lock", "1"),
+                ("programs/amm/src/lib.rs", "2"),
+                ("README.md", "3"),
+            ]),
+            &files(&[
+                ("Cargo.lock", "1"),
+                ("programs/amm/src/lib.rs", "4"),
+                ("programs/amm/src/fee.rs", "5"),
+            ]),
+        );
+        assert_eq!(diff.identical, 1);
+        assert_eq!(
+            diff.modified,
+            vec![PathBuf::from("programs/amm/src/lib.rs")]
+        );
+        assert_eq!(diff.only_a, vec![PathBuf::from("README.md")]);
+        assert_eq!(diff.only_b, vec![PathBuf::from("programs/amm/src/fee.rs")]);
+        assert!(!diff.is_empty());
+
+        let build = |hashes: Result<(String, String), String>| RepoBuild {
+            repo_url: "https://github.com/acme/amm".to_string(),
+            commit: "abc".to_string(),
+            verification: ProgramVerification {
+                program_id: "AMM".to_string(),
+                library_name: None,
+                hashes,
+            },
+        };
+        let comparison = RepoComparison {
+            a: build(Ok(("h".to_string(), "h".to_string()))),
+            b: build(Err("build failed".to_string())),
+            diff: Some(diff),
+        };
+        assert_eq!(
+            comparison.verdict(),
+            "Only repo A reproduces the on-chain program"
+        );
+    }
+}
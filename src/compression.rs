@@ -0,0 +1,191 @@
+use anyhow::anyhow;
+use sha2::{Digest, Sha256};
+use std::{
+    fs::File,
+    io::{BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::encryption::{encrypted_path, get_encryption_recipients, is_encrypted, Encryptor};
+
+/// zstd's default level, which keeps compressing multi-GB bundles fast
+const ZSTD_LEVEL: i32 = 3;
+
+/// Magic of a zstd skippable frame, which decoders such as the `zstd` CLI ignore
+const SKIPPABLE_FRAME_MAGIC: u32 = 0x184D2A5B;
+
+/// Tag of the integrity footer, followed by the SHA-256 of the uncompressed contents
+const FOOTER_TAG: &[u8] = b"svb-sha256";
+
+const FOOTER_LEN: usize = 8 + FOOTER_TAG.len() + 32;
+
+/// Artifacts whose path ends in `.zst` are stored zstd-compressed.
+pub fn is_compressed(path: impl AsRef<Path>) -> bool {
+    path.as_ref()
+        .extension()
+        .is_some_and(|extension| extension == "zst")
+}
+
+/// Compresses what is written to it into `inner`, followed by an integrity footer holding
+/// the SHA-256 of the uncompressed contents, so that a truncated or corrupted transfer is
+/// detected instead of yielding a partial artifact. The footer is a skippable frame, which
+/// keeps the output readable with `zstd -d`.
+struct Compressor<W: Write> {
+    encoder: zstd::Encoder<'static, W>,
+    hasher: Sha256,
+}
+
+impl<W: Write> Compressor<W> {
+    fn new(inner: W) -> anyhow::Result<Compressor<W>> {
+        Ok(Compressor {
+            encoder: zstd::Encoder::new(inner, ZSTD_LEVEL)?,
+            hasher: Sha256::new(),
+        })
+    }
+
+    fn finish(self) -> anyhow::Result<W> {
+        let mut inner = self.encoder.finish()?;
+        inner.write_all(&SKIPPABLE_FRAME_MAGIC.to_le_bytes())?;
+        inner.write_all(&((FOOTER_TAG.len() + 32) as u32).to_le_bytes())?;
+        inner.write_all(FOOTER_TAG)?;
+        inner.write_all(&self.hasher.finalize())?;
+        Ok(inner)
+    }
+}
+
+impl<W: Write> Write for Compressor<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.encoder.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.encoder.flush()
+    }
+}
+
+/// Compresses `contents` with the integrity footer of the `.zst` artifacts.
+pub fn compress(contents: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut compressor = Compressor::new(vec![])?;
+    compressor.write_all(contents)?;
+    compressor.finish()
+}
+
+/// Decompresses the output of `compress`, checking the integrity footer if there is one.
+pub fn decompress(compressed: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let contents = zstd::decode_all(compressed)
+        .map_err(|e| anyhow!("Failed to decompress zstd data: {}", e))?;
+
+    let footer = compressed
+        .len()
+        .checked_sub(FOOTER_LEN)
+        .map(|start| &compressed[start..])
+        .filter(|footer| {
+            footer[..4] == SKIPPABLE_FRAME_MAGIC.to_le_bytes()
+                && &footer[8..8 + FOOTER_TAG.len()] == FOOTER_TAG
+        });
+    if let Some(footer) = footer {
+        if footer[8 + FOOTER_TAG.len()..] != Sha256::digest(&contents)[..] {
+            return Err(anyhow!(
+                "Integrity check failed: the decompressed contents do not match their recorded SHA-256"
+            ));
+        }
+    }
+    Ok(contents)
+}
+
+/// Copies `contents` into `output`, compressing them if `path` ends in `.zst`.
+fn copy_artifact(
+    path: &Path,
+    contents: &mut impl Read,
+    output: &mut impl Write,
+) -> anyhow::Result<()> {
+    if is_compressed(path) {
+        let mut compressor = Compressor::new(output)?;
+        std::io::copy(contents, &mut compressor)?;
+        compressor.finish()?.flush()?;
+    } else {
+        std::io::copy(contents, output)?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+/// Writes an artifact, compressing it if `path` ends in `.zst`. With `--encrypt-to` it is
+/// then encrypted and written to `path` with an `.age` extension. Returns the path written.
+pub fn write_artifact(path: impl AsRef<Path>, contents: &[u8]) -> anyhow::Result<PathBuf> {
+    write_artifact_from(path, contents)
+}
+
+/// `write_artifact` for contents read from `contents`, which are streamed to the file rather
+/// than held in memory.
+pub fn write_artifact_from(
+    path: impl AsRef<Path>,
+    mut contents: impl Read,
+) -> anyhow::Result<PathBuf> {
+    let path = path.as_ref();
+    let recipients = get_encryption_recipients();
+    if recipients.is_empty() {
+        let file =
+            File::create(path).map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+        copy_artifact(path, &mut contents, &mut BufWriter::new(file))
+            .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+        return Ok(path.to_path_buf());
+    }
+
+    let encrypted = encrypted_path(path);
+    let mut encryptor = Encryptor::create(recipients, &encrypted)?;
+    let copied = copy_artifact(path, &mut contents, &mut encryptor);
+    // An error of age explains why the copy failed
+    encryptor.finish()?;
+    copied.map_err(|e| anyhow!("Failed to write {}: {}", encrypted.display(), e))?;
+    Ok(encrypted)
+}
+
+/// Reads an artifact written by `write_artifact`.
+pub fn read_artifact(path: impl AsRef<Path>) -> anyhow::Result<Vec<u8>> {
+    let path = path.as_ref();
+    if is_encrypted(path) {
+        return Err(anyhow!(
+            "{} is encrypted, decrypt it first with `age -d -i <identity> -o <output> {}`",
+            path.display(),
+            path.display()
+        ));
+    }
+    let contents =
+        std::fs::read(path).map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+    if is_compressed(path) {
+        decompress(&contents).map_err(|e| anyhow!("{}: {}", path.display(), e))
+    } else {
+        Ok(contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress_round_trip() {
+        let contents =
+            b"{\"program_id\": \"PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY\"}".repeat(100);
+        let compressed = compress(&contents).unwrap();
+        assert!(compressed.len() < contents.len());
+        assert_eq!(decompress(&compressed).unwrap(), contents);
+
+        // Plain zstd decoders skip the footer
+        assert_eq!(zstd::decode_all(&compressed[..]).unwrap(), contents);
+    }
+
+    #[test]
+    fn test_integrity_footer() {
+        let compressed = compress(b"verified").unwrap();
+        let mut corrupted = compressed.clone();
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 1;
+        assert!(decompress(&corrupted).is_err());
+
+        assert!(decompress(&compressed[..compressed.len() / 2]).is_err());
+    }
+}
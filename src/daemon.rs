@@ -0,0 +1,398 @@
+use anyhow::anyhow;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use signal_hook::consts::SIGHUP;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::Path,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::api::sleep_unless_interrupted;
+use crate::cleanup::{check_interrupted, interrupted, remove_dir};
+use crate::git_provider::get_commit_hash_from_remote;
+use crate::{
+    build_and_verify_repo, build_args, clone_repo_and_checkout, get_basename, get_program_hash,
+};
+
+const DEFAULT_INTERVAL_SECS: u64 = 3600;
+const DEFAULT_LISTEN: &str = "127.0.0.1:8787";
+
+/// Time a client has to send its request and read the response
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Program the daemon keeps verified.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchedProgram {
+    pub program_id: String,
+    pub repo_url: String,
+    /// Commit to verify. Defaults to the tip of the default branch at each check
+    #[serde(default)]
+    pub commit: Option<String>,
+    #[serde(default)]
+    pub mount_path: String,
+    #[serde(default)]
+    pub library_name: Option<String>,
+    #[serde(default)]
+    pub base_image: Option<String>,
+    #[serde(default)]
+    pub bpf: bool,
+    #[serde(default)]
+    pub cargo_args: Vec<String>,
+    /// Overrides the interval of the config for this program
+    #[serde(default)]
+    pub interval_secs: Option<u64>,
+}
+
+/// Config file of `solana-verify daemon`, reloaded on SIGHUP.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct DaemonConfig {
+    /// Seconds between two checks of a program
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    /// Address the status API listens on
+    #[serde(default = "default_listen")]
+    pub listen: String,
+    pub programs: Vec<WatchedProgram>,
+}
+
+fn default_interval_secs() -> u64 {
+    DEFAULT_INTERVAL_SECS
+}
+
+fn default_listen() -> String {
+    DEFAULT_LISTEN.to_string()
+}
+
+impl DaemonConfig {
+    pub fn load(path: &Path) -> anyhow::Result<DaemonConfig> {
+        let config: DaemonConfig = serde_json::from_str(
+            &std::fs::read_to_string(path)
+                .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?,
+        )
+        .map_err(|e| anyhow!("Invalid daemon config {}: {}", path.display(), e))?;
+        for program in &config.programs {
+            Pubkey::from_str(&program.program_id)
+                .map_err(|e| anyhow!("Invalid program id {}: {}", program.program_id, e))?;
+        }
+        Ok(config)
+    }
+}
+
+/// Last verification of a watched program, as served by the status API.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProgramStatus {
+    pub program_id: String,
+    pub repo_url: String,
+    pub commit: Option<String>,
+    pub on_chain_hash: Option<String>,
+    pub executable_hash: Option<String>,
+    pub is_verified: bool,
+    pub last_checked_at: Option<String>,
+    pub error: Option<String>,
+}
+
+type Statuses = Arc<Mutex<BTreeMap<String, ProgramStatus>>>;
+
+/// Response to a request of the status API: `GET /status` lists every program and
+/// `GET /status/<program-id>` returns one.
+fn route(path: &str, statuses: &BTreeMap<String, ProgramStatus>) -> (u16, String) {
+    let not_found = || (404, r#"{"error":"not found"}"#.to_string());
+    match path.trim_end_matches('/') {
+        "/status" => (
+            200,
+            serde_json::to_string(&statuses.values().collect::<Vec<_>>()).unwrap_or_default(),
+        ),
+        path => match path
+            .strip_prefix("/status/")
+            .and_then(|program_id| statuses.get(program_id))
+        {
+            Some(status) => (200, serde_json::to_string(status).unwrap_or_default()),
+            None => not_found(),
+        },
+    }
+}
+
+/// Bounds the time a connection can hold its handler, so an idle client cannot block it.
+pub fn set_connection_timeouts(stream: &TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(CONNECTION_TIMEOUT))?;
+    stream.set_write_timeout(Some(CONNECTION_TIMEOUT))
+}
+
+fn handle_connection(mut stream: TcpStream, statuses: &Statuses) -> anyhow::Result<()> {
+    set_connection_timeouts(&stream)?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let (code, body) = match request_line.split_whitespace().collect::<Vec<_>>()[..] {
+        ["GET", path, ..] => route(path, &statuses.lock().unwrap()),
+        _ => (405, r#"{"error":"method not allowed"}"#.to_string()),
+    };
+    write_json_response(&mut stream, code, &body)
+}
+
+/// Writes a JSON response and closes the connection.
+pub fn write_json_response(stream: &mut TcpStream, code: u16, body: &str) -> anyhow::Result<()> {
+    let reason = match code {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        _ => "Method Not Allowed",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        code,
+        reason,
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+fn serve_status(listen: &str, statuses: Statuses) -> anyhow::Result<()> {
+    let listener =
+        TcpListener::bind(listen).map_err(|e| anyhow!("Failed to listen on {}: {}", listen, e))?;
+    info!("Status API listening on http://{}/status", listen);
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let statuses = statuses.clone();
+            std::thread::spawn(move || {
+                if let Err(err) = handle_connection(stream, &statuses) {
+                    error!("Status API request failed: {}", err);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+/// Builds the program's source and compares it with the on-chain program. The build is
+/// skipped if neither the commit nor the on-chain program changed since the last check.
+pub fn check_program(
+    connection: &RpcClient,
+    program: &WatchedProgram,
+    last: Option<&ProgramStatus>,
+) -> anyhow::Result<ProgramStatus> {
+    let program_id = Pubkey::from_str(&program.program_id)?;
+    let on_chain_hash = get_program_hash(connection, program_id)?;
+    let commit = match &program.commit {
+        Some(commit) => commit.clone(),
+        None => get_commit_hash_from_remote(&program.repo_url)?,
+    };
+
+    let mut status = ProgramStatus {
+        program_id: program.program_id.clone(),
+        repo_url: program.repo_url.clone(),
+        commit: Some(commit.clone()),
+        on_chain_hash: Some(on_chain_hash.clone()),
+        last_checked_at: Some(chrono::Utc::now().to_rfc3339()),
+        ..Default::default()
+    };
+    if let Some(last) = last.filter(|last| {
+        last.error.is_none()
+            && last.commit == status.commit
+            && last.on_chain_hash == status.on_chain_hash
+            && last.repo_url == status.repo_url
+    }) {
+        info!("{}: unchanged since the last check", program.program_id);
+        status.executable_hash = last.executable_hash.clone();
+        status.is_verified = last.is_verified;
+        return Ok(status);
+    }
+
+    let (repo_path, verify_dir) = clone_repo_and_checkout(
+        &program.repo_url,
+        false,
+        &get_basename(&program.repo_url)?,
+        Some(commit),
+    )?;
+    let result = build_args(
+        &program.mount_path,
+        program.library_name.clone(),
+        &repo_path,
+        program.base_image.clone(),
+        program.bpf,
+        program.cargo_args.clone(),
+    )
+    .and_then(|(_, mount_path, library_name)| {
+        build_and_verify_repo(
+            mount_path,
+            program.base_image.clone(),
+            program.bpf,
+            library_name,
+            connection,
+            program_id,
+            program.cargo_args.clone(),
+        )
+    });
+    remove_dir(&verify_dir)?;
+
+    let (build_hash, _, _) = result?;
+    status.is_verified = build_hash == on_chain_hash;
+    status.executable_hash = Some(build_hash);
+    Ok(status)
+}
+
+/// Re-verifies the programs of the config on a schedule until interrupted, serving their
+/// status over HTTP. The config is reloaded on SIGHUP.
+pub async fn run_daemon(connection: &RpcClient, config_path: &Path) -> anyhow::Result<()> {
+    let mut config = DaemonConfig::load(config_path)?;
+    let reload = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(SIGHUP, Arc::clone(&reload))?;
+
+    let statuses: Statuses = Arc::new(Mutex::new(BTreeMap::new()));
+    serve_status(&config.listen, Arc::clone(&statuses))?;
+    info!(
+        "Watching {} program(s), reload the config with SIGHUP",
+        config.programs.len()
+    );
+
+    let mut next_checks: BTreeMap<String, Instant> = BTreeMap::new();
+    while !interrupted() {
+        if reload.swap(false, Ordering::Relaxed) {
+            match DaemonConfig::load(config_path) {
+                Ok(new_config) => {
+                    if new_config.listen != config.listen {
+                        warn!(
+                            "Changing the listen address requires a restart, keeping {}",
+                            config.listen
+                        );
+                    }
+                    // Programs whose entry changed are rebuilt right away
+                    for program in &new_config.programs {
+                        if !config.programs.contains(program) {
+                            next_checks.remove(&program.program_id);
+                            statuses.lock().unwrap().remove(&program.program_id);
+                        }
+                    }
+                    let watched = new_config
+                        .programs
+                        .iter()
+                        .map(|program| program.program_id.clone())
+                        .collect::<Vec<_>>();
+                    statuses
+                        .lock()
+                        .unwrap()
+                        .retain(|program_id, _| watched.contains(program_id));
+                    next_checks.retain(|program_id, _| watched.contains(program_id));
+                    config = new_config;
+                    info!(
+                        "Reloaded {}, watching {} program(s)",
+                        config_path.display(),
+                        config.programs.len()
+                    );
+                }
+                Err(err) => error!(
+                    "Failed to reload the config, keeping the previous one: {}",
+                    err
+                ),
+            }
+        }
+
+        for program in &config.programs {
+            check_interrupted()?;
+            if next_checks
+                .get(&program.program_id)
+                .is_some_and(|next_check| *next_check > Instant::now())
+            {
+                continue;
+            }
+            info!("Checking {}", program.program_id);
+            let last = statuses.lock().unwrap().get(&program.program_id).cloned();
+            let status = match check_program(connection, program, last.as_ref()) {
+                Ok(status) => {
+                    info!(
+                        "{}: {}",
+                        program.program_id,
+                        if status.is_verified {
+                            "verified ✅"
+                        } else {
+                            "not verified ❌"
+                        }
+                    );
+                    status
+                }
+                Err(err) => {
+                    error!("{}: check failed: {}", program.program_id, err);
+                    ProgramStatus {
+                        program_id: program.program_id.clone(),
+                        repo_url: program.repo_url.clone(),
+                        last_checked_at: Some(chrono::Utc::now().to_rfc3339()),
+                        error: Some(err.to_string()),
+                        ..Default::default()
+                    }
+                }
+            };
+            statuses
+                .lock()
+                .unwrap()
+                .insert(program.program_id.clone(), status);
+            let interval = program.interval_secs.unwrap_or(config.interval_secs);
+            next_checks.insert(
+                program.program_id.clone(),
+                Instant::now() + Duration::from_secs(interval),
+            );
+        }
+
+        sleep_unless_interrupted(Duration::from_secs(1)).await;
+    }
+    check_interrupted()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_config_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("daemon.json");
+        std::fs::write(
+            &path,
+            r#"{"programs": [{"program_id": "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY", "repo_url": "https://github.com/Ellipsis-Labs/phoenix-v1"}]}"#,
+        )
+        .unwrap();
+        let config = DaemonConfig::load(&path).unwrap();
+        assert_eq!(config.interval_secs, DEFAULT_INTERVAL_SECS);
+        assert_eq!(config.listen, DEFAULT_LISTEN);
+        assert_eq!(config.programs[0].mount_path, "");
+        assert_eq!(config.programs[0].commit, None);
+
+        std::fs::write(
+            &path,
+            r#"{"programs": [{"program_id": "x", "repo_url": ""}]}"#,
+        )
+        .unwrap();
+        assert!(DaemonConfig::load(&path).is_err());
+    }
+
+    #[test]
+    fn test_route() {
+        let mut statuses = BTreeMap::new();
+        statuses.insert(
+            "prog".to_string(),
+            ProgramStatus {
+                program_id: "prog".to_string(),
+                is_verified: true,
+                ..Default::default()
+            },
+        );
+        let (code, body) = route("/status", &statuses);
+        assert_eq!(code, 200);
+        assert!(body.starts_with(r#"[{"program_id":"prog""#));
+        assert_eq!(route("/status/prog/", &statuses).0, 200);
+        assert_eq!(route("/status/other", &statuses).0, 404);
+        assert_eq!(route("/", &statuses).0, 404);
+    }
+}
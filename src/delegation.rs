@@ -0,0 +1,348 @@
+use anyhow::anyhow;
+use base64::{prelude::BASE64_STANDARD, Engine};
+use log::info;
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    compute_budget,
+    message::Message,
+    pubkey::Pubkey,
+    signature::Signature,
+    signer::Signer,
+    system_instruction::{self, SystemInstruction},
+    system_program,
+    transaction::Transaction,
+};
+use std::{path::PathBuf, str::FromStr};
+
+use crate::{
+    compression::{read_artifact, write_artifact},
+    solana_program::{
+        compose_instructions, find_build_params_pda, prompt_user_input, send_and_confirm,
+        with_compute_budget, InputParams, OtterVerifyInstructions, OTTER_VERIFY_PROGRAM_ID,
+    },
+    tx_export::{fetch_nonce, nonce_account, BlockhashSource},
+};
+
+/// File `partial-sign` writes when none is given
+pub const DEFAULT_PARTIAL_UPLOAD: &str = "upload.partial.json";
+
+/// Upload of a PDA signed by the machine that built the program, as fee payer, and waiting
+/// for the signature of the program authority. It can only land until its blockhash expires,
+/// or until its durable nonce is advanced, so the delegation is limited in time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PartiallySignedUpload {
+    pub program_id: String,
+    pub authority: String,
+    pub fee_payer: String,
+    pub pda: String,
+    /// Block height after which the transaction can no longer land, none with a durable nonce
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_valid_block_height: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce_account: Option<String>,
+    /// Base64 of the partially signed transaction
+    pub transaction: String,
+}
+
+impl PartiallySignedUpload {
+    /// Writes the upload as JSON, zstd-compressed if `path` ends in `.zst`. Returns the path
+    /// written, which ends in `.age` if the upload was encrypted.
+    pub fn write(&self, path: &str) -> anyhow::Result<PathBuf> {
+        write_artifact(path, serde_json::to_string_pretty(self)?.as_bytes())
+            .map_err(|e| anyhow!("Failed to write partially signed upload: {}", e))
+    }
+
+    pub fn read(path: &str) -> anyhow::Result<PartiallySignedUpload> {
+        let contents = read_artifact(path)
+            .map_err(|e| anyhow!("Failed to read partially signed upload: {}", e))?;
+        Ok(serde_json::from_slice(&contents)?)
+    }
+
+    pub fn decode_transaction(&self) -> anyhow::Result<Transaction> {
+        let bytes = BASE64_STANDARD
+            .decode(self.transaction.trim())
+            .map_err(|e| anyhow!("Invalid transaction encoding: {}", e))?;
+        bincode::deserialize(&bytes).map_err(|e| anyhow!("Invalid transaction: {}", e))
+    }
+}
+
+/// Composes the upload of `input_params` to the PDA of `authority` and signs it as
+/// `fee_payer`. The authority's signature is still missing.
+pub fn partial_sign(
+    connection: &RpcClient,
+    input_params: &InputParams,
+    program_id: Pubkey,
+    authority: Pubkey,
+    fee_payer: &dyn Signer,
+    blockhash: BlockhashSource,
+    compute_unit_price: u64,
+) -> anyhow::Result<PartiallySignedUpload> {
+    let payer = fee_payer.try_pubkey()?;
+    let (pda, _) = find_build_params_pda(&program_id, &authority);
+    let instruction = if connection.get_account(&pda).is_ok() {
+        OtterVerifyInstructions::Update
+    } else {
+        OtterVerifyInstructions::Initialize
+    };
+    let mut instructions = with_compute_budget(
+        connection,
+        compose_instructions(
+            input_params,
+            authority,
+            pda,
+            program_id,
+            instruction,
+            compute_unit_price,
+        )?,
+        &payer,
+    );
+
+    let (recent_blockhash, last_valid_block_height, nonce) = match blockhash {
+        BlockhashSource::None => {
+            return Err(anyhow!(
+                "A partially signed upload needs the latest blockhash or a durable nonce"
+            ))
+        }
+        BlockhashSource::Latest => {
+            let (hash, height) =
+                connection.get_latest_blockhash_with_commitment(connection.commitment())?;
+            (hash, Some(height), None)
+        }
+        BlockhashSource::Nonce { account, authority } => {
+            instructions.insert(
+                0,
+                system_instruction::advance_nonce_account(&account, &authority),
+            );
+            (fetch_nonce(connection, &account)?, None, Some(account))
+        }
+    };
+
+    let mut tx = Transaction::new_unsigned(Message::new_with_blockhash(
+        &instructions,
+        Some(&payer),
+        &recent_blockhash,
+    ));
+    tx.try_partial_sign(&[fee_payer], recent_blockhash)?;
+
+    Ok(PartiallySignedUpload {
+        program_id: program_id.to_string(),
+        authority: authority.to_string(),
+        fee_payer: payer.to_string(),
+        pda: pda.to_string(),
+        last_valid_block_height,
+        nonce_account: nonce.map(|account| account.to_string()),
+        transaction: BASE64_STANDARD.encode(bincode::serialize(&tx)?),
+    })
+}
+
+/// Checks that `message` only uploads the PDA of `authority` for `program_id`, besides
+/// setting its compute budget and advancing its durable nonce, and returns the upload.
+fn check_upload_message(
+    message: &Message,
+    program_id: &Pubkey,
+    authority: &Pubkey,
+) -> anyhow::Result<(OtterVerifyInstructions, InputParams)> {
+    let signers = &message.account_keys[..message.header.num_required_signatures as usize];
+    if !signers.contains(authority) {
+        return Err(anyhow!(
+            "The transaction does not require the signature of {}",
+            authority
+        ));
+    }
+
+    let (pda, _) = find_build_params_pda(program_id, authority);
+    let nonce = nonce_account(message);
+    let mut upload = None;
+    for (index, ix) in message.instructions.iter().enumerate() {
+        let key = |position: usize| {
+            ix.accounts
+                .get(position)
+                .and_then(|account| message.account_keys.get(*account as usize))
+        };
+        match message.account_keys.get(ix.program_id_index as usize) {
+            Some(id) if *id == compute_budget::id() => {}
+            Some(id) if *id == system_program::id() && index == 0 && nonce.is_some() => {
+                if !matches!(
+                    bincode::deserialize(&ix.data),
+                    Ok(SystemInstruction::AdvanceNonceAccount)
+                ) {
+                    return Err(anyhow!("Unexpected system instruction"));
+                }
+            }
+            Some(id) if *id == OTTER_VERIFY_PROGRAM_ID && upload.is_none() => {
+                let instruction = OtterVerifyInstructions::from_ix_data(&ix.data)
+                    .filter(|ix| *ix != OtterVerifyInstructions::Close)
+                    .ok_or_else(|| {
+                        anyhow!("Only Initialize and Update uploads can be countersigned")
+                    })?;
+                let params = InputParams::from_ix_data(&ix.data)
+                    .ok_or_else(|| anyhow!("Invalid verification params"))?;
+                if key(0) != Some(&pda) || key(1) != Some(authority) || key(2) != Some(program_id) {
+                    return Err(anyhow!(
+                        "The upload does not write PDA {} of program {} for {}",
+                        pda,
+                        program_id,
+                        authority
+                    ));
+                }
+                upload = Some((instruction, params));
+            }
+            Some(id) => return Err(anyhow!("Unexpected instruction of program {}", id)),
+            None => return Err(anyhow!("Invalid instruction")),
+        }
+    }
+    upload.ok_or_else(|| anyhow!("The transaction does not upload verification params"))
+}
+
+/// Checks a partially signed upload, shows what it writes and, once confirmed, adds the
+/// signature of `authority` and sends it.
+pub fn countersign(
+    connection: &RpcClient,
+    upload: &PartiallySignedUpload,
+    authority: &dyn Signer,
+    skip_prompt: bool,
+) -> anyhow::Result<Option<Signature>> {
+    let mut tx = upload.decode_transaction()?;
+    let program_id = Pubkey::from_str(&upload.program_id)?;
+    let fee_payer = Pubkey::from_str(&upload.fee_payer)?;
+    let authority_pubkey = authority.try_pubkey()?;
+    if authority_pubkey.to_string() != upload.authority {
+        return Err(anyhow!(
+            "The upload must be countersigned by {}, not {}",
+            upload.authority,
+            authority_pubkey
+        ));
+    }
+    if tx.message.account_keys.first() != Some(&fee_payer)
+        || !tx.verify_with_results().first().copied().unwrap_or(false)
+    {
+        return Err(anyhow!(
+            "The transaction is not signed by its fee payer {}",
+            fee_payer
+        ));
+    }
+    let (instruction, params) = check_upload_message(&tx.message, &program_id, &authority_pubkey)?;
+
+    match nonce_account(&tx.message) {
+        Some(account) => {
+            if fetch_nonce(connection, &account)? != tx.message.recent_blockhash {
+                return Err(anyhow!(
+                    "Nonce account {} was advanced, the upload expired. Ask the builder to run partial-sign again",
+                    account
+                ));
+            }
+        }
+        None => {
+            let block_height = connection.get_block_height()?;
+            if upload
+                .last_valid_block_height
+                .is_some_and(|last_valid| block_height > last_valid)
+                || !connection
+                    .is_blockhash_valid(&tx.message.recent_blockhash, connection.commitment())?
+            {
+                return Err(anyhow!(
+                    "The blockhash of the upload expired. Ask the builder to run partial-sign again, with --blockhash nonce to leave more time to countersign"
+                ));
+            }
+        }
+    }
+
+    println!("Program: {}", program_id);
+    println!("PDA: {} ({:?})", upload.pda, instruction);
+    println!("Git Url: {}", params.git_url);
+    println!("Commit: {}", params.commit);
+    println!("Args: {:?}", params.args);
+    println!("Deployed slot: {}", params.deployed_slot);
+    println!("Fee payer: {}", fee_payer);
+    if !skip_prompt && !prompt_user_input("Do you want to countersign and send the upload? (y/n) ")?
+    {
+        info!("Exiting without countersigning the upload.");
+        return Ok(None);
+    }
+
+    tx.try_partial_sign(&[authority], tx.message.recent_blockhash)?;
+    let signature = send_and_confirm(connection, &tx)
+        .map_err(|err| anyhow!("Failed to send the countersigned upload: {}", err))?;
+    println!(
+        "Program uploaded successfully. Transaction ID: {}",
+        signature
+    );
+    Ok(Some(signature))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{hash::Hash, instruction::Instruction, signature::Keypair};
+
+    fn upload_instructions(authority: &Pubkey, program_id: &Pubkey) -> Vec<Instruction> {
+        let params = InputParams {
+            version: "0.4.1".to_string(),
+            git_url: "https://github.com/Ellipsis-Labs/phoenix-v1".to_string(),
+            commit: "abc".to_string(),
+            args: vec![],
+            deployed_slot: 42,
+        };
+        let (pda, _) = find_build_params_pda(program_id, authority);
+        compose_instructions(
+            &params,
+            *authority,
+            pda,
+            *program_id,
+            OtterVerifyInstructions::Initialize,
+            100,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_check_upload_message() {
+        let fee_payer = Keypair::new();
+        let (authority, program_id) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let message = |instructions: &[Instruction]| {
+            Message::new_with_blockhash(
+                instructions,
+                Some(&fee_payer.pubkey()),
+                &Hash::new_unique(),
+            )
+        };
+        let upload = upload_instructions(&authority, &program_id);
+
+        let (instruction, params) =
+            check_upload_message(&message(&upload), &program_id, &authority).unwrap();
+        assert_eq!(instruction, OtterVerifyInstructions::Initialize);
+        assert_eq!(params.deployed_slot, 42);
+        assert!(
+            check_upload_message(&message(&upload), &Pubkey::new_unique(), &authority).is_err()
+        );
+        assert!(
+            check_upload_message(&message(&upload), &program_id, &Pubkey::new_unique()).is_err()
+        );
+
+        // Authorities never sign anything but the upload
+        let mut drain = upload.clone();
+        drain.push(system_instruction::transfer(
+            &authority,
+            &fee_payer.pubkey(),
+            1,
+        ));
+        assert!(check_upload_message(&message(&drain), &program_id, &authority).is_err());
+
+        // The builder's signature survives the round trip through the file
+        let mut tx = Transaction::new_unsigned(message(&upload));
+        tx.try_partial_sign(&[&fee_payer], tx.message.recent_blockhash)
+            .unwrap();
+        let file = PartiallySignedUpload {
+            program_id: program_id.to_string(),
+            authority: authority.to_string(),
+            fee_payer: fee_payer.pubkey().to_string(),
+            pda: find_build_params_pda(&program_id, &authority).0.to_string(),
+            last_valid_block_height: Some(100),
+            nonce_account: None,
+            transaction: BASE64_STANDARD.encode(bincode::serialize(&tx).unwrap()),
+        };
+        let decoded = file.decode_transaction().unwrap();
+        assert_eq!(decoded.verify_with_results(), vec![true, false]);
+    }
+}
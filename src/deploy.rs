@@ -0,0 +1,164 @@
+use anyhow::anyhow;
+use base64::{prelude::BASE64_STANDARD, Engine};
+use bincode::serialize;
+use log::info;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    message::Message,
+    pubkey::Pubkey,
+    transaction::Transaction,
+};
+use solana_transaction_status::UiTransactionEncoding;
+use std::{path::PathBuf, process::Command, str::FromStr};
+
+use crate::account_data::get_large_account_data;
+use crate::solana_program::get_program_upgrade_authority;
+use crate::{build, find_executable, get_binary_hash, get_file_hash};
+
+/// Runs a `solana program` subcommand with JSON output and returns the value of `field`.
+pub fn solana_program_command(args: &[&str], url: &str, field: &str) -> anyhow::Result<String> {
+    let output = Command::new("solana")
+        .arg("program")
+        .args(args)
+        .args(["--url", url, "--output", "json"])
+        .output()
+        .map_err(|e| anyhow!("Failed to run the solana CLI, is it installed? {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "solana program {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    parse_json_field(&String::from_utf8(output.stdout)?, field)
+}
+
+fn parse_json_field(output: &str, field: &str) -> anyhow::Result<String> {
+    let value: serde_json::Value = serde_json::from_str(output.trim())
+        .map_err(|e| anyhow!("Unexpected output from the solana CLI: {}", e))?;
+    value[field]
+        .as_str()
+        .map(|s| s.to_string())
+        .ok_or_else(|| anyhow!("Missing {} in the solana CLI output: {}", field, output))
+}
+
+/// Builds the program verifiably, writes the build to a buffer owned by the program's
+/// upgrade authority and prints the upgrade transaction for the authority to sign. The
+/// buffer is hashed after it is written, so only the verified bytes can be deployed.
+#[allow(clippy::too_many_arguments)]
+pub fn deploy_verified(
+    connection: &RpcClient,
+    program_id: Pubkey,
+    mount_directory: Option<PathBuf>,
+    library_name: String,
+    base_image: Option<String>,
+    bpf_flag: bool,
+    cargo_args: Vec<String>,
+    buffer_keypair: Option<String>,
+    path_to_keypair: Option<String>,
+    upgrade_authority: Option<Pubkey>,
+    encoding: UiTransactionEncoding,
+) -> anyhow::Result<()> {
+    let upgrade_authority = match upgrade_authority {
+        Some(upgrade_authority) => upgrade_authority,
+        None => get_program_upgrade_authority(connection, &program_id)?
+            .ok_or_else(|| anyhow!("Program {} is immutable", program_id))?,
+    };
+
+    let mount_path = match mount_directory {
+        Some(mount_directory) => mount_directory,
+        None => std::env::current_dir()?,
+    };
+    build(
+        Some(mount_path.clone()),
+        Some(library_name.clone()),
+        base_image,
+        bpf_flag,
+        cargo_args,
+    )?;
+    let executable_path = find_executable(&mount_path, &library_name)?;
+    let build_hash = get_file_hash(&executable_path)?;
+    println!("Executable Program Hash: {}", build_hash);
+
+    let executable_path = executable_path
+        .to_str()
+        .ok_or_else(|| anyhow!("Executable path is not valid UTF-8"))?;
+    let mut write_args = vec!["write-buffer", executable_path];
+    if let Some(buffer_keypair) = &buffer_keypair {
+        write_args.extend(["--buffer", buffer_keypair]);
+    }
+    if let Some(path_to_keypair) = &path_to_keypair {
+        write_args.extend(["--keypair", path_to_keypair]);
+    }
+    info!("Writing the build to a buffer...");
+    let buffer = Pubkey::from_str(&solana_program_command(
+        &write_args,
+        &connection.url(),
+        "buffer",
+    )?)?;
+    println!("Buffer: {}", buffer);
+
+    let offset = UpgradeableLoaderState::size_of_buffer_metadata();
+    let buffer_hash =
+        get_binary_hash(get_large_account_data(connection, &buffer)?[offset..].to_vec());
+    println!("Buffer Hash: {}", buffer_hash);
+    if buffer_hash != build_hash {
+        return Err(anyhow!(
+            "Buffer {} does not contain the verified build, do not deploy it",
+            buffer
+        ));
+    }
+
+    let upgrade_authority_arg = upgrade_authority.to_string();
+    let buffer_arg = buffer.to_string();
+    let mut authority_args = vec![
+        "set-buffer-authority",
+        &buffer_arg,
+        "--new-buffer-authority",
+        &upgrade_authority_arg,
+    ];
+    if let Some(path_to_keypair) = &path_to_keypair {
+        authority_args.extend(["--keypair", path_to_keypair]);
+    }
+    solana_program_command(&authority_args, &connection.url(), "authority")?;
+    println!("Buffer authority set to {}", upgrade_authority);
+
+    let instruction = bpf_loader_upgradeable::upgrade(
+        &program_id,
+        &buffer,
+        &upgrade_authority,
+        &upgrade_authority,
+    );
+    let tx = Transaction::new_unsigned(Message::new(&[instruction], Some(&upgrade_authority)));
+    println!("Upgrade transaction for {}:", upgrade_authority);
+    match encoding {
+        UiTransactionEncoding::Base58 => {
+            println!("{}", bs58::encode(serialize(&tx)?).into_string());
+        }
+        UiTransactionEncoding::Base64 => {
+            println!("{}", BASE64_STANDARD.encode(serialize(&tx)?));
+        }
+        _ => unreachable!(),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_json_field() {
+        assert_eq!(
+            parse_json_field(
+                "{\"buffer\": \"9VWiUUhgNoRwTH5NVehYJEDwcotwYX3VgW4MChiHPAqU\"}\n",
+                "buffer"
+            )
+            .unwrap(),
+            "9VWiUUhgNoRwTH5NVehYJEDwcotwYX3VgW4MChiHPAqU"
+        );
+        assert!(parse_json_field("{\"authority\": \"x\"}", "buffer").is_err());
+        assert!(parse_json_field("Error: insufficient funds", "buffer").is_err());
+    }
+}
@@ -0,0 +1,205 @@
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    io::{self, BufRead},
+};
+
+use crate::support_bundle::log_output;
+
+/// Compiler message reported by cargo with `--message-format=json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub level: String,
+    /// Crate the message was emitted for
+    pub target: String,
+    pub message: String,
+    /// `file:line` of the primary span, if any
+    pub location: Option<String>,
+}
+
+/// Warnings and errors of a container build, collected from cargo's JSON output.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BuildDiagnostics {
+    pub errors: Vec<Diagnostic>,
+    pub warnings: Vec<Diagnostic>,
+}
+
+#[derive(Deserialize)]
+struct CargoMessage {
+    reason: String,
+    target: Option<CargoTarget>,
+    message: Option<CompilerMessage>,
+}
+
+#[derive(Deserialize)]
+struct CargoTarget {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct CompilerMessage {
+    level: String,
+    message: String,
+    rendered: Option<String>,
+    #[serde(default)]
+    spans: Vec<Span>,
+}
+
+#[derive(Deserialize)]
+struct Span {
+    file_name: String,
+    line_start: u64,
+    is_primary: bool,
+}
+
+/// A line of cargo output, which is JSON for cargo's own messages and plain text for the
+/// output of build scripts and `cargo build-sbf`.
+enum Line {
+    Diagnostic(Diagnostic, Option<String>),
+    Other(Option<String>),
+}
+
+fn parse_line(line: &str) -> Line {
+    if !line.starts_with('{') {
+        return Line::Other(Some(line.to_string()));
+    }
+    let Ok(message) = serde_json::from_str::<CargoMessage>(line) else {
+        return Line::Other(Some(line.to_string()));
+    };
+    match (message.reason.as_str(), message.message) {
+        ("compiler-message", Some(compiler_message)) => {
+            let location = compiler_message
+                .spans
+                .iter()
+                .find(|span| span.is_primary)
+                .map(|span| format!("{}:{}", span.file_name, span.line_start));
+            Line::Diagnostic(
+                Diagnostic {
+                    level: compiler_message.level,
+                    target: message.target.map(|t| t.name).unwrap_or_default(),
+                    message: compiler_message.message,
+                    location,
+                },
+                compiler_message.rendered,
+            )
+        }
+        // Artifact and build script notifications are noise for a verification
+        _ => Line::Other(None),
+    }
+}
+
+impl BuildDiagnostics {
+    /// Reads cargo's output, printing errors in full and plain text lines as they come and
+    /// collecting the diagnostics for `print_summary`.
+    pub fn collect(reader: impl BufRead) -> io::Result<BuildDiagnostics> {
+        let mut diagnostics = BuildDiagnostics::default();
+        for line in reader.lines() {
+            let line = line?;
+            log_output(&line);
+            match parse_line(&line) {
+                Line::Diagnostic(diagnostic, rendered) => match diagnostic.level.as_str() {
+                    "error" => {
+                        if let Some(rendered) = rendered {
+                            info!("{}", rendered.trim_end());
+                        }
+                        diagnostics.errors.push(diagnostic);
+                    }
+                    "warning" => diagnostics.warnings.push(diagnostic),
+                    // Notes and help attached to other messages
+                    _ => {}
+                },
+                Line::Other(Some(line)) => info!("{}", line),
+                Line::Other(None) => {}
+            }
+        }
+        Ok(diagnostics)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+
+    /// Diagnostics grouped by level, crate and message, most frequent first.
+    fn grouped(&self) -> Vec<(usize, &Diagnostic)> {
+        let mut groups: BTreeMap<(&str, &str, &str), (usize, &Diagnostic)> = BTreeMap::new();
+        for diagnostic in self.errors.iter().chain(&self.warnings) {
+            groups
+                .entry((
+                    diagnostic.level.as_str(),
+                    diagnostic.target.as_str(),
+                    diagnostic.message.as_str(),
+                ))
+                .or_insert((0, diagnostic))
+                .0 += 1;
+        }
+        let mut groups: Vec<_> = groups.into_values().collect();
+        // Errors first, then by frequency
+        groups.sort_by_key(|(count, diagnostic)| (diagnostic.level != "error", Reverse(*count)));
+        groups
+    }
+
+    pub fn print_summary(&self) {
+        info!(
+            "Build diagnostics: {} error(s), {} warning(s)",
+            self.errors.len(),
+            self.warnings.len()
+        );
+        let groups = self.grouped();
+        if groups.is_empty() {
+            return;
+        }
+        let target_width = groups
+            .iter()
+            .map(|(_, diagnostic)| diagnostic.target.len())
+            .max()
+            .unwrap_or_default()
+            .max("CRATE".len());
+        info!(
+            "{:>6}  {:<7}  {:<target_width$}  MESSAGE",
+            "COUNT", "LEVEL", "CRATE"
+        );
+        for (count, diagnostic) in groups {
+            info!(
+                "{:>6}  {:<7}  {:<target_width$}  {}",
+                count,
+                diagnostic.level,
+                diagnostic.target,
+                diagnostic.message.lines().next().unwrap_or_default()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WARNING: &str = r#"{"reason":"compiler-message","package_id":"phoenix 0.1.0 (path+file:///build)","target":{"name":"phoenix"},"message":{"rendered":"warning: unused variable: `x`\n","level":"warning","message":"unused variable: `x`","spans":[{"file_name":"src/lib.rs","line_start":3,"is_primary":true}]}}"#;
+    const ERROR: &str = r#"{"reason":"compiler-message","target":{"name":"phoenix"},"message":{"rendered":"error[E0425]: cannot find value `y`\n","level":"error","message":"cannot find value `y` in this scope","spans":[]}}"#;
+    const ARTIFACT: &str = r#"{"reason":"compiler-artifact","target":{"name":"phoenix"}}"#;
+
+    #[test]
+    fn test_collect_diagnostics() {
+        let output = [WARNING, "To deploy this program:", ARTIFACT, WARNING, ERROR].join("\n");
+        let diagnostics = BuildDiagnostics::collect(output.as_bytes()).unwrap();
+
+        assert!(diagnostics.has_errors());
+        assert_eq!(diagnostics.warnings.len(), 2);
+        assert_eq!(
+            diagnostics.warnings[0],
+            Diagnostic {
+                level: "warning".to_string(),
+                target: "phoenix".to_string(),
+                message: "unused variable: `x`".to_string(),
+                location: Some("src/lib.rs:3".to_string()),
+            }
+        );
+
+        let grouped = diagnostics.grouped();
+        assert_eq!(grouped.len(), 2);
+        assert_eq!(grouped[0].1.level, "error");
+        assert_eq!(grouped[1].0, 2);
+    }
+}
@@ -0,0 +1,724 @@
+use anyhow::anyhow;
+use chrono::{DateTime, Duration, Utc};
+use log::{info, warn};
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+    sync::OnceLock,
+};
+
+use crate::{
+    hash::get_raw_file_sha256, humanize::format_bytes, parse_output, paths::bind_mount_arg,
+};
+
+/// Label attached to every container started by solana-verify so leftovers can be found later
+pub const CONTAINER_LABEL: &str = "solana-verify";
+
+/// Repositories of the images solana-verify pulls for building
+const BUILD_IMAGE_REPOSITORIES: [&str; 2] = [
+    "solanafoundation/solana-verifiable-build",
+    "projectserum/build",
+];
+
+/// Environment variable selecting the container runtime when `--container-runtime` is not given
+pub const CONTAINER_RUNTIME_ENV: &str = "SVB_CONTAINER_RUNTIME";
+
+/// CLI running the build containers. Podman accepts the docker commands solana-verify issues.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerRuntime {
+    Docker,
+    Podman,
+}
+
+impl FromStr for ContainerRuntime {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "docker" => Ok(ContainerRuntime::Docker),
+            "podman" => Ok(ContainerRuntime::Podman),
+            _ => Err(anyhow!("Unsupported container runtime: {}", s)),
+        }
+    }
+}
+
+impl ContainerRuntime {
+    pub fn binary(&self) -> &'static str {
+        match self {
+            ContainerRuntime::Docker => "docker",
+            ContainerRuntime::Podman => "podman",
+        }
+    }
+
+    fn is_installed(&self) -> bool {
+        Command::new(self.binary())
+            .arg("--version")
+            .output()
+            .is_ok_and(|output| output.status.success())
+    }
+
+    /// Runtime selected with `SVB_CONTAINER_RUNTIME`, none if it is not set.
+    pub fn from_env() -> anyhow::Result<Option<ContainerRuntime>> {
+        match std::env::var(CONTAINER_RUNTIME_ENV) {
+            Ok(runtime) => runtime
+                .parse()
+                .map(Some)
+                .map_err(|err| anyhow!("Invalid {}: {}", CONTAINER_RUNTIME_ENV, err)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Docker if it is installed, else podman if it is, else docker so the missing
+    /// installation is reported with docker's instructions.
+    fn detect() -> ContainerRuntime {
+        match ContainerRuntime::from_env() {
+            Ok(Some(runtime)) => return runtime,
+            Ok(None) => {}
+            Err(err) => warn!("{}", err),
+        }
+        [ContainerRuntime::Docker, ContainerRuntime::Podman]
+            .into_iter()
+            .find(ContainerRuntime::is_installed)
+            .unwrap_or(ContainerRuntime::Docker)
+    }
+}
+
+static CONTAINER_RUNTIME: OnceLock<ContainerRuntime> = OnceLock::new();
+static DOCKER_CONTEXT: OnceLock<String> = OnceLock::new();
+static REQUIRED_DOCKER_VERSION: OnceLock<String> = OnceLock::new();
+
+pub fn set_container_runtime(runtime: ContainerRuntime) {
+    let _ = CONTAINER_RUNTIME.set(runtime);
+}
+
+/// Runtime given with `--container-runtime` or `SVB_CONTAINER_RUNTIME`, detected otherwise.
+pub fn container_runtime() -> ContainerRuntime {
+    *CONTAINER_RUNTIME.get_or_init(ContainerRuntime::detect)
+}
+
+/// Returns a command of the container runtime, targeting the docker context selected for
+/// this run, if any.
+pub fn docker_command() -> Command {
+    let mut cmd = Command::new(container_runtime().binary());
+    if let Some(context) = DOCKER_CONTEXT.get() {
+        cmd.args(["--context", context]);
+    }
+    cmd
+}
+
+/// `--mount` argument binding `source` to `target`. Podman relabels the source so that the
+/// container can read it on SELinux hosts.
+pub fn bind_mount(source: &Path, target: &Path) -> OsString {
+    let mut arg = bind_mount_arg(source, target);
+    if container_runtime() == ContainerRuntime::Podman {
+        arg.push(",relabel=shared");
+    }
+    arg
+}
+
+/// Checks that `context` exists and uses it for every docker command issued afterwards.
+pub fn set_docker_context(context: &str) -> anyhow::Result<()> {
+    if container_runtime() == ContainerRuntime::Podman {
+        return Err(anyhow!(
+            "--docker-context is not supported with podman, select a podman connection with `podman system connection default` instead"
+        ));
+    }
+    let output = Command::new("docker")
+        .args(["context", "inspect", context])
+        .output()
+        .map_err(|e| anyhow!("Failed to inspect docker context: {}", e))?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Docker context '{}' does not exist. Run `docker context ls` to see the available contexts: {}",
+            context,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    DOCKER_CONTEXT
+        .set(context.to_string())
+        .map_err(|_| anyhow!("Docker context has already been set"))
+}
+
+/// Sets the minimum docker server version checked by [`check_docker_available`].
+pub fn set_required_docker_version(version: &str) -> anyhow::Result<()> {
+    parse_version(version)
+        .ok_or_else(|| anyhow!("Invalid docker version requirement: {}", version))?;
+    REQUIRED_DOCKER_VERSION
+        .set(version.to_string())
+        .map_err(|_| anyhow!("Required docker version has already been set"))
+}
+
+fn parse_version(version: &str) -> Option<Vec<u32>> {
+    version
+        .trim()
+        .trim_start_matches('v')
+        .split(['.', '-', '+'])
+        .take(3)
+        .map(|part| part.parse::<u32>().ok())
+        .collect()
+}
+
+fn docker_install_instructions() -> &'static str {
+    if container_runtime() == ContainerRuntime::Podman {
+        return "Install podman by following https://podman.io/docs/installation";
+    }
+    match std::env::consts::OS {
+        "macos" => "Install Docker Desktop from https://docs.docker.com/desktop/install/mac-install/ or run `brew install --cask docker`",
+        "windows" => "Install Docker Desktop from https://docs.docker.com/desktop/install/windows-install/",
+        _ => "Install Docker Engine by following https://docs.docker.com/engine/install/",
+    }
+}
+
+fn docker_start_instructions() -> &'static str {
+    if container_runtime() == ContainerRuntime::Podman {
+        return match std::env::consts::OS {
+            "macos" | "windows" => "Start the podman machine with `podman machine start`",
+            _ => "Check that `podman info` succeeds for your user",
+        };
+    }
+    match std::env::consts::OS {
+        "macos" | "windows" => "Start Docker Desktop and wait until it reports that the engine is running",
+        _ => "Start the docker daemon with `sudo systemctl start docker` and make sure your user can access it (see https://docs.docker.com/engine/install/linux-postinstall/)",
+    }
+}
+
+/// Fails early with instructions when docker is missing, the daemon is unreachable or
+/// the server is older than the version required with `--require-docker-version`.
+pub fn check_docker_available() -> anyhow::Result<()> {
+    let runtime = container_runtime();
+    // Podman runs containers without a server, the version of the CLI is the one that matters
+    let version_format = match runtime {
+        ContainerRuntime::Docker => "{{.Server.Version}}",
+        ContainerRuntime::Podman => "{{.Client.Version}}",
+    };
+    let output = match docker_command()
+        .args(["version", "--format", version_format])
+        .output()
+    {
+        Ok(output) => output,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(anyhow!(
+                "{} is not installed or not in PATH. {}",
+                runtime.binary(),
+                docker_install_instructions()
+            ));
+        }
+        Err(e) => return Err(anyhow!("Failed to run {}: {}", runtime.binary(), e)),
+    };
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Unable to reach the {} service: {}\n{}",
+            runtime.binary(),
+            String::from_utf8_lossy(&output.stderr).trim(),
+            docker_start_instructions()
+        ));
+    }
+
+    if let Some(required) = REQUIRED_DOCKER_VERSION.get() {
+        let server_version = parse_output(output.stdout)?;
+        let is_recent_enough = match (parse_version(&server_version), parse_version(required)) {
+            (Some(actual), Some(required)) => actual >= required,
+            _ => false,
+        };
+        if !is_recent_enough {
+            return Err(anyhow!(
+                "Docker server version {} is older than the required version {}",
+                server_version,
+                required
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Settings files of Docker Desktop, newest format first.
+fn docker_desktop_settings_paths() -> Vec<PathBuf> {
+    let dir = match std::env::consts::OS {
+        "macos" => std::env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join("Library/Group Containers/group.com.docker")),
+        "windows" => {
+            std::env::var_os("APPDATA").map(|appdata| PathBuf::from(appdata).join("Docker"))
+        }
+        _ => None,
+    };
+    dir.map(|dir| vec![dir.join("settings-store.json"), dir.join("settings.json")])
+        .unwrap_or_default()
+}
+
+/// Directories Docker Desktop shares with its VM, none if every path is shared, as with the
+/// WSL 2 backend.
+fn parse_shared_directories(settings: &str) -> Option<Vec<PathBuf>> {
+    let settings: serde_json::Value = serde_json::from_str(settings).ok()?;
+    let setting = |name: &str| {
+        // settings-store.json capitalizes the keys of settings.json
+        let mut capitalized = name[..1].to_uppercase();
+        capitalized.push_str(&name[1..]);
+        settings.get(name).or_else(|| settings.get(&capitalized))
+    };
+    if setting("wslEngineEnabled").and_then(|value| value.as_bool()) == Some(true) {
+        return None;
+    }
+    Some(
+        setting("filesharingDirectories")?
+            .as_array()?
+            .iter()
+            .filter_map(|directory| directory.as_str().map(PathBuf::from))
+            .collect(),
+    )
+}
+
+/// Shared directory containing `path`, if any.
+fn find_shared_directory<'a>(path: &Path, shared: &'a [PathBuf]) -> Option<&'a PathBuf> {
+    shared.iter().find(|directory| {
+        path.starts_with(directory)
+            || directory
+                .canonicalize()
+                .is_ok_and(|directory| path.starts_with(directory))
+    })
+}
+
+/// Returned when docker would mount a directory Docker Desktop does not share, which the
+/// container sees as empty.
+#[derive(Debug)]
+pub struct UnsharedMountError {
+    pub path: PathBuf,
+    pub shared: Vec<PathBuf>,
+}
+
+impl std::fmt::Display for UnsharedMountError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} is not shared with Docker Desktop, the build container would see it as empty. ",
+            self.path.display()
+        )?;
+        write!(
+            f,
+            "Add it, or one of its parents, in Docker Desktop under Settings > Resources > File sharing and restart Docker Desktop"
+        )?;
+        if let Some(shared) = self.shared.first() {
+            write!(
+                f,
+                ", or build from a shared directory such as {}, e.g. by running verify-from-repo with --current-dir from there",
+                shared.display()
+            )?;
+        }
+        write!(
+            f,
+            ". Shared directories: {}",
+            self.shared
+                .iter()
+                .map(|directory| directory.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+impl std::error::Error for UnsharedMountError {}
+
+/// Checks that Docker Desktop shares `mount_path` with its VM. Only Docker Desktop on macOS
+/// and Windows restricts bind mounts to configured directories, other setups are not checked.
+pub fn check_mount_shared(mount_path: &Path) -> anyhow::Result<()> {
+    if container_runtime() != ContainerRuntime::Docker
+        || DOCKER_CONTEXT
+            .get()
+            .is_some_and(|context| !context.starts_with("desktop"))
+    {
+        return Ok(());
+    }
+    let Some(shared) = docker_desktop_settings_paths()
+        .iter()
+        .find_map(|path| std::fs::read_to_string(path).ok())
+        .and_then(|settings| parse_shared_directories(&settings))
+    else {
+        return Ok(());
+    };
+    let path = mount_path
+        .canonicalize()
+        .unwrap_or_else(|_| mount_path.to_path_buf());
+    if find_shared_directory(&path, &shared).is_some() {
+        return Ok(());
+    }
+    Err(anyhow!(UnsharedMountError { path, shared }))
+}
+
+/// Checks that the container sees the Cargo.lock of the mounted directory, which is not the
+/// case when the docker daemon could not share the directory, e.g. a remote context or a VM
+/// without the path.
+pub fn check_mount_visible(
+    container_id: &str,
+    mount_path: &Path,
+    workdir: &str,
+) -> anyhow::Result<()> {
+    let lockfile = format!("{}/Cargo.lock", workdir.trim_end_matches('/'));
+    let visible = docker_command()
+        .args(["exec", container_id, "test", "-f", &lockfile])
+        .output()
+        .map_err(|e| anyhow!("Failed to inspect the build container: {}", e))?
+        .status
+        .success();
+    if visible {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "{} has a Cargo.lock, but the build container sees {} as empty: the docker daemon did not share the directory. {}",
+        mount_path.display(),
+        workdir,
+        match std::env::consts::OS {
+            "macos" | "windows" => "Add the directory in Docker Desktop under Settings > Resources > File sharing, or build from a directory that is already shared",
+            _ => "Check that the docker context runs on this machine (`docker context ls`) and that the directory is visible to the daemon, e.g. not inside a snap or rootless confinement",
+        }
+    ))
+}
+
+/// Prints which docker context and daemon endpoint the run is going to use.
+pub fn print_docker_daemon() {
+    if container_runtime() == ContainerRuntime::Podman {
+        info!("Using podman");
+        return;
+    }
+    let context = DOCKER_CONTEXT
+        .get()
+        .cloned()
+        .unwrap_or_else(|| "current".to_string());
+    let endpoint = Command::new("docker")
+        .args([
+            "context",
+            "inspect",
+            "--format",
+            "{{.Endpoints.docker.Host}}",
+        ])
+        .args(DOCKER_CONTEXT.get())
+        .output()
+        .ok()
+        .and_then(|output| parse_output(output.stdout).ok());
+
+    match endpoint {
+        Some(endpoint) => info!("Using docker context {} ({})", context, endpoint),
+        None => info!("Using docker context {}", context),
+    }
+}
+
+pub struct CachedImage {
+    pub id: String,
+    pub reference: String,
+    pub size: u64,
+    pub created: DateTime<Utc>,
+}
+
+/// Lists the build images pulled by solana-verify, newest first.
+pub fn list_cached_images() -> anyhow::Result<Vec<CachedImage>> {
+    let mut ids: Vec<String> = vec![];
+    for repository in BUILD_IMAGE_REPOSITORIES {
+        let output = docker_command()
+            .args(["images", "--quiet", "--no-trunc", repository])
+            .output()
+            .map_err(|e| anyhow!("Failed to list docker images: {}", e))?;
+        for id in String::from_utf8(output.stdout)?.lines() {
+            if !ids.iter().any(|known| known == id) {
+                ids.push(id.to_string());
+            }
+        }
+    }
+
+    if ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let output = docker_command()
+        .args([
+            "image",
+            "inspect",
+            "--format",
+            "{{.Id}}|{{.Size}}|{{.Created}}|{{join .RepoDigests \",\"}}",
+        ])
+        .args(&ids)
+        .output()
+        .map_err(|e| anyhow!("Failed to inspect docker images: {}", e))?;
+
+    let mut images = vec![];
+    for line in String::from_utf8(output.stdout)?.lines() {
+        let parts: Vec<&str> = line.splitn(4, '|').collect();
+        if parts.len() != 4 {
+            continue;
+        }
+        let created = DateTime::parse_from_rfc3339(parts[2])
+            .map_err(|e| anyhow!("Failed to parse image creation time {}: {}", parts[2], e))?
+            .with_timezone(&Utc);
+        images.push(CachedImage {
+            id: parts[0].to_string(),
+            reference: parts[3]
+                .split(',')
+                .next()
+                .filter(|reference| !reference.is_empty())
+                .unwrap_or(parts[0])
+                .to_string(),
+            size: parts[1].parse().unwrap_or(0),
+            created,
+        });
+    }
+    images.sort_by_key(|image| std::cmp::Reverse(image.created));
+    Ok(images)
+}
+
+/// Lists stopped containers left behind by interrupted solana-verify runs.
+pub fn list_leftover_containers() -> anyhow::Result<Vec<String>> {
+    let output = docker_command()
+        .args([
+            "ps",
+            "--all",
+            "--quiet",
+            "--filter",
+            &format!("label={}", CONTAINER_LABEL),
+            "--filter",
+            "status=exited",
+        ])
+        .output()
+        .map_err(|e| anyhow!("Failed to list docker containers: {}", e))?;
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .map(|s| s.to_string())
+        .collect())
+}
+
+/// Removes stopped containers left behind by interrupted solana-verify runs.
+pub fn remove_leftover_containers() -> anyhow::Result<usize> {
+    let containers = list_leftover_containers()?;
+    if !containers.is_empty() {
+        docker_command()
+            .args(["rm", "--force"])
+            .args(&containers)
+            .output()
+            .map_err(|e| anyhow!("Failed to remove docker containers: {}", e))?;
+    }
+    Ok(containers.len())
+}
+
+pub fn print_cache_stats() -> anyhow::Result<()> {
+    let images = list_cached_images()?;
+    let containers = list_leftover_containers()?;
+
+    for image in &images {
+        println!(
+            "{:>10}  {}  {}",
+            format_bytes(image.size),
+            image.created.format("%Y-%m-%d"),
+            image.reference
+        );
+    }
+    println!("----------------------------------------------------------------");
+    println!(
+        "{} build image(s) using {}",
+        images.len(),
+        format_bytes(images.iter().map(|image| image.size).sum())
+    );
+    println!("{} leftover container(s)", containers.len());
+    Ok(())
+}
+
+/// Selects the images to remove: anything older than `older_than`, then the oldest images
+/// until the remaining ones fit in `max_size` bytes. `images` must be sorted newest first.
+fn select_images_to_prune(
+    images: &[CachedImage],
+    older_than: Option<Duration>,
+    max_size: Option<u64>,
+    now: DateTime<Utc>,
+) -> Vec<&CachedImage> {
+    let mut kept_size = 0u64;
+    images
+        .iter()
+        .filter(|image| {
+            let too_old = older_than.is_some_and(|age| now - image.created > age);
+            let over_budget = max_size.is_some_and(|max| kept_size + image.size > max);
+            if too_old || over_budget {
+                true
+            } else {
+                kept_size += image.size;
+                false
+            }
+        })
+        .collect()
+}
+
+pub fn prune_cache(older_than: Option<Duration>, max_size: Option<u64>) -> anyhow::Result<()> {
+    let removed_containers = remove_leftover_containers()?;
+    println!("Removed {} leftover container(s)", removed_containers);
+
+    let images = list_cached_images()?;
+    let mut freed = 0;
+    for image in select_images_to_prune(&images, older_than, max_size, Utc::now()) {
+        let output = docker_command()
+            .args(["rmi", &image.id])
+            .output()
+            .map_err(|e| anyhow!("Failed to remove docker image: {}", e))?;
+        if output.status.success() {
+            println!("Removed {} ({})", image.reference, format_bytes(image.size));
+            freed += image.size;
+        } else {
+            println!(
+                "Unable to remove {}: {}",
+                image.reference,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+    }
+    println!("Freed {}", format_bytes(freed));
+    Ok(())
+}
+
+fn parse_sha256sum(output: &str) -> anyhow::Result<String> {
+    let hash = output.split_whitespace().next().unwrap_or_default();
+    if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(anyhow!("Unexpected sha256sum output: {}", output));
+    }
+    Ok(hash.to_lowercase())
+}
+
+/// Hashes `path` inside the container with `sha256sum`.
+pub fn container_file_sha256(container_id: &str, path: &str) -> anyhow::Result<String> {
+    let output = docker_command()
+        .args(["exec", container_id, "sha256sum", path])
+        .output()
+        .map_err(|e| anyhow!("Failed to hash {} in the container: {}", path, e))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to hash {} in the container: {}",
+            path,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    parse_sha256sum(&String::from_utf8(output.stdout)?)
+}
+
+/// Checks that `host_path` holds exactly the bytes of `container_path` in the still running
+/// container, catching executables corrupted by bind mounts or `docker cp`.
+pub fn check_host_copy(
+    container_id: &str,
+    container_path: &str,
+    host_path: &Path,
+) -> anyhow::Result<()> {
+    let container_hash = container_file_sha256(container_id, container_path)?;
+    let host_hash = get_raw_file_sha256(host_path)?;
+    if container_hash != host_hash {
+        return Err(anyhow!(
+            "{} differs from {} in the container (sha256 {} on the host, {} in the container). \
+             The file was corrupted while being shared with the host, check the file sharing \
+             settings of your docker installation",
+            host_path.display(),
+            container_path,
+            host_hash,
+            container_hash
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_docker_desktop_shared_directories() {
+        let shared = parse_shared_directories(
+            r#"{"filesharingDirectories": ["/Users", "/Volumes", "/private", "/tmp"]}"#,
+        )
+        .unwrap();
+        assert_eq!(shared.len(), 4);
+        assert!(find_shared_directory(Path::new("/Users/dev/program"), &shared).is_some());
+        assert!(find_shared_directory(Path::new("/opt/program"), &shared).is_none());
+        // Components are compared, not prefixes of the string
+        assert!(find_shared_directory(Path::new("/tmpfs/program"), &shared).is_none());
+
+        // settings-store.json capitalizes the keys
+        assert_eq!(
+            parse_shared_directories(r#"{"FilesharingDirectories": ["C:\\Users"]}"#),
+            Some(vec![PathBuf::from("C:\\Users")])
+        );
+        // WSL 2 shares every path
+        assert_eq!(
+            parse_shared_directories(r#"{"wslEngineEnabled": true, "filesharingDirectories": []}"#),
+            None
+        );
+        assert_eq!(parse_shared_directories("{}"), None);
+
+        let err = UnsharedMountError {
+            path: PathBuf::from("/opt/program"),
+            shared,
+        }
+        .to_string();
+        assert!(err.contains("Settings > Resources > File sharing"));
+        assert!(err.contains("such as /Users"));
+    }
+
+    #[test]
+    fn test_container_runtime() {
+        assert_eq!(
+            "podman".parse::<ContainerRuntime>().unwrap(),
+            ContainerRuntime::Podman
+        );
+        assert_eq!(ContainerRuntime::Docker.binary(), "docker");
+        assert!("nerdctl".parse::<ContainerRuntime>().is_err());
+
+        std::env::set_var(CONTAINER_RUNTIME_ENV, "nerdctl");
+        assert!(ContainerRuntime::from_env().is_err());
+        std::env::set_var(CONTAINER_RUNTIME_ENV, "podman");
+        assert_eq!(
+            ContainerRuntime::from_env().unwrap(),
+            Some(ContainerRuntime::Podman)
+        );
+        std::env::remove_var(CONTAINER_RUNTIME_ENV);
+        assert_eq!(ContainerRuntime::from_env().unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("28.2.2"), Some(vec![28, 2, 2]));
+        assert_eq!(parse_version("v20.10.7-ce"), Some(vec![20, 10, 7]));
+        assert!(parse_version("24.0.9") > parse_version("24.0"));
+        assert_eq!(parse_version("latest"), None);
+    }
+
+    #[test]
+    fn test_parse_sha256sum() {
+        let hash = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+        assert_eq!(
+            parse_sha256sum(&format!("{}  /work/target/deploy/a.so\n", hash)).unwrap(),
+            hash
+        );
+        assert_eq!(parse_sha256sum(&hash.to_uppercase()).unwrap(), hash);
+        assert!(parse_sha256sum("sha256sum: a.so: No such file or directory").is_err());
+        assert!(parse_sha256sum("").is_err());
+    }
+
+    fn image(days_old: i64, size: u64, now: DateTime<Utc>) -> CachedImage {
+        CachedImage {
+            id: format!("sha256:{}", days_old),
+            reference: format!("image-{}", days_old),
+            size,
+            created: now - Duration::days(days_old),
+        }
+    }
+
+    #[test]
+    fn test_select_images_to_prune() {
+        let now = Utc::now();
+        let images = vec![image(1, 10, now), image(5, 10, now), image(30, 10, now)];
+
+        let pruned = select_images_to_prune(&images, Some(Duration::days(7)), None, now);
+        assert_eq!(pruned.len(), 1);
+        assert_eq!(pruned[0].reference, "image-30");
+
+        let pruned = select_images_to_prune(&images, None, Some(15), now);
+        let references: Vec<&str> = pruned.iter().map(|i| i.reference.as_str()).collect();
+        assert_eq!(references, vec!["image-5", "image-30"]);
+
+        assert!(select_images_to_prune(&images, None, None, now).is_empty());
+    }
+}
@@ -0,0 +1,210 @@
+use anyhow::anyhow;
+use goblin::elf::{
+    header::{EM_BPF, ET_DYN},
+    section_header::SHN_UNDEF,
+    Elf,
+};
+use log::warn;
+use std::fmt;
+
+/// Machine type of programs built for SBPF v2 and later
+const EM_SBPF: u16 = 263;
+
+/// Syscalls available to programs built against each Solana version. Symbols left undefined
+/// in a program are resolved against these by the loader and fail the deployment otherwise.
+const SYSCALLS: &[(&str, (u32, u32, u32))] = &[
+    ("abort", (1, 0, 0)),
+    ("sol_panic_", (1, 0, 0)),
+    ("sol_log_", (1, 0, 0)),
+    ("sol_log_64_", (1, 0, 0)),
+    ("sol_log_compute_units_", (1, 0, 0)),
+    ("sol_log_pubkey", (1, 0, 0)),
+    ("sol_log_data", (1, 0, 0)),
+    ("sol_create_program_address", (1, 0, 0)),
+    ("sol_try_find_program_address", (1, 0, 0)),
+    ("sol_sha256", (1, 0, 0)),
+    ("sol_keccak256", (1, 0, 0)),
+    ("sol_secp256k1_recover", (1, 0, 0)),
+    ("sol_blake3", (1, 0, 0)),
+    ("sol_get_clock_sysvar", (1, 0, 0)),
+    ("sol_get_epoch_schedule_sysvar", (1, 0, 0)),
+    ("sol_get_fees_sysvar", (1, 0, 0)),
+    ("sol_get_rent_sysvar", (1, 0, 0)),
+    ("sol_memcpy_", (1, 0, 0)),
+    ("sol_memmove_", (1, 0, 0)),
+    ("sol_memcmp_", (1, 0, 0)),
+    ("sol_memset_", (1, 0, 0)),
+    ("sol_invoke_signed_c", (1, 0, 0)),
+    ("sol_invoke_signed_rust", (1, 0, 0)),
+    ("sol_alloc_free_", (1, 0, 0)),
+    ("sol_set_return_data", (1, 0, 0)),
+    ("sol_get_return_data", (1, 0, 0)),
+    ("sol_get_processed_sibling_instruction", (1, 0, 0)),
+    ("sol_get_stack_height", (1, 0, 0)),
+    ("sol_curve_validate_point", (1, 14, 0)),
+    ("sol_curve_group_op", (1, 14, 0)),
+    ("sol_curve_multiscalar_mul", (1, 14, 0)),
+    ("sol_curve_pairing_map", (1, 14, 0)),
+    ("sol_alt_bn128_group_op", (1, 14, 0)),
+    ("sol_big_mod_exp", (1, 16, 0)),
+    ("sol_get_last_restart_slot", (1, 16, 0)),
+    ("sol_alt_bn128_compression", (1, 17, 0)),
+    ("sol_poseidon", (1, 17, 0)),
+    ("sol_remaining_compute_units", (1, 17, 0)),
+    ("sol_get_epoch_rewards_sysvar", (1, 18, 0)),
+    ("sol_get_sysvar", (2, 1, 0)),
+    ("sol_get_epoch_stake", (2, 1, 0)),
+];
+
+/// Newest Solana version the syscall table above is known to be complete for
+const SYSCALLS_KNOWN_UNTIL: (u32, u32, u32) = (2, 1, u32::MAX);
+
+/// Returned when the build produced an artifact that can't be a deployable program, as
+/// opposed to a valid program whose hash does not match.
+#[derive(Debug)]
+pub struct InvalidArtifactError {
+    pub path: String,
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for InvalidArtifactError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Built artifact {} is not a valid SBF program:",
+            self.path
+        )?;
+        for problem in &self.problems {
+            write!(f, "\n - {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for InvalidArtifactError {}
+
+/// Splits undefined symbols into ones that are not syscalls of `solana_version` (problems)
+/// and ones that can't be checked because the version is newer than the syscall table
+/// (warnings).
+fn check_undefined_symbols(
+    symbols: &[&str],
+    solana_version: (u32, u32, u32),
+) -> (Vec<String>, Vec<String>) {
+    let mut problems = vec![];
+    let mut warnings = vec![];
+    for symbol in symbols {
+        match SYSCALLS.iter().find(|(name, _)| name == symbol) {
+            Some((_, since)) if *since <= solana_version => {}
+            Some((_, since)) => problems.push(format!(
+                "syscall {} requires Solana v{}.{}.{} or later",
+                symbol, since.0, since.1, since.2
+            )),
+            None if solana_version > SYSCALLS_KNOWN_UNTIL => {
+                warnings.push(format!("unknown syscall {}", symbol))
+            }
+            None => problems.push(format!("undefined symbol {}", symbol)),
+        }
+    }
+    (problems, warnings)
+}
+
+fn undefined_symbols<'a>(elf: &Elf<'a>) -> Vec<&'a str> {
+    elf.dynsyms
+        .iter()
+        .filter(|sym| sym.st_shndx == SHN_UNDEF as usize && sym.st_name != 0)
+        .filter_map(|sym| elf.dynstrtab.get_at(sym.st_name))
+        .collect()
+}
+
+/// Returns the syscalls imported by a program.
+pub fn get_syscalls(bytes: &[u8]) -> anyhow::Result<Vec<String>> {
+    let elf = Elf::parse(bytes).map_err(|e| anyhow!("Failed to parse program: {}", e))?;
+    Ok(undefined_symbols(&elf)
+        .into_iter()
+        .map(|symbol| symbol.to_string())
+        .collect())
+}
+
+/// Checks that `bytes` is an SBF shared object with an entrypoint that only imports syscalls
+/// available in `solana_version`.
+pub fn validate_sbf_program(
+    path: &str,
+    bytes: &[u8],
+    solana_version: (u32, u32, u32),
+) -> anyhow::Result<()> {
+    let invalid = |problems: Vec<String>| InvalidArtifactError {
+        path: path.to_string(),
+        problems,
+    };
+
+    let elf = Elf::parse(bytes).map_err(|e| invalid(vec![format!("not an ELF file: {}", e)]))?;
+
+    let mut problems = vec![];
+    if !elf.is_64 || !elf.little_endian {
+        problems.push("not a 64-bit little-endian ELF".to_string());
+    }
+    if elf.header.e_type != ET_DYN {
+        problems.push(format!(
+            "not a shared object (ELF type {})",
+            elf.header.e_type
+        ));
+    }
+    if elf.header.e_machine != EM_BPF && elf.header.e_machine != EM_SBPF {
+        problems.push(format!(
+            "built for machine type {} instead of SBF",
+            elf.header.e_machine
+        ));
+    }
+
+    let has_entrypoint_symbol = elf.dynsyms.iter().any(|sym| {
+        sym.st_shndx != SHN_UNDEF as usize
+            && elf.dynstrtab.get_at(sym.st_name) == Some("entrypoint")
+    });
+    if elf.entry == 0 && !has_entrypoint_symbol {
+        problems.push("no entrypoint".to_string());
+    }
+
+    let undefined_symbols = undefined_symbols(&elf);
+    let (symbol_problems, warnings) = check_undefined_symbols(&undefined_symbols, solana_version);
+    problems.extend(symbol_problems);
+    for warning in warnings {
+        warn!("{} in {}", warning, path);
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!(invalid(problems)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_undefined_symbols() {
+        let (problems, warnings) =
+            check_undefined_symbols(&["sol_log_", "sol_poseidon"], (1, 18, 23));
+        assert!(problems.is_empty() && warnings.is_empty());
+
+        let (problems, _) = check_undefined_symbols(&["sol_get_epoch_stake"], (1, 18, 23));
+        assert_eq!(
+            problems,
+            vec!["syscall sol_get_epoch_stake requires Solana v2.1.0 or later"]
+        );
+
+        let (problems, _) = check_undefined_symbols(&["memcpy"], (1, 18, 23));
+        assert_eq!(problems, vec!["undefined symbol memcpy"]);
+
+        let (problems, warnings) = check_undefined_symbols(&["sol_new_syscall"], (2, 2, 0));
+        assert!(problems.is_empty());
+        assert_eq!(warnings, vec!["unknown syscall sol_new_syscall"]);
+    }
+
+    #[test]
+    fn test_validate_rejects_non_elf() {
+        let err = validate_sbf_program("program.so", b"not an elf", (1, 18, 23)).unwrap_err();
+        assert!(err.downcast_ref::<InvalidArtifactError>().is_some());
+    }
+}
@@ -0,0 +1,199 @@
+use anyhow::anyhow;
+use std::{
+    ffi::OsString,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, Command, Stdio},
+    str::FromStr,
+    sync::OnceLock,
+};
+
+/// Extension of artifacts encrypted with age
+pub const AGE_EXTENSION: &str = "age";
+
+/// Recipients every artifact is encrypted to, set with `--encrypt-to`
+static RECIPIENTS: OnceLock<Vec<Recipient>> = OnceLock::new();
+
+/// Who can decrypt the artifacts of a run: an age public key (`age1...`), an SSH public key
+/// (`ssh-ed25519 ...` or `ssh-rsa ...`), or a file listing recipients one per line, such as
+/// an `authorized_keys` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Recipient {
+    Key(String),
+    File(PathBuf),
+}
+
+impl FromStr for Recipient {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let s = s.trim();
+        if s.starts_with("age1") || s.starts_with("ssh-ed25519 ") || s.starts_with("ssh-rsa ") {
+            Ok(Recipient::Key(s.to_string()))
+        } else if Path::new(s).is_file() {
+            Ok(Recipient::File(PathBuf::from(s)))
+        } else {
+            Err(anyhow!(
+                "Invalid recipient {:?}, expected an age public key (age1...), an SSH public key (ssh-ed25519 or ssh-rsa) or a file of recipients",
+                s
+            ))
+        }
+    }
+}
+
+/// Sets the recipients artifacts are encrypted to. Fails up front if the `age` CLI is missing,
+/// rather than on the first artifact written.
+pub fn set_encryption_recipients(recipients: Vec<Recipient>) -> anyhow::Result<()> {
+    if !recipients.is_empty() {
+        check_age_available()?;
+    }
+    let _ = RECIPIENTS.set(recipients);
+    Ok(())
+}
+
+pub fn get_encryption_recipients() -> &'static [Recipient] {
+    RECIPIENTS.get().map(Vec::as_slice).unwrap_or_default()
+}
+
+pub fn is_encrypted(path: impl AsRef<Path>) -> bool {
+    path.as_ref()
+        .extension()
+        .is_some_and(|extension| extension == AGE_EXTENSION)
+}
+
+/// Path an artifact for `path` is written to once encrypted.
+pub fn encrypted_path(path: &Path) -> PathBuf {
+    if is_encrypted(path) {
+        return path.to_path_buf();
+    }
+    let mut encrypted = path.as_os_str().to_owned();
+    encrypted.push(".");
+    encrypted.push(AGE_EXTENSION);
+    PathBuf::from(encrypted)
+}
+
+fn age_args(recipients: &[Recipient]) -> Vec<OsString> {
+    let mut args = vec![];
+    for recipient in recipients {
+        match recipient {
+            Recipient::Key(key) => args.extend(["-r".into(), key.into()]),
+            Recipient::File(path) => args.extend(["-R".into(), path.into()]),
+        }
+    }
+    args
+}
+
+pub fn check_age_available() -> anyhow::Result<()> {
+    let output = Command::new("age")
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| {
+            anyhow!(
+                "Failed to run age, install it from https://age-encryption.org: {}",
+                e
+            )
+        })?;
+    if !output.success() {
+        return Err(anyhow!("age --version failed, check the age installation"));
+    }
+    Ok(())
+}
+
+/// Encrypts what is written to it to `recipients` with the `age` CLI, in the binary age
+/// format, into a file.
+pub struct Encryptor {
+    child: Child,
+    stdin: Option<ChildStdin>,
+}
+
+impl Encryptor {
+    pub fn create(recipients: &[Recipient], output: &Path) -> anyhow::Result<Encryptor> {
+        let mut child = Command::new("age")
+            .args(age_args(recipients))
+            .arg("-o")
+            .arg(output)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                anyhow!(
+                    "Failed to run age, install it from https://age-encryption.org: {}",
+                    e
+                )
+            })?;
+        let stdin = child.stdin.take();
+        Ok(Encryptor { child, stdin })
+    }
+
+    /// Waits for age to write the encrypted file.
+    pub fn finish(mut self) -> anyhow::Result<()> {
+        drop(self.stdin.take());
+        let output = self.child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "age failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Write for Encryptor {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self.stdin.as_mut() {
+            Some(stdin) => stdin.write(buf),
+            None => Err(std::io::ErrorKind::BrokenPipe.into()),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self.stdin.as_mut() {
+            Some(stdin) => stdin.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_recipient() {
+        let key = "age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p";
+        assert_eq!(
+            Recipient::from_str(key).unwrap(),
+            Recipient::Key(key.to_string())
+        );
+        let ssh = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIHsKLqeplhpW+uObz5dvMgjz1OxfM/XXUB+VHtZ6isGN ci@example";
+        assert_eq!(
+            Recipient::from_str(ssh).unwrap(),
+            Recipient::Key(ssh.to_string())
+        );
+        assert!(Recipient::from_str("not-a-key").is_err());
+
+        assert_eq!(
+            age_args(&[
+                Recipient::Key(key.to_string()),
+                Recipient::File(PathBuf::from("recipients.txt"))
+            ]),
+            vec!["-r", key, "-R", "recipients.txt"]
+        );
+    }
+
+    #[test]
+    fn test_encrypted_path() {
+        assert_eq!(
+            encrypted_path(Path::new("report.json.zst")),
+            Path::new("report.json.zst.age")
+        );
+        assert_eq!(
+            encrypted_path(Path::new("bundle.age")),
+            Path::new("bundle.age")
+        );
+    }
+}
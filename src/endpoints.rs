@@ -0,0 +1,345 @@
+use anyhow::anyhow;
+use log::warn;
+use serde::Deserialize;
+use solana_cli_config::{Config, CONFIG_FILE};
+use std::{collections::BTreeMap, path::Path, sync::OnceLock};
+
+use crate::secrets::register_rpc_url;
+use crate::signers::CONFIG_FILE_NAME;
+
+/// Remote verification API used unless configured otherwise
+pub const DEFAULT_REMOTE_API_URL: &str = "https://verify.osec.io";
+
+/// Endpoints of the run, resolved once from the command line and the config files
+static ENDPOINTS: OnceLock<EndpointConfig> = OnceLock::new();
+
+/// Clusters that can be named instead of giving an RPC URL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cluster {
+    Mainnet,
+    Devnet,
+    Testnet,
+    Localnet,
+}
+
+impl Cluster {
+    const ALL: [Cluster; 4] = [
+        Cluster::Mainnet,
+        Cluster::Devnet,
+        Cluster::Testnet,
+        Cluster::Localnet,
+    ];
+
+    pub fn from_moniker(moniker: &str) -> Option<Cluster> {
+        match moniker {
+            "mainnet" | "main" | "m" | "mainnet-beta" => Some(Cluster::Mainnet),
+            "devnet" | "dev" | "d" => Some(Cluster::Devnet),
+            "testnet" | "test" | "t" => Some(Cluster::Testnet),
+            "localnet" | "localhost" | "l" | "local" => Some(Cluster::Localnet),
+            _ => None,
+        }
+    }
+
+    /// Key of the cluster in the `[endpoints.clusters]` table
+    pub fn name(&self) -> &'static str {
+        match self {
+            Cluster::Mainnet => "mainnet",
+            Cluster::Devnet => "devnet",
+            Cluster::Testnet => "testnet",
+            Cluster::Localnet => "localnet",
+        }
+    }
+
+    pub fn public_url(&self) -> &'static str {
+        match self {
+            Cluster::Mainnet => "https://api.mainnet-beta.solana.com",
+            Cluster::Devnet => "https://api.devnet.solana.com",
+            Cluster::Testnet => "https://api.testnet.solana.com",
+            Cluster::Localnet => "http://localhost:8899",
+        }
+    }
+}
+
+/// Endpoints configured for a cluster in solana-verify.toml.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ClusterEndpoints {
+    /// RPC used when the cluster is named, instead of its public endpoint
+    pub rpc_url: Option<String>,
+    #[serde(default)]
+    pub cross_check_rpcs: Vec<String>,
+}
+
+/// The `[endpoints]` section of solana-verify.toml:
+///
+/// ```toml
+/// [endpoints]
+/// remote-api-url = "https://verify.osec.io"
+///
+/// [endpoints.clusters.mainnet]
+/// rpc-url = "https://mainnet.helius-rpc.com/?api-key=..."
+/// cross-check-rpcs = ["https://api.mainnet-beta.solana.com"]
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct EndpointsFile {
+    pub remote_api_url: Option<String>,
+    #[serde(default)]
+    pub clusters: BTreeMap<String, ClusterEndpoints>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    endpoints: EndpointsFile,
+}
+
+impl EndpointsFile {
+    pub fn parse(contents: &str) -> anyhow::Result<EndpointsFile> {
+        let config: ConfigFile =
+            toml::from_str(contents).map_err(|e| anyhow!("Invalid {}: {}", CONFIG_FILE_NAME, e))?;
+        for name in config.endpoints.clusters.keys() {
+            if Cluster::from_moniker(name).map(|cluster| cluster.name()) != Some(name.as_str()) {
+                return Err(anyhow!(
+                    "Unknown cluster {} in the [endpoints.clusters] section of {}, expected one of mainnet, devnet, testnet or localnet",
+                    name,
+                    CONFIG_FILE_NAME
+                ));
+            }
+        }
+        Ok(config.endpoints)
+    }
+
+    /// Loads the endpoints of `path`, none if the file does not exist.
+    pub fn load(path: &Path) -> anyhow::Result<EndpointsFile> {
+        if !path.exists() {
+            return Ok(EndpointsFile::default());
+        }
+        EndpointsFile::parse(&std::fs::read_to_string(path)?)
+    }
+
+    fn cluster(&self, cluster: Cluster) -> Option<&ClusterEndpoints> {
+        self.clusters.get(cluster.name())
+    }
+
+    /// Cluster whose configured or public RPC is `url`.
+    fn cluster_of_url(&self, url: &str) -> Option<Cluster> {
+        Cluster::ALL.into_iter().find(|cluster| {
+            cluster.public_url() == url
+                || self
+                    .cluster(*cluster)
+                    .and_then(|endpoints| endpoints.rpc_url.as_deref())
+                    == Some(url)
+        })
+    }
+
+    /// RPC URL of a URL or cluster moniker.
+    pub fn rpc_url(&self, url_or_moniker: &str) -> String {
+        match Cluster::from_moniker(url_or_moniker) {
+            Some(cluster) => self
+                .cluster(cluster)
+                .and_then(|endpoints| endpoints.rpc_url.clone())
+                .unwrap_or_else(|| cluster.public_url().to_string()),
+            None => url_or_moniker.to_string(),
+        }
+    }
+}
+
+/// Every endpoint a run talks to. Resolved once so all commands of a run use the same RPC
+/// and remote API.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndpointConfig {
+    pub rpc_url: String,
+    pub remote_api_url: String,
+    /// Additional RPC providers that must agree on the on-chain program hash
+    pub cross_check_rpcs: Vec<String>,
+    /// Kept to resolve cluster monikers given later in the run
+    file: EndpointsFile,
+}
+
+impl EndpointConfig {
+    /// Resolves the endpoints from, in order of precedence, the command line, solana-verify.toml
+    /// and the RPC of the Solana CLI config, defaulting to mainnet. Without `--url`, the RPC
+    /// configured in solana-verify.toml for the cluster of the Solana CLI config, else for
+    /// mainnet, is used.
+    pub fn resolve(
+        file: &EndpointsFile,
+        url: Option<&str>,
+        cli_config_url: Option<&str>,
+        cross_check_rpcs: Option<Vec<String>>,
+        remote_api_url: Option<&str>,
+    ) -> EndpointConfig {
+        let rpc_url = match (url, cli_config_url) {
+            (Some(url), _) => file.rpc_url(url),
+            (None, cli_config_url) => {
+                let cluster = cli_config_url
+                    .and_then(|url| file.cluster_of_url(url))
+                    .unwrap_or(Cluster::Mainnet);
+                file.cluster(cluster)
+                    .and_then(|endpoints| endpoints.rpc_url.clone())
+                    .or(cli_config_url.map(str::to_string))
+                    .unwrap_or_else(|| cluster.public_url().to_string())
+            }
+        };
+        let cross_check_rpcs = match cross_check_rpcs {
+            Some(urls) => urls.iter().map(|url| file.rpc_url(url)).collect(),
+            None => file
+                .cluster_of_url(&rpc_url)
+                .and_then(|cluster| file.cluster(cluster))
+                .map(|endpoints| endpoints.cross_check_rpcs.clone())
+                .unwrap_or_default(),
+        };
+        EndpointConfig {
+            rpc_url,
+            remote_api_url: remote_api_url
+                .or(file.remote_api_url.as_deref())
+                .unwrap_or(DEFAULT_REMOTE_API_URL)
+                .trim_end_matches('/')
+                .to_string(),
+            cross_check_rpcs,
+            file: file.clone(),
+        }
+    }
+
+    /// Registers the RPC URLs of the run and of solana-verify.toml, which may carry API keys,
+    /// to scrub them from output.
+    pub fn register_secrets(&self) {
+        let configured = self
+            .file
+            .clusters
+            .values()
+            .flat_map(|endpoints| endpoints.rpc_url.iter().chain(&endpoints.cross_check_rpcs));
+        for url in std::iter::once(&self.rpc_url)
+            .chain(&self.cross_check_rpcs)
+            .chain(configured)
+        {
+            register_rpc_url(url);
+        }
+    }
+
+    /// RPC URL of a URL or cluster moniker given during the run, the run's RPC if none is.
+    pub fn rpc_url_for(&self, url_or_moniker: Option<&str>) -> String {
+        match url_or_moniker {
+            Some(url_or_moniker) => self.file.rpc_url(url_or_moniker),
+            None => self.rpc_url.clone(),
+        }
+    }
+}
+
+/// RPC of the Solana CLI config, if one is set up.
+pub fn get_cli_config_url() -> Option<String> {
+    let config_file = CONFIG_FILE.as_ref()?;
+    Config::load(config_file)
+        .ok()
+        .map(|config| config.json_rpc_url)
+}
+
+pub fn set_endpoints(endpoints: EndpointConfig) {
+    let _ = ENDPOINTS.set(endpoints);
+}
+
+/// Endpoints of the run. Outside the CLI they are resolved from the config files on first use.
+pub fn endpoints() -> &'static EndpointConfig {
+    ENDPOINTS.get_or_init(|| {
+        let file = EndpointsFile::load(Path::new(CONFIG_FILE_NAME)).unwrap_or_else(|err| {
+            warn!("Ignoring the endpoints of {}: {}", CONFIG_FILE_NAME, err);
+            EndpointsFile::default()
+        });
+        let endpoints =
+            EndpointConfig::resolve(&file, None, get_cli_config_url().as_deref(), None, None);
+        endpoints.register_secrets();
+        endpoints
+    })
+}
+
+pub fn remote_api_url() -> &'static str {
+    &endpoints().remote_api_url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+[signers]
+deployer = "keys/deployer.json"
+
+[endpoints]
+remote-api-url = "https://verify.example.com/"
+
+[endpoints.clusters.mainnet]
+rpc-url = "https://mainnet.example.com/?api-key=secret"
+cross-check-rpcs = ["https://api.mainnet-beta.solana.com"]
+"#;
+
+    #[test]
+    fn test_resolve_endpoints() {
+        let file = EndpointsFile::parse(CONFIG).unwrap();
+
+        let endpoints = EndpointConfig::resolve(&file, Some("m"), None, None, None);
+        assert_eq!(
+            endpoints.rpc_url,
+            "https://mainnet.example.com/?api-key=secret"
+        );
+        assert_eq!(
+            endpoints.cross_check_rpcs,
+            vec!["https://api.mainnet-beta.solana.com"]
+        );
+        assert_eq!(endpoints.remote_api_url, "https://verify.example.com");
+        assert_eq!(
+            endpoints.rpc_url_for(Some("mainnet")),
+            "https://mainnet.example.com/?api-key=secret"
+        );
+        assert_eq!(
+            endpoints.rpc_url_for(Some("d")),
+            "https://api.devnet.solana.com"
+        );
+        assert_eq!(endpoints.rpc_url_for(None), endpoints.rpc_url);
+
+        // The command line wins over the config files
+        let endpoints = EndpointConfig::resolve(
+            &file,
+            Some("devnet"),
+            Some("https://cli.example.com"),
+            Some(vec!["t".to_string()]),
+            Some("http://localhost:3000"),
+        );
+        assert_eq!(endpoints.rpc_url, "https://api.devnet.solana.com");
+        assert_eq!(
+            endpoints.cross_check_rpcs,
+            vec!["https://api.testnet.solana.com"]
+        );
+        assert_eq!(endpoints.remote_api_url, "http://localhost:3000");
+
+        // Without --url solana-verify.toml decides for the cluster of the Solana CLI config,
+        // else for mainnet
+        let endpoints =
+            EndpointConfig::resolve(&file, None, Some("https://cli.example.com"), None, None);
+        assert_eq!(
+            endpoints.rpc_url,
+            "https://mainnet.example.com/?api-key=secret"
+        );
+        let endpoints = EndpointConfig::resolve(
+            &file,
+            None,
+            Some("https://api.devnet.solana.com"),
+            None,
+            None,
+        );
+        assert_eq!(endpoints.rpc_url, "https://api.devnet.solana.com");
+        assert!(endpoints.cross_check_rpcs.is_empty());
+        let endpoints = EndpointConfig::resolve(
+            &EndpointsFile::default(),
+            None,
+            Some("https://cli.example.com"),
+            None,
+            None,
+        );
+        assert_eq!(endpoints.rpc_url, "https://cli.example.com");
+        let defaults = EndpointConfig::resolve(&EndpointsFile::default(), None, None, None, None);
+        assert_eq!(defaults.rpc_url, "https://api.mainnet-beta.solana.com");
+        assert_eq!(defaults.remote_api_url, DEFAULT_REMOTE_API_URL);
+
+        assert!(EndpointsFile::parse("[endpoints.clusters.m]\nrpc-url = \"x\"\n").is_err());
+    }
+}
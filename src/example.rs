@@ -0,0 +1,153 @@
+use anyhow::anyhow;
+use std::path::Path;
+
+/// Name of the crate in examples/hello_world, replaced by the name of the new project
+const TEMPLATE_NAME: &str = "hello_world";
+
+const CARGO_TOML: &str = include_str!("../examples/hello_world/Cargo.toml");
+// The lockfile pins the dependencies, which `solana-verify build` requires
+const CARGO_LOCK: &str = include_str!("../examples/hello_world/Cargo.lock");
+const LIB_RS: &str = include_str!("../examples/hello_world/src/lib.rs");
+
+const GITIGNORE: &str = "/target\n";
+
+const MAKEFILE: &str = r#"# Deploys the program to devnet and verifies it with solana-verify, to check that the
+# verifiable build environment works before verifying a production program.
+#
+#   make build                       build the program in the verifiable build container
+#   make deploy                      deploy target/deploy/PROGRAM.so to devnet
+#   make verify REPO_URL=<git url>   rebuild the committed checkout, compare it with the
+#                                    deployed program and upload the verification PDA
+
+PROGRAM := {name}
+URL ?= devnet
+KEYPAIR ?= $(HOME)/.config/solana/id.json
+PROGRAM_KEYPAIR := target/deploy/$(PROGRAM)-keypair.json
+
+.PHONY: build hash deploy verify status
+
+build:
+	solana-verify build --library-name $(PROGRAM)
+
+hash: build
+	solana-verify get-executable-hash target/deploy/$(PROGRAM).so
+
+deploy: build
+	solana program deploy --url $(URL) --keypair $(KEYPAIR) --program-id $(PROGRAM_KEYPAIR) target/deploy/$(PROGRAM).so
+
+verify: deploy
+	@test -n "$(REPO_URL)" || (echo "Push the project and set REPO_URL to its git url" && exit 1)
+	solana-verify verify-from-repo --url $(URL) --keypair $(KEYPAIR) --skip-prompt \
+		--program-id $$(solana address -k $(PROGRAM_KEYPAIR)) \
+		--library-name $(PROGRAM) --skip-clone . $(REPO_URL)
+
+status:
+	solana-verify get-program-pda --url $(URL) --program-id $$(solana address -k $(PROGRAM_KEYPAIR)) \
+		--signer $$(solana address -k $(KEYPAIR))
+"#;
+
+/// Turns a directory name into a crate name usable as the library name.
+fn crate_name(dir_name: &str) -> String {
+    let name = dir_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>()
+        .to_lowercase();
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", name)
+    } else {
+        name
+    }
+}
+
+/// Files of the example project, relative to its root.
+fn render(name: &str) -> Vec<(&'static str, String)> {
+    let rename = |contents: &str| {
+        contents.replace(
+            &format!("name = \"{}\"", TEMPLATE_NAME),
+            &format!("name = \"{}\"", name),
+        )
+    };
+    vec![
+        ("Cargo.toml", rename(CARGO_TOML)),
+        ("Cargo.lock", rename(CARGO_LOCK)),
+        ("src/lib.rs", LIB_RS.to_string()),
+        (".gitignore", GITIGNORE.to_string()),
+        ("Makefile", MAKEFILE.replace("{name}", name)),
+    ]
+}
+
+/// Scaffolds a hello world program set up for verifiable builds in `dir`, which must not
+/// exist or be empty.
+pub fn new_example(dir: &Path, name: Option<&str>) -> anyhow::Result<String> {
+    if dir
+        .read_dir()
+        .is_ok_and(|mut entries| entries.next().is_some())
+    {
+        return Err(anyhow!("{} already exists and is not empty", dir.display()));
+    }
+    let name = match name {
+        Some(name) => crate_name(name),
+        None => crate_name(
+            &dir.canonicalize()
+                .unwrap_or_else(|_| dir.to_path_buf())
+                .file_name()
+                .ok_or_else(|| anyhow!("Cannot name a project after {}", dir.display()))?
+                .to_string_lossy(),
+        ),
+    };
+
+    for (path, contents) in render(&name) {
+        let path = dir.join(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, contents)
+            .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+    }
+    git2::Repository::init(dir)
+        .map_err(|e| anyhow!("Failed to initialize a git repository: {}", e))?;
+
+    println!("Created example program {} in {}", name, dir.display());
+    println!("Next steps:");
+    println!("  cd {}", dir.display());
+    println!("  make build");
+    println!("  git add . && git commit -m \"Hello world\" && git push <your remote>");
+    println!("  make verify REPO_URL=<your remote's https url>");
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crate_name() {
+        assert_eq!(crate_name("my-program"), "my_program");
+        assert_eq!(crate_name("Verify.Sandbox"), "verify_sandbox");
+        assert_eq!(crate_name("2024-test"), "_2024_test");
+    }
+
+    #[test]
+    fn test_new_example() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("verify-sandbox");
+        let name = new_example(&dir, None).unwrap();
+        assert_eq!(name, "verify_sandbox");
+
+        let cargo_toml = std::fs::read_to_string(dir.join("Cargo.toml")).unwrap();
+        assert!(cargo_toml.contains(&format!("name = \"{}\"", name)));
+        assert!(!cargo_toml.contains(TEMPLATE_NAME));
+        let lockfile = cargo_lock::Lockfile::load(dir.join("Cargo.lock")).unwrap();
+        assert!(lockfile
+            .packages
+            .iter()
+            .any(|package| package.name.as_str() == name));
+        assert!(std::fs::read_to_string(dir.join("Makefile"))
+            .unwrap()
+            .contains(&format!("PROGRAM := {}", name)));
+        assert!(dir.join(".git").exists());
+
+        assert!(new_example(&dir, None).is_err());
+    }
+}
@@ -0,0 +1,331 @@
+use anyhow::anyhow;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::api::get_last_deployed_slot;
+use crate::cleanup::{check_interrupted, remove_dir};
+use crate::git_backend;
+use crate::{
+    build, build_args, clone_repo_and_checkout, find_executable, get_basename, get_file_hash,
+    get_program_hash,
+};
+
+/// Hashes of previous builds, so that walking the same history again doesn't rebuild
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BuildHashCache(HashMap<String, String>);
+
+impl BuildHashCache {
+    fn path() -> Option<PathBuf> {
+        let home = std::env::var_os("HOME")?;
+        Some(PathBuf::from(home).join(".cache/solana-verify/build-hashes.json"))
+    }
+
+    pub fn load() -> BuildHashCache {
+        Self::path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = Self::path().ok_or_else(|| anyhow!("Unable to find the home directory"))?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    fn key(repo_url: &str, commit: &str, args: &[String]) -> String {
+        format!("{}@{} {}", repo_url, commit, args.join(" "))
+    }
+
+    pub fn get(&self, repo_url: &str, commit: &str, args: &[String]) -> Option<&String> {
+        self.0.get(&Self::key(repo_url, commit, args))
+    }
+
+    pub fn insert(&mut self, repo_url: &str, commit: &str, args: &[String], hash: String) {
+        self.0.insert(Self::key(repo_url, commit, args), hash);
+    }
+}
+
+/// Builds the commits of a repository, newest first, until one reproduces the on-chain hash
+/// of the program. Without a range, only commits made before the program was last deployed
+/// are considered.
+#[allow(clippy::too_many_arguments)]
+pub async fn find_commit(
+    connection: &RpcClient,
+    program_id: Pubkey,
+    repo_url: &str,
+    range: Option<&str>,
+    max_commits: Option<usize>,
+    relative_mount_path: &str,
+    library_name: Option<String>,
+    base_image: Option<String>,
+    bpf_flag: bool,
+    cargo_args: Vec<String>,
+) -> anyhow::Result<String> {
+    let program_hash = get_program_hash(connection, program_id)?;
+    println!("On-chain Program Hash: {}", program_hash);
+
+    let before = if range.is_none() {
+        let deployed_slot = get_last_deployed_slot(connection, &program_id.to_string())
+            .await
+            .map_err(|err| anyhow!("Unable to get last deployed slot: {}", err))?;
+        match connection.get_block_time(deployed_slot) {
+            Ok(block_time) => {
+                info!(
+                    "Program deployed at slot {}, only considering earlier commits",
+                    deployed_slot
+                );
+                Some(block_time)
+            }
+            Err(_) => None,
+        }
+    } else {
+        None
+    };
+
+    let (repo_path, verify_dir) =
+        clone_repo_and_checkout(repo_url, false, &get_basename(repo_url)?, None)?;
+
+    let result = walk_commits(
+        &repo_path,
+        &program_hash,
+        repo_url,
+        range.unwrap_or("HEAD"),
+        before,
+        max_commits,
+        relative_mount_path,
+        library_name,
+        base_image,
+        bpf_flag,
+        cargo_args,
+    );
+
+    remove_dir(&verify_dir)?;
+
+    let commit =
+        result?.ok_or_else(|| anyhow!("No candidate commit reproduces the on-chain program ❌"))?;
+    println!("Commit {} reproduces the on-chain program ✅", commit);
+    Ok(commit)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn walk_commits(
+    repo_path: &Path,
+    program_hash: &str,
+    repo_url: &str,
+    range: &str,
+    before: Option<i64>,
+    max_commits: Option<usize>,
+    relative_mount_path: &str,
+    library_name: Option<String>,
+    base_image: Option<String>,
+    bpf_flag: bool,
+    cargo_args: Vec<String>,
+) -> anyhow::Result<Option<String>> {
+    // Commits not touching the program or the lockfile can't change the build
+    let paths = if relative_mount_path.is_empty() {
+        vec![]
+    } else {
+        vec![relative_mount_path, "Cargo.lock"]
+    };
+    let commits = git_backend::rev_list(repo_path, range, before, &paths, max_commits)?;
+    info!("Checking {} candidate commit(s)", commits.len());
+
+    let mut cache = BuildHashCache::load();
+    for (i, commit) in commits.iter().enumerate() {
+        check_interrupted()?;
+        info!("[{}/{}] Checking commit {}", i + 1, commits.len(), commit);
+        let build_hash = match build_commit(
+            repo_path,
+            repo_url,
+            commit,
+            &mut cache,
+            relative_mount_path,
+            library_name.clone(),
+            base_image.clone(),
+            bpf_flag,
+            cargo_args.clone(),
+        ) {
+            Ok(build_hash) => build_hash,
+            Err(err) => {
+                warn!("Skipping commit {}: {}", commit, err);
+                continue;
+            }
+        };
+
+        info!("Executable Program Hash: {}", build_hash);
+        if build_hash == program_hash {
+            return Ok(Some(commit.clone()));
+        }
+    }
+    Ok(None)
+}
+
+/// Checks out `commit` in the clone at `repo_path` and returns the hash of its build. The
+/// clone's target directory is kept between commits, so dependencies are only rebuilt when
+/// they change.
+#[allow(clippy::too_many_arguments)]
+fn build_commit(
+    repo_path: &Path,
+    repo_url: &str,
+    commit: &str,
+    cache: &mut BuildHashCache,
+    relative_mount_path: &str,
+    library_name: Option<String>,
+    base_image: Option<String>,
+    bpf_flag: bool,
+    cargo_args: Vec<String>,
+) -> anyhow::Result<String> {
+    // Builds may touch tracked files such as Cargo.lock, the checkout discards their changes
+    if !git_backend::checkout(repo_path, commit)? {
+        return Err(anyhow!("commit {} is not in the clone", commit));
+    }
+
+    let (args, mount_path, library_name) = build_args(
+        relative_mount_path,
+        library_name,
+        repo_path,
+        base_image.clone(),
+        bpf_flag,
+        cargo_args.clone(),
+    )?;
+
+    if let Some(build_hash) = cache.get(repo_url, commit, &args) {
+        info!("Using cached build hash");
+        return Ok(build_hash.clone());
+    }
+    let build_hash = build(
+        Some(mount_path.clone()),
+        Some(library_name.clone()),
+        base_image,
+        bpf_flag,
+        cargo_args,
+    )
+    .and_then(|_| Ok(get_file_hash(find_executable(&mount_path, &library_name)?)?))
+    .map_err(|err| anyhow!("build failed: {}", err))?;
+
+    cache.insert(repo_url, commit, &args, build_hash.clone());
+    if let Err(err) = cache.save() {
+        warn!("Unable to save the build hash cache: {}", err);
+    }
+    Ok(build_hash)
+}
+
+/// Resolves a commit, tag or branch of the clone to a commit hash. Branches other than the
+/// default one only exist as remote-tracking branches in a fresh clone.
+fn resolve_commit(repo_path: &Path, rev: &str) -> anyhow::Result<String> {
+    match git_backend::resolve_commit(repo_path, rev)? {
+        Some(commit) => Ok(commit),
+        None => git_backend::resolve_commit(repo_path, &format!("origin/{}", rev))?
+            .ok_or_else(|| anyhow!("{} is not a commit, tag or branch of the repository", rev)),
+    }
+}
+
+/// Which of the compared commits reproduce the on-chain program.
+fn describe_comparison(matching: &[&str]) -> String {
+    match matching {
+        [] => "Neither commit reproduces the on-chain program ❌".to_string(),
+        [commit] => format!("The on-chain program was built from {} ✅", commit),
+        _ => format!(
+            "Both commits reproduce the on-chain program ✅ ({} builds identically)",
+            matching.join(" and ")
+        ),
+    }
+}
+
+/// Builds two revisions of a repository in the same clone and reports which of them, if
+/// any, reproduces the on-chain program, e.g. a release tag and a hotfix branch.
+#[allow(clippy::too_many_arguments)]
+pub fn compare_commits(
+    connection: &RpcClient,
+    program_id: Pubkey,
+    repo_url: &str,
+    revs: [&str; 2],
+    relative_mount_path: &str,
+    library_name: Option<String>,
+    base_image: Option<String>,
+    bpf_flag: bool,
+    cargo_args: Vec<String>,
+) -> anyhow::Result<()> {
+    let program_hash = get_program_hash(connection, program_id)?;
+
+    let (repo_path, verify_dir) =
+        clone_repo_and_checkout(repo_url, false, &get_basename(repo_url)?, None)?;
+
+    let mut cache = BuildHashCache::load();
+    let result = revs
+        .iter()
+        .map(|rev| {
+            check_interrupted()?;
+            let commit = resolve_commit(&repo_path, rev)?;
+            info!("Building {} ({})", rev, commit);
+            let build_hash = build_commit(
+                &repo_path,
+                repo_url,
+                &commit,
+                &mut cache,
+                relative_mount_path,
+                library_name.clone(),
+                base_image.clone(),
+                bpf_flag,
+                cargo_args.clone(),
+            );
+            Ok((commit, build_hash))
+        })
+        .collect::<anyhow::Result<Vec<_>>>();
+
+    remove_dir(&verify_dir)?;
+    let builds = result?;
+
+    println!("----------------------------------------------------------------");
+    println!("On-chain Program Hash: {}", program_hash);
+    let mut matching = vec![];
+    for (rev, (commit, build_hash)) in revs.iter().zip(&builds) {
+        match build_hash {
+            Ok(build_hash) if *build_hash == program_hash => {
+                println!("{} ({}): {} ✅", rev, commit, build_hash);
+                matching.push(*rev);
+            }
+            Ok(build_hash) => println!("{} ({}): {} ❌", rev, commit, build_hash),
+            Err(err) => println!("{} ({}): {}", rev, commit, err),
+        }
+    }
+    println!("{}", describe_comparison(&matching));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_comparison() {
+        assert!(describe_comparison(&[]).starts_with("Neither"));
+        assert_eq!(
+            describe_comparison(&["v1.2.0"]),
+            "The on-chain program was built from v1.2.0 ✅"
+        );
+        assert!(describe_comparison(&["v1.2.0", "hotfix"]).starts_with("Both"));
+    }
+
+    #[test]
+    fn test_build_hash_cache_key() {
+        let mut cache = BuildHashCache::default();
+        let args = vec!["--library-name".to_string(), "phoenix".to_string()];
+        cache.insert("https://github.com/a/b", "abc", &args, "hash".to_string());
+        assert_eq!(
+            cache.get("https://github.com/a/b", "abc", &args),
+            Some(&"hash".to_string())
+        );
+        assert_eq!(cache.get("https://github.com/a/b", "abc", &[]), None);
+    }
+}
@@ -0,0 +1,385 @@
+use anyhow::anyhow;
+use git2::{
+    build::CheckoutBuilder, build::RepoBuilder, Commit, DiffOptions, Direction, Oid, Remote,
+    Repository, Sort,
+};
+use log::warn;
+use std::{path::Path, process::Command, str::FromStr, sync::OnceLock};
+
+use crate::support_bundle::log_command;
+
+/// How repositories are cloned and checked out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GitBackend {
+    /// The bundled libgit2, falling back to the git CLI for what it cannot do, e.g. SSH remotes
+    Auto,
+    Libgit2,
+    Cli,
+}
+
+impl FromStr for GitBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "auto" => Ok(GitBackend::Auto),
+            "libgit2" => Ok(GitBackend::Libgit2),
+            "cli" => Ok(GitBackend::Cli),
+            _ => Err(anyhow!("Invalid git backend: {}", s)),
+        }
+    }
+}
+
+/// Backend given with `--git-backend`
+static GIT_BACKEND: OnceLock<GitBackend> = OnceLock::new();
+
+pub fn set_git_backend(backend: GitBackend) {
+    let _ = GIT_BACKEND.set(backend);
+}
+
+fn get_git_backend() -> GitBackend {
+    GIT_BACKEND.get().copied().unwrap_or(GitBackend::Auto)
+}
+
+/// Runs `libgit2`, then `cli` if libgit2 failed and the backend allows falling back.
+fn with_fallback<T>(
+    action: &str,
+    libgit2: impl FnOnce() -> Result<T, git2::Error>,
+    cli: impl FnOnce() -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    match get_git_backend() {
+        GitBackend::Cli => cli(),
+        GitBackend::Libgit2 => libgit2().map_err(|e| anyhow!("Failed to {}: {}", action, e)),
+        GitBackend::Auto => libgit2().or_else(|e| {
+            warn!("libgit2 failed to {} ({}), using the git CLI", action, e);
+            cli()
+        }),
+    }
+}
+
+fn git_cli(repo_path: Option<&Path>, args: &[&str]) -> anyhow::Result<bool> {
+    let mut git = Command::new("git");
+    if let Some(repo_path) = repo_path {
+        git.arg("-C").arg(repo_path);
+    }
+    git.args(args);
+    log_command(&git);
+    Ok(git
+        .output()
+        .map_err(|e| anyhow!("Failed to run git: {}", e))?
+        .status
+        .success())
+}
+
+/// Clones `repo_url` into `dest`, checking out its default branch.
+pub fn clone(repo_url: &str, dest: &Path) -> anyhow::Result<()> {
+    with_fallback(
+        "clone the repository",
+        || RepoBuilder::new().clone(repo_url, dest).map(|_| ()),
+        || {
+            // libgit2 may have left a partial clone behind
+            let _ = std::fs::remove_dir_all(dest);
+            let dest = dest.to_string_lossy();
+            if !git_cli(None, &["clone", repo_url, &dest])? {
+                return Err(anyhow!("git clone {} failed", repo_url));
+            }
+            Ok(())
+        },
+    )
+}
+
+fn checkout_oid(repo: &Repository, oid: Oid) -> Result<(), git2::Error> {
+    let commit = repo.find_commit(oid)?;
+    repo.checkout_tree(commit.as_object(), Some(CheckoutBuilder::new().force()))?;
+    repo.set_head_detached(commit.id())
+}
+
+/// Checks out `rev` with a detached HEAD. Returns false if the clone does not contain it.
+pub fn checkout(repo_path: &Path, rev: &str) -> anyhow::Result<bool> {
+    with_fallback(
+        "check out the commit",
+        || {
+            let repo = Repository::open(repo_path)?;
+            let oid = match repo.revparse_single(rev) {
+                Ok(object) => object.peel_to_commit()?.id(),
+                Err(e) if e.code() == git2::ErrorCode::NotFound => return Ok(false),
+                Err(e) => return Err(e),
+            };
+            checkout_oid(&repo, oid).map(|_| true)
+        },
+        || git_cli(Some(repo_path), &["checkout", rev]),
+    )
+}
+
+/// Fetches a commit by SHA from `origin` and checks it out, for commits no branch or tag
+/// contains. Returns false if the remote does not serve it.
+pub fn fetch_and_checkout(repo_path: &Path, commit: &str) -> anyhow::Result<bool> {
+    with_fallback(
+        "fetch the commit",
+        || {
+            let repo = Repository::open(repo_path)?;
+            let Ok(oid) = Oid::from_str(commit) else {
+                // Only full hashes can be fetched
+                return Ok(false);
+            };
+            if repo
+                .find_remote("origin")?
+                .fetch(&[commit], None, None)
+                .is_err()
+            {
+                return Ok(false);
+            }
+            checkout_oid(&repo, oid).map(|_| true)
+        },
+        || {
+            Ok(git_cli(Some(repo_path), &["fetch", "origin", commit])?
+                && git_cli(Some(repo_path), &["checkout", "FETCH_HEAD"])?)
+        },
+    )
+}
+
+/// Commit at the tip of the default branch of `repo_url`, and that branch if the remote
+/// advertises it.
+pub fn remote_head(repo_url: &str) -> anyhow::Result<(String, Option<String>)> {
+    with_fallback(
+        "list the remote",
+        || {
+            let mut remote = Remote::create_detached(repo_url)?;
+            remote.connect(Direction::Fetch)?;
+            let head = remote
+                .list()?
+                .iter()
+                .find(|head| head.name() == "HEAD")
+                .map(|head| {
+                    (
+                        head.oid().to_string(),
+                        head.symref_target()
+                            .and_then(|target| target.strip_prefix("refs/heads/"))
+                            .map(|branch| branch.to_string()),
+                    )
+                });
+            head.ok_or_else(|| git2::Error::from_str("the remote does not list HEAD"))
+        },
+        || {
+            let mut git = Command::new("git");
+            git.args(["ls-remote", "--symref", repo_url]);
+            log_command(&git);
+            let output = git
+                .output()
+                .map_err(|e| anyhow!("Failed to run git: {}", e))?;
+            if !output.status.success() {
+                return Err(anyhow!(
+                    "Failed to reach remote repository '{}': {}",
+                    repo_url,
+                    String::from_utf8_lossy(&output.stderr)
+                ));
+            }
+            parse_ls_remote_head(&String::from_utf8(output.stdout)?)
+                .ok_or_else(|| anyhow!("Failed to parse commit hash from git ls-remote output"))
+        },
+    )
+}
+
+/// HEAD and the branch it points to in the output of `git ls-remote --symref`.
+fn parse_ls_remote_head(output: &str) -> Option<(String, Option<String>)> {
+    let default_branch = output.lines().find_map(|line| {
+        line.strip_prefix("ref: refs/heads/")?
+            .split_whitespace()
+            .next()
+            .map(|branch| branch.to_string())
+    });
+    output
+        .lines()
+        .filter(|line| !line.starts_with("ref: "))
+        .find_map(
+            |line| match line.split_whitespace().collect::<Vec<_>>()[..] {
+                [hash, "HEAD"] => Some((hash.to_string(), default_branch.clone())),
+                _ => None,
+            },
+        )
+}
+
+/// Resolves `rev` in the clone at `repo_path` to a commit hash, or None if it is not a commit.
+pub fn resolve_commit(repo_path: &Path, rev: &str) -> anyhow::Result<Option<String>> {
+    let repo = Repository::open(repo_path)?;
+    let commit = repo
+        .revparse_single(rev)
+        .and_then(|object| object.peel_to_commit())
+        .ok()
+        .map(|commit| commit.id().to_string());
+    Ok(commit)
+}
+
+/// Whether `commit` changes one of `paths`. Like `git rev-list`, a merge only does if it
+/// differs from each of its parents.
+fn touches_paths(repo: &Repository, commit: &Commit, paths: &[&str]) -> Result<bool, git2::Error> {
+    let mut options = DiffOptions::new();
+    for path in paths {
+        options.pathspec(path);
+    }
+    let tree = commit.tree()?;
+    if commit.parent_count() == 0 {
+        let diff = repo.diff_tree_to_tree(None, Some(&tree), Some(&mut options))?;
+        return Ok(diff.deltas().len() > 0);
+    }
+    for parent in commit.parents() {
+        let diff =
+            repo.diff_tree_to_tree(Some(&parent.tree()?), Some(&tree), Some(&mut options))?;
+        if diff.deltas().len() == 0 {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Commits of `range` (a revision or `from..to`), newest first, like `git rev-list`: only
+/// those made at or before the `before` timestamp and changing one of `paths` if given, at
+/// most `max_commits` of them.
+pub fn rev_list(
+    repo_path: &Path,
+    range: &str,
+    before: Option<i64>,
+    paths: &[&str],
+    max_commits: Option<usize>,
+) -> anyhow::Result<Vec<String>> {
+    let repo = Repository::open(repo_path)?;
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    if range.contains("..") {
+        revwalk.push_range(range)?;
+    } else {
+        revwalk.push(repo.revparse_single(range)?.peel_to_commit()?.id())?;
+    }
+
+    let mut commits = vec![];
+    for oid in revwalk {
+        if commits.len() >= max_commits.unwrap_or(usize::MAX) {
+            break;
+        }
+        let commit = repo.find_commit(oid?)?;
+        if before.is_some_and(|before| commit.time().seconds() > before) {
+            continue;
+        }
+        if !paths.is_empty() && !touches_paths(&repo, &commit, paths)? {
+            continue;
+        }
+        commits.push(commit.id().to_string());
+    }
+    Ok(commits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_libgit2_checkout() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let repo = Repository::init(root).unwrap();
+        let signature = git2::Signature::now("test", "test@example.com").unwrap();
+        let commit = |content: &str, parents: &[&git2::Commit]| {
+            std::fs::write(root.join("lib.rs"), content).unwrap();
+            let mut index = repo.index().unwrap();
+            index.add_path(Path::new("lib.rs")).unwrap();
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            repo.commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                content,
+                &tree,
+                parents,
+            )
+            .unwrap()
+        };
+        let first = commit("first", &[]);
+        let first_commit = repo.find_commit(first).unwrap();
+        commit("second", &[&first_commit]);
+
+        set_git_backend(GitBackend::Libgit2);
+        assert!(checkout(root, &first.to_string()).unwrap());
+        assert_eq!(
+            std::fs::read_to_string(root.join("lib.rs")).unwrap(),
+            "first"
+        );
+        assert!(!checkout(root, "0000000000000000000000000000000000000001").unwrap());
+    }
+
+    #[test]
+    fn test_rev_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let repo = Repository::init(root).unwrap();
+        let signature = git2::Signature::now("test", "test@example.com").unwrap();
+        let commit = |path: &str| {
+            let file = root.join(path);
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(&file, path.len().to_string() + path).unwrap();
+            let mut index = repo.index().unwrap();
+            index.add_path(Path::new(path)).unwrap();
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let parents = repo
+                .head()
+                .ok()
+                .map(|head| head.peel_to_commit().unwrap())
+                .into_iter()
+                .collect::<Vec<_>>();
+            repo.commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                path,
+                &tree,
+                &parents.iter().collect::<Vec<_>>(),
+            )
+            .unwrap()
+            .to_string()
+        };
+        let first = commit("program/lib.rs");
+        let second = commit("README.md");
+        let third = commit("program/src/main.rs");
+
+        assert_eq!(
+            rev_list(root, "HEAD", None, &[], None).unwrap(),
+            vec![third.clone(), second, first.clone()]
+        );
+        assert_eq!(
+            rev_list(root, "HEAD", None, &["program", "Cargo.lock"], None).unwrap(),
+            vec![third.clone(), first.clone()]
+        );
+        assert_eq!(
+            rev_list(root, &format!("{}..HEAD", first), None, &["program"], None).unwrap(),
+            vec![third.clone()]
+        );
+        assert_eq!(
+            rev_list(root, "HEAD", None, &[], Some(1)).unwrap(),
+            vec![third.clone()]
+        );
+        assert!(rev_list(root, "HEAD", Some(0), &[], None)
+            .unwrap()
+            .is_empty());
+
+        assert_eq!(resolve_commit(root, "HEAD").unwrap(), Some(third.clone()));
+        assert_eq!(resolve_commit(root, "missing").unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_ls_remote_head() {
+        let output = "ref: refs/heads/master\tHEAD\n\
+                      5a7a8fd2b5a4c3a7f1b0e9d8c7b6a5f4e3d2c1b0\tHEAD\n\
+                      5a7a8fd2b5a4c3a7f1b0e9d8c7b6a5f4e3d2c1b0\trefs/heads/master\n";
+        assert_eq!(
+            parse_ls_remote_head(output),
+            Some((
+                "5a7a8fd2b5a4c3a7f1b0e9d8c7b6a5f4e3d2c1b0".to_string(),
+                Some("master".to_string())
+            ))
+        );
+        assert_eq!(
+            parse_ls_remote_head(&output[output.find('\n').unwrap() + 1..]),
+            Some(("5a7a8fd2b5a4c3a7f1b0e9d8c7b6a5f4e3d2c1b0".to_string(), None))
+        );
+        assert_eq!(parse_ls_remote_head(""), None);
+    }
+}
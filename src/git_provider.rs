@@ -0,0 +1,272 @@
+use anyhow::anyhow;
+use log::info;
+use reqwest::{Client, RequestBuilder, StatusCode};
+use std::time::Duration;
+
+use crate::git_backend;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GitProvider {
+    GitHub,
+    GitLab,
+    Bitbucket,
+    /// Self-hosted or unknown forge, only reachable through git itself
+    Other,
+}
+
+/// A git remote split into the parts needed to talk to its forge's API.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GitRemote {
+    pub url: String,
+    pub host: String,
+    /// Repository path without the `.git` suffix, e.g. `group/subgroup/project`
+    pub path: String,
+    pub provider: GitProvider,
+}
+
+impl GitRemote {
+    /// Parses HTTPS and scp-like SSH (`git@host:path`) remotes.
+    pub fn parse(url: &str) -> anyhow::Result<GitRemote> {
+        let invalid = || {
+            anyhow!(
+                "Invalid repository url {}. Expected https://<host>/<path>",
+                url
+            )
+        };
+
+        let (host, path) = if let Some(rest) = url
+            .strip_prefix("https://")
+            .or_else(|| url.strip_prefix("http://"))
+            .or_else(|| url.strip_prefix("ssh://"))
+        {
+            rest.split_once('/').ok_or_else(invalid)?
+        } else if let Some((user_host, path)) = url.split_once(':') {
+            if !user_host.contains('@') {
+                return Err(invalid());
+            }
+            (user_host, path)
+        } else {
+            return Err(invalid());
+        };
+
+        // Drop credentials and ports, e.g. `git@gitlab.com` or `forge.example.com:8443`
+        let host = host.rsplit('@').next().unwrap_or(host);
+        let host = host.split(':').next().unwrap_or(host).to_lowercase();
+        let path = path
+            .trim_end_matches('/')
+            .trim_end_matches(".git")
+            .trim_matches('/')
+            .to_string();
+        if host.is_empty() || !path.contains('/') {
+            return Err(invalid());
+        }
+
+        let provider = if host == "github.com" {
+            GitProvider::GitHub
+        } else if host == "bitbucket.org" {
+            GitProvider::Bitbucket
+        } else if host == "gitlab.com" || host.starts_with("gitlab.") {
+            GitProvider::GitLab
+        } else {
+            GitProvider::Other
+        };
+
+        Ok(GitRemote {
+            url: url.to_string(),
+            host,
+            path,
+            provider,
+        })
+    }
+
+    /// Name of the directory the repository is cloned into.
+    pub fn name(&self) -> &str {
+        self.path.rsplit('/').next().unwrap_or(&self.path)
+    }
+
+    /// API endpoint describing `commit`, and the env variable holding the token for it.
+    fn commit_api(&self, commit: &str) -> Option<(String, &'static str)> {
+        match self.provider {
+            GitProvider::GitHub => Some((
+                format!(
+                    "https://api.github.com/repos/{}/commits/{}",
+                    self.path, commit
+                ),
+                "GITHUB_TOKEN",
+            )),
+            GitProvider::GitLab => Some((
+                format!(
+                    "https://{}/api/v4/projects/{}/repository/commits/{}",
+                    self.host,
+                    self.path.replace('/', "%2F"),
+                    commit
+                ),
+                "GITLAB_TOKEN",
+            )),
+            GitProvider::Bitbucket => Some((
+                format!(
+                    "https://api.bitbucket.org/2.0/repositories/{}/commit/{}",
+                    self.path, commit
+                ),
+                "BITBUCKET_TOKEN",
+            )),
+            GitProvider::Other => None,
+        }
+    }
+
+    fn authorize(&self, request: RequestBuilder, token: &str) -> RequestBuilder {
+        match self.provider {
+            GitProvider::GitLab => request.header("PRIVATE-TOKEN", token),
+            _ => request.bearer_auth(token),
+        }
+    }
+
+    /// Checks through the forge API that `commit` exists, when a token for the forge is set.
+    /// Returns `None` if the check could not be made.
+    pub async fn commit_exists(&self, commit: &str) -> anyhow::Result<Option<bool>> {
+        let Some((url, token_var)) = self.commit_api(commit) else {
+            return Ok(None);
+        };
+        let Ok(token) = std::env::var(token_var) else {
+            return Ok(None);
+        };
+
+        let client = Client::builder().timeout(Duration::from_secs(30)).build()?;
+        let request = client
+            .get(url)
+            .header(reqwest::header::USER_AGENT, "solana-verify");
+        let response = self.authorize(request, &token).send().await?;
+
+        match response.status() {
+            status if status.is_success() => Ok(Some(true)),
+            StatusCode::NOT_FOUND | StatusCode::UNPROCESSABLE_ENTITY => Ok(Some(false)),
+            status => Err(anyhow!(
+                "Failed to look up commit {} in {}: {}",
+                commit,
+                self.url,
+                status
+            )),
+        }
+    }
+}
+
+/// What is known about a recorded commit that a fresh clone does not contain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissingCommit {
+    /// Served by SHA although no branch or tag contains it, typically after a force-push
+    Unreachable,
+    /// Known to the forge but no longer served by git, typically after a force-push
+    ForgeOnly,
+    /// Unknown to both git and the forge
+    NeverExisted,
+    /// Not served by git, and the forge could not be asked
+    Unknown,
+}
+
+impl MissingCommit {
+    /// Classifies a commit that was not in the clone from whether fetching it by SHA
+    /// succeeded and whether the forge API knows it.
+    pub fn classify(fetched: bool, forge_knows: Option<bool>) -> MissingCommit {
+        match (fetched, forge_knows) {
+            (true, _) => MissingCommit::Unreachable,
+            (false, Some(true)) => MissingCommit::ForgeOnly,
+            (false, Some(false)) => MissingCommit::NeverExisted,
+            (false, None) => MissingCommit::Unknown,
+        }
+    }
+}
+
+impl std::fmt::Display for MissingCommit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MissingCommit::Unreachable => write!(
+                f,
+                "the commit is no longer on any branch or tag, most likely because it was force-pushed away. \
+                 It can still be fetched for now, but the forge may garbage-collect it at any time, \
+                 after which this verification can no longer be reproduced"
+            ),
+            MissingCommit::ForgeOnly => write!(
+                f,
+                "the forge still knows the commit but git no longer serves it, most likely because it was \
+                 force-pushed away. The verified source cannot be rebuilt from the repository; ask the \
+                 maintainers to push it to a branch or tag"
+            ),
+            MissingCommit::NeverExisted => write!(
+                f,
+                "the commit does not exist in the repository. Either it was never pushed there, or it was \
+                 garbage-collected after a force-push; in both cases the recorded source cannot be \
+                 retrieved and the verification claim cannot be reproduced"
+            ),
+            MissingCommit::Unknown => write!(
+                f,
+                "the commit is not reachable and could not be fetched by SHA. It may never have existed or \
+                 may have been force-pushed away; set a token for the forge (e.g. GITHUB_TOKEN) to tell \
+                 these cases apart"
+            ),
+        }
+    }
+}
+
+/// Resolves the commit at the tip of the remote's default branch using only git, so it works
+/// with any forge.
+pub fn get_commit_hash_from_remote(repo_url: &str) -> anyhow::Result<String> {
+    let (commit, default_branch) = git_backend::remote_head(repo_url)?;
+
+    // Some self-hosted forges don't advertise which branch HEAD points to
+    match default_branch {
+        Some(default_branch) => println!("Default branch detected: {}", default_branch),
+        None => info!("Remote does not advertise its default branch, using HEAD"),
+    }
+    Ok(commit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_git_remote() {
+        let remote = GitRemote::parse("https://github.com/Ellipsis-Labs/phoenix-v1.git").unwrap();
+        assert_eq!(remote.provider, GitProvider::GitHub);
+        assert_eq!(remote.path, "Ellipsis-Labs/phoenix-v1");
+        assert_eq!(remote.name(), "phoenix-v1");
+
+        let remote = GitRemote::parse("https://gitlab.com/group/subgroup/program/").unwrap();
+        assert_eq!(remote.provider, GitProvider::GitLab);
+        assert_eq!(remote.path, "group/subgroup/program");
+        assert_eq!(
+            remote.commit_api("abc").unwrap().0,
+            "https://gitlab.com/api/v4/projects/group%2Fsubgroup%2Fprogram/repository/commits/abc"
+        );
+
+        let remote = GitRemote::parse("git@bitbucket.org:team/program.git").unwrap();
+        assert_eq!(remote.provider, GitProvider::Bitbucket);
+        assert_eq!(remote.host, "bitbucket.org");
+        assert_eq!(remote.path, "team/program");
+
+        let remote = GitRemote::parse("https://user@git.example.com:8443/team/program").unwrap();
+        assert_eq!(remote.provider, GitProvider::Other);
+        assert_eq!(remote.host, "git.example.com");
+        assert!(remote.commit_api("abc").is_none());
+
+        assert!(GitRemote::parse("github.com/Ellipsis-Labs/phoenix-v1").is_err());
+        assert!(GitRemote::parse("https://github.com/Ellipsis-Labs").is_err());
+    }
+
+    #[test]
+    fn test_classify_missing_commit() {
+        assert_eq!(
+            MissingCommit::classify(true, None),
+            MissingCommit::Unreachable
+        );
+        assert_eq!(
+            MissingCommit::classify(false, Some(true)),
+            MissingCommit::ForgeOnly
+        );
+        assert_eq!(
+            MissingCommit::classify(false, Some(false)),
+            MissingCommit::NeverExisted
+        );
+        assert_eq!(MissingCommit::classify(false, None), MissingCommit::Unknown);
+    }
+}
@@ -0,0 +1,292 @@
+use anyhow::anyhow;
+use log::info;
+use reqwest::{header, Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::{path::Path, str::FromStr};
+
+use crate::get_file_hash;
+use crate::params_file::VerifiedParamsFile;
+use crate::solana_program::find_build_params_pda;
+
+pub const GITHUB_API_URL: &str = "https://api.github.com";
+const GITHUB_UPLOADS_URL: &str = "https://uploads.github.com";
+
+/// Metadata attached to a release next to the verified program.
+#[derive(Debug, Serialize)]
+pub struct ReleaseMetadata {
+    pub program_id: String,
+    pub executable_hash: String,
+    pub pda: Option<String>,
+    pub pda_transaction_signature: Option<String>,
+    pub provenance: VerifiedParamsFile,
+}
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    id: u64,
+    html_url: String,
+    #[serde(default)]
+    assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseAsset {
+    id: u64,
+    name: String,
+}
+
+/// Extracts `(owner, repo)` from a GitHub repository url.
+pub fn parse_github_repo(repo_url: &str) -> anyhow::Result<(String, String)> {
+    let path = repo_url
+        .trim_end_matches('/')
+        .trim_end_matches(".git")
+        .split_once("github.com")
+        .map(|(_, path)| path.trim_start_matches([':', '/']))
+        .ok_or_else(|| anyhow!("{} is not a GitHub repository", repo_url))?;
+
+    match path.split('/').collect::<Vec<_>>().as_slice() {
+        [owner, repo] if !owner.is_empty() && !repo.is_empty() => {
+            Ok((owner.to_string(), repo.to_string()))
+        }
+        _ => Err(anyhow!("{} is not a GitHub repository", repo_url)),
+    }
+}
+
+/// HTTP client authenticated against the GitHub API with `token`.
+pub fn github_client(token: &str) -> anyhow::Result<Client> {
+    let mut headers = header::HeaderMap::new();
+    headers.insert(
+        header::AUTHORIZATION,
+        header::HeaderValue::from_str(&format!("Bearer {}", token))?,
+    );
+    headers.insert(
+        header::ACCEPT,
+        header::HeaderValue::from_static("application/vnd.github+json"),
+    );
+    headers.insert(
+        header::USER_AGENT,
+        header::HeaderValue::from_static("solana-verify"),
+    );
+    Ok(Client::builder().default_headers(headers).build()?)
+}
+
+/// Finds the most recent transaction that wrote the verification PDA of `uploader`.
+fn get_pda_transaction_signature(
+    connection: &RpcClient,
+    program_id: &Pubkey,
+    uploader: &Pubkey,
+) -> anyhow::Result<(Pubkey, Option<String>)> {
+    let (pda, _) = find_build_params_pda(program_id, uploader);
+    let signatures = connection
+        .get_signatures_for_address(&pda)
+        .map_err(|e| anyhow!("Failed to fetch transactions for PDA {}: {}", pda, e))?;
+    Ok((pda, signatures.first().map(|s| s.signature.clone())))
+}
+
+async fn get_or_create_release(
+    client: &Client,
+    owner: &str,
+    repo: &str,
+    tag: &str,
+    commit: &str,
+) -> anyhow::Result<Release> {
+    let response = client
+        .get(format!(
+            "{}/repos/{}/{}/releases/tags/{}",
+            GITHUB_API_URL, owner, repo, tag
+        ))
+        .send()
+        .await?;
+
+    if response.status().is_success() {
+        return Ok(response.json().await?);
+    }
+    if response.status() != StatusCode::NOT_FOUND {
+        return Err(anyhow!(
+            "Failed to look up release {}: {}",
+            tag,
+            response.text().await?
+        ));
+    }
+
+    info!("Creating release {} at commit {}", tag, commit);
+    let mut body = serde_json::json!({ "tag_name": tag, "name": tag });
+    if !commit.is_empty() {
+        body["target_commitish"] = commit.into();
+    }
+    let response = client
+        .post(format!(
+            "{}/repos/{}/{}/releases",
+            GITHUB_API_URL, owner, repo
+        ))
+        .json(&body)
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!(
+            "Failed to create release {}: {}",
+            tag,
+            response.text().await?
+        ));
+    }
+    Ok(response.json().await?)
+}
+
+async fn upload_asset(
+    client: &Client,
+    owner: &str,
+    repo: &str,
+    release: &Release,
+    name: &str,
+    content: Vec<u8>,
+) -> anyhow::Result<()> {
+    // Assets can't be overwritten, so replace any left over from a previous publish
+    if let Some(asset) = release.assets.iter().find(|asset| asset.name == name) {
+        client
+            .delete(format!(
+                "{}/repos/{}/{}/releases/assets/{}",
+                GITHUB_API_URL, owner, repo, asset.id
+            ))
+            .send()
+            .await?
+            .error_for_status()
+            .map_err(|e| anyhow!("Failed to replace release asset {}: {}", name, e))?;
+    }
+
+    let response = client
+        .post(format!(
+            "{}/repos/{}/{}/releases/{}/assets",
+            GITHUB_UPLOADS_URL, owner, repo, release.id
+        ))
+        .query(&[("name", name)])
+        .header(header::CONTENT_TYPE, "application/octet-stream")
+        .body(content)
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!(
+            "Failed to upload release asset {}: {}",
+            name,
+            response.text().await?
+        ));
+    }
+    println!("Uploaded {}", name);
+    Ok(())
+}
+
+/// Attaches the verified program, its hash and the verification metadata to the GitHub
+/// release `tag` of the verified repository, creating the release at the verified commit
+/// if it does not exist yet.
+pub async fn publish_release(
+    connection: &RpcClient,
+    tag: &str,
+    executable_path: &str,
+    params_path: &str,
+    uploader: Option<Pubkey>,
+    token: &str,
+) -> anyhow::Result<()> {
+    let params_file = VerifiedParamsFile::read(params_path)?;
+    params_file.verify_signature()?;
+    let program_id = params_file.program_id()?;
+
+    let executable_hash = get_file_hash(executable_path)?;
+    if executable_hash != params_file.executable_hash {
+        return Err(anyhow!(
+            "Hash of {} ({}) does not match the verified hash {}",
+            executable_path,
+            executable_hash,
+            params_file.executable_hash
+        ));
+    }
+
+    let (owner, repo) = parse_github_repo(&params_file.git_url)?;
+
+    let uploader = match (uploader, &params_file.signer) {
+        (Some(uploader), _) => Some(uploader),
+        (None, Some(signer)) => Some(Pubkey::from_str(signer)?),
+        (None, None) => None,
+    };
+    let (pda, pda_transaction_signature) = match uploader {
+        Some(uploader) => {
+            let (pda, signature) =
+                get_pda_transaction_signature(connection, &program_id, &uploader)?;
+            (Some(pda.to_string()), signature)
+        }
+        None => {
+            println!("No uploader known, the PDA transaction signature will not be attached");
+            (None, None)
+        }
+    };
+
+    let executable_name = Path::new(executable_path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| anyhow!("Invalid executable path {}", executable_path))?
+        .to_string();
+
+    let metadata = ReleaseMetadata {
+        program_id: program_id.to_string(),
+        executable_hash: executable_hash.clone(),
+        pda,
+        pda_transaction_signature,
+        provenance: params_file.clone(),
+    };
+
+    let client = github_client(token)?;
+    let release = get_or_create_release(&client, &owner, &repo, tag, &params_file.commit).await?;
+
+    upload_asset(
+        &client,
+        &owner,
+        &repo,
+        &release,
+        &executable_name,
+        std::fs::read(executable_path)?,
+    )
+    .await?;
+    upload_asset(
+        &client,
+        &owner,
+        &repo,
+        &release,
+        &format!("{}.sha256", executable_name),
+        format!("{}  {}\n", executable_hash, executable_name).into_bytes(),
+    )
+    .await?;
+    upload_asset(
+        &client,
+        &owner,
+        &repo,
+        &release,
+        "verification.json",
+        serde_json::to_vec_pretty(&metadata)?,
+    )
+    .await?;
+
+    println!("Published verified build to {}", release.html_url);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_github_repo() {
+        let expected = ("Ellipsis-Labs".to_string(), "phoenix-v1".to_string());
+        for url in [
+            "https://github.com/Ellipsis-Labs/phoenix-v1",
+            "https://github.com/Ellipsis-Labs/phoenix-v1/",
+            "https://github.com/Ellipsis-Labs/phoenix-v1.git",
+            "git@github.com:Ellipsis-Labs/phoenix-v1.git",
+        ] {
+            assert_eq!(parse_github_repo(url).unwrap(), expected);
+        }
+        assert!(parse_github_repo("https://gitlab.com/Ellipsis-Labs/phoenix-v1").is_err());
+        assert!(parse_github_repo("https://github.com/Ellipsis-Labs").is_err());
+    }
+}
@@ -0,0 +1,86 @@
+use sha2::{Digest, Sha256};
+use std::{
+    io::{self, Read},
+    path::Path,
+};
+
+/// Size of the buffer executables are streamed through while hashing
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Hashes program data read from `reader` the same way as `get_binary_hash`, ignoring
+/// trailing zeros, without holding the whole program in memory.
+pub fn get_reader_hash<R: Read>(mut reader: R) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; CHUNK_SIZE];
+    let zeros = vec![0; CHUNK_SIZE];
+    // Zeros seen but not hashed yet, as they are only part of the program if data follows
+    let mut pending_zeros = 0usize;
+
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        let chunk = &buffer[..read];
+
+        match chunk.iter().rposition(|&b| b != 0) {
+            Some(last_non_zero) => {
+                while pending_zeros > 0 {
+                    let len = pending_zeros.min(CHUNK_SIZE);
+                    hasher.update(&zeros[..len]);
+                    pending_zeros -= len;
+                }
+                hasher.update(&chunk[..=last_non_zero]);
+                pending_zeros = read - last_non_zero - 1;
+            }
+            None => pending_zeros += read,
+        }
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Hashes an executable file, or stdin when `filepath` is `-`.
+pub fn get_file_hash(filepath: impl AsRef<Path>) -> io::Result<String> {
+    let filepath = filepath.as_ref();
+    if filepath == Path::new("-") {
+        get_reader_hash(io::stdin().lock())
+    } else {
+        get_reader_hash(std::fs::File::open(filepath)?)
+    }
+}
+
+/// SHA-256 of a file's exact contents, trailing zeros included, as `sha256sum` prints it.
+pub fn get_raw_file_sha256(filepath: impl AsRef<Path>) -> io::Result<String> {
+    let mut file = std::fs::File::open(filepath)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_binary_hash;
+
+    #[test]
+    fn test_reader_hash_matches_binary_hash() {
+        let mut program = vec![0u8; 3 * CHUNK_SIZE + 17];
+        program[1] = 1;
+        program[CHUNK_SIZE + 5] = 2;
+        // Zeros spanning whole chunks inside the program must be kept
+        program[3 * CHUNK_SIZE] = 3;
+
+        let mut padded = program.clone();
+        padded.extend(vec![0u8; 2 * CHUNK_SIZE + 3]);
+
+        for data in [&program, &padded, &vec![], &vec![0u8; CHUNK_SIZE + 1]] {
+            assert_eq!(
+                get_reader_hash(&data[..]).unwrap(),
+                get_binary_hash(data.to_vec())
+            );
+        }
+    }
+}
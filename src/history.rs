@@ -0,0 +1,490 @@
+use anyhow::anyhow;
+use chrono::DateTime;
+use parquet::{
+    data_type::{BoolType, ByteArray, ByteArrayType, Int64Type},
+    file::{properties::WriterProperties, writer::SerializedFileWriter},
+    schema::parser::parse_message_type,
+};
+use serde::Serialize;
+use solana_client::{
+    rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient},
+    rpc_config::RpcTransactionConfig,
+};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use solana_transaction_status::UiTransactionEncoding;
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
+
+use crate::{
+    api::fetch_remote_statuses,
+    compression::write_artifact,
+    solana_program::{
+        find_build_params_pda, get_all_pdas_available, InputParams, OtterVerifyInstructions,
+        OTTER_VERIFY_PROGRAM_ID,
+    },
+};
+
+/// File formats `registry history` writes, chosen by the extension of the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryFormat {
+    Csv,
+    JsonLines,
+    Parquet,
+}
+
+impl HistoryFormat {
+    pub fn from_path(path: &Path) -> anyhow::Result<HistoryFormat> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => Ok(HistoryFormat::Csv),
+            Some("jsonl") | Some("ndjson") => Ok(HistoryFormat::JsonLines),
+            Some("parquet") => Ok(HistoryFormat::Parquet),
+            _ => Err(anyhow!(
+                "Unknown history format for {}, use a .csv, .jsonl or .parquet extension",
+                path.display()
+            )),
+        }
+    }
+}
+
+/// A change to the verification of a program: a transaction writing one of its PDAs, or a
+/// verification recorded by the remote API.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistoryEvent {
+    /// RFC 3339, empty if unknown
+    pub timestamp: String,
+    pub slot: Option<u64>,
+    /// `on-chain` or `remote`
+    pub source: String,
+    /// `initialize`, `update` or `close` for PDA writes, `verification` for remote records
+    pub action: String,
+    pub signer: String,
+    pub repo_url: String,
+    pub commit: String,
+    pub verified: Option<bool>,
+    pub executable_hash: String,
+    pub on_chain_hash: String,
+    /// Transaction that wrote the PDA, which notarizes the event on chain
+    pub signature: String,
+}
+
+const CSV_COLUMNS: [&str; 11] = [
+    "timestamp",
+    "slot",
+    "source",
+    "action",
+    "signer",
+    "repo_url",
+    "commit",
+    "verified",
+    "executable_hash",
+    "on_chain_hash",
+    "signature",
+];
+
+/// Schema of the Parquet output, with the columns of the CSV output
+const PARQUET_SCHEMA: &str = "
+message history_event {
+    REQUIRED BYTE_ARRAY timestamp (UTF8);
+    OPTIONAL INT64 slot (INTEGER(64, false));
+    REQUIRED BYTE_ARRAY source (UTF8);
+    REQUIRED BYTE_ARRAY action (UTF8);
+    REQUIRED BYTE_ARRAY signer (UTF8);
+    REQUIRED BYTE_ARRAY repo_url (UTF8);
+    REQUIRED BYTE_ARRAY commit (UTF8);
+    OPTIONAL BOOLEAN verified;
+    REQUIRED BYTE_ARRAY executable_hash (UTF8);
+    REQUIRED BYTE_ARRAY on_chain_hash (UTF8);
+    REQUIRED BYTE_ARRAY signature (UTF8);
+}
+";
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+impl HistoryEvent {
+    /// Fields in the order of `CSV_COLUMNS`, empty when unset.
+    fn fields(&self) -> [String; 11] {
+        [
+            self.timestamp.clone(),
+            self.slot.map(|slot| slot.to_string()).unwrap_or_default(),
+            self.source.clone(),
+            self.action.clone(),
+            self.signer.clone(),
+            self.repo_url.clone(),
+            self.commit.clone(),
+            self.verified
+                .map(|verified| verified.to_string())
+                .unwrap_or_default(),
+            self.executable_hash.clone(),
+            self.on_chain_hash.clone(),
+            self.signature.clone(),
+        ]
+    }
+
+    fn csv_row(&self) -> String {
+        self.fields()
+            .iter()
+            .map(|field| csv_field(field))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    fn sort_key(&self) -> (i64, u64) {
+        let time = DateTime::parse_from_rfc3339(&self.timestamp)
+            .map(|time| time.timestamp())
+            .unwrap_or(i64::MAX);
+        (time, self.slot.unwrap_or(u64::MAX))
+    }
+}
+
+pub fn render_history(events: &[HistoryEvent], format: HistoryFormat) -> anyhow::Result<Vec<u8>> {
+    let mut output = String::new();
+    match format {
+        HistoryFormat::Csv => {
+            output.push_str(&CSV_COLUMNS.join(","));
+            output.push('\n');
+            for event in events {
+                output.push_str(&event.csv_row());
+                output.push('\n');
+            }
+        }
+        HistoryFormat::JsonLines => {
+            for event in events {
+                output.push_str(&serde_json::to_string(event)?);
+                output.push('\n');
+            }
+        }
+        HistoryFormat::Parquet => return render_parquet(events),
+    }
+    Ok(output.into_bytes())
+}
+
+/// Writes the events as a single row group, one column per CSV column.
+fn render_parquet(events: &[HistoryEvent]) -> anyhow::Result<Vec<u8>> {
+    let schema = Arc::new(parse_message_type(PARQUET_SCHEMA)?);
+    let properties = Arc::new(WriterProperties::builder().build());
+    let mut output = vec![];
+    let mut writer = SerializedFileWriter::new(&mut output, schema, properties)?;
+    let mut row_group = writer.next_row_group()?;
+    let fields = events.iter().map(HistoryEvent::fields).collect::<Vec<_>>();
+    for (i, name) in CSV_COLUMNS.iter().enumerate() {
+        let mut column = row_group
+            .next_column()?
+            .ok_or_else(|| anyhow!("Parquet schema has no column {}", name))?;
+        match *name {
+            "slot" => {
+                let slots = events.iter().map(|event| event.slot).collect::<Vec<_>>();
+                let values = slots.iter().flatten().map(|&slot| slot as i64);
+                column.typed::<Int64Type>().write_batch(
+                    &values.collect::<Vec<_>>(),
+                    Some(&definition_levels(&slots)),
+                    None,
+                )?;
+            }
+            "verified" => {
+                let verified = events
+                    .iter()
+                    .map(|event| event.verified)
+                    .collect::<Vec<_>>();
+                column.typed::<BoolType>().write_batch(
+                    &verified.iter().flatten().copied().collect::<Vec<_>>(),
+                    Some(&definition_levels(&verified)),
+                    None,
+                )?;
+            }
+            _ => {
+                let values = fields
+                    .iter()
+                    .map(|fields| ByteArray::from(fields[i].as_str()))
+                    .collect::<Vec<_>>();
+                column
+                    .typed::<ByteArrayType>()
+                    .write_batch(&values, None, None)?;
+            }
+        }
+        column.close()?;
+    }
+    row_group.close()?;
+    writer.close()?;
+    Ok(output)
+}
+
+/// Parquet definition levels of an optional column: 1 for set values, 0 for nulls.
+fn definition_levels<T>(values: &[Option<T>]) -> Vec<i16> {
+    values
+        .iter()
+        .map(|value| i16::from(value.is_some()))
+        .collect()
+}
+
+/// Every successful transaction that touched `address`, newest first.
+fn get_all_signatures(connection: &RpcClient, address: &Pubkey) -> anyhow::Result<Vec<String>> {
+    let mut signatures = vec![];
+    let mut before = None;
+    loop {
+        let page = connection
+            .get_signatures_for_address_with_config(
+                address,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    ..Default::default()
+                },
+            )
+            .map_err(|e| anyhow!("Failed to fetch transactions for {}: {}", address, e))?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        before = Some(Signature::from_str(&last.signature)?);
+        signatures.extend(
+            page.into_iter()
+                .filter(|status| status.err.is_none())
+                .map(|status| status.signature),
+        );
+    }
+    Ok(signatures)
+}
+
+/// Writes of `pda` made by the transaction `signature`.
+fn pda_events(
+    connection: &RpcClient,
+    pda: &Pubkey,
+    signature: &str,
+) -> anyhow::Result<Vec<HistoryEvent>> {
+    let confirmed = connection.get_transaction_with_config(
+        &Signature::from_str(signature)?,
+        RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Base64),
+            commitment: Some(connection.commitment()),
+            max_supported_transaction_version: Some(0),
+        },
+    )?;
+    let transaction = confirmed
+        .transaction
+        .transaction
+        .decode()
+        .ok_or_else(|| anyhow!("Unable to decode transaction {}", signature))?;
+    let keys = transaction.message.static_account_keys();
+    let timestamp = confirmed
+        .block_time
+        .and_then(|time| DateTime::from_timestamp(time, 0))
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_default();
+
+    Ok(transaction
+        .message
+        .instructions()
+        .iter()
+        .filter(|ix| keys.get(ix.program_id_index as usize) == Some(&OTTER_VERIFY_PROGRAM_ID))
+        // Accounts are the PDA, then its signer, see `create_otter_verify_ix`
+        .filter(|ix| {
+            match ix
+                .accounts
+                .first()
+                .and_then(|index| keys.get(*index as usize))
+            {
+                Some(account) => account == pda,
+                None => true,
+            }
+        })
+        .filter_map(|ix| {
+            let action = OtterVerifyInstructions::from_ix_data(&ix.data)?;
+            let params = InputParams::from_ix_data(&ix.data);
+            Some(HistoryEvent {
+                timestamp: timestamp.clone(),
+                slot: Some(confirmed.slot),
+                source: "on-chain".to_string(),
+                action: format!("{:?}", action).to_lowercase(),
+                signer: ix
+                    .accounts
+                    .get(1)
+                    .and_then(|index| keys.get(*index as usize))
+                    .map(|signer| signer.to_string())
+                    .unwrap_or_default(),
+                repo_url: params
+                    .as_ref()
+                    .map(|params| params.git_url.clone())
+                    .unwrap_or_default(),
+                commit: params.map(|params| params.commit).unwrap_or_default(),
+                verified: None,
+                executable_hash: String::new(),
+                on_chain_hash: String::new(),
+                signature: signature.to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Timeline of the verifications of `program_id`: every write of its PDAs, including closed
+/// ones whose signer the remote API knows, and the verifications the remote API recorded.
+pub async fn collect_history(
+    connection: &RpcClient,
+    program_id: &Pubkey,
+) -> anyhow::Result<Vec<HistoryEvent>> {
+    let mut events = vec![];
+    let mut pdas = get_all_pdas_available(connection, program_id)
+        .await?
+        .into_iter()
+        .map(|(pda, _)| pda)
+        .collect::<BTreeSet<_>>();
+
+    match fetch_remote_statuses(*program_id, None, false).await {
+        Ok(statuses) => {
+            for status in statuses {
+                if let Ok(signer) = Pubkey::from_str(&status.signer) {
+                    pdas.insert(find_build_params_pda(program_id, &signer).0);
+                }
+                events.push(HistoryEvent {
+                    timestamp: status.last_verified_at,
+                    slot: None,
+                    source: "remote".to_string(),
+                    action: "verification".to_string(),
+                    signer: status.signer,
+                    repo_url: status.repo_url,
+                    commit: status.commit,
+                    verified: Some(status.is_verified),
+                    executable_hash: status.executable_hash,
+                    on_chain_hash: status.on_chain_hash,
+                    signature: String::new(),
+                });
+            }
+        }
+        Err(err) => println!(
+            "Warning: unable to fetch the remote verification records: {}",
+            err
+        ),
+    }
+
+    for pda in &pdas {
+        let signatures = get_all_signatures(connection, pda)?;
+        println!("PDA {}: {} transaction(s)", pda, signatures.len());
+        for signature in signatures {
+            events.extend(pda_events(connection, pda, &signature)?);
+        }
+    }
+
+    events.sort_by_key(HistoryEvent::sort_key);
+    Ok(events)
+}
+
+/// Writes the history through `write_artifact`, returning the path written.
+pub fn write_history(events: &[HistoryEvent], path: &Path) -> anyhow::Result<PathBuf> {
+    let output = render_history(events, HistoryFormat::from_path(path)?)?;
+    write_artifact(path, &output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana_program::compose_instructions;
+    use parquet::file::metadata::ParquetMetaDataReader;
+
+    fn event(timestamp: &str, commit: &str) -> HistoryEvent {
+        HistoryEvent {
+            timestamp: timestamp.to_string(),
+            slot: Some(1),
+            source: "on-chain".to_string(),
+            action: "update".to_string(),
+            signer: Pubkey::new_unique().to_string(),
+            repo_url: "https://github.com/Ellipsis-Labs/phoenix-v1".to_string(),
+            commit: commit.to_string(),
+            verified: None,
+            executable_hash: String::new(),
+            on_chain_hash: String::new(),
+            signature: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_history_format() {
+        assert_eq!(
+            HistoryFormat::from_path(Path::new("history.csv")).unwrap(),
+            HistoryFormat::Csv
+        );
+        assert_eq!(
+            HistoryFormat::from_path(Path::new("history.jsonl")).unwrap(),
+            HistoryFormat::JsonLines
+        );
+        assert_eq!(
+            HistoryFormat::from_path(Path::new("history.parquet")).unwrap(),
+            HistoryFormat::Parquet
+        );
+        assert!(HistoryFormat::from_path(Path::new("history")).is_err());
+    }
+
+    #[test]
+    fn test_render_history() {
+        let mut events = vec![
+            event("2024-06-01T00:00:00+00:00", "b"),
+            event("2024-05-01T00:00:00+00:00", "a,\"quoted\""),
+        ];
+        events.sort_by_key(HistoryEvent::sort_key);
+        let csv = String::from_utf8(render_history(&events, HistoryFormat::Csv).unwrap()).unwrap();
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], CSV_COLUMNS.join(","));
+        assert!(lines[1].contains(",\"a,\"\"quoted\"\"\","));
+        assert!(lines[2].contains(",b,"));
+
+        let jsonl =
+            String::from_utf8(render_history(&events, HistoryFormat::JsonLines).unwrap()).unwrap();
+        assert_eq!(jsonl.lines().count(), 2);
+        let first: serde_json::Value = serde_json::from_str(jsonl.lines().next().unwrap()).unwrap();
+        assert_eq!(first["commit"], "a,\"quoted\"");
+
+        let parquet = render_history(&events, HistoryFormat::Parquet).unwrap();
+        assert!(parquet.starts_with(b"PAR1") && parquet.ends_with(b"PAR1"));
+        let footer = parquet.len() - 8;
+        let metadata_len =
+            u32::from_le_bytes(parquet[footer..footer + 4].try_into().unwrap()) as usize;
+        let metadata =
+            ParquetMetaDataReader::decode_metadata(&parquet[footer - metadata_len..footer])
+                .unwrap();
+        assert_eq!(metadata.file_metadata().num_rows(), 2);
+        let columns = metadata.file_metadata().schema_descr().columns().to_vec();
+        assert_eq!(
+            columns
+                .iter()
+                .map(|column| column.name())
+                .collect::<Vec<_>>(),
+            CSV_COLUMNS
+        );
+    }
+
+    #[test]
+    fn test_decode_instruction() {
+        let params = InputParams {
+            version: "0.4.0".to_string(),
+            git_url: "https://github.com/Ellipsis-Labs/phoenix-v1".to_string(),
+            commit: "deadbeef".to_string(),
+            args: vec!["--library-name".to_string(), "phoenix".to_string()],
+            deployed_slot: 42,
+        };
+        let ix = compose_instructions(
+            &params,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            OtterVerifyInstructions::Update,
+            0,
+        )
+        .unwrap()
+        .pop()
+        .unwrap();
+        assert_eq!(
+            OtterVerifyInstructions::from_ix_data(&ix.data),
+            Some(OtterVerifyInstructions::Update)
+        );
+        let decoded = InputParams::from_ix_data(&ix.data).unwrap();
+        assert_eq!(decoded.commit, "deadbeef");
+        assert_eq!(decoded.deployed_slot, 42);
+        assert_eq!(OtterVerifyInstructions::from_ix_data(&[0; 8]), None);
+    }
+}
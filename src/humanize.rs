@@ -0,0 +1,109 @@
+use solana_sdk::{clock::UnixTimestamp, native_token::lamports_to_sol};
+use std::time::Duration;
+
+/// Units durations are broken into, largest first
+const DURATION_UNITS: [(u64, &str); 4] = [(86_400, "d"), (3_600, "h"), (60, "m"), (1, "s")];
+
+const BYTE_UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+/// Formats a duration with its two largest units, such as `2h 5m` or `42s`, and durations
+/// under a second in milliseconds.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let Some(index) = DURATION_UNITS.iter().position(|&(unit, _)| secs >= unit) else {
+        return format!("{}ms", duration.as_millis());
+    };
+    let (unit, suffix) = DURATION_UNITS[index];
+    let mut formatted = format!("{}{}", secs / unit, suffix);
+    if let Some(&(next, next_suffix)) = DURATION_UNITS.get(index + 1) {
+        let rest = secs % unit / next;
+        if rest > 0 {
+            formatted.push_str(&format!(" {}{}", rest, next_suffix));
+        }
+    }
+    formatted
+}
+
+/// Formats how long ago `timestamp` was, such as `3d 4h ago`, or `in 5m` if it is after `now`.
+pub fn format_age(timestamp: UnixTimestamp, now: UnixTimestamp) -> String {
+    let duration = Duration::from_secs(now.abs_diff(timestamp));
+    if timestamp > now {
+        format!("in {}", format_duration(duration))
+    } else {
+        format!("{} ago", format_duration(duration))
+    }
+}
+
+/// Formats a size in binary units with one decimal, such as `1.5 KiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < BYTE_UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, BYTE_UNITS[unit])
+}
+
+/// Formats an integer with commas between groups of three digits, regardless of the locale.
+// `is_multiple_of` needs Rust 1.87
+#[allow(clippy::manual_is_multiple_of)]
+pub fn format_number(number: u64) -> String {
+    let digits = number.to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+/// Formats an amount of lamports in SOL, with the exact amount in lamports.
+pub fn format_lamports(lamports: u64) -> String {
+    format!(
+        "{} SOL ({} lamports)",
+        lamports_to_sol(lamports),
+        format_number(lamports)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_millis(350)), "350ms");
+        assert_eq!(format_duration(Duration::from_secs(42)), "42s");
+        assert_eq!(format_duration(Duration::from_secs(125)), "2m 5s");
+        assert_eq!(format_duration(Duration::from_secs(600)), "10m");
+        assert_eq!(format_duration(Duration::from_secs(3_600 + 5)), "1h");
+        assert_eq!(
+            format_duration(Duration::from_secs(3 * 86_400 + 43_200)),
+            "3d 12h"
+        );
+        assert_eq!(format_age(1_000, 1_000 + 90), "1m 30s ago");
+        assert_eq!(format_age(1_000 + 300, 1_000), "in 5m");
+    }
+
+    #[test]
+    fn test_format_numbers() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024), "3.0 MiB");
+        assert_eq!(format_bytes(5 * 1024 * 1024 * 1024 / 2), "2.5 GiB");
+        assert_eq!(format_number(0), "0");
+        assert_eq!(format_number(999), "999");
+        assert_eq!(format_number(2_853_120), "2,853,120");
+        assert_eq!(format_number(100_000), "100,000");
+        assert_eq!(
+            format_lamports(2_853_120),
+            "0.00285312 SOL (2,853,120 lamports)"
+        );
+    }
+}
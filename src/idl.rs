@@ -0,0 +1,234 @@
+use anyhow::anyhow;
+use flate2::read::ZlibDecoder;
+use log::info;
+use serde_json::Value;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::{io::Read, path::Path, process::Command};
+
+use crate::{
+    account_data::get_large_account_data, cleanup::remove_dir, clone_repo_and_checkout,
+    get_basename, solana_program::get_program_pda, support_bundle::log_command,
+    verify_all::ManifestProgram,
+};
+
+const IDL_SEED: &str = "anchor:idl";
+
+/// Anchor discriminator, authority and length of the compressed IDL
+const IDL_HEADER_LEN: usize = 8 + 32 + 4;
+
+/// Address of the IDL account anchor creates for a program with `anchor idl init`.
+pub fn idl_address(program_id: &Pubkey) -> anyhow::Result<Pubkey> {
+    let base = Pubkey::find_program_address(&[], program_id).0;
+    Ok(Pubkey::create_with_seed(&base, IDL_SEED, program_id)?)
+}
+
+fn decode_idl_account(data: &[u8]) -> anyhow::Result<Value> {
+    let len_bytes = data
+        .get(IDL_HEADER_LEN - 4..IDL_HEADER_LEN)
+        .ok_or_else(|| anyhow!("IDL account is too small"))?;
+    let len = u32::from_le_bytes(len_bytes.try_into()?) as usize;
+    let compressed = data
+        .get(IDL_HEADER_LEN..IDL_HEADER_LEN + len)
+        .ok_or_else(|| anyhow!("IDL account is shorter than the IDL length it records"))?;
+
+    let mut json = String::new();
+    ZlibDecoder::new(compressed)
+        .read_to_string(&mut json)
+        .map_err(|e| anyhow!("Failed to decompress the on-chain IDL: {}", e))?;
+    Ok(serde_json::from_str(&json)?)
+}
+
+/// Key of each item of an IDL list, so that reordered instructions or types are matched up
+/// by name rather than position.
+fn names(items: &[Value]) -> Option<Vec<&str>> {
+    items
+        .iter()
+        .map(|item| item.get("name")?.as_str())
+        .collect()
+}
+
+/// Collects the differences between two IDLs as `path: on-chain != built` lines.
+fn diff_idl(path: &str, on_chain: &Value, local: &Value, diffs: &mut Vec<String>) {
+    let child = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+    match (on_chain, local) {
+        (Value::Object(on_chain), Value::Object(local)) => {
+            for (key, value) in on_chain {
+                match local.get(key) {
+                    Some(local_value) => diff_idl(&child(key), value, local_value, diffs),
+                    None => diffs.push(format!("{}: only on-chain", child(key))),
+                }
+            }
+            for key in local.keys().filter(|key| !on_chain.contains_key(*key)) {
+                diffs.push(format!("{}: only in the built IDL", child(key)));
+            }
+        }
+        (Value::Array(on_chain), Value::Array(local)) => match (names(on_chain), names(local)) {
+            (Some(on_chain_names), Some(local_names)) => {
+                for (name, value) in on_chain_names.iter().zip(on_chain) {
+                    match local_names.iter().position(|n| n == name) {
+                        Some(i) => diff_idl(&child(name), value, &local[i], diffs),
+                        None => diffs.push(format!("{}: only on-chain", child(name))),
+                    }
+                }
+                for name in local_names.iter().filter(|n| !on_chain_names.contains(n)) {
+                    diffs.push(format!("{}: only in the built IDL", child(name)));
+                }
+            }
+            _ if on_chain.len() != local.len() => diffs.push(format!(
+                "{}: {} item(s) on-chain != {} item(s) built",
+                path,
+                on_chain.len(),
+                local.len()
+            )),
+            _ => {
+                for (i, (on_chain, local)) in on_chain.iter().zip(local).enumerate() {
+                    diff_idl(&child(&i.to_string()), on_chain, local, diffs);
+                }
+            }
+        },
+        _ if on_chain != local => diffs.push(format!("{}: {} != {}", path, on_chain, local)),
+        _ => {}
+    }
+}
+
+/// Generates the IDL of the anchor program at `mount_path` with `anchor idl build`.
+fn build_idl(mount_path: &Path, library_name: Option<&str>) -> anyhow::Result<Value> {
+    let out = mount_path.join("target").join("verified-idl.json");
+    let mut anchor = Command::new("anchor");
+    anchor
+        .current_dir(mount_path)
+        .args(["idl", "build", "-o"])
+        .arg(&out);
+    if let Some(library_name) = library_name {
+        anchor.args(["-p", library_name]);
+    }
+    log_command(&anchor);
+    let status = anchor.status().map_err(|e| {
+        anyhow!(
+            "Failed to run anchor, install the Anchor CLI to build the IDL: {}",
+            e
+        )
+    })?;
+    if !status.success() {
+        return Err(anyhow!(
+            "anchor idl build failed in {}",
+            mount_path.display()
+        ));
+    }
+    let idl = std::fs::read_to_string(&out)
+        .map_err(|e| anyhow!("Failed to read the built IDL {}: {}", out.display(), e))?;
+    Ok(serde_json::from_str(&idl)?)
+}
+
+/// Compares the IDL published on-chain for a program with the IDL built from the source its
+/// verification PDA records, at the recorded commit and with the recorded build arguments.
+pub async fn verify_idl(
+    connection: &RpcClient,
+    program_id: Pubkey,
+    signer: Option<String>,
+) -> anyhow::Result<()> {
+    let address = idl_address(&program_id)?;
+    info!("IDL account: {}", address);
+    let data = get_large_account_data(connection, &address)
+        .map_err(|e| anyhow!("No IDL account found for program {}: {}", program_id, e))?;
+    let on_chain = decode_idl_account(&data)?;
+
+    let (pda, params) = get_program_pda(connection, &program_id, signer).await?;
+    info!(
+        "Building the IDL of {} at commit {}, as recorded in {}",
+        params.git_url, params.commit, pda
+    );
+    let settings = ManifestProgram::from_build_args(&params.args)?;
+    let (repo_path, verify_dir) = clone_repo_and_checkout(
+        &params.git_url,
+        false,
+        &get_basename(&params.git_url)?,
+        Some(params.commit.clone()),
+    )?;
+    let built = build_idl(
+        &repo_path.join(&settings.mount_path),
+        settings.library_name.as_deref(),
+    );
+    remove_dir(&verify_dir)?;
+    let built = built?;
+
+    let mut diffs = vec![];
+    diff_idl("", &on_chain, &built, &mut diffs);
+    if !diffs.is_empty() {
+        println!(
+            "On-chain IDL differs from the IDL built at {} ❌",
+            params.commit
+        );
+        for diff in &diffs {
+            println!("  {}", diff);
+        }
+        return Err(anyhow!(
+            "The on-chain IDL of {} does not match its verified source",
+            program_id
+        ));
+    }
+    println!("On-chain IDL matches the IDL built at {} ✅", params.commit);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::ZlibEncoder, Compression};
+    use serde_json::json;
+    use std::io::Write;
+
+    #[test]
+    fn test_decode_idl_account() {
+        let idl = json!({"version": "0.1.0", "name": "phoenix", "instructions": []});
+        let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+        encoder.write_all(idl.to_string().as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut data = vec![0u8; 8 + 32];
+        data.extend((compressed.len() as u32).to_le_bytes());
+        data.extend(compressed);
+        // Accounts are allocated larger than the IDL they hold
+        data.extend([0u8; 16]);
+        assert_eq!(decode_idl_account(&data).unwrap(), idl);
+    }
+
+    #[test]
+    fn test_diff_idl() {
+        let on_chain = json!({
+            "name": "phoenix",
+            "instructions": [
+                {"name": "swap", "args": [{"name": "amount", "type": "u64"}]},
+                {"name": "cancel", "args": []},
+            ],
+        });
+        let local = json!({
+            "name": "phoenix",
+            "instructions": [
+                {"name": "deposit", "args": []},
+                {"name": "swap", "args": [{"name": "amount", "type": "u128"}]},
+            ],
+        });
+        let mut diffs = vec![];
+        diff_idl("", &on_chain, &local, &mut diffs);
+        assert_eq!(
+            diffs,
+            vec![
+                "instructions.swap.args.amount.type: \"u64\" != \"u128\"",
+                "instructions.cancel: only on-chain",
+                "instructions.deposit: only in the built IDL",
+            ]
+        );
+
+        diffs.clear();
+        diff_idl("", &on_chain, &on_chain, &mut diffs);
+        assert!(diffs.is_empty());
+    }
+}
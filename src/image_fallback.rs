@@ -0,0 +1,112 @@
+use anyhow::anyhow;
+use std::{collections::BTreeMap, str::FromStr, sync::OnceLock};
+
+use crate::image_config::IMAGE_MAP;
+
+pub type SolanaVersion = (u32, u32, u32);
+
+/// Which image to build with when none exists for the program's exact Solana version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFallback {
+    NearestLower,
+    NearestHigher,
+    Fail,
+}
+
+impl FromStr for ImageFallback {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "nearest-lower" => Ok(ImageFallback::NearestLower),
+            "nearest-higher" => Ok(ImageFallback::NearestHigher),
+            "fail" => Ok(ImageFallback::Fail),
+            _ => Err(anyhow!("Invalid image fallback: {}", s)),
+        }
+    }
+}
+
+/// Policy given with `--image-fallback`, applied to every build
+static IMAGE_FALLBACK: OnceLock<ImageFallback> = OnceLock::new();
+
+pub fn set_image_fallback(fallback: ImageFallback) {
+    let _ = IMAGE_FALLBACK.set(fallback);
+}
+
+pub fn get_image_fallback() -> ImageFallback {
+    IMAGE_FALLBACK
+        .get()
+        .copied()
+        .unwrap_or(ImageFallback::NearestLower)
+}
+
+fn select_from<'a>(
+    images: &BTreeMap<SolanaVersion, &'a str>,
+    version: SolanaVersion,
+    fallback: ImageFallback,
+) -> anyhow::Result<(SolanaVersion, &'a str)> {
+    if let Some(digest) = images.get(&version) {
+        return Ok((version, digest));
+    }
+    let candidate = match fallback {
+        ImageFallback::NearestLower => images.range(..version).next_back(),
+        ImageFallback::NearestHigher => images.range(version..).next(),
+        ImageFallback::Fail => None,
+    };
+    candidate
+        .map(|(version, digest)| (*version, *digest))
+        .ok_or_else(|| {
+            anyhow!(
+                "No docker image for Solana version {}.{}.{} with image fallback {:?}. Pass --base-image or another --image-fallback",
+                version.0,
+                version.1,
+                version.2,
+                fallback
+            )
+        })
+}
+
+/// Returns the Solana version and digest of the image to build a program depending on
+/// `version` with, following the configured fallback when there is no exact match.
+pub fn select_image(version: SolanaVersion) -> anyhow::Result<(SolanaVersion, &'static str)> {
+    select_from(&IMAGE_MAP, version, get_image_fallback())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_version_has_its_own_image() {
+        for (version, digest) in IMAGE_MAP.iter() {
+            for fallback in [
+                ImageFallback::NearestLower,
+                ImageFallback::NearestHigher,
+                ImageFallback::Fail,
+            ] {
+                assert_eq!(
+                    select_from(&IMAGE_MAP, *version, fallback).unwrap(),
+                    (*version, *digest)
+                );
+            }
+            assert!(digest.starts_with("sha256:") && digest.len() == 71);
+        }
+    }
+
+    #[test]
+    fn test_image_fallback() {
+        let images = BTreeMap::from([((1, 17, 3), "a"), ((1, 18, 1), "b")]);
+        let version = (1, 18, 0);
+        assert_eq!(
+            select_from(&images, version, ImageFallback::NearestLower).unwrap(),
+            ((1, 17, 3), "a")
+        );
+        assert_eq!(
+            select_from(&images, version, ImageFallback::NearestHigher).unwrap(),
+            ((1, 18, 1), "b")
+        );
+        assert!(select_from(&images, version, ImageFallback::Fail).is_err());
+        assert!(select_from(&images, (1, 17, 0), ImageFallback::NearestLower).is_err());
+        assert!(select_from(&images, (1, 19, 0), ImageFallback::NearestHigher).is_err());
+    }
+}
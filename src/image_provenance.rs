@@ -0,0 +1,162 @@
+use anyhow::anyhow;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{docker::docker_command, git_provider::GitRemote};
+
+const LABEL_PREFIX: &str = "org.opencontainers.image.";
+const SOURCE_LABEL: &str = "org.opencontainers.image.source";
+const REVISION_LABEL: &str = "org.opencontainers.image.revision";
+
+/// The OCI annotations an image was labeled with at build time, which is the only record of
+/// where the source baked into a cached image came from.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImageProvenance {
+    pub labels: BTreeMap<String, String>,
+}
+
+impl ImageProvenance {
+    /// Keeps the `org.opencontainers.image.*` labels of `docker inspect` output.
+    fn from_labels_json(json: &str) -> anyhow::Result<ImageProvenance> {
+        let labels: Option<BTreeMap<String, String>> = serde_json::from_str(json.trim())
+            .map_err(|e| anyhow!("Failed to parse image labels: {}", e))?;
+        Ok(ImageProvenance {
+            labels: labels
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|(key, _)| key.starts_with(LABEL_PREFIX))
+                .collect(),
+        })
+    }
+
+    pub fn source(&self) -> Option<&str> {
+        self.labels.get(SOURCE_LABEL).map(|s| s.as_str())
+    }
+
+    pub fn revision(&self) -> Option<&str> {
+        self.labels.get(REVISION_LABEL).map(|s| s.as_str())
+    }
+
+    /// Compares the labels with the repository and commit the image is expected to be built
+    /// from. Returns the mismatches; labels that are absent are only warned about, since most
+    /// images are built without them.
+    pub fn check(
+        &self,
+        expected_repo: Option<&str>,
+        expected_commit: Option<&str>,
+    ) -> anyhow::Result<Vec<String>> {
+        let mut mismatches = vec![];
+        if let Some(expected_repo) = expected_repo {
+            match self.source() {
+                Some(source) => {
+                    if !same_repository(source, expected_repo)? {
+                        mismatches.push(format!(
+                            "{} is {}, expected {}",
+                            SOURCE_LABEL, source, expected_repo
+                        ));
+                    }
+                }
+                None => warn!("the image has no {} label", SOURCE_LABEL),
+            }
+        }
+        if let Some(expected_commit) = expected_commit {
+            match self.revision() {
+                Some(revision) => {
+                    if !same_commit(revision, expected_commit) {
+                        mismatches.push(format!(
+                            "{} is {}, expected {}",
+                            REVISION_LABEL, revision, expected_commit
+                        ));
+                    }
+                }
+                None => warn!("the image has no {} label", REVISION_LABEL),
+            }
+        }
+        Ok(mismatches)
+    }
+
+    pub fn print(&self) {
+        if self.labels.is_empty() {
+            println!("Image provenance: no OCI labels, the source of the image is unknown");
+            return;
+        }
+        println!("Image provenance:");
+        for (key, value) in &self.labels {
+            println!("  {}: {}", key, value);
+        }
+    }
+}
+
+/// Repositories are compared by host and path, so that https and SSH remotes or a trailing
+/// `.git` do not count as differences.
+fn same_repository(a: &str, b: &str) -> anyhow::Result<bool> {
+    let (a, b) = (GitRemote::parse(a)?, GitRemote::parse(b)?);
+    Ok(a.host == b.host && a.path.eq_ignore_ascii_case(&b.path))
+}
+
+/// Either side may be an abbreviated hash.
+fn same_commit(a: &str, b: &str) -> bool {
+    let (a, b) = (a.to_lowercase(), b.to_lowercase());
+    a.len() >= 7 && b.len() >= 7 && (a.starts_with(&b) || b.starts_with(&a))
+}
+
+/// Reads the provenance labels of a local image.
+pub fn get_image_provenance(image: &str) -> anyhow::Result<ImageProvenance> {
+    let output = docker_command()
+        .args([
+            "image",
+            "inspect",
+            "--format",
+            "{{json .Config.Labels}}",
+            image,
+        ])
+        .output()
+        .map_err(|e| anyhow!("Failed to inspect image {}: {}", image, e))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to inspect image {}: {}",
+            image,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    ImageProvenance::from_labels_json(&String::from_utf8(output.stdout)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_provenance() {
+        let provenance = ImageProvenance::from_labels_json(
+            r#"{"org.opencontainers.image.source":"https://github.com/Ellipsis-Labs/phoenix-v1","org.opencontainers.image.revision":"6a8f3d2c1b0e9f8a7d6c5b4a3f2e1d0c9b8a7f6e","maintainer":"ellipsis"}"#,
+        )
+        .unwrap();
+        assert_eq!(provenance.labels.len(), 2);
+        assert!(provenance
+            .check(
+                Some("git@github.com:Ellipsis-Labs/phoenix-v1.git"),
+                Some("6a8f3d2")
+            )
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            provenance
+                .check(
+                    Some("https://github.com/Ellipsis-Labs/phoenix-v2"),
+                    Some("6a8f3d3")
+                )
+                .unwrap()
+                .len(),
+            2
+        );
+
+        let unlabeled = ImageProvenance::from_labels_json("null\n").unwrap();
+        assert!(unlabeled.labels.is_empty());
+        assert!(unlabeled
+            .check(Some("https://github.com/Ellipsis-Labs/phoenix-v1"), None)
+            .unwrap()
+            .is_empty());
+    }
+}
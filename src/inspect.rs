@@ -0,0 +1,173 @@
+use anyhow::anyhow;
+use log::warn;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    pubkey::Pubkey,
+};
+
+use crate::account_data::get_large_account_data;
+use crate::api::fetch_api_status;
+use crate::cluster_time::ClusterClock;
+use crate::humanize::{format_bytes, format_number};
+use crate::solana_program::{get_all_pdas_available, get_verification_source};
+
+const SECURITY_TXT_BEGIN: &[u8] = b"=======BEGIN SECURITY.TXT V1=======\0";
+const SECURITY_TXT_END: &[u8] = b"=======END SECURITY.TXT V1=======\0";
+
+/// Extracts the key/value pairs of the security.txt embedded with the `security_txt!` macro.
+pub fn parse_security_txt(data: &[u8]) -> Option<Vec<(String, String)>> {
+    let start = data
+        .windows(SECURITY_TXT_BEGIN.len())
+        .position(|w| w == SECURITY_TXT_BEGIN)?
+        + SECURITY_TXT_BEGIN.len();
+    let end = start
+        + data[start..]
+            .windows(SECURITY_TXT_END.len())
+            .position(|w| w == SECURITY_TXT_END)?;
+
+    let fields = data[start..end]
+        .split(|&b| b == 0)
+        .map(|field| String::from_utf8_lossy(field).to_string())
+        .collect::<Vec<_>>();
+    Some(
+        fields
+            .chunks_exact(2)
+            .map(|pair| (pair[0].clone(), pair[1].clone()))
+            .collect(),
+    )
+}
+
+/// Deployment state of a program, read from its program data account.
+struct Deployment {
+    upgrade_authority: Option<Pubkey>,
+    slot: Option<u64>,
+    program_data: Vec<u8>,
+}
+
+fn get_deployment(client: &RpcClient, program_id: &Pubkey) -> anyhow::Result<Deployment> {
+    let account = client
+        .get_account(program_id)
+        .map_err(|_| anyhow!("Program {} is not deployed", program_id))?;
+
+    // Programs of the older loaders are immutable and hold their code in the program account
+    if account.owner != bpf_loader_upgradeable::id() {
+        return Ok(Deployment {
+            upgrade_authority: None,
+            slot: None,
+            program_data: account.data,
+        });
+    }
+
+    let program_data_address =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id()).0;
+    let data = get_large_account_data(client, &program_data_address)?;
+    let metadata_len = UpgradeableLoaderState::size_of_programdata_metadata();
+    if data.len() < metadata_len {
+        return Err(anyhow!(
+            "Program data account for {} is too small",
+            program_id
+        ));
+    }
+    match bincode::deserialize::<UpgradeableLoaderState>(&data[..metadata_len])? {
+        UpgradeableLoaderState::ProgramData {
+            slot,
+            upgrade_authority_address,
+        } => Ok(Deployment {
+            upgrade_authority: upgrade_authority_address,
+            slot: Some(slot),
+            program_data: data[metadata_len..].to_vec(),
+        }),
+        _ => Err(anyhow!("Account for {} is not program data", program_id)),
+    }
+}
+
+/// Prints what the chain, the verification PDAs and the OtterSec API say about a program,
+/// without building anything.
+pub async fn inspect(client: &RpcClient, program_id: Pubkey) -> anyhow::Result<()> {
+    let deployment = get_deployment(client, &program_id)?;
+
+    println!("Program Id: {}", program_id);
+    match deployment.upgrade_authority {
+        Some(authority) => println!("Upgrade Authority: {}", authority),
+        None => println!("Upgrade Authority: none (immutable)"),
+    }
+    if let Some(slot) = deployment.slot {
+        let deployed_at = ClusterClock::new(client)
+            .slot_time(slot)
+            .map(|time| time.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        println!("Last Deployed Slot: {} ({})", slot, deployed_at);
+    }
+    let program_len = deployment
+        .program_data
+        .iter()
+        .rposition(|&b| b != 0)
+        .map_or(0, |last| last + 1);
+    println!(
+        "Program Size: {} ({} bytes, {} allocated)",
+        format_bytes(program_len as u64),
+        format_number(program_len as u64),
+        format_bytes(deployment.program_data.len() as u64)
+    );
+
+    println!("\nVerification PDAs:");
+    match get_all_pdas_available(client, &program_id).await {
+        Ok(pdas) if pdas.is_empty() => println!("None"),
+        Ok(pdas) => {
+            for (pda, build_params) in pdas {
+                println!("----------------------------------------------------------------");
+                println!("PDA: {}", pda);
+                println!(
+                    "Uploaded by: {}",
+                    get_verification_source(&build_params.signer, deployment.upgrade_authority)
+                );
+                print!("{}", build_params);
+            }
+        }
+        Err(err) => warn!("unable to fetch verification PDAs: {}", err),
+    }
+
+    println!("\nOtterSec API Status:");
+    match fetch_api_status(program_id).await {
+        Ok(status) => println!("{}", status),
+        Err(err) => warn!("unable to fetch the API status: {}", err),
+    }
+
+    println!("\nsecurity.txt:");
+    match parse_security_txt(&deployment.program_data) {
+        Some(fields) => {
+            for (key, value) in fields {
+                println!("{}: {}", key, value);
+            }
+        }
+        None => println!("None"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_security_txt() {
+        let mut data = vec![0x7f, b'E', b'L', b'F', 0, 0];
+        data.extend_from_slice(SECURITY_TXT_BEGIN);
+        data.extend_from_slice(b"name\0Phoenix\0contacts\0email:security@ellipsislabs.xyz\0");
+        data.extend_from_slice(SECURITY_TXT_END);
+        data.extend_from_slice(&[0, 0, 1]);
+
+        assert_eq!(
+            parse_security_txt(&data).unwrap(),
+            vec![
+                ("name".to_string(), "Phoenix".to_string()),
+                (
+                    "contacts".to_string(),
+                    "email:security@ellipsislabs.xyz".to_string()
+                ),
+            ]
+        );
+        assert!(parse_security_txt(&data[..20]).is_none());
+    }
+}
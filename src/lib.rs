@@ -0,0 +1,2318 @@
+//! Verifiable builds of Solana programs: building a program in a reproducible docker
+//! image, comparing its hash with the program deployed on chain and uploading the
+//! verification to the otter-verify program. The `solana-verify` CLI is a thin wrapper over
+//! this library, which tools such as explorers and deploy pipelines can embed directly.
+use anyhow::anyhow;
+use api::{
+    fetch_remote_statuses, get_last_deployed_slot, lockfile_compatible_base_image,
+    send_job_with_uploader_to_remote, with_base_image, KnownFailure, RemoteJobFailed,
+};
+use cargo_lock::Lockfile;
+use cargo_toml::Manifest;
+use log::{debug, error, info, warn};
+use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
+use solana_client::{
+    rpc_client::RpcClient, rpc_config::RpcAccountInfoConfig, rpc_request::MAX_MULTIPLE_ACCOUNTS,
+};
+use solana_program::get_address_from_keypair_or_config;
+use solana_sdk::{
+    bpf_loader, bpf_loader_deprecated,
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    commitment_config::CommitmentConfig,
+    pubkey::Pubkey,
+};
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+use uuid::Uuid;
+pub mod account_data;
+pub mod api;
+pub mod attestation;
+pub mod audit;
+pub mod authority_tx;
+pub mod badge;
+pub mod buffer;
+pub mod build_cache;
+pub mod cleanup;
+pub mod cluster_features;
+pub mod cluster_time;
+pub mod compare_repos;
+pub mod compression;
+pub mod daemon;
+pub mod delegation;
+pub mod deploy;
+pub mod diagnostics;
+pub mod docker;
+pub mod elf;
+pub mod encryption;
+pub mod endpoints;
+pub mod example;
+pub mod find_commit;
+pub mod git_backend;
+pub mod git_provider;
+pub mod github_release;
+pub mod hash;
+pub mod history;
+pub mod humanize;
+pub mod idl;
+pub mod oci_artifact;
+#[rustfmt::skip]
+pub mod image_config;
+pub mod image_fallback;
+pub mod image_provenance;
+pub mod inspect;
+pub mod local_validator;
+pub mod lockfile;
+pub mod logging;
+pub mod mismatch;
+pub mod package_metadata;
+pub mod params_file;
+pub mod paths;
+pub mod profiles;
+pub mod record_in_repo;
+pub mod remote_signer;
+pub mod report;
+pub mod resource_usage;
+pub mod rpc_fixtures;
+pub mod rpc_headers;
+pub mod rpc_usage;
+pub mod sbom;
+pub mod secrets;
+pub mod serve;
+pub mod signers;
+pub mod solana_program;
+pub mod source_archive;
+pub mod support_bundle;
+pub mod tx_export;
+pub mod upload_only;
+pub mod verify_all;
+pub mod verify_suite;
+use account_data::{get_large_account_data, slice_program_data};
+use attestation::Attestation;
+use audit::audit_lockfile;
+use build_cache::{build_cache_use, get_build_cache, BuildCacheUse};
+use cleanup::{
+    check_interrupted, kill_container, remove_dir, track_container, track_temp_dir, track_volume,
+    untrack_temp_dir, untrack_volume,
+};
+use cluster_features::print_cluster_features;
+use cluster_time::{describe_verification_delay, ClusterClock, SlotTime};
+use diagnostics::BuildDiagnostics;
+use docker::{
+    bind_mount, check_docker_available, check_host_copy, check_mount_shared, check_mount_visible,
+    docker_command, print_docker_daemon, remove_leftover_containers, CONTAINER_LABEL,
+};
+use elf::validate_sbf_program;
+use endpoints::endpoints;
+use git_provider::{get_commit_hash_from_remote, GitRemote, MissingCommit};
+pub use hash::get_file_hash;
+use image_fallback::{get_image_fallback, select_image};
+use image_provenance::get_image_provenance;
+use lockfile::ensure_lockfile_readable;
+use logging::command_output;
+use mismatch::MismatchReport;
+use report::{DependencySnapshot, VerificationReport};
+use resource_usage::ResourceUsage;
+use rpc_usage::new_rpc_client;
+use sbom::{parse_cargo_metadata, Sbom};
+use secrets::{redact_rpc_url, strip_url_credentials};
+use support_bundle::log_command;
+use tx_export::{export_transaction, fetch_blockhash, BlockhashSource, TxExportFormat};
+
+use crate::package_metadata::apply_package_metadata;
+use crate::params_file::VerifiedParamsFile;
+use crate::paths::{copy_source, find_cargo_tomls};
+use crate::solana_program::{
+    compose_instructions, fetch_pdas, find_build_params_pda, get_all_pdas_available,
+    get_all_pdas_by_signer, get_program_pda, get_program_pda_headers,
+    get_signer_from_path_or_config, preview_program_verification_data, prompt_user_confirmation,
+    prompt_user_input, resolve_trusted_verification, simulate_upload, upload_input_params,
+    upload_input_params_batch, upload_program_verification_data, with_compute_budget, InputParams,
+    OtterBuildParams, OtterVerifyInstructions, PdaCoverage, PdaFreshness, StaleDeploymentError,
+};
+use crate::verify_all::{apply_project_config, ManifestProgram};
+
+pub const MAINNET_GENESIS_HASH: &str = "5eykt4UsFv8P8NJdTREpY1vzqKqZKvdpKuc147dw2N9d";
+
+/// Rebuilds allowed by `--rebuild-if-stale` before giving up on a program that keeps being redeployed
+pub const MAX_STALE_REBUILDS: u32 = 3;
+
+/// Client for `url`, a URL or cluster moniker, or for the RPC of the run if none is given.
+pub fn get_client(url: Option<String>, commitment: CommitmentConfig) -> RpcClient {
+    new_rpc_client(endpoints().rpc_url_for(url.as_deref()), commitment)
+}
+
+pub fn get_binary_hash(program_data: Vec<u8>) -> String {
+    let buffer = program_data
+        .into_iter()
+        .rev()
+        .skip_while(|&x| x == 0)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect::<Vec<_>>();
+    sha256::digest(&buffer[..])
+}
+
+pub fn get_buffer_hash(
+    url: Option<String>,
+    commitment: CommitmentConfig,
+    buffer_address: Pubkey,
+) -> anyhow::Result<String> {
+    let client = get_client(url, commitment);
+    fetch_buffer_hash(&client, buffer_address)
+}
+
+/// Hash of the program staged in a buffer account, as it would be once deployed.
+pub fn fetch_buffer_hash(client: &RpcClient, buffer_address: Pubkey) -> anyhow::Result<String> {
+    Ok(get_binary_hash(fetch_buffer_data(client, buffer_address)?))
+}
+
+/// Executable staged in a buffer account, with the zeros padding the account.
+pub fn fetch_buffer_data(client: &RpcClient, buffer_address: Pubkey) -> anyhow::Result<Vec<u8>> {
+    let account_data = get_large_account_data(client, &buffer_address)
+        .map_err(|e| anyhow!("Failed to fetch buffer {}: {}", buffer_address, e))?;
+    let offset = UpgradeableLoaderState::size_of_buffer_metadata();
+    if account_data.len() < offset
+        || !matches!(
+            bincode::deserialize::<UpgradeableLoaderState>(&account_data[..offset]),
+            Ok(UpgradeableLoaderState::Buffer { .. })
+        )
+    {
+        return Err(anyhow!("{} is not a buffer account", buffer_address));
+    }
+    Ok(account_data[offset..].to_vec())
+}
+
+pub fn get_account_executable_hash(
+    url: Option<String>,
+    commitment: CommitmentConfig,
+    address: Pubkey,
+    data_offset: usize,
+    data_length: Option<usize>,
+) -> anyhow::Result<String> {
+    let client = get_client(url, commitment);
+    let account_data = get_large_account_data(&client, &address)?;
+    let executable = slice_program_data(&account_data, data_offset, data_length)?;
+    Ok(get_binary_hash(executable.to_vec()))
+}
+
+pub fn get_program_hash(client: &RpcClient, program_id: Pubkey) -> anyhow::Result<String> {
+    let program_hash = fetch_program_hash(client, program_id)?;
+
+    let cross_check_rpcs = &endpoints().cross_check_rpcs;
+    let mismatches = cross_check_rpcs
+        .iter()
+        .filter_map(|url| {
+            let other_client = new_rpc_client(url.clone(), client.commitment());
+            match fetch_program_hash(&other_client, program_id) {
+                Ok(other_hash) if other_hash == program_hash => None,
+                Ok(other_hash) => Some(format!("{} reports {}", redact_rpc_url(url), other_hash)),
+                Err(err) => Some(format!("{} failed: {}", redact_rpc_url(url), err)),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if !mismatches.is_empty() {
+        return Err(anyhow!(
+            "RPC providers disagree on the hash of program {}. {} reports {}, while:\n{}",
+            program_id,
+            redact_rpc_url(&client.url()),
+            program_hash,
+            mismatches.join("\n")
+        ));
+    }
+    if !cross_check_rpcs.is_empty() {
+        info!(
+            "On-chain program hash confirmed by {} additional RPC provider(s)",
+            cross_check_rpcs.len()
+        );
+    }
+
+    Ok(program_hash)
+}
+
+/// Whether `owner` is one of the BPF loaders preceding the upgradeable loader. Their
+/// programs hold the executable in the program account itself, without program data, and
+/// cannot be upgraded.
+pub fn is_legacy_loader(owner: &Pubkey) -> bool {
+    *owner == bpf_loader::id() || *owner == bpf_loader_deprecated::id()
+}
+
+fn fetch_program_hash(client: &RpcClient, program_id: Pubkey) -> anyhow::Result<String> {
+    Ok(get_binary_hash(fetch_program_data(client, program_id)?))
+}
+
+/// Executable of a deployed program, with the zeros padding its account.
+pub fn fetch_program_data(client: &RpcClient, program_id: Pubkey) -> anyhow::Result<Vec<u8>> {
+    // First check if the program account exists
+    let Ok(program_account) = client.get_account(&program_id) else {
+        return Err(anyhow!("Program {} is not deployed", program_id));
+    };
+    if is_legacy_loader(&program_account.owner) {
+        // The account holds the executable from its first byte, there is no header to skip
+        return Ok(program_account.data);
+    }
+
+    let program_buffer =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id()).0;
+
+    // Then check if the program data account exists
+    match get_large_account_data(client, &program_buffer) {
+        Ok(data) => {
+            let offset = UpgradeableLoaderState::size_of_programdata_metadata();
+            Ok(data[offset..].to_vec())
+        }
+        Err(_) => Err(anyhow!(
+            "Could not find program data for {}. This could mean:\n\
+             1. The program is not deployed\n\
+             2. The program is not upgradeable\n\
+             3. The program was deployed with a different loader",
+            program_id
+        )),
+    }
+}
+
+/// Slot the program was last deployed at, read from its program data header. `None` if the
+/// program has no program data.
+pub fn get_program_data_slot(
+    client: &RpcClient,
+    program_id: Pubkey,
+) -> anyhow::Result<Option<u64>> {
+    Ok(get_program_data_header(client, program_id)?.map(|(slot, _)| slot))
+}
+
+/// Last deployed slot and upgrade authority of the program data header.
+fn get_program_data_header(
+    client: &RpcClient,
+    program_id: Pubkey,
+) -> anyhow::Result<Option<(u64, Option<Pubkey>)>> {
+    let program_data_address =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id()).0;
+    let config = RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        data_slice: Some(UiDataSliceConfig {
+            offset: 0,
+            length: UpgradeableLoaderState::size_of_programdata_metadata(),
+        }),
+        commitment: Some(client.commitment()),
+        min_context_slot: None,
+    };
+    let Some(account) = client
+        .get_account_with_config(&program_data_address, config)?
+        .value
+    else {
+        return Ok(None);
+    };
+    match bincode::deserialize::<UpgradeableLoaderState>(&account.data) {
+        Ok(UpgradeableLoaderState::ProgramData {
+            slot,
+            upgrade_authority_address,
+        }) => Ok(Some((slot, upgrade_authority_address))),
+        _ => Ok(None),
+    }
+}
+
+/// What reviewers ask about a deployment besides its hash.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgramDataInfo {
+    pub upgrade_authority: Option<Pubkey>,
+    pub slot: u64,
+    pub deploy_time: Option<SlotTime>,
+}
+
+impl std::fmt::Display for ProgramDataInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.upgrade_authority {
+            Some(authority) => writeln!(f, "Upgrade authority: {}", authority)?,
+            None => writeln!(f, "Upgrade authority: none (immutable)")?,
+        }
+        writeln!(f, "Last deployed slot: {}", self.slot)?;
+        match &self.deploy_time {
+            Some(time) => write!(f, "Last deployed at: {}", time),
+            None => write!(f, "Last deployed at: unknown"),
+        }
+    }
+}
+
+/// Reads the upgrade authority and last deployment of a program. `None` if the program has
+/// no program data.
+pub fn get_program_data_info(
+    client: &RpcClient,
+    program_id: Pubkey,
+) -> anyhow::Result<Option<ProgramDataInfo>> {
+    let Some((slot, upgrade_authority)) = get_program_data_header(client, program_id)? else {
+        return Ok(None);
+    };
+    let deploy_time = ClusterClock::new(client).slot_time(slot);
+    Ok(Some(ProgramDataInfo {
+        upgrade_authority,
+        slot,
+        deploy_time,
+    }))
+}
+
+/// Logs the deployment details of a program, next to its hash on stdout.
+pub fn print_program_data_info(client: &RpcClient, program_id: Pubkey) {
+    match get_program_data_info(client, program_id) {
+        Ok(Some(info)) => info!("{}", info),
+        Ok(None) => match client.get_account(&program_id) {
+            Ok(account) if is_legacy_loader(&account.owner) => info!(
+                "Program {} was deployed with the legacy loader {} and cannot be upgraded",
+                program_id, account.owner
+            ),
+            _ => info!("Program {} has no program data", program_id),
+        },
+        Err(err) => warn!("Unable to read the program data of {}: {}", program_id, err),
+    }
+}
+
+/// Returns `program_hash` once the deployment slot read after it matches `slot_before`, read
+/// before it was fetched. Otherwise the program was upgraded in between and its hash is
+/// fetched again, so a verdict is never given against a deployment that was replaced.
+pub fn get_settled_program_hash(
+    client: &RpcClient,
+    program_id: Pubkey,
+    mut program_hash: String,
+    mut slot_before: Option<u64>,
+) -> anyhow::Result<String> {
+    for _ in 0..5 {
+        let slot_after = get_program_data_slot(client, program_id)?;
+        if slot_after == slot_before {
+            return Ok(program_hash);
+        }
+        info!(
+            "Program {} was redeployed during the comparison, fetching its hash again",
+            program_id
+        );
+        slot_before = slot_after;
+        program_hash = get_program_hash(client, program_id)?;
+    }
+    Err(anyhow!(
+        "Program {} keeps being redeployed, unable to compare against a settled deployment",
+        program_id
+    ))
+}
+
+/// Re-reads the program hash at finalized commitment, regardless of the run's commitment.
+pub fn get_finalized_program_hash(
+    client: &RpcClient,
+    program_id: Pubkey,
+) -> anyhow::Result<String> {
+    let finalized_client = new_rpc_client(client.url(), CommitmentConfig::finalized());
+    let program_hash = get_program_hash(&finalized_client, program_id)?;
+    Ok(program_hash)
+}
+
+pub fn get_genesis_hash(client: &RpcClient) -> anyhow::Result<String> {
+    let genesis_hash = client.get_genesis_hash()?;
+    Ok(genesis_hash.to_string())
+}
+
+pub fn get_docker_resource_limits() -> Option<(String, String)> {
+    let memory = std::env::var("SVB_DOCKER_MEMORY_LIMIT").ok();
+    let cpus = std::env::var("SVB_DOCKER_CPU_LIMIT").ok();
+    if memory.is_some() || cpus.is_some() {
+        debug!(
+            "Using docker resource limits: memory: {:?}, cpus: {:?}",
+            memory, cpus
+        );
+    } else {
+        // Print message to user that they can set these environment variables to limit docker resources
+        debug!("No Docker resource limits are set.");
+        debug!("You can set the SVB_DOCKER_MEMORY_LIMIT and SVB_DOCKER_CPU_LIMIT environment variables to limit Docker resources.");
+        debug!("For example: SVB_DOCKER_MEMORY_LIMIT=2g SVB_DOCKER_CPU_LIMIT=2.");
+    }
+    memory.zip(cpus)
+}
+
+/// What a build used and recorded, besides the executable itself.
+pub struct BuildOutput {
+    /// Docker image the program was built in
+    pub image: String,
+    pub dependencies: Option<DependencySnapshot>,
+    /// CycloneDX SBOM of the crates the program was built with
+    pub sbom: Option<Sbom>,
+    pub diagnostics: BuildDiagnostics,
+    /// Time, memory and CPU the build took
+    pub resource_usage: ResourceUsage,
+    /// Compile cache of `--build-cache`, if one was given
+    pub build_cache: Option<BuildCacheUse>,
+}
+
+/// Copies the source files of `source` into a temporary directory, leaving out what git
+/// ignores. The copy is made in the current directory if `current_dir` is set, for docker
+/// setups that only share it.
+pub fn copy_to_temp_dir(source: &Path, current_dir: bool) -> anyhow::Result<PathBuf> {
+    let uuid = Uuid::new_v4().to_string();
+    let copy_dir = if current_dir {
+        std::env::current_dir()?.join(format!(".{}", uuid))
+    } else {
+        std::env::temp_dir().join("solana-verify").join(uuid)
+    };
+    track_temp_dir(&copy_dir);
+
+    match copy_source(source, &copy_dir) {
+        Ok(copied) => {
+            info!("Copied {} source file(s) to {}", copied, copy_dir.display());
+            Ok(copy_dir)
+        }
+        Err(err) => {
+            remove_dir(&copy_dir)?;
+            Err(err)
+        }
+    }
+}
+
+/// Builds a copy of the files git tracks in the mount directory, so local artifacts
+/// neither leak into the build nor slow down docker file sharing, then copies the build
+/// output back to the mount directory's target/deploy.
+pub fn build_from_copy(
+    mount_directory: Option<PathBuf>,
+    library_name: Option<String>,
+    base_image: Option<String>,
+    bpf_flag: bool,
+    cargo_args: Vec<String>,
+) -> anyhow::Result<BuildOutput> {
+    let mount_path = match mount_directory {
+        Some(mount_directory) => mount_directory,
+        None => std::env::current_dir()?,
+    };
+    let copy_dir = copy_to_temp_dir(&mount_path, false)?;
+
+    let result = build(
+        Some(copy_dir.clone()),
+        library_name,
+        base_image,
+        bpf_flag,
+        cargo_args,
+    )
+    .and_then(|build_output| {
+        let deploy_dir = mount_path.join("target").join("deploy");
+        std::fs::create_dir_all(&deploy_dir)?;
+        for entry in std::fs::read_dir(copy_dir.join("target").join("deploy"))? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                std::fs::copy(entry.path(), deploy_dir.join(entry.file_name()))?;
+            }
+        }
+        info!("Copied build output to {}", deploy_dir.display());
+        Ok(build_output)
+    });
+
+    remove_dir(&copy_dir)?;
+    result
+}
+
+pub fn build(
+    mount_directory: Option<PathBuf>,
+    library_name: Option<String>,
+    base_image: Option<String>,
+    bpf_flag: bool,
+    cargo_args: Vec<String>,
+) -> anyhow::Result<BuildOutput> {
+    check_docker_available()?;
+
+    let mount_path = match mount_directory {
+        Some(mount_directory) => mount_directory,
+        None => std::env::current_dir()?,
+    }
+    .components()
+    .collect::<PathBuf>();
+    debug!("Mounting path: {}", mount_path.display());
+
+    let lockfile = mount_path.join("Cargo.lock");
+    if !lockfile.exists() {
+        error!("Mount directory must contain a Cargo.lock file");
+        return Err(anyhow!(format!(
+            "No lockfile found at {}",
+            lockfile.display()
+        )));
+    }
+
+    let build_command = if bpf_flag { "build-bpf" } else { "build-sbf" };
+
+    let (major, minor, patch) = get_pkg_version_from_cargo_lock("solana-program", &lockfile)?;
+
+    let mut solana_version: Option<String> = None;
+    let image: String = match base_image {
+        Some(base_image) => base_image,
+        // Use this for backwards compatibility with anchor verified builds
+        None if bpf_flag => {
+            solana_version = Some("v1.13.5".to_string());
+            "projectserum/build@sha256:75b75eab447ebcca1f471c98583d9b5d82c4be122c470852a022afcf9c98bead".to_string()
+        }
+        None => {
+            let (version, digest) = select_image((major, minor, patch))?;
+            if version == (major, minor, patch) {
+                info!(
+                    "Found docker image for Solana version {}.{}.{}",
+                    major, minor, patch
+                );
+            } else {
+                warn!(
+                    "no docker image for Solana version {}.{}.{}, falling back to the image for {}.{}.{} ({:?}). The build may not match the deployed program",
+                    major, minor, patch, version.0, version.1, version.2, get_image_fallback()
+                );
+            }
+            solana_version = Some(format!("v{}.{}.{}", version.0, version.1, version.2));
+            format!("solanafoundation/solana-verifiable-build@{}", digest)
+        }
+    };
+    info!("Using docker image: {}", image);
+
+    // Path of the program's Cargo.toml relative to the mount path
+    let manifest_path = find_cargo_tomls(&mount_path)
+        .into_iter()
+        .find(|p| {
+            get_lib_name_from_cargo_toml(p)
+                .is_ok_and(|name| name == library_name.clone().unwrap_or_default())
+        })
+        .and_then(|p| p.strip_prefix(&mount_path).ok().map(PathBuf::from));
+    let relative_build_path = manifest_path
+        .as_ref()
+        .and_then(|m| m.parent())
+        .map(PathBuf::from)
+        .unwrap_or_default();
+
+    print_docker_daemon();
+    check_mount_shared(&mount_path)?;
+    // Clean up containers left behind by previously interrupted builds
+    if let Ok(removed) = remove_leftover_containers() {
+        if removed > 0 {
+            info!(
+                "Removed {} leftover container(s) from previous builds",
+                removed
+            );
+        }
+    }
+    let workdir = docker_command()
+        .args(["run", "--rm", &image, "pwd"])
+        .stderr(command_output())
+        .output()
+        .map_err(|e| anyhow::format_err!("Failed to get workdir: {}", e.to_string()))
+        .and_then(|output| parse_output(output.stdout))?;
+
+    debug!("Workdir: {}", workdir);
+
+    let build_path = Path::new(&workdir).join(relative_build_path);
+    info!("Building program at {}", build_path.display());
+
+    let manifest_path_filter = manifest_path
+        .as_ref()
+        .map(|m| {
+            vec![
+                OsString::from("--manifest-path"),
+                Path::new(&workdir).join(m).into_os_string(),
+            ]
+        })
+        .unwrap_or_default();
+
+    if let Some(manifest_path) = &manifest_path {
+        debug!(
+            "Building manifest path: {}",
+            Path::new(&workdir).join(manifest_path).display()
+        );
+    }
+
+    // A cache volume created by this run is removed if the run is interrupted before the build
+    // fills it
+    let created_volume = match get_build_cache() {
+        Some(volume) if build_cache::create_volume(volume)? => {
+            track_volume(volume);
+            Some(volume)
+        }
+        _ => None,
+    };
+
+    // change directory to program/build dir
+    let mount_params = bind_mount(&mount_path, Path::new(&workdir));
+    let container_id = {
+        let mut cmd = docker_command();
+        cmd.args(["run", "--rm", "--mount"]);
+        cmd.arg(&mount_params);
+        cmd.arg("-dit");
+        cmd.args(["--label", CONTAINER_LABEL]);
+        cmd.stderr(command_output());
+        if let Some(volume) = get_build_cache() {
+            cmd.args(build_cache::docker_run_args(volume));
+        }
+
+        if let Some((memory_limit, cpu_limit)) = get_docker_resource_limits() {
+            cmd.arg("--memory")
+                .arg(memory_limit)
+                .arg("--cpus")
+                .arg(cpu_limit);
+        }
+
+        cmd.args([&image, "bash"]);
+        log_command(&cmd);
+        let output = cmd
+            .output()
+            .map_err(|e| anyhow!("Docker build failed: {}", e.to_string()))?;
+
+        parse_output(output.stdout)?
+    };
+
+    // Set the container id so we can kill it later if the process is interrupted
+    track_container(&container_id);
+    if let Err(err) = check_mount_visible(&container_id, &mount_path, &workdir) {
+        let _ = kill_container(&container_id);
+        return Err(err);
+    }
+
+    // Solana v1.17 uses Rust 1.73, which defaults to the sparse registry, making
+    // this fetch unnecessary, but requires us to omit the "frozen" argument
+    let locked_args = if major == 1 && minor < 17 {
+        // First, we resolve the dependencies and cache them in the Docker container
+        // ARM processors running Linux have a bug where the build fails if the dependencies are not preloaded.
+        // Running the build without the pre-fetch will cause the container to run out of memory.
+        // This is a workaround for that issue.
+        docker_command()
+            .args(["exec", &container_id])
+            .args([
+                "cargo",
+                "--config",
+                "net.git-fetch-with-cli=true",
+                "fetch",
+                "--locked",
+            ])
+            .stderr(command_output())
+            .stdout(command_output())
+            .output()?;
+        info!("Finished fetching build dependencies");
+
+        ["--frozen", "--locked"].as_slice()
+    } else {
+        // To be totally safe, force the build to use the sparse registry
+        [
+            "--config",
+            "registries.crates-io.protocol=\"sparse\"",
+            "--locked",
+        ]
+        .as_slice()
+    };
+
+    // Cargo reports diagnostics as JSON on stdout, unless a format was asked for explicitly
+    let message_format: &[&str] = if cargo_args
+        .iter()
+        .any(|arg| arg.starts_with("--message-format"))
+    {
+        &[]
+    } else {
+        &["--message-format=json"]
+    };
+    let cache_args = get_build_cache()
+        .map(|volume| build_cache::docker_exec_args(&container_id, volume))
+        .unwrap_or_default();
+    let mut build_cmd = docker_command();
+    build_cmd
+        .arg("exec")
+        .args(&cache_args)
+        .arg("-w")
+        .arg(&build_path)
+        .arg(&container_id)
+        .args(["cargo", build_command])
+        .args(["--"])
+        .args(locked_args)
+        .args(&manifest_path_filter)
+        .args(message_format)
+        .args(&cargo_args)
+        .stderr(command_output())
+        .stdout(Stdio::piped());
+    log_command(&build_cmd);
+    let build_started = std::time::Instant::now();
+    let mut build_process = build_cmd.spawn()?;
+    let diagnostics = match build_process.stdout.take() {
+        Some(stdout) => BuildDiagnostics::collect(std::io::BufReader::new(stdout))?,
+        None => BuildDiagnostics::default(),
+    };
+    let build_status = build_process.wait()?;
+    diagnostics.print_summary();
+    let resource_usage = ResourceUsage::collect(&container_id, build_started.elapsed());
+    resource_usage.print_summary();
+
+    // A failed build may leave the executable of a previous build in place
+    if !build_status.success() || diagnostics.has_errors() {
+        kill_container(&container_id)?;
+        return Err(anyhow!(
+            "Build failed with {} error(s) ({})",
+            diagnostics.errors.len(),
+            build_status
+        ));
+    }
+
+    info!("Finished building program");
+    if let Some(volume) = created_volume {
+        untrack_volume(volume);
+    }
+    let build_cache = get_build_cache()
+        .map(|volume| build_cache_use(&container_id, volume, !cache_args.is_empty()));
+
+    // Snapshot the dependency closure while the container still has the registry cache
+    let dependencies = docker_command()
+        .args(["exec", "-w"])
+        .arg(&build_path)
+        .arg(&container_id)
+        .args(["cargo", "tree", "--locked"])
+        .args(&manifest_path_filter)
+        .stderr(command_output())
+        .output()
+        .map_err(|e| anyhow!("Failed to run cargo tree: {}", e))
+        .and_then(|output| {
+            if !output.status.success() {
+                return Err(anyhow!("cargo tree exited with {}", output.status));
+            }
+            Ok(DependencySnapshot::new(String::from_utf8(output.stdout)?))
+        });
+    let dependencies = match dependencies {
+        Ok(dependencies) => {
+            info!("Dependency tree hash: {}", dependencies.sha256);
+            Some(dependencies)
+        }
+        Err(err) => {
+            warn!("unable to snapshot the dependency tree: {}", err);
+            None
+        }
+    };
+    let sbom = docker_command()
+        .args(["exec", "-w"])
+        .arg(&build_path)
+        .arg(&container_id)
+        .args([
+            "cargo",
+            "metadata",
+            "--format-version",
+            "1",
+            "--locked",
+            "--offline",
+        ])
+        .args(&manifest_path_filter)
+        .stderr(command_output())
+        .output()
+        .map_err(|e| anyhow!("Failed to run cargo metadata: {}", e))
+        .and_then(|output| {
+            if !output.status.success() {
+                return Err(anyhow!("cargo metadata exited with {}", output.status));
+            }
+            let resolved = parse_cargo_metadata(&String::from_utf8(output.stdout)?)?;
+            let lockfile = Lockfile::load(mount_path.join("Cargo.lock"))?;
+            Ok(Sbom::new(&lockfile, &resolved, library_name.as_deref()))
+        });
+    let sbom = match sbom {
+        Ok(sbom) => {
+            info!("SBOM lists {} crates", sbom.components.len());
+            Some(sbom)
+        }
+        Err(err) => {
+            warn!("unable to generate the SBOM: {}", err);
+            None
+        }
+    };
+    info!("Program Solana version: v{}.{}.{}", major, minor, patch);
+
+    if let Some(solana_version) = &solana_version {
+        info!("Docker image Solana version: {}", solana_version);
+    }
+
+    let host_copy_check = library_name.as_ref().map(|program_name| {
+        let container_path = Path::new(&workdir)
+            .join("target")
+            .join("deploy")
+            .join(format!("{}.so", program_name));
+        find_executable(&mount_path, program_name).and_then(|executable_path| {
+            check_host_copy(
+                &container_id,
+                &container_path.display().to_string(),
+                &executable_path,
+            )
+        })
+    });
+
+    kill_container(&container_id)?;
+
+    if let Some(program_name) = library_name {
+        host_copy_check.transpose()?;
+        let executable_path = find_executable(&mount_path, &program_name)?;
+        validate_sbf_program(
+            &executable_path.display().to_string(),
+            &std::fs::read(&executable_path)?,
+            (major, minor, patch),
+        )?;
+        let executable_hash = get_file_hash(&executable_path)?;
+        println!("{}", executable_hash);
+    }
+    Ok(BuildOutput {
+        image,
+        dependencies,
+        sbom,
+        diagnostics,
+        resource_usage,
+        build_cache,
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn verify_from_image(
+    executable_path: String,
+    image: String,
+    network: Option<String>,
+    commitment: CommitmentConfig,
+    program_id: Pubkey,
+    current_dir: bool,
+    expected_repo: Option<&str>,
+    expected_commit: Option<&str>,
+) -> anyhow::Result<()> {
+    info!(
+        "Verifying image: {:?}, on network {:?} against program ID {}",
+        image, network, program_id
+    );
+    debug!("Executable path in container: {:?}", executable_path);
+
+    check_docker_available()?;
+
+    print_docker_daemon();
+    let workdir = docker_command()
+        .args(["run", "--rm", &image, "pwd"])
+        .stderr(command_output())
+        .output()
+        .map_err(|e| anyhow::format_err!("Failed to get workdir: {}", e.to_string()))
+        .and_then(|output| parse_output(output.stdout))?;
+
+    debug!("Workdir: {}", workdir);
+
+    // The image was pulled by the run above, so its labels can be inspected locally
+    let provenance = get_image_provenance(&image)?;
+    provenance.print();
+    let mismatches = provenance.check(expected_repo, expected_commit)?;
+    if !mismatches.is_empty() {
+        for mismatch in &mismatches {
+            warn!("  {}", mismatch);
+        }
+        return Err(anyhow!(
+            "Image {} was not built from the expected source",
+            image
+        ));
+    }
+
+    let container_id = {
+        let mut cmd = docker_command();
+        cmd.args(["run", "--rm", "-dit"]);
+        cmd.args(["--label", CONTAINER_LABEL]);
+        cmd.stderr(command_output());
+
+        if let Some((memory_limit, cpu_limit)) = get_docker_resource_limits() {
+            cmd.arg("--memory")
+                .arg(memory_limit)
+                .arg("--cpus")
+                .arg(cpu_limit);
+        }
+
+        let output = cmd
+            .args([&image])
+            .output()
+            .map_err(|e| anyhow!("Docker build failed: {}", e.to_string()))?;
+        parse_output(output.stdout)?
+    };
+    track_container(&container_id);
+
+    let uuid = Uuid::new_v4().to_string();
+
+    // Create a temporary directory to clone the repo into
+    let verify_dir = if current_dir {
+        std::env::current_dir()?.join(format!(".{}", uuid))
+    } else {
+        PathBuf::from("/tmp")
+    };
+
+    let program_filepath = verify_dir.join("program.so");
+    track_temp_dir(&program_filepath);
+    docker_command()
+        .arg("cp")
+        .arg(format!("{}:{}/{}", container_id, workdir, executable_path))
+        .arg(&program_filepath)
+        .stdout(command_output())
+        .stderr(command_output())
+        .output()
+        .map_err(|e| anyhow::format_err!("Failed to copy executable file {}", e.to_string()))?;
+    let host_copy_check = check_host_copy(
+        &container_id,
+        &format!("{}/{}", workdir, executable_path),
+        &program_filepath,
+    );
+
+    let executable_hash: String = get_file_hash(&program_filepath)?;
+    let client = get_client(network, commitment);
+    let slot_before = get_program_data_slot(&client, program_id)?;
+    let program_hash = get_program_hash(&client, program_id)?;
+    let program_hash = get_settled_program_hash(&client, program_id, program_hash, slot_before)?;
+    println!("Executable hash: {}", executable_hash);
+    println!("Program hash: {}", program_hash);
+
+    // Cleanup docker and rm file
+    kill_container(&container_id)
+        .map_err(|e| anyhow::format_err!("Docker kill failed: {}", e.to_string()))?;
+
+    remove_dir(&program_filepath).map_err(|e| {
+        anyhow::format_err!("Failed to remove temp program file: {}", e.to_string())
+    })?;
+    host_copy_check?;
+
+    if program_hash != executable_hash {
+        println!("Executable hash mismatch");
+        return Err(anyhow::Error::msg("Executable hash mismatch"));
+    } else {
+        println!("Executable matches on-chain program data ✅");
+    }
+    Ok(())
+}
+
+fn build_args(
+    relative_mount_path: &str,
+    library_name_opt: Option<String>,
+    verify_tmp_root_path: &Path,
+    base_image: Option<String>,
+    bpf_flag: bool,
+    cargo_args: Vec<String>,
+) -> anyhow::Result<(Vec<String>, PathBuf, String)> {
+    let mut args: Vec<String> = Vec::new();
+    if !relative_mount_path.is_empty() {
+        args.push("--mount-path".to_string());
+        args.push(relative_mount_path.to_string());
+    }
+    // Get the absolute build path to the solana program directory to build inside docker
+    let mount_path = verify_tmp_root_path.join(relative_mount_path);
+
+    args.push("--library-name".to_string());
+    let library_name = match library_name_opt.clone() {
+        Some(p) => p,
+        None => {
+            let options = find_cargo_tomls(&mount_path)
+                .iter()
+                .filter_map(|path| get_lib_name_from_cargo_toml(path).ok())
+                .collect::<Vec<_>>();
+            if options.len() != 1 {
+                warn!(
+                    "Found multiple possible targets in root directory: {:?}",
+                    options
+                );
+                warn!("Please explicitly specify the target with the --library-name <name> option",);
+                return Err(anyhow::format_err!(
+                    "Failed to find unique Cargo.toml file in root directory"
+                ));
+            }
+            options[0].clone()
+        }
+    };
+    args.push(library_name.clone());
+
+    if let Some(base_image) = &base_image {
+        args.push("--base-image".to_string());
+        args.push(base_image.clone());
+    }
+
+    if bpf_flag {
+        args.push("--bpf".to_string());
+    }
+
+    if !cargo_args.is_empty() {
+        args.push("--".to_string());
+        for arg in &cargo_args {
+            args.push(arg.clone());
+        }
+    }
+
+    Ok((args, mount_path, library_name))
+}
+
+/// Removes a temporary workspace, unless it is kept to inspect a failed verification.
+pub fn remove_temp_dir(dir: &Path, keep: bool) -> anyhow::Result<()> {
+    if keep {
+        untrack_temp_dir(dir);
+        info!("Keeping the workspace for inspection: {}", dir.display());
+        info!("Remove it once done with: rm -rf {}", dir.display());
+        return Ok(());
+    }
+    remove_dir(dir)?;
+    Ok(())
+}
+
+/// Runs `future` from synchronous code, inside or outside the runtime. Inside one it must be
+/// a multi-thread runtime: `block_in_place` panics on a current-thread runtime.
+pub fn block_on<F: std::future::Future>(future: F) -> anyhow::Result<F::Output> {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => Ok(tokio::task::block_in_place(|| handle.block_on(future))),
+        Err(_) => Ok(tokio::runtime::Runtime::new()?.block_on(future)),
+    }
+}
+
+/// Checks out `commit_hash` in a fresh clone. Commits that are no longer on a branch or tag
+/// are fetched by SHA, and if that fails the forge is asked whether the commit ever existed.
+fn checkout_commit(repo_path: &Path, repo_url: &str, commit_hash: &str) -> anyhow::Result<()> {
+    if git_backend::checkout(repo_path, commit_hash)? {
+        info!("Checked out commit hash: {}", commit_hash);
+        return Ok(());
+    }
+
+    info!(
+        "Commit {} is not in the clone, fetching it directly",
+        commit_hash
+    );
+    let fetched = git_backend::fetch_and_checkout(repo_path, commit_hash)?;
+    // Local paths and remotes without a known forge leave the commit unknown
+    let remote = GitRemote::parse(repo_url).ok().filter(|_| !fetched);
+    let forge_knows = match remote {
+        None => None,
+        Some(remote) => match block_on(remote.commit_exists(commit_hash))? {
+            Ok(known) => known,
+            Err(err) => {
+                warn!(
+                    "Unable to look up the commit through the forge API: {}",
+                    err
+                );
+                None
+            }
+        },
+    };
+
+    let missing = MissingCommit::classify(fetched, forge_knows);
+    if missing == MissingCommit::Unreachable {
+        warn!("{}", missing);
+        info!("Checked out commit hash: {}", commit_hash);
+        return Ok(());
+    }
+    Err(anyhow!(
+        "Failed to check out commit {} of {}: {}",
+        commit_hash,
+        repo_url,
+        missing
+    ))
+}
+
+fn clone_repo_and_checkout(
+    repo_url: &str,
+    current_dir: bool,
+    base_name: &str,
+    commit_hash: Option<String>,
+) -> anyhow::Result<(PathBuf, PathBuf)> {
+    let uuid = Uuid::new_v4().to_string();
+
+    // Create a temporary directory to clone the repo into
+    let verify_dir = if current_dir {
+        std::env::current_dir()?.join(format!(".{}", uuid))
+    } else {
+        PathBuf::from("/tmp/solana-verify").join(uuid)
+    };
+    track_temp_dir(&verify_dir);
+
+    let verify_tmp_root_path = verify_dir.join(base_name);
+    info!("Cloning repo into: {}", verify_tmp_root_path.display());
+
+    git_backend::clone(repo_url, &verify_tmp_root_path)?;
+
+    if let Some(commit_hash) = commit_hash.as_ref() {
+        if let Err(err) = checkout_commit(&verify_tmp_root_path, repo_url, commit_hash) {
+            remove_dir(&verify_dir)?;
+            return Err(err);
+        }
+    }
+
+    Ok((verify_tmp_root_path, verify_dir))
+}
+
+/// Directory a repository is cloned into: the repository name of a forge URL, else the last
+/// component of a local path or `file://` URL.
+fn get_basename(repo_url: &str) -> anyhow::Result<String> {
+    if let Ok(remote) = GitRemote::parse(repo_url) {
+        return Ok(remote.name().to_string());
+    }
+    repo_url
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .map(|name| name.trim_end_matches(".git"))
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_string())
+        .ok_or_else(|| anyhow!("Failed to get basename of repo_url {}", repo_url))
+}
+
+/// Parts of `verify-from-repo` to skip, so the pipeline can be run piecewise.
+#[derive(Debug, Clone, Default)]
+pub struct VerifyPhases {
+    /// Existing checkout to build instead of cloning the repository
+    pub skip_clone: Option<PathBuf>,
+    /// Build a copy of the files git tracks in `skip_clone`
+    pub copy_source: bool,
+    /// Upload the PDA even if the build does not match the on-chain program
+    pub skip_hash_check: bool,
+    /// Stop after comparing the build with the on-chain program
+    pub only_build: bool,
+    /// Check the Cargo.lock of the build against the RustSec advisory database
+    pub audit_deps: bool,
+    /// Rewrite a version 4 Cargo.lock of the clone as version 3 if the build image's cargo
+    /// cannot read it
+    pub downgrade_lockfile: bool,
+    /// Sign the program hash and the slot it was read at if the build matches
+    pub attest: bool,
+    /// File to write the signed attestation to
+    pub attestation_path: Option<PathBuf>,
+    /// Compare the build with the program staged in this buffer instead of the deployed
+    /// program, without uploading the PDA
+    pub buffer: Option<Pubkey>,
+    /// Compare the sections and build metadata of both executables if they do not match
+    pub explain_mismatch: bool,
+    /// Simulate the upload of the PDA and print what it would write, without sending it
+    pub dry_run: bool,
+}
+
+/// Program to verify with `verify_from_repo`, and how to build it and upload its verification.
+#[derive(Debug, Clone, Default)]
+pub struct VerifyOptions {
+    pub repo_url: String,
+    /// Commit to check out, the default branch if none
+    pub commit_hash: Option<String>,
+    pub program_id: Pubkey,
+    /// Directory of the program, relative to the root of the repository
+    pub mount_path: String,
+    pub library_name: Option<String>,
+    pub base_image: Option<String>,
+    /// Build with `cargo build-bpf`, unset to follow the verify.toml of the repository
+    pub bpf: Option<bool>,
+    pub cargo_args: Vec<String>,
+    /// Have the remote server build the program instead of building it locally
+    pub remote: bool,
+    /// Clone the repository into the current directory instead of the system temp dir
+    pub current_dir: bool,
+    /// Upload without asking for confirmation on the terminal
+    pub skip_prompt: bool,
+    /// Keypair signing the upload, the Solana CLI config one if none
+    pub keypair: Option<String>,
+    /// 0 to price each transaction once its accounts are known
+    pub compute_unit_price: u64,
+    pub phases: VerifyPhases,
+    /// Rebuilds allowed if the program is redeployed before the upload
+    pub stale_rebuilds: u32,
+    /// Keep the clone if the build fails or does not match
+    pub keep_temp_on_failure: bool,
+    /// File to save the verified params to once the build matches
+    pub params_file: Option<String>,
+    /// File to write the verification report to
+    pub report_path: Option<String>,
+}
+
+/// What `verify_from_repo` established about a program.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifyOutcome {
+    /// Hash of the executable built from the repository, none for remote verification
+    pub executable_hash: Option<String>,
+    pub on_chain_hash: Option<String>,
+    /// Buffer the build was compared with instead of the deployed program
+    pub buffer: Option<Pubkey>,
+    /// Whether the build matches the finalized on-chain program
+    pub matches: bool,
+    /// Whether the verification was written on chain
+    pub uploaded: bool,
+    /// Signed claim of the match, if one was requested
+    pub attestation: Option<Attestation>,
+}
+
+impl VerifyOutcome {
+    /// Prints the hashes that were compared and the verdict, then the attestation if one was
+    /// signed.
+    pub fn print(&self) {
+        if let (Some(executable_hash), Some(on_chain_hash)) =
+            (&self.executable_hash, &self.on_chain_hash)
+        {
+            println!("Executable Program Hash from repo: {}", executable_hash);
+            match self.buffer {
+                Some(buffer) => println!("Buffer {} Program Hash: {}", buffer, on_chain_hash),
+                None => println!("On-chain Program Hash: {}", on_chain_hash),
+            }
+            match (self.buffer, self.matches) {
+                (Some(_), true) => println!("Buffer matches the build, it is safe to deploy ✅"),
+                (Some(_), false) => println!("Buffer does not match the build ❌"),
+                (None, true) => println!("Program hash matches ✅"),
+                (None, false) => println!("Program hashes do not match ❌"),
+            }
+        }
+        if let Some(attestation) = &self.attestation {
+            attestation.print();
+        }
+    }
+}
+
+/// Builds a repository and compares the executable with the on-chain program, then uploads
+/// the verification if it matches, as `options` describe. Nothing is printed, the caller
+/// reports the returned outcome.
+pub async fn verify_from_repo(
+    connection: &RpcClient,
+    options: VerifyOptions,
+) -> anyhow::Result<VerifyOutcome> {
+    let VerifyOptions {
+        repo_url,
+        commit_hash,
+        program_id,
+        mount_path: relative_mount_path,
+        library_name: library_name_opt,
+        base_image,
+        bpf: bpf_flag,
+        cargo_args,
+        remote,
+        current_dir,
+        skip_prompt,
+        keypair: path_to_keypair,
+        compute_unit_price,
+        phases,
+        stale_rebuilds,
+        keep_temp_on_failure,
+        params_file,
+        report_path,
+    } = options.clone();
+
+    // The remote server builds the program, so it is not built locally
+    let skip_build = remote;
+
+    // Fail before cloning the repository if the build cannot run
+    if !skip_build {
+        check_docker_available()?;
+    }
+
+    if phases.buffer.is_some() && (remote || phases.attest || phases.skip_hash_check) {
+        return Err(anyhow!(
+            "--buffer only compares the build with the buffer, it cannot be combined with --remote, --attest or --skip-hash-check"
+        ));
+    }
+
+    if phases.dry_run && remote {
+        return Err(anyhow!(
+            "--dry-run does not upload the PDA the remote verification needs, it cannot be combined with --remote"
+        ));
+    }
+
+    if phases.downgrade_lockfile && phases.skip_clone.is_some() && !phases.copy_source {
+        return Err(anyhow!(
+            "--downgrade-lockfile rewrites Cargo.lock, use --copy-source to build a copy of the checkout"
+        ));
+    }
+
+    check_interrupted()?;
+
+    // Only directories cloned here are removed afterwards
+    let (verify_tmp_root_path, verify_dir) = match &phases.skip_clone {
+        Some(checkout) if phases.copy_source => {
+            info!("Copying existing checkout: {}", checkout.display());
+            let copy_dir = copy_to_temp_dir(checkout, current_dir)?;
+            (copy_dir.clone(), Some(copy_dir))
+        }
+        Some(checkout) => {
+            info!("Using existing checkout: {}", checkout.display());
+            (checkout.clone(), None)
+        }
+        None => {
+            // Get source code from repo_url
+            let base_name = get_basename(&repo_url)?;
+            let (verify_tmp_root_path, verify_dir) =
+                clone_repo_and_checkout(&repo_url, current_dir, &base_name, commit_hash.clone())?;
+            (verify_tmp_root_path, Some(verify_dir))
+        }
+    };
+
+    check_interrupted()?;
+
+    // Settings committed in the repository, in verify.toml then in the Cargo.toml of the
+    // program, overridden by the command line
+    let settings = apply_project_config(
+        &verify_tmp_root_path,
+        Some(&program_id.to_string()),
+        ManifestProgram {
+            library_name: library_name_opt,
+            mount_path: relative_mount_path,
+            base_image,
+            bpf: bpf_flag,
+            cargo_args,
+        },
+    )?;
+    let ManifestProgram {
+        library_name: library_name_opt,
+        mount_path: relative_mount_path,
+        base_image,
+        bpf,
+        cargo_args,
+    } = apply_package_metadata(&verify_tmp_root_path, &program_id, settings)?;
+    let bpf_flag = bpf.unwrap_or_default();
+
+    let (args, mount_path, library_name) = build_args(
+        &relative_mount_path,
+        library_name_opt.clone(),
+        &verify_tmp_root_path,
+        base_image.clone(),
+        bpf_flag,
+        cargo_args.clone(),
+    )?;
+    info!("Build path: {}", mount_path.display());
+    info!("Verifying program: {}", library_name);
+
+    check_interrupted()?;
+
+    // Remember which deployment is being verified so the upload can detect a redeploy
+    let deployed_slot = if !skip_build && phases.buffer.is_none() {
+        Some(
+            get_last_deployed_slot(connection, &program_id.to_string())
+                .await
+                .map_err(|err| anyhow!("Unable to get last deployed slot: {}", err))?,
+        )
+    } else {
+        None
+    };
+
+    // Deployment the on-chain hash will be fetched from, to detect upgrades racing the build
+    let slot_before = if !skip_build && phases.buffer.is_none() {
+        get_program_data_slot(connection, program_id)?
+    } else {
+        None
+    };
+
+    let audit = if phases.audit_deps && !skip_build {
+        match audit_lockfile(&mount_path.join("Cargo.lock")) {
+            Ok(audit) => {
+                audit.print_summary();
+                Some(audit)
+            }
+            Err(err) => {
+                warn!("unable to audit the dependencies: {}", err);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let explained_mount_path = mount_path.clone();
+    // The params uploaded for the commit do not rewrite its Cargo.lock
+    let mut lockfile_downgraded = false;
+    let result: Result<(String, String, Option<BuildOutput>), anyhow::Error> = if !skip_build {
+        ensure_lockfile_readable(
+            &mount_path,
+            base_image.as_deref(),
+            bpf_flag,
+            phases.downgrade_lockfile,
+        )
+        .map(|downgraded| lockfile_downgraded = downgraded)
+        .and_then(|_| match phases.buffer {
+            Some(buffer) => {
+                let (build_hash, build_output) = build_repo(
+                    &mount_path,
+                    base_image.clone(),
+                    bpf_flag,
+                    &library_name,
+                    connection,
+                    cargo_args.clone(),
+                )?;
+                info!("Fetching program data staged in buffer: {}", buffer);
+                let buffer_hash = fetch_buffer_hash(connection, buffer)?;
+                Ok((build_hash, buffer_hash, Some(build_output)))
+            }
+            None => build_and_verify_repo(
+                mount_path,
+                base_image.clone(),
+                bpf_flag,
+                library_name.clone(),
+                connection,
+                program_id,
+                cargo_args.clone(),
+            ),
+        })
+    } else {
+        Ok(("skipped".to_string(), "skipped".to_string(), None))
+    };
+
+    let result = result.and_then(|(build_hash, program_hash, build_output)| {
+        let program_hash = if !skip_build && phases.buffer.is_none() {
+            get_settled_program_hash(connection, program_id, program_hash, slot_before)?
+        } else {
+            program_hash
+        };
+        // The executable is only available until the clone is removed
+        if phases.explain_mismatch && !skip_build && build_hash != program_hash {
+            if let Err(err) = explain_mismatch(
+                connection,
+                program_id,
+                phases.buffer,
+                &find_executable(&explained_mount_path, &library_name)?,
+            ) {
+                warn!("unable to explain the mismatch: {}", err);
+            }
+        }
+        Ok((build_hash, program_hash, build_output))
+    });
+
+    // Cleanup no matter the result, unless the failure is to be inspected
+    if let Some(verify_dir) = &verify_dir {
+        let failed =
+            !matches!(&result, Ok((build_hash, program_hash, _)) if build_hash == program_hash);
+        remove_temp_dir(verify_dir, keep_temp_on_failure && failed)?;
+    }
+
+    // Handle the result
+    match result {
+        Ok((build_hash, program_hash, build_output)) => {
+            if !skip_build && phases.buffer.is_none() {
+                print_program_data_info(connection, program_id);
+            }
+
+            // Guard against comparing with an upgrade that has not been finalized yet
+            let mut build_matches = build_hash == program_hash;
+            if build_matches
+                && phases.buffer.is_none()
+                && connection.commitment() != CommitmentConfig::finalized()
+            {
+                let finalized_hash = get_finalized_program_hash(connection, program_id)?;
+                if finalized_hash != program_hash {
+                    warn!(
+                        "On-chain program hash at finalized commitment differs: {}",
+                        finalized_hash
+                    );
+                    warn!("The program may have been upgraded recently, retry once the upgrade is finalized");
+                    build_matches = false;
+                }
+            }
+
+            if let Some(report_path) = &report_path {
+                if let Some(build_output) = build_output {
+                    VerificationReport {
+                        version: env!("CARGO_PKG_VERSION").to_string(),
+                        program_id: program_id.to_string(),
+                        repo_url: strip_url_credentials(&repo_url),
+                        commit: commit_hash.clone().unwrap_or_default(),
+                        args: args.clone(),
+                        executable_hash: build_hash.clone(),
+                        on_chain_hash: program_hash.clone(),
+                        matches: build_matches,
+                        image: build_output.image,
+                        dependencies: build_output.dependencies,
+                        sbom: build_output.sbom,
+                        audit,
+                        diagnostics: Some(build_output.diagnostics),
+                        resource_usage: Some(build_output.resource_usage),
+                        build_cache: build_output.build_cache,
+                    }
+                    .write(report_path)?;
+                }
+            }
+
+            let mut outcome = if skip_build {
+                VerifyOutcome {
+                    executable_hash: None,
+                    on_chain_hash: None,
+                    buffer: None,
+                    matches: false,
+                    uploaded: false,
+                    attestation: None,
+                }
+            } else {
+                VerifyOutcome {
+                    executable_hash: Some(build_hash.clone()),
+                    on_chain_hash: Some(program_hash.clone()),
+                    buffer: phases.buffer,
+                    matches: build_matches,
+                    uploaded: false,
+                    attestation: None,
+                }
+            };
+
+            if phases.attest && !skip_build {
+                if build_matches {
+                    let signer = get_signer_from_path_or_config(path_to_keypair.as_ref())?;
+                    let attestation = Attestation::sign(
+                        signer.as_ref(),
+                        &program_id,
+                        &program_hash,
+                        connection.get_slot()?,
+                        &commit_hash.clone().unwrap_or_default(),
+                    )?;
+                    if let Some(attestation_path) = &phases.attestation_path {
+                        attestation.write(attestation_path)?;
+                        info!("Attestation written to {}", attestation_path.display());
+                    }
+                    outcome.attestation = Some(attestation);
+                } else {
+                    warn!("Not signing an attestation for a build that does not match the on-chain program");
+                }
+            }
+
+            if let Some(buffer) = phases.buffer {
+                info!(
+                    "Skipping the upload of verify data, upload it once buffer {} is deployed",
+                    buffer
+                );
+                return Ok(outcome);
+            }
+
+            if phases.only_build {
+                info!("Skipping the upload of verify data (--only-build)");
+                return Ok(outcome);
+            }
+
+            if lockfile_downgraded && (phases.skip_hash_check || build_matches) {
+                return Err(anyhow!(
+                    "Cargo.lock was downgraded for this build (--downgrade-lockfile), which the uploaded params would not reproduce. Commit a version 3 Cargo.lock and verify that commit to upload them"
+                ));
+            }
+
+            if skip_build || phases.skip_hash_check || build_matches {
+                if skip_build {
+                    info!("Skipping local build for remote verification and writing verify data on chain");
+                } else if phases.skip_hash_check && !build_matches {
+                    warn!("writing verify data on chain for a build that does not match the on-chain program (--skip-hash-check)");
+                } else if let Some(params_file) = &params_file {
+                    // Without the verified deployment the PDA would claim slot 0
+                    let deployed_slot = deployed_slot.ok_or_else(|| {
+                        anyhow!(
+                            "Cannot write {}: the verified build is not tied to a deployment of {}. Verify the deployed program without --buffer to write a params file",
+                            params_file,
+                            program_id
+                        )
+                    })?;
+                    write_verified_params_file(
+                        params_file,
+                        skip_prompt,
+                        &program_id,
+                        InputParams {
+                            version: env!("CARGO_PKG_VERSION").to_string(),
+                            git_url: strip_url_credentials(&repo_url),
+                            commit: commit_hash.clone().unwrap_or_default(),
+                            args: args.clone(),
+                            deployed_slot,
+                        },
+                        build_hash,
+                        path_to_keypair.as_ref(),
+                    )?;
+                }
+
+                if phases.dry_run {
+                    preview_program_verification_data(
+                        repo_url.clone(),
+                        &commit_hash,
+                        args.iter().map(|s| s.to_string()).collect(),
+                        program_id,
+                        connection,
+                        path_to_keypair.clone(),
+                        compute_unit_price,
+                        deployed_slot,
+                    )
+                    .await?;
+                    info!("Skipping the upload of verify data (--dry-run)");
+                    return Ok(outcome);
+                }
+
+                let upload_result = upload_program_verification_data(
+                    repo_url.clone(),
+                    &commit_hash.clone(),
+                    args.iter().map(|s| s.to_string()).collect(),
+                    program_id,
+                    connection,
+                    skip_prompt,
+                    path_to_keypair.clone(),
+                    compute_unit_price,
+                    deployed_slot,
+                )
+                .await;
+
+                if let Err(err) = &upload_result {
+                    if stale_rebuilds > 0 && err.downcast_ref::<StaleDeploymentError>().is_some() {
+                        warn!("{}", err);
+                        info!(
+                            "Rebuilding against the new deployment ({} rebuild(s) left)...",
+                            stale_rebuilds - 1
+                        );
+                        return Box::pin(verify_from_repo(
+                            connection,
+                            VerifyOptions {
+                                stale_rebuilds: stale_rebuilds - 1,
+                                ..options
+                            },
+                        ))
+                        .await;
+                    }
+                }
+                upload_result?;
+                outcome.uploaded = true;
+
+                if remote {
+                    check_interrupted()?;
+                    let genesis_hash = get_genesis_hash(connection)?;
+                    if genesis_hash != MAINNET_GENESIS_HASH {
+                        return Err(anyhow!("Remote verification only works with mainnet. Please omit the --remote flag to verify locally."));
+                    }
+
+                    let uploader = get_address_from_keypair_or_config(path_to_keypair.as_ref())?;
+                    info!(
+                        "Sending verify command to remote machine with uploader: {}",
+                        &uploader
+                    );
+                    info!(
+                        "\nPlease note that if the desired uploader is not the provided keypair, you will need to run `solana-verify remote submit-job --program-id {} --uploader <uploader-address>.\n",
+                        &program_id,
+                    );
+                    let submitted =
+                        send_job_with_uploader_to_remote(connection, &program_id, &uploader).await;
+                    let lockfile_failure = submitted.as_ref().err().is_some_and(|err| {
+                        err.downcast_ref::<RemoteJobFailed>()
+                            .is_some_and(|failed| failed.failure == KnownFailure::LockfileVersion)
+                    });
+                    if lockfile_failure
+                        && !skip_prompt
+                        && prompt_user_confirmation(&format!(
+                            "Upload the verification again with --base-image {} and resubmit? The build may not match the deployed program if it was built with an older toolchain (y/n) ",
+                            lockfile_compatible_base_image()
+                        ))?
+                    {
+                        upload_program_verification_data(
+                            repo_url.clone(),
+                            &commit_hash,
+                            with_base_image(&args, &lockfile_compatible_base_image()),
+                            program_id,
+                            connection,
+                            skip_prompt,
+                            path_to_keypair.clone(),
+                            compute_unit_price,
+                            deployed_slot,
+                        )
+                        .await?;
+                        send_job_with_uploader_to_remote(connection, &program_id, &uploader)
+                            .await?;
+                    } else {
+                        submitted?;
+                    }
+                }
+
+                Ok(outcome)
+            } else {
+                Ok(outcome)
+            }
+        }
+        Err(e) => Err(anyhow!("Error verifying program: {:?}", e)),
+    }
+}
+
+fn write_verified_params_file(
+    path: &str,
+    skip_prompt: bool,
+    program_id: &Pubkey,
+    input_params: InputParams,
+    executable_hash: String,
+    path_to_keypair: Option<&String>,
+) -> anyhow::Result<()> {
+    if std::path::Path::new(path).exists()
+        && !skip_prompt
+        && !prompt_user_input(&format!("{} already exists. Overwrite it? (y/n) ", path))?
+    {
+        info!("Keeping the existing params file at {}", path);
+        return Ok(());
+    }
+
+    let mut params_file = VerifiedParamsFile::new(program_id, &input_params, executable_hash);
+    match get_signer_from_path_or_config(path_to_keypair) {
+        Ok(signer) => params_file.sign(signer.as_ref())?,
+        Err(err) => warn!(
+            "Writing unsigned params file, no keypair available: {}",
+            err
+        ),
+    }
+    let path = params_file.write(path)?;
+    info!("Verified params written to {}", path.display());
+    Ok(())
+}
+
+pub async fn upload_params_file(
+    path: &str,
+    program_ids: Vec<Pubkey>,
+    connection: &RpcClient,
+    skip_prompt: bool,
+    path_to_keypair: Option<String>,
+    compute_unit_price: u64,
+    allow_unsigned: bool,
+) -> anyhow::Result<()> {
+    let params_file = VerifiedParamsFile::read(path)?;
+    params_file.check_signature(allow_unsigned)?;
+
+    if !program_ids.is_empty() {
+        return upload_params_file_to_programs(
+            &params_file,
+            program_ids,
+            connection,
+            skip_prompt,
+            path_to_keypair,
+            compute_unit_price,
+        )
+        .await;
+    }
+
+    let program_id = params_file.program_id()?;
+
+    println!("Program Id: {}", program_id);
+    println!("Git Url: {}", params_file.git_url);
+    println!("Commit: {}", params_file.commit);
+    println!("Args: {:?}", params_file.args);
+    println!("Executable Hash: {}", params_file.executable_hash);
+
+    if skip_prompt
+        || prompt_user_input(
+            "Do you want to upload the program verification to the Solana Blockchain? (y/n) ",
+        )?
+    {
+        upload_input_params(
+            &params_file.input_params(),
+            program_id,
+            connection,
+            skip_prompt,
+            path_to_keypair,
+            compute_unit_price,
+        )
+        .await
+    } else {
+        info!("Exiting without uploading the program.");
+        Ok(())
+    }
+}
+
+/// Uploads the params of a verified build for every program deployed from that same build.
+async fn upload_params_file_to_programs(
+    params_file: &VerifiedParamsFile,
+    program_ids: Vec<Pubkey>,
+    connection: &RpcClient,
+    skip_prompt: bool,
+    path_to_keypair: Option<String>,
+    compute_unit_price: u64,
+) -> anyhow::Result<()> {
+    println!("Git Url: {}", params_file.git_url);
+    println!("Commit: {}", params_file.commit);
+    println!("Args: {:?}", params_file.args);
+    println!("Executable Hash: {}", params_file.executable_hash);
+
+    let mut params = vec![];
+    for program_id in program_ids {
+        // The params only describe programs running the verified build
+        let program_hash = get_program_hash(connection, program_id)?;
+        if program_hash != params_file.executable_hash {
+            return Err(anyhow!(
+                "On-chain hash of {} ({}) does not match the verified hash {}",
+                program_id,
+                program_hash,
+                params_file.executable_hash
+            ));
+        }
+        let deployed_slot = get_last_deployed_slot(connection, &program_id.to_string())
+            .await
+            .map_err(|err| anyhow!("Unable to get last deployed slot: {}", err))?;
+        println!(
+            "Program Id: {} (deployed at slot {})",
+            program_id, deployed_slot
+        );
+        params.push((
+            program_id,
+            InputParams {
+                deployed_slot,
+                ..params_file.input_params()
+            },
+        ));
+    }
+
+    upload_input_params_batch(
+        params,
+        connection,
+        skip_prompt,
+        path_to_keypair,
+        compute_unit_price,
+    )
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn build_and_verify_repo(
+    mount_path: PathBuf,
+    base_image: Option<String>,
+    bpf_flag: bool,
+    library_name: String,
+    connection: &RpcClient,
+    program_id: Pubkey,
+    cargo_args: Vec<String>,
+) -> anyhow::Result<(String, String, Option<BuildOutput>)> {
+    let (build_hash, build_output) = build_repo(
+        &mount_path,
+        base_image,
+        bpf_flag,
+        &library_name,
+        connection,
+        cargo_args,
+    )?;
+
+    // Get the hash of the deployed program
+    info!(
+        "Fetching on-chain program data for program ID: {}",
+        program_id,
+    );
+    let program_hash = get_program_hash(connection, program_id)?;
+
+    Ok((build_hash, program_hash, Some(build_output)))
+}
+
+/// Prints how the executable at `executable_path` differs from the deployed program, or from
+/// the program staged in `buffer`.
+fn explain_mismatch(
+    connection: &RpcClient,
+    program_id: Pubkey,
+    buffer: Option<Pubkey>,
+    executable_path: &Path,
+) -> anyhow::Result<()> {
+    let on_chain = match buffer {
+        Some(buffer) => fetch_buffer_data(connection, buffer)?,
+        None => fetch_program_data(connection, program_id)?,
+    };
+    let local = std::fs::read(executable_path)
+        .map_err(|e| anyhow!("Failed to read {}: {}", executable_path.display(), e))?;
+    print!("{}", MismatchReport::new(&local, &on_chain)?);
+    Ok(())
+}
+
+/// Builds the program and returns the hash of its executable.
+fn build_repo(
+    mount_path: &Path,
+    base_image: Option<String>,
+    bpf_flag: bool,
+    library_name: &str,
+    connection: &RpcClient,
+    cargo_args: Vec<String>,
+) -> anyhow::Result<(String, BuildOutput)> {
+    // Build the code using the docker container
+    let build_output = build(
+        Some(mount_path.to_path_buf()),
+        Some(library_name.to_string()),
+        base_image,
+        bpf_flag,
+        cargo_args,
+    )?;
+
+    // Get the hash of the build
+    let executable_path = find_executable(mount_path, library_name)?;
+    info!("Executable file found at path: {:?}", executable_path);
+    let build_hash = get_file_hash(&executable_path)?;
+
+    // Only informational, a matching build is still verified even if it can't run yet
+    if let Err(err) = print_cluster_features(connection, &std::fs::read(&executable_path)?) {
+        warn!("unable to check the cluster feature set: {}", err);
+    }
+
+    Ok((build_hash, build_output))
+}
+
+/// Path of the executable built for `library_name` in the mounted directory.
+fn find_executable(mount_path: &Path, library_name: &str) -> anyhow::Result<PathBuf> {
+    let executable_path = mount_path
+        .join("target")
+        .join("deploy")
+        .join(format!("{}.so", library_name));
+    if !executable_path.is_file() {
+        return Err(anyhow!(
+            "Failed to find program at {}",
+            executable_path.display()
+        ));
+    }
+    Ok(executable_path)
+}
+
+pub fn parse_output(output: Vec<u8>) -> anyhow::Result<String> {
+    let parsed_output = String::from_utf8(output)?
+        .strip_suffix("\n")
+        .ok_or_else(|| anyhow!("Failed to parse output"))?
+        .to_string();
+    Ok(parsed_output)
+}
+
+pub fn get_pkg_version_from_cargo_lock(
+    package_name: &str,
+    cargo_lock_file: impl AsRef<Path>,
+) -> anyhow::Result<(u32, u32, u32)> {
+    let lockfile = Lockfile::load(cargo_lock_file)?;
+    let res = lockfile
+        .packages
+        .iter()
+        .filter(|pkg| pkg.name.to_string() == *package_name)
+        .filter_map(|pkg| {
+            let version = pkg.version.clone().to_string();
+            let version_parts: Vec<&str> = version.split(".").collect();
+            if version_parts.len() == 3 {
+                let major = version_parts[0].parse::<u32>().unwrap_or(0);
+                let minor = version_parts[1].parse::<u32>().unwrap_or(0);
+                let patch = version_parts[2].parse::<u32>().unwrap_or(0);
+                return Some((major, minor, patch));
+            }
+            None
+        })
+        .next()
+        .ok_or_else(|| anyhow!("Failed to parse solana-program version from Cargo.lock"))?;
+    Ok(res)
+}
+
+pub fn get_lib_name_from_cargo_toml(cargo_toml_file: impl AsRef<Path>) -> anyhow::Result<String> {
+    let manifest = Manifest::from_path(cargo_toml_file)?;
+    let lib = manifest
+        .lib
+        .ok_or_else(|| anyhow!("Failed to parse lib from Cargo.toml"))?;
+    lib.name
+        .ok_or_else(|| anyhow!("Failed to parse lib name from Cargo.toml"))
+}
+
+pub fn get_pkg_name_from_cargo_toml(cargo_toml_file: &str) -> Option<String> {
+    let manifest = Manifest::from_path(cargo_toml_file).ok()?;
+    let pkg = manifest.package?;
+    Some(pkg.name)
+}
+
+pub fn print_build_params(pubkey: &Pubkey, build_params: &OtterBuildParams) {
+    println!("----------------------------------------------------------------");
+    println!("Address: {:?}", pubkey);
+    println!("----------------------------------------------------------------");
+    println!("{}", build_params);
+}
+
+/// Reports which verification PDAs of a program were confirmed by the remote verifier to
+/// build the currently deployed executable. Logged, so the hash stays the only output.
+pub async fn check_pda_coverage(
+    client: &RpcClient,
+    program_id: Pubkey,
+    program_hash: &str,
+) -> anyhow::Result<()> {
+    let pdas = get_all_pdas_available(client, &program_id).await?;
+    let statuses = fetch_remote_statuses(program_id, None, false).await?;
+    let mut covered = 0;
+    for (pda, build_params) in &pdas {
+        let coverage = PdaCoverage::classify(build_params, &statuses, program_hash);
+        if coverage == PdaCoverage::Covered {
+            covered += 1;
+        }
+        info!(
+            "{} (signer {}, commit {}): {}",
+            pda, build_params.signer, build_params.commit, coverage
+        );
+    }
+    info!(
+        "The current deployment is covered by {} of {} verification PDA(s)",
+        covered,
+        pdas.len()
+    );
+    Ok(())
+}
+
+/// Prints the PDAs of a program, only the ones uploaded by `signers` if any are given. The
+/// PDAs are listed by their headers first and fetched in full one batch at a time.
+pub async fn list_program_pdas(
+    program_id: Pubkey,
+    signers: &[Pubkey],
+    client: &RpcClient,
+) -> anyhow::Result<()> {
+    let pdas: Vec<Pubkey> = get_program_pda_headers(client, &program_id)?
+        .into_iter()
+        .filter(|header| signers.is_empty() || signers.contains(&header.signer))
+        .map(|header| header.pda)
+        .collect();
+    if pdas.is_empty() {
+        println!("No verification PDAs found for {}", program_id);
+        return Ok(());
+    }
+    let clock = ClusterClock::new(client);
+    for chunk in pdas.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        for (pda, build_params) in fetch_pdas(client, chunk)? {
+            print_build_params(&pda, &build_params);
+            // PDAs uploaded before deployed slots were recorded hold 0
+            if build_params.deployed_slot == 0 {
+                continue;
+            }
+            let verified = client
+                .get_signatures_for_address(&pda)
+                .ok()
+                .and_then(|signatures| signatures.first()?.block_time);
+            if let (Some(deployed), Some(verified)) =
+                (clock.slot_time(build_params.deployed_slot), verified)
+            {
+                println!("{}", describe_verification_delay(deployed, verified));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints every program `signer` uploaded a verification for, with whether it matches the
+/// current deployment.
+pub async fn list_uploader_pdas(signer: Pubkey, client: &RpcClient) -> anyhow::Result<()> {
+    let mut pdas = get_all_pdas_by_signer(client, &signer).await?;
+    if pdas.is_empty() {
+        println!("No verification PDAs uploaded by {}", signer);
+        return Ok(());
+    }
+    pdas.sort_by_key(|(_, build_params)| build_params.address.to_string());
+
+    let mut stale = 0;
+    for (pda, build_params) in &pdas {
+        let freshness = PdaFreshness::classify(
+            build_params,
+            get_program_data_slot(client, build_params.address)?,
+        );
+        if freshness != PdaFreshness::Fresh {
+            stale += 1;
+        }
+        println!("----------------------------------------------------------------");
+        println!("Program: {}", build_params.address);
+        println!("PDA: {}", pda);
+        println!(
+            "Repository: {} at {}",
+            build_params.git_url, build_params.commit
+        );
+        println!("Deployed slot: {}", build_params.deployed_slot);
+        println!("Status: {}", freshness);
+    }
+    println!("----------------------------------------------------------------");
+    println!(
+        "{} program(s) verified by {}, {} not current",
+        pdas.len(),
+        signer,
+        stale
+    );
+    Ok(())
+}
+
+pub async fn print_program_pda(
+    program_id: Pubkey,
+    signer: Option<String>,
+    client: &RpcClient,
+) -> anyhow::Result<()> {
+    let (pda, build_params) = get_program_pda(client, &program_id, signer).await?;
+    print_build_params(&pda, &build_params);
+    Ok(())
+}
+
+pub async fn print_trusted_verification(
+    program_id: Pubkey,
+    client: &RpcClient,
+) -> anyhow::Result<()> {
+    match resolve_trusted_verification(client, &program_id).await? {
+        Some(verdict) => {
+            println!("----------------------------------------------------------------");
+            println!("{}", verdict);
+            Ok(())
+        }
+        None => Err(anyhow!(
+            "No verification has been uploaded for program {}",
+            program_id
+        )),
+    }
+}
+
+pub async fn get_commit_hash(commit_hash: Option<&str>, repo_url: &str) -> anyhow::Result<String> {
+    let remote = GitRemote::parse(repo_url)?;
+
+    let commit_hash = match commit_hash {
+        Some(commit_hash) => {
+            // Catch typos before spending time on a clone and build
+            if remote.commit_exists(commit_hash).await? == Some(false) {
+                return Err(anyhow!(
+                    "Commit {} does not exist in {}",
+                    commit_hash,
+                    repo_url
+                ));
+            }
+            commit_hash.to_string()
+        }
+        None => get_commit_hash_from_remote(repo_url).map_err(|e| {
+            anyhow!(
+                "Commit hash must be provided or inferred from the remote repository: {}",
+                e
+            )
+        })?,
+    };
+
+    info!("Commit hash from remote: {}", commit_hash);
+    Ok(commit_hash)
+}
+
+/// Commit checked out in an existing repository, which must be clean and, if a commit is
+/// requested, at that commit.
+pub fn get_checkout_commit_hash(
+    checkout: &Path,
+    requested: Option<&str>,
+) -> anyhow::Result<String> {
+    let git = |args: &[&str]| -> anyhow::Result<String> {
+        let output = std::process::Command::new("git")
+            .arg("-C")
+            .arg(checkout)
+            .args(args)
+            .output()
+            .map_err(|e| anyhow!("Failed to run git: {}", e))?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "git {} failed in {}: {}",
+                args.join(" "),
+                checkout.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(String::from_utf8(output.stdout)?.trim().to_string())
+    };
+
+    let head = git(&["rev-parse", "HEAD"])?;
+    if let Some(requested) = requested {
+        let requested_commit = git(&["rev-parse", &format!("{}^{{commit}}", requested)])?;
+        if requested_commit != head {
+            return Err(anyhow!(
+                "{} is at commit {}, not {}",
+                checkout.display(),
+                head,
+                requested
+            ));
+        }
+    }
+    // Untracked files that git does not ignore can change the build as much as edits
+    if !git(&["status", "--porcelain", "--untracked-files=all"])?.is_empty() {
+        return Err(anyhow!(
+            "{} has uncommitted changes or untracked files, the build would not match commit {}",
+            checkout.display(),
+            head
+        ));
+    }
+
+    info!("Commit hash from checkout: {}", head);
+    Ok(head)
+}
+
+/// Verification params of the repository at `commit_hash` for the current deployment of
+/// `program_id`, resolved from a clone without building it.
+#[allow(clippy::too_many_arguments)]
+pub async fn resolve_upload_params(
+    connection: &RpcClient,
+    program_id: Pubkey,
+    repo_url: String,
+    commit_hash: String,
+    mount_path: String,
+    library_name: Option<String>,
+    base_image: Option<String>,
+    bpf_flag: bool,
+    cargo_args: Vec<String>,
+) -> anyhow::Result<InputParams> {
+    let last_deployed_slot = get_last_deployed_slot(connection, &program_id.to_string())
+        .await
+        .map_err(|err| anyhow!("Unable to get last deployed slot: {}", err))?;
+
+    let (temp_root_path, verify_dir) = clone_repo_and_checkout(
+        &repo_url,
+        true,
+        &get_basename(&repo_url)?,
+        Some(commit_hash.clone()),
+    )?;
+
+    let input_params = InputParams {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_url: repo_url,
+        commit: commit_hash.clone(),
+        args: build_args(
+            &mount_path,
+            library_name.clone(),
+            &temp_root_path,
+            base_image.clone(),
+            bpf_flag,
+            cargo_args,
+        )?
+        .0,
+        deployed_slot: last_deployed_slot,
+    };
+
+    remove_dir(&verify_dir)?;
+
+    Ok(input_params)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn export_pda_tx(
+    connection: &RpcClient,
+    program_id: Pubkey,
+    uploader: Pubkey,
+    repo_url: String,
+    commit_hash: String,
+    mount_path: String,
+    library_name: Option<String>,
+    base_image: Option<String>,
+    bpf_flag: bool,
+    encoding: TxExportFormat,
+    blockhash: BlockhashSource,
+    cargo_args: Vec<String>,
+    compute_unit_price: u64,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let input_params = resolve_upload_params(
+        connection,
+        program_id,
+        repo_url,
+        commit_hash,
+        mount_path,
+        library_name,
+        base_image,
+        bpf_flag,
+        cargo_args,
+    )
+    .await?;
+
+    let (pda, _) = find_build_params_pda(&program_id, &uploader);
+
+    // check if account already exists
+    let instruction = match connection.get_account(&pda) {
+        Ok(account_info) => {
+            if !account_info.data.is_empty() {
+                info!("PDA already exists, creating update transaction");
+                OtterVerifyInstructions::Update
+            } else {
+                info!("PDA does not exist, creating initialize transaction");
+                OtterVerifyInstructions::Initialize
+            }
+        }
+        Err(_) => OtterVerifyInstructions::Initialize,
+    };
+
+    let instructions = with_compute_budget(
+        connection,
+        compose_instructions(
+            &input_params,
+            uploader,
+            pda,
+            program_id,
+            instruction,
+            compute_unit_price,
+        )?,
+        &uploader,
+    );
+
+    if dry_run {
+        simulate_upload(
+            connection,
+            &instructions,
+            &uploader,
+            program_id,
+            pda,
+            instruction,
+        )?
+        .print()?;
+        return Ok(());
+    }
+
+    println!(
+        "{}",
+        export_transaction(
+            &instructions,
+            &uploader,
+            encoding,
+            &fetch_blockhash(connection, blockhash)?
+        )?
+    );
+
+    Ok(())
+}
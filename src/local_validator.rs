@@ -0,0 +1,333 @@
+use anyhow::anyhow;
+use log::info;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{write_keypair_file, Keypair},
+    signer::Signer,
+};
+use std::{
+    path::Path,
+    process::{Child, Command, Stdio},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use crate::cleanup::{remove_dir, track_process, untrack_process};
+use crate::deploy::solana_program_command;
+use crate::humanize::format_duration;
+use crate::logging::command_output;
+use crate::rpc_usage::new_rpc_client;
+use crate::solana_program::{
+    get_program_pda, upload_program_verification_data, OtterBuildParams, OTTER_VERIFY_PROGRAM_ID,
+};
+use crate::{
+    build, build_args, clone_repo_and_checkout, fetch_program_hash, find_executable, get_basename,
+    get_file_hash,
+};
+
+pub const LOCAL_VALIDATOR_URL: &str = "http://127.0.0.1:8899";
+
+/// Cluster the otter-verify program is cloned from
+const MAINNET_URL: &str = "https://api.mainnet-beta.solana.com";
+
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// A solana-test-validator, started for the run unless one was already listening. A started
+/// validator is stopped when dropped.
+pub struct LocalValidator {
+    pub client: RpcClient,
+    process: Option<Child>,
+}
+
+impl LocalValidator {
+    /// Attaches to the validator on the default RPC port, or starts one with a fresh ledger
+    /// in `ledger_dir` and the otter-verify program cloned from mainnet.
+    pub fn start_or_attach(ledger_dir: &Path) -> anyhow::Result<LocalValidator> {
+        let client = new_rpc_client(LOCAL_VALIDATOR_URL, CommitmentConfig::confirmed());
+        if client.get_health().is_ok() {
+            info!("Attaching to the validator at {}", LOCAL_VALIDATOR_URL);
+            if client.get_account(&OTTER_VERIFY_PROGRAM_ID).is_err() {
+                return Err(anyhow!(
+                    "The running validator has no otter-verify program, restart it with --clone-upgradeable-program {} --url {}",
+                    OTTER_VERIFY_PROGRAM_ID,
+                    MAINNET_URL
+                ));
+            }
+            return Ok(LocalValidator {
+                client,
+                process: None,
+            });
+        }
+
+        info!("Starting solana-test-validator in {}", ledger_dir.display());
+        let process = validator_command(ledger_dir)
+            .stdout(Stdio::null())
+            .stderr(command_output())
+            .spawn()
+            .map_err(|e| {
+                anyhow!(
+                    "Failed to start solana-test-validator, is it installed? {}",
+                    e
+                )
+            })?;
+        track_process(process.id());
+        let mut validator = LocalValidator {
+            client,
+            process: Some(process),
+        };
+
+        let started = Instant::now();
+        while validator.client.get_health().is_err() {
+            if let Some(status) = validator.process.as_mut().and_then(|p| p.try_wait().ok()?) {
+                return Err(anyhow!("solana-test-validator exited with {}", status));
+            }
+            if started.elapsed() > STARTUP_TIMEOUT {
+                return Err(anyhow!(
+                    "solana-test-validator did not become healthy within {}",
+                    format_duration(STARTUP_TIMEOUT)
+                ));
+            }
+            std::thread::sleep(Duration::from_secs(1));
+        }
+        info!("Validator ready at {}", LOCAL_VALIDATOR_URL);
+        Ok(validator)
+    }
+
+    /// Creates a keypair funded by airdrop and saves it to `path` for the solana CLI.
+    pub fn funded_keypair(&self, path: &Path, sol: u64) -> anyhow::Result<Keypair> {
+        let keypair = Keypair::new();
+        write_keypair_file(&keypair, path)
+            .map_err(|e| anyhow!("Failed to write keypair {}: {}", path.display(), e))?;
+        let signature = self
+            .client
+            .request_airdrop(&keypair.pubkey(), sol * LAMPORTS_PER_SOL)?;
+        self.client.poll_for_signature(&signature)?;
+        Ok(keypair)
+    }
+}
+
+impl Drop for LocalValidator {
+    fn drop(&mut self) {
+        if let Some(process) = self.process.as_mut() {
+            let _ = process.kill();
+            let _ = process.wait();
+            untrack_process(process.id());
+        }
+    }
+}
+
+/// Command starting a validator with a fresh ledger in `ledger_dir` and the otter-verify
+/// program cloned from mainnet.
+fn validator_command(ledger_dir: &Path) -> Command {
+    let mut command = Command::new("solana-test-validator");
+    command
+        .arg("--reset")
+        .arg("--quiet")
+        .arg("--ledger")
+        .arg(ledger_dir)
+        .args(["--url", MAINNET_URL])
+        .args([
+            "--clone-upgradeable-program",
+            &OTTER_VERIFY_PROGRAM_ID.to_string(),
+        ]);
+    command
+}
+
+/// Fields of a PDA read back that differ from the uploaded params.
+fn pda_mismatches(
+    build_params: &OtterBuildParams,
+    repo_url: &str,
+    commit_hash: &Option<String>,
+    args: &[String],
+) -> Vec<&'static str> {
+    [
+        ("git_url", build_params.git_url == repo_url),
+        (
+            "commit",
+            build_params.commit == commit_hash.clone().unwrap_or_default(),
+        ),
+        ("args", build_params.args == args),
+    ]
+    .into_iter()
+    .filter(|(_, matches)| !matches)
+    .map(|(field, _)| field)
+    .collect()
+}
+
+/// Builds a repository, deploys the build to a local validator and uploads its verification
+/// PDA there, then checks that the deployed program and the PDA read back match: the whole
+/// verify-and-upload path without touching mainnet.
+#[allow(clippy::too_many_arguments)]
+pub async fn verify_on_local_validator(
+    repo_url: String,
+    commit_hash: Option<String>,
+    relative_mount_path: String,
+    library_name: Option<String>,
+    base_image: Option<String>,
+    bpf_flag: bool,
+    cargo_args: Vec<String>,
+) -> anyhow::Result<()> {
+    let (repo_path, verify_dir) = clone_repo_and_checkout(
+        &repo_url,
+        false,
+        &get_basename(&repo_url)?,
+        commit_hash.clone(),
+    )?;
+    let result = async {
+        let (args, mount_path, library_name) = build_args(
+            &relative_mount_path,
+            library_name,
+            &repo_path,
+            base_image.clone(),
+            bpf_flag,
+            cargo_args.clone(),
+        )?;
+        build(
+            Some(mount_path.clone()),
+            Some(library_name.clone()),
+            base_image,
+            bpf_flag,
+            cargo_args,
+        )?;
+        let executable_path = find_executable(&mount_path, &library_name)?;
+        let build_hash = get_file_hash(&executable_path)?;
+        println!("Executable Program Hash from repo: {}", build_hash);
+
+        let validator = LocalValidator::start_or_attach(&verify_dir.join("test-ledger"))?;
+        let payer_path = verify_dir.join("payer.json");
+        let payer = validator.funded_keypair(&payer_path, 100)?;
+        let program_keypair_path = verify_dir.join("program.json");
+        write_keypair_file(&Keypair::new(), &program_keypair_path)
+            .map_err(|e| anyhow!("Failed to write program keypair: {}", e))?;
+
+        let path_arg = |path: &Path| path.display().to_string();
+        let program_id = Pubkey::from_str(&solana_program_command(
+            &[
+                "deploy",
+                &path_arg(&executable_path),
+                "--program-id",
+                &path_arg(&program_keypair_path),
+                "--keypair",
+                &path_arg(&payer_path),
+            ],
+            LOCAL_VALIDATOR_URL,
+            "programId",
+        )?)?;
+        info!("Deployed to {}", program_id);
+
+        let program_hash = fetch_program_hash(&validator.client, program_id)?;
+        println!("On-chain Program Hash: {}", program_hash);
+        if program_hash != build_hash {
+            return Err(anyhow!(
+                "Deployed program hash {} differs from the build {}",
+                program_hash,
+                build_hash
+            ));
+        }
+
+        upload_program_verification_data(
+            repo_url.clone(),
+            &commit_hash,
+            args.clone(),
+            program_id,
+            &validator.client,
+            true,
+            Some(path_arg(&payer_path)),
+            0,
+            None,
+        )
+        .await?;
+
+        let (pda, build_params) = get_program_pda(
+            &validator.client,
+            &program_id,
+            Some(payer.pubkey().to_string()),
+        )
+        .await?;
+        let mismatches = pda_mismatches(&build_params, &repo_url, &commit_hash, &args);
+        if !mismatches.is_empty() {
+            return Err(anyhow!(
+                "PDA {} read back with different {}",
+                pda,
+                mismatches.join(", ")
+            ));
+        }
+        info!("PDA {} round-tripped through the local validator ✅", pda);
+        Ok(())
+    }
+    .await;
+
+    remove_dir(&verify_dir)?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana_program::InputParams;
+    use borsh::{BorshDeserialize, BorshSerialize};
+
+    #[test]
+    fn test_validator_command() {
+        let command = validator_command(Path::new("/tmp/verify/test-ledger"));
+        assert_eq!(command.get_program(), "solana-test-validator");
+        let args = command
+            .get_args()
+            .map(|arg| arg.to_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            args,
+            [
+                "--reset",
+                "--quiet",
+                "--ledger",
+                "/tmp/verify/test-ledger",
+                "--url",
+                MAINNET_URL,
+                "--clone-upgradeable-program",
+                &OTTER_VERIFY_PROGRAM_ID.to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pda_round_trip() {
+        let repo_url = "https://github.com/Ellipsis-Labs/phoenix-v1";
+        let commit_hash = Some("c9e8a4b".to_string());
+        let args = vec!["--library-name".to_string(), "phoenix".to_string()];
+        let input_params = InputParams {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git_url: repo_url.to_string(),
+            commit: commit_hash.clone().unwrap(),
+            args: args.clone(),
+            deployed_slot: 42,
+        };
+
+        // Account layout: discriminator, program, signer, the uploaded params and the bump
+        let mut data = vec![0; 8];
+        Pubkey::new_unique().serialize(&mut data).unwrap();
+        Pubkey::new_unique().serialize(&mut data).unwrap();
+        input_params.serialize(&mut data).unwrap();
+        data.push(255);
+        let build_params = OtterBuildParams::try_from_slice(&data[8..]).unwrap();
+        assert_eq!(build_params.deployed_slot, 42);
+        assert!(pda_mismatches(&build_params, repo_url, &commit_hash, &args).is_empty());
+
+        assert_eq!(
+            pda_mismatches(&build_params, repo_url, &None, &args[..1]),
+            ["commit", "args"]
+        );
+        assert_eq!(
+            pda_mismatches(
+                &build_params,
+                "https://github.com/Ellipsis-Labs/phoenix",
+                &commit_hash,
+                &args
+            ),
+            ["git_url"]
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use anyhow::anyhow;
+use cargo_lock::{Lockfile, ResolveVersion};
+use log::info;
+use std::path::Path;
+
+use crate::{
+    get_pkg_version_from_cargo_lock,
+    image_fallback::{select_image, SolanaVersion},
+};
+
+/// First Solana release whose build image ships a cargo that reads version 4 lockfiles
+pub const LOCKFILE_V4_SOLANA_VERSION: SolanaVersion = (2, 1, 0);
+
+/// Solana version of the image used for `--bpf` builds without a base image
+const BPF_IMAGE_SOLANA_VERSION: SolanaVersion = (1, 13, 5);
+
+/// Rewrites `lockfile` in the version 3 format, failing if the rewritten file would resolve a
+/// different dependency graph.
+pub fn downgrade_lockfile(lockfile: &Path) -> anyhow::Result<()> {
+    let original = Lockfile::load(lockfile)
+        .map_err(|e| anyhow!("Failed to parse {}: {}", lockfile.display(), e))?;
+    let downgraded = downgrade(&original)?;
+    std::fs::write(lockfile, downgraded)
+        .map_err(|e| anyhow!("Failed to write {}: {}", lockfile.display(), e))
+}
+
+fn downgrade(original: &Lockfile) -> anyhow::Result<String> {
+    let mut lockfile = original.clone();
+    lockfile.version = ResolveVersion::V3;
+    let downgraded = lockfile.to_string();
+
+    let reparsed: Lockfile = downgraded
+        .parse()
+        .map_err(|e| anyhow!("The version 3 lockfile does not parse: {}", e))?;
+    if reparsed.packages != original.packages
+        || reparsed.root != original.root
+        || reparsed.metadata != original.metadata
+        || reparsed.patch != original.patch
+    {
+        return Err(anyhow!(
+            "Downgrading Cargo.lock to version 3 changes its dependency graph"
+        ));
+    }
+    Ok(downgraded)
+}
+
+/// Checks that the cargo of the image building `mount_path` reads its Cargo.lock. A version 4
+/// lockfile built with an older image is downgraded to version 3 if `downgrade` is set, and
+/// is an error otherwise. Images given with `--base-image` are trusted to read it. Returns
+/// whether the lockfile was downgraded.
+pub fn ensure_lockfile_readable(
+    mount_path: &Path,
+    base_image: Option<&str>,
+    bpf_flag: bool,
+    downgrade: bool,
+) -> anyhow::Result<bool> {
+    let lockfile = mount_path.join("Cargo.lock");
+    if base_image.is_some() || !lockfile.exists() {
+        return Ok(false);
+    }
+    let version = Lockfile::load(&lockfile)
+        .map_err(|e| anyhow!("Failed to parse {}: {}", lockfile.display(), e))?
+        .version;
+    if version < ResolveVersion::V4 {
+        return Ok(false);
+    }
+    let image_version = if bpf_flag {
+        BPF_IMAGE_SOLANA_VERSION
+    } else {
+        select_image(get_pkg_version_from_cargo_lock(
+            "solana-program",
+            &lockfile,
+        )?)?
+        .0
+    };
+    if image_version >= LOCKFILE_V4_SOLANA_VERSION {
+        return Ok(false);
+    }
+
+    let (major, minor, patch) = image_version;
+    if !downgrade {
+        return Err(anyhow!(
+            "Cargo.lock is version 4 but the cargo of the image for Solana {}.{}.{} only reads version 3. Rerun with --downgrade-lockfile to build with an equivalent version 3 lockfile, or set `version = 3` at the top of Cargo.lock and commit it",
+            major,
+            minor,
+            patch
+        ));
+    }
+    downgrade_lockfile(&lockfile)?;
+    info!(
+        "Downgraded Cargo.lock to version 3 for the image for Solana {}.{}.{}, the dependency graph is unchanged",
+        major, minor, patch
+    );
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCKFILE_V4: &str = r#"# This file is automatically @generated by Cargo.
+# It is not intended for manual editing.
+version = 4
+
+[[package]]
+name = "hello_world"
+version = "0.1.0"
+dependencies = [
+ "solana-program",
+ "spl-token",
+]
+
+[[package]]
+name = "solana-program"
+version = "1.18.23"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "1fd0fd5e41e1bf7d3bc6ad46e3bfb1ef0b8ec2d3b0f8ab03b7a61ad1fe4f2e38"
+
+[[package]]
+name = "spl-token"
+version = "4.0.0"
+source = "git+https://github.com/solana-labs/solana-program-library?branch=release%2Fv1#2a5a9cd8b04fb6dc50f3b4dd5e0e1d1d6b4d0fb9"
+dependencies = [
+ "solana-program",
+]
+"#;
+
+    #[test]
+    fn test_downgrade_lockfile() {
+        let original: Lockfile = LOCKFILE_V4.parse().unwrap();
+        assert_eq!(original.version, ResolveVersion::V4);
+
+        let downgraded = downgrade(&original).unwrap();
+        assert!(downgraded.contains("version = 3"));
+        let reparsed: Lockfile = downgraded.parse().unwrap();
+        assert_eq!(reparsed.version, ResolveVersion::V3);
+        assert_eq!(reparsed.packages, original.packages);
+    }
+
+    #[test]
+    fn test_ensure_lockfile_readable() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let lockfile = dir.join("Cargo.lock");
+        std::fs::write(&lockfile, LOCKFILE_V4).unwrap();
+
+        // The image for solana-program 1.18.23 predates version 4 lockfiles
+        assert!(ensure_lockfile_readable(dir, None, false, false).is_err());
+        assert!(ensure_lockfile_readable(dir, Some("custom"), false, false).is_ok());
+        assert!(ensure_lockfile_readable(dir, None, false, true).unwrap());
+        assert_eq!(
+            Lockfile::load(&lockfile).unwrap().version,
+            ResolveVersion::V3
+        );
+        assert!(ensure_lockfile_readable(dir, None, false, false).is_ok());
+    }
+}
@@ -0,0 +1,119 @@
+use log::{Level, LevelFilter};
+use std::{io::Write, process::Stdio, sync::OnceLock};
+
+use crate::secrets::redact;
+
+/// Verbosity of the run, set once from `-v` and `--quiet`
+static VERBOSITY: OnceLock<Verbosity> = OnceLock::new();
+
+/// How much a run prints besides its results. Results go to stdout, everything else is logged
+/// to stderr, so that the output of a command can be piped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Results and errors only, without the output of docker and git
+    Quiet,
+    /// Progress and warnings
+    Normal,
+    /// The details of each step, with `-v`
+    Verbose,
+    /// Everything, including the logs of the Solana client, with `-vv`
+    Trace,
+}
+
+impl Verbosity {
+    pub fn from_flags(verbose: u64, quiet: bool) -> Verbosity {
+        match (quiet, verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Verbose,
+            _ => Verbosity::Trace,
+        }
+    }
+
+    fn level(&self) -> LevelFilter {
+        match self {
+            Verbosity::Quiet => LevelFilter::Error,
+            Verbosity::Normal => LevelFilter::Info,
+            Verbosity::Verbose => LevelFilter::Debug,
+            Verbosity::Trace => LevelFilter::Trace,
+        }
+    }
+
+    /// Level of the logs of dependencies, which are too noisy below `-vv`
+    fn dependency_level(&self) -> LevelFilter {
+        match self {
+            Verbosity::Quiet => LevelFilter::Error,
+            Verbosity::Normal | Verbosity::Verbose => LevelFilter::Warn,
+            Verbosity::Trace => LevelFilter::Debug,
+        }
+    }
+}
+
+fn format_line(level: Level, message: &str) -> String {
+    match level {
+        Level::Info => message.to_string(),
+        Level::Warn => format!("Warning: {}", message),
+        Level::Error => format!("Error: {}", message),
+        Level::Debug | Level::Trace => format!("[{}] {}", level.as_str().to_lowercase(), message),
+    }
+}
+
+/// Logs to stderr at `verbosity`, with credentials redacted. `RUST_LOG` overrides the levels.
+pub fn init_logging(verbosity: Verbosity) {
+    let _ = VERBOSITY.set(verbosity);
+    let _ = env_logger::Builder::new()
+        .filter_level(verbosity.dependency_level())
+        .filter_module("solana_verify", verbosity.level())
+        .parse_default_env()
+        .format(|buf, record| {
+            writeln!(
+                buf,
+                "{}",
+                format_line(record.level(), &redact(&record.args().to_string()))
+            )
+        })
+        .try_init();
+}
+
+pub fn get_verbosity() -> Verbosity {
+    VERBOSITY.get().copied().unwrap_or(Verbosity::Normal)
+}
+
+pub fn is_quiet() -> bool {
+    get_verbosity() == Verbosity::Quiet
+}
+
+/// Where the output of docker and git goes: stderr, unless the run is quiet. Stdout is kept
+/// for the results of the command.
+pub fn command_output() -> Stdio {
+    if is_quiet() {
+        Stdio::null()
+    } else {
+        Stdio::from(std::io::stderr())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verbosity() {
+        assert_eq!(Verbosity::from_flags(0, true), Verbosity::Quiet);
+        assert_eq!(Verbosity::from_flags(0, false), Verbosity::Normal);
+        assert_eq!(Verbosity::from_flags(1, false), Verbosity::Verbose);
+        assert_eq!(Verbosity::from_flags(3, false), Verbosity::Trace);
+        assert_eq!(Verbosity::Quiet.level(), LevelFilter::Error);
+        assert_eq!(Verbosity::Verbose.dependency_level(), LevelFilter::Warn);
+
+        assert_eq!(format_line(Level::Info, "Cloning"), "Cloning");
+        assert_eq!(
+            format_line(Level::Warn, "no sccache"),
+            "Warning: no sccache"
+        );
+        assert_eq!(
+            format_line(Level::Debug, "Workdir: /build"),
+            "[debug] Workdir: /build"
+        );
+    }
+}
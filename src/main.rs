@@ -1,129 +1,262 @@
 use anyhow::anyhow;
-use api::{
-    get_last_deployed_slot, get_remote_job, get_remote_status, send_job_with_uploader_to_remote,
-};
-use base64::{prelude::BASE64_STANDARD, Engine};
-use bincode::serialize;
-use cargo_lock::Lockfile;
-use cargo_toml::Manifest;
-use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use clap::{App, AppSettings, Arg, ArgGroup, ArgMatches, SubCommand};
+use log::{debug, info, warn};
 use signal_hook::{
     consts::{SIGINT, SIGTERM},
     iterator::Signals,
 };
-use solana_cli_config::{Config, CONFIG_FILE};
-use solana_client::rpc_client::RpcClient;
-use solana_program::get_address_from_keypair_or_config;
 use solana_sdk::{
-    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    commitment_config::{CommitmentConfig, CommitmentLevel},
     pubkey::Pubkey,
 };
 use solana_transaction_status::UiTransactionEncoding;
+use solana_verify::api::{
+    get_api_status, get_remote_job, get_remote_status, send_job_with_uploader_to_remote,
+};
+use solana_verify::attestation::Attestation;
+use solana_verify::authority_tx::wait_for_authority_tx;
+use solana_verify::badge::generate_badge;
+use solana_verify::buffer::compare_buffer;
+use solana_verify::build_cache::set_build_cache;
+use solana_verify::cleanup::{cleanup_all, interrupt, interrupted};
+use solana_verify::cluster_features::print_cluster_features;
+use solana_verify::compare_repos::{compare_repos, print_comparison};
+use solana_verify::daemon::run_daemon;
+use solana_verify::delegation::{
+    countersign, partial_sign, PartiallySignedUpload, DEFAULT_PARTIAL_UPLOAD,
+};
+use solana_verify::deploy::deploy_verified;
+use solana_verify::docker::{
+    print_cache_stats, prune_cache, set_container_runtime, set_docker_context,
+    set_required_docker_version, ContainerRuntime,
+};
+use solana_verify::encryption::{set_encryption_recipients, Recipient};
+use solana_verify::endpoints::{
+    endpoints, get_cli_config_url, set_endpoints, EndpointConfig, EndpointsFile,
+};
+use solana_verify::example::new_example;
+use solana_verify::find_commit::{compare_commits, find_commit};
+use solana_verify::git_backend::{set_git_backend, GitBackend};
+use solana_verify::github_release::publish_release;
+use solana_verify::hash::get_file_hash;
+use solana_verify::history::{collect_history, write_history, HistoryFormat};
+use solana_verify::idl::verify_idl;
+use solana_verify::image_fallback::{set_image_fallback, ImageFallback};
+use solana_verify::inspect::inspect;
+use solana_verify::local_validator::verify_on_local_validator;
+use solana_verify::logging::{init_logging, Verbosity};
+use solana_verify::oci_artifact::{fetch_artifact, push_artifact};
+use solana_verify::profiles::{add_profile, list_profiles, remove_profile, Profile, ProfileStore};
+use solana_verify::record_in_repo::{record_in_repo, VERIFIED_BUILDS_FILE};
+use solana_verify::rpc_fixtures::{record_fixtures, replay_fixtures};
+use solana_verify::rpc_headers::{set_rpc_headers, RpcHeader};
+use solana_verify::rpc_usage::{print_rpc_usage, set_rpc_budget};
+use solana_verify::secrets::{
+    clear_secrets, redact, redact_rpc_url, register_env_secrets, register_rpc_header,
+    register_rpc_url, register_secret, register_url,
+};
+use solana_verify::serve::{run_service, DEFAULT_SERVE_LISTEN};
+use solana_verify::source_archive::{verify_from_archive, ArchiveSource};
+use solana_verify::support_bundle::{set_support_bundle_path, write_support_bundle};
+use solana_verify::tx_export::{refresh_transaction, BlockhashSource, TxExportFormat};
+use solana_verify::upload_only::{upload_only, UploadSource};
+use solana_verify::verify_all::{
+    self, apply_project_config, verify_all, ManifestProgram, VerifyManifest, DEFAULT_MANIFEST,
+};
+use solana_verify::verify_suite::{
+    print_suite_summary, verify_suite, write_suite_report, SuiteManifest, DEFAULT_SUITE_MANIFEST,
+};
+use solana_verify::{
+    build, build_from_copy, check_pda_coverage, export_pda_tx, get_account_executable_hash,
+    get_buffer_hash, get_checkout_commit_hash, get_client, get_commit_hash, get_program_hash,
+    list_program_pdas, list_uploader_pdas, print_program_data_info, print_program_pda,
+    print_trusted_verification, resolve_upload_params, signers, upload_params_file,
+    verify_from_image, verify_from_repo, VerifyOptions, VerifyPhases, MAX_STALE_REBUILDS,
+};
 use std::{
-    io::Read,
-    path::PathBuf,
-    process::{Command, Stdio},
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
-    },
+    path::{Path, PathBuf},
+    str::FromStr,
 };
-use uuid::Uuid;
-pub mod api;
-#[rustfmt::skip]
-pub mod image_config;
-pub mod solana_program;
-use image_config::IMAGE_MAP;
 
 #[cfg(test)]
 mod test;
 
-use crate::solana_program::{
-    compose_transaction, find_build_params_pda, get_all_pdas_available, get_program_pda,
-    process_close, resolve_rpc_url, upload_program_verification_data, InputParams,
-    OtterBuildParams, OtterVerifyInstructions,
+use solana_verify::solana_program::{
+    get_signer_from_path_or_config, process_close, process_revoke, set_auto_compute_unit_price,
+    set_compute_unit_limit, set_prompt_answer, set_replace_policy, ReplacePolicy,
+    AUTO_COMPUTE_UNIT_PRICE,
 };
 
-const MAINNET_GENESIS_HASH: &str = "5eykt4UsFv8P8NJdTREpY1vzqKqZKvdpKuc147dw2N9d";
-
-pub fn get_network(network_str: &str) -> &str {
-    match network_str {
-        "devnet" | "dev" | "d" => "https://api.devnet.solana.com",
-        "mainnet" | "main" | "m" | "mainnet-beta" => "https://api.mainnet-beta.solana.com",
-        "localnet" | "localhost" | "l" | "local" => "http://localhost:8899",
-        _ => network_str,
-    }
-}
-
-// At the top level, make the signal handler accessible throughout the program
-lazy_static::lazy_static! {
-    static ref SIGNAL_RECEIVED: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
-}
-
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // Handle SIGTERM and SIGINT gracefully by stopping the docker container
     let mut signals = Signals::new([SIGTERM, SIGINT])?;
-    let mut container_id: Option<String> = None;
-    let mut temp_dir: Option<String> = None;
 
     let handle = signals.handle();
     std::thread::spawn(move || {
-        if signals.forever().next().is_some() {
-            SIGNAL_RECEIVED.store(true, Ordering::Relaxed);
+        let mut signals = signals.forever();
+        if signals.next().is_some() {
+            interrupt();
         }
-    });
-
-    // Add a function to check if we should abort
-    let check_signal = |container_id: &mut Option<String>, temp_dir: &mut Option<String>| {
-        if SIGNAL_RECEIVED.load(Ordering::Relaxed) {
-            println!("\nReceived interrupt signal, cleaning up...");
-
-            if let Some(container_id) = container_id.take() {
-                if std::process::Command::new("docker")
-                    .args(["kill", &container_id])
-                    .output()
-                    .is_err()
-                {
-                    println!("Failed to close docker container");
-                } else {
-                    println!("Stopped container {}", container_id)
-                }
-            }
-
-            if let Some(temp_dir) = temp_dir.take() {
-                if std::process::Command::new("rm")
-                    .args(["-rf", &temp_dir])
-                    .output()
-                    .is_err()
-                {
-                    println!("Failed to remove temporary directory");
-                } else {
-                    println!("Removed temporary directory {}", temp_dir);
-                }
-            }
-
+        // A second interrupt cleans up right away, without waiting for the current step
+        if signals.next().is_some() {
+            warn!("Received a second interrupt signal, cleaning up...");
+            cleanup_all();
             std::process::exit(130);
         }
-    };
+    });
 
     let matches = App::new("solana-verify")
         .author("Ellipsis Labs <maintainers@ellipsislabs.xyz>")
         .version(env!("CARGO_PKG_VERSION"))
         .about("A CLI tool for building verifiable Solana programs")
         .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(Arg::with_name("verbose")
+            .short("v")
+            .long("verbose")
+            .global(true)
+            .multiple(true)
+            .help("Log the details of each step to stderr, or everything including the Solana client with -vv. RUST_LOG overrides the levels"))
+        .arg(Arg::with_name("quiet")
+            .short("q")
+            .long("quiet")
+            .global(true)
+            .conflicts_with("verbose")
+            .help("Only print results and errors, without progress or the output of docker and git"))
         .arg(Arg::with_name("url")
             .short("u")
             .long("url")
             .global(true)
             .takes_value(true)
-            .help("Optionally include your RPC endpoint. Defaults to Solana CLI config file"))
+            .help("Optionally include your RPC endpoint, or mainnet, devnet, testnet or localnet. Defaults to the [endpoints] of solana-verify.toml, then to the Solana CLI config file"))
+        .arg(Arg::with_name("remote-api-url")
+            .long("remote-api-url")
+            .global(true)
+            .takes_value(true)
+            .help("URL of the remote verification API. Defaults to the [endpoints] of solana-verify.toml, then to https://verify.osec.io"))
         .arg(Arg::with_name("compute-unit-price")
             .long("compute-unit-price")
             .global(true)
             .takes_value(true)
             .default_value("100000")
-            .help("Priority fee in micro-lamports per compute unit"))
+            .help("Priority fee in micro-lamports per compute unit, or `auto` to pay a percentile of the priority fees recently paid for the otter-verify program and the accounts each transaction writes, and at least 10000"))
+        .arg(Arg::with_name("compute-unit-price-percentile")
+            .long("compute-unit-price-percentile")
+            .global(true)
+            .takes_value(true)
+            .default_value("75")
+            .help("Percentile of recent prioritization fees paid with --compute-unit-price auto, from 1 to 100"))
+        .arg(Arg::with_name("compute-unit-limit")
+            .long("compute-unit-limit")
+            .global(true)
+            .takes_value(true)
+            .help("Compute unit limit of the transactions sent. Defaults to the units a simulation of each transaction consumes, with a 20% margin"))
+        .arg(Arg::with_name("commitment")
+            .long("commitment")
+            .global(true)
+            .takes_value(true)
+            .default_value("confirmed")
+            .possible_values(&["processed", "confirmed", "finalized"])
+            .help("Commitment level used for reading accounts and confirming transactions"))
+        .arg(Arg::with_name("cross-check-rpcs")
+            .long("cross-check-rpcs")
+            .global(true)
+            .takes_value(true)
+            .use_delimiter(true)
+            .multiple(true)
+            .help("Comma-separated RPC urls that must report the same on-chain program hash as --url"))
+        .arg(Arg::with_name("rpc-budget")
+            .long("rpc-budget")
+            .global(true)
+            .takes_value(true)
+            .help("Fail once this many RPC calls have been made, to bound the cost of a run on metered RPC plans"))
+        .arg(Arg::with_name("encrypt-to")
+            .long("encrypt-to")
+            .global(true)
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .value_name("RECIPIENT")
+            .help("Encrypt reports, verified params files, SBOMs and support bundles with age to this age or SSH public key, or to the recipients listed in this file, writing them with an .age extension. Can be repeated"))
+        .arg(Arg::with_name("rpc-header")
+            .long("rpc-header")
+            .global(true)
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .value_name("[HOST=]NAME: VALUE")
+            .help("Header to send with RPC requests, e.g. \"x-api-key: KEY\" for providers requiring header authentication. Prefix it with HOST= to send it only to that endpoint. Can be repeated"))
+        .arg(Arg::with_name("record-fixtures")
+            .long("record-fixtures")
+            .global(true)
+            .takes_value(true)
+            .value_name("DIR")
+            .conflicts_with("replay-fixtures")
+            .help("Record every RPC response of the run to DIR, for replaying it later with --replay-fixtures"))
+        .arg(Arg::with_name("replay-fixtures")
+            .long("replay-fixtures")
+            .global(true)
+            .takes_value(true)
+            .value_name("DIR")
+            .help("Answer RPC requests from the responses recorded in DIR with --record-fixtures instead of the network"))
+        .arg(Arg::with_name("build-cache")
+            .long("build-cache")
+            .global(true)
+            .takes_value(true)
+            .value_name("VOLUME")
+            .help("Docker volume to keep an sccache compile cache in across builds. Requires an image with sccache, and does not affect the executable hash"))
+        .arg(Arg::with_name("support-bundle")
+            .long("support-bundle")
+            .global(true)
+            .takes_value(true)
+            .value_name("PATH")
+            .help("If the run fails, write a .tar.gz (or a zstd-compressed .tar.zst) with the environment, commands run, build output and RPC endpoints (secrets redacted) to attach to a GitHub issue"))
+        .arg(Arg::with_name("yes")
+            .long("yes")
+            .global(true)
+            .conflicts_with("no")
+            .help("Answer yes to every prompt"))
+        .arg(Arg::with_name("no")
+            .long("no")
+            .global(true)
+            .help("Answer no to every prompt"))
+        .arg(Arg::with_name("replace-policy")
+            .long("replace-policy")
+            .global(true)
+            .takes_value(true)
+            .possible_values(&["never", "always", "prompt"])
+            .default_value("prompt")
+            .help("Whether to upload a new PDA when other signers already uploaded one for the program"))
+        .arg(Arg::with_name("image-fallback")
+            .long("image-fallback")
+            .global(true)
+            .takes_value(true)
+            .possible_values(&["nearest-lower", "nearest-higher", "fail"])
+            .default_value("nearest-lower")
+            .help("Docker image to build with when none exists for the program's Solana version"))
+        .arg(Arg::with_name("git-backend")
+            .long("git-backend")
+            .global(true)
+            .takes_value(true)
+            .possible_values(&["auto", "libgit2", "cli"])
+            .default_value("auto")
+            .help("Clone and check out with the bundled libgit2, the system git, or libgit2 falling back to git (auto)"))
+        .arg(Arg::with_name("container-runtime")
+            .long("container-runtime")
+            .global(true)
+            .takes_value(true)
+            .possible_values(&["docker", "podman"])
+            .help("Container runtime to build with. Defaults to SVB_CONTAINER_RUNTIME, else docker if it is installed, else podman"))
+        .arg(Arg::with_name("docker-context")
+            .long("docker-context")
+            .global(true)
+            .takes_value(true)
+            .help("Docker context to run builds with (e.g. colima or a remote builder). Defaults to the current docker context"))
+        .arg(Arg::with_name("require-docker-version")
+            .long("require-docker-version")
+            .global(true)
+            .takes_value(true)
+            .help("Fail before building if the docker server is older than this version"))
         .subcommand(SubCommand::with_name("build")
             .about("Deterministically build the program in a Docker container")
             .arg(Arg::with_name("mount-directory")
@@ -141,6 +274,13 @@ async fn main() -> anyhow::Result<()> {
             .arg(Arg::with_name("bpf")
                 .long("bpf")
                 .help("If the program requires cargo build-bpf (instead of cargo build-sbf), set this flag"))
+            .arg(Arg::with_name("no-bpf")
+                .long("no-bpf")
+                .conflicts_with("bpf")
+                .help("Build with cargo build-sbf even if the verify.toml of the repository sets bpf"))
+            .arg(Arg::with_name("copy-source")
+                .long("copy-source")
+                .help("Build a copy of the files git tracks in the directory instead of mounting it, leaving out build output, untracked and ignored files (target/, node_modules/, .env, ...), then copy target/deploy back"))
             .arg(Arg::with_name("cargo-args")
                 .multiple(true)
                 .last(true)
@@ -167,9 +307,22 @@ async fn main() -> anyhow::Result<()> {
                 .help("The Program ID of the program to verify"))
             .arg(Arg::with_name("current-dir")
                 .long("current-dir")
-                .help("Verify in current directory")))
+                .help("Verify in current directory"))
+            .arg(Arg::with_name("repo-url")
+                .long("repo-url")
+                .takes_value(true)
+                .help("Repository the image is expected to be built from, checked against its org.opencontainers.image.source label"))
+            .arg(Arg::with_name("commit-hash")
+                .long("commit-hash")
+                .takes_value(true)
+                .help("Commit the image is expected to be built from, checked against its org.opencontainers.image.revision label")))
         .subcommand(SubCommand::with_name("get-executable-hash")
             .about("Get the hash of a program binary from an executable file")
+            .arg(Arg::with_name("filepath")
+                .required(true)
+                .help("Path to the executable solana program, or - to read it from stdin")))
+        .subcommand(SubCommand::with_name("check-features")
+            .about("Check that the feature gated syscalls used by a program are active on the cluster")
             .arg(Arg::with_name("filepath")
                 .required(true)
                 .help("Path to the executable solana program")))
@@ -177,12 +330,53 @@ async fn main() -> anyhow::Result<()> {
             .about("Get the hash of a program binary from the deployed on-chain program")
             .arg(Arg::with_name("program-id")
                 .required(true)
-                .help("The Program ID of the program to verify")))
+                .help("The Program ID of the program to verify"))
+            .arg(Arg::with_name("check-pdas")
+                .long("check-pdas")
+                .help("Also report which existing verification PDAs the remote verifier confirmed for the deployed hash")))
         .subcommand(SubCommand::with_name("get-buffer-hash")
             .about("Get the hash of a program binary from the deployed buffer address")
             .arg(Arg::with_name("buffer-address")
+                .help("Address of the buffer account containing the deployed program data"))
+            .arg(Arg::with_name("account")
+                .long("account")
+                .takes_value(true)
+                .help("Hash the executable stored in an arbitrary account instead, e.g. a loader-v4 program or a copy kept by a registry"))
+            .group(ArgGroup::with_name("source")
+                .args(&["buffer-address", "account"])
+                .required(true))
+            .arg(Arg::with_name("data-offset")
+                .long("data-offset")
+                .takes_value(true)
+                .requires("account")
+                .help("Offset of the executable in the account data, e.g. 48 for loader-v4 programs"))
+            .arg(Arg::with_name("data-length")
+                .long("data-length")
+                .takes_value(true)
+                .requires("account")
+                .help("Length of the executable. Defaults to the rest of the account")))
+        .subcommand(SubCommand::with_name("verify-idl")
+            .about("Compare the anchor IDL published on-chain for a program with the IDL built from its verified source")
+            .arg(Arg::with_name("program-id")
+                .long("program-id")
+                .required(true)
+                .takes_value(true)
+                .help("The program whose on-chain IDL to check"))
+            .arg(Arg::with_name("signer")
+                .long("signer")
+                .takes_value(true)
+                .help("Build the IDL from the source recorded in the PDA of this signer, by default the signer of the Solana CLI config. Requires the Anchor CLI")))
+        .subcommand(SubCommand::with_name("compare-buffer")
+            .about("Compare a buffer that may still be partially written against a local build")
+            .arg(Arg::with_name("buffer-address")
+                .required(true)
+                .help("Address of the buffer account the program is being written to"))
+            .arg(Arg::with_name("executable")
+                .short("e")
+                .long("executable")
                 .required(true)
-                .help("Address of the buffer account containing the deployed program data")))
+                .takes_value(true)
+                .help("Path to the locally built executable")))
         .subcommand(SubCommand::with_name("verify-from-repo")
             .about("Builds and verifies a program from a given repository URL and a program ID")
             .arg(Arg::with_name("remote")
@@ -204,9 +398,13 @@ async fn main() -> anyhow::Result<()> {
                 .help("Commit hash to checkout. Required to know the correct program snapshot. Will fallback to HEAD if not provided"))
             .arg(Arg::with_name("program-id")
                 .long("program-id")
-                .required(true)
+                .required_unless("local-validator")
                 .takes_value(true)
                 .help("The Program ID of the program to verify"))
+            .arg(Arg::with_name("local-validator")
+                .long("local-validator")
+                .conflicts_with_all(&["remote", "program-id", "skip-clone", "skip-hash-check", "only-build", "keypair"])
+                .help("Deploy the build to a local solana-test-validator (attaching to one on the default RPC port if running) and upload and read back its PDA there, without prompts or mainnet transactions"))
             .arg(Arg::with_name("base-image")
                 .short("b")
                 .long("base-image")
@@ -219,6 +417,10 @@ async fn main() -> anyhow::Result<()> {
             .arg(Arg::with_name("bpf")
                 .long("bpf")
                 .help("If the program requires cargo build-bpf (instead of cargo build-sbf), set this flag"))
+            .arg(Arg::with_name("no-bpf")
+                .long("no-bpf")
+                .conflicts_with("bpf")
+                .help("Build with cargo build-sbf even if the verify.toml of the repository sets bpf"))
             .arg(Arg::with_name("current-dir")
                 .long("current-dir")
                 .help("Verify in current directory"))
@@ -230,45 +432,297 @@ async fn main() -> anyhow::Result<()> {
                 .short("k")
                 .long("keypair")
                 .takes_value(true)
-                .help("Optionally specify a keypair to use for uploading the program verification args"))
+                .help("Optionally specify a keypair, or the URL of a remote signing service, to use for uploading the program verification args"))
+            .arg(Arg::with_name("signer-name")
+                .long("signer-name")
+                .takes_value(true)
+                .conflicts_with("keypair")
+                .help("Sign with a signer named in the [signers] section of solana-verify.toml, e.g. deployer"))
             .arg(Arg::with_name("cargo-args")
                 .multiple(true)
                 .last(true)
                 .help("Arguments to pass to the underlying `cargo build-sbf` command"))
             .arg(Arg::with_name("skip-build")
                 .long("skip-build")
-                .help("Skip building and verification, only upload the PDA")
-                .takes_value(false)))
-        .subcommand(SubCommand::with_name("export-pda-tx")
-            .about("Export the transaction as base58 for use with Squads")
-            .arg(Arg::with_name("uploader")
-                .long("uploader")
+                .hidden(true)
+                .takes_value(false))
+            .arg(Arg::with_name("skip-clone")
+                .long("skip-clone")
                 .takes_value(true)
+                .value_name("DIR")
+                .help("Use an existing clean checkout of the repository instead of cloning it. The commit checked out there is the one verified"))
+            .arg(Arg::with_name("copy-source")
+                .long("copy-source")
+                .requires("skip-clone")
+                .help("Build a copy of the files git tracks in the --skip-clone checkout, so local artifacts do not leak into the build"))
+            .arg(Arg::with_name("skip-hash-check")
+                .long("skip-hash-check")
+                .conflicts_with_all(&["only-build", "remote"])
+                .help("Build the program but upload the PDA without comparing the build with the on-chain program"))
+            .arg(Arg::with_name("only-build")
+                .long("only-build")
+                .conflicts_with("remote")
+                .help("Build the program and compare it with the on-chain program, without uploading the PDA"))
+            .arg(Arg::with_name("audit-deps")
+                .long("audit-deps")
+                .conflicts_with("remote")
+                .help("Check the Cargo.lock of the build against the RustSec advisory database with cargo-audit and include the findings in the report. Findings do not fail the verification"))
+            .arg(Arg::with_name("downgrade-lockfile")
+                .long("downgrade-lockfile")
+                .conflicts_with("remote")
+                .help("If Cargo.lock is version 4 and the build image's cargo only reads version 3, rewrite it as an equivalent version 3 lockfile in the clone before building. The verify data of such a build is not uploaded, as the commit alone does not reproduce it"))
+            .arg(Arg::with_name("attest")
+                .long("attest")
+                .conflicts_with("remote")
+                .help("If the build matches, sign the program ID, on-chain hash, current slot and commit with the keypair and print the signature, so the claim can be checked offline with `solana-verify verify-attestation`"))
+            .arg(Arg::with_name("attestation-out")
+                .long("attestation-out")
+                .takes_value(true)
+                .value_name("FILE")
+                .requires("attest")
+                .help("Write the signed attestation to FILE as JSON"))
+            .arg(Arg::with_name("buffer")
+                .long("buffer")
+                .takes_value(true)
+                .value_name("ADDRESS")
+                .conflicts_with_all(&["remote", "attest", "skip-hash-check"])
+                .help("Compare the build with the program staged in this buffer instead of the deployed program, to check an upgrade before it is executed. The PDA is not uploaded"))
+            .arg(Arg::with_name("explain-mismatch")
+                .long("explain-mismatch")
+                .conflicts_with("remote")
+                .help("If the build does not match, compare its ELF sections, Solana crate versions and compiler with the on-chain program and print the likely causes"))
+            .arg(Arg::with_name("dry-run")
+                .long("dry-run")
+                .conflicts_with_all(&["remote", "only-build", "buffer"])
+                .help("Simulate the transaction writing the PDA and print its address, rent and fee, without sending it"))
+            .arg(Arg::with_name("rebuild-if-stale")
+                .long("rebuild-if-stale")
+                .help("Rebuild and compare again if the program is redeployed before the verification is uploaded, up to 3 times"))
+            .arg(Arg::with_name("keep-temp-on-failure")
+                .long("keep-temp-on-failure")
+                .help("Keep the cloned repository and its target directory if the build fails or does not match the on-chain program"))
+            .arg(Arg::with_name("write-params")
+                .long("write-params")
+                .takes_value(true)
+                .help("After a successful hash match, save the verification params to this file so they can be uploaded later with `upload-pda --from-file`. Compressed with zstd if it ends in .zst"))
+            .arg(Arg::with_name("report")
+                .long("report")
+                .takes_value(true)
+                .help("Write a JSON report of the build, including the dependency tree and a CycloneDX SBOM of the crates the program was built with, to this file. Compressed with zstd if it ends in .zst")))
+        .subcommand(SubCommand::with_name("verify-all")
+            .about("Build every program of a workspace from a single clone and compare each with its deployed program")
+            .arg(Arg::with_name("manifest")
+                .long("manifest")
+                .takes_value(true)
+                .default_value(DEFAULT_MANIFEST)
+                .help("TOML manifest mapping program IDs to their library name and mount path"))
+            .arg(Arg::with_name("repo-url")
+                .help("Git repository to clone, overriding the repo-url of the manifest"))
+            .arg(Arg::with_name("commit-hash")
+                .long("commit-hash")
+                .takes_value(true)
+                .help("Commit to check out, overriding the commit-hash of the manifest. Defaults to the head of the default branch"))
+            .arg(Arg::with_name("current-dir")
+                .long("current-dir")
+                .help("Clone into the current directory")))
+        .subcommand(SubCommand::with_name("verify-suite")
+            .about("Verify a program together with its companion programs, e.g. a Token-2022 transfer hook, resolving their program IDs on chain, and print a combined trust report")
+            .arg(Arg::with_name("manifest")
+                .long("manifest")
+                .takes_value(true)
+                .default_value(DEFAULT_SUITE_MANIFEST)
+                .help("TOML manifest of the primary program and its companions"))
+            .arg(Arg::with_name("report")
+                .long("report")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Write the combined trust report to FILE as JSON"))
+            .arg(Arg::with_name("current-dir")
+                .long("current-dir")
+                .help("Clone into the current directory")))
+        .subcommand(SubCommand::with_name("compare-repos")
+            .about("Build a program from two repositories, e.g. an upstream protocol and a fork claimed to be unmodified, report which reproduces the on-chain program and summarize how their sources differ")
+            .arg(Arg::with_name("program-id")
+                .long("program-id")
                 .required(true)
-                .help("Specifies an address to use for uploading the program verification args (should be the program authority)"))
-            .arg(Arg::with_name("encoding")
-                .long("encoding")
                 .takes_value(true)
-                .default_value("base58")
-                .possible_values(&["base58", "base64"])
-                .help("The encoding to use for the transaction"))   
+                .help("The program address both builds are compared with"))
+            .arg(Arg::with_name("repo-a")
+                .long("repo-a")
+                .required(true)
+                .takes_value(true)
+                .help("URL of the first repository, usually the upstream one"))
+            .arg(Arg::with_name("repo-b")
+                .long("repo-b")
+                .required(true)
+                .takes_value(true)
+                .help("URL of the second repository, usually the fork"))
+            .arg(Arg::with_name("commit")
+                .long("commit")
+                .takes_value(true)
+                .help("Commit to check out in both repositories. Defaults to HEAD"))
+            .arg(Arg::with_name("commit-a")
+                .long("commit-a")
+                .takes_value(true)
+                .help("Commit of the first repository, overriding --commit"))
+            .arg(Arg::with_name("commit-b")
+                .long("commit-b")
+                .takes_value(true)
+                .help("Commit of the second repository, overriding --commit"))
             .arg(Arg::with_name("mount-path")
                 .long("mount-path")
                 .takes_value(true)
                 .default_value("")
-                .help("Relative path to the root directory or the source code repository from which to build the program"))
-            .arg(Arg::with_name("repo-url")
+                .help("Relative path to the root directory of the program in both repositories"))
+            .arg(Arg::with_name("library-name")
+                .long("library-name")
+                .takes_value(true)
+                .help("Specify the name of the library to build"))
+            .arg(Arg::with_name("base-image")
+                .short("b")
+                .long("base-image")
+                .takes_value(true)
+                .help("Optionally specify a custom base docker image to use for building"))
+            .arg(Arg::with_name("bpf")
+                .long("bpf")
+                .help("If the program requires cargo build-bpf (instead of cargo build-sbf), set this flag"))
+            .arg(Arg::with_name("current-dir")
+                .long("current-dir")
+                .help("Clone into the current directory"))
+            .arg(Arg::with_name("cargo-args")
+                .multiple(true)
+                .last(true)
+                .help("Arguments to pass to the underlying `cargo build-sbf` command")))
+        .subcommand(SubCommand::with_name("verify-attestation")
+            .about("Check offline the signature of an attestation written with verify-from-repo --attest")
+            .arg(Arg::with_name("attestation")
                 .required(true)
-                .help("The HTTPS URL of the repo to clone"))
+                .help("Attestation JSON file"))
+            .arg(Arg::with_name("signer")
+                .long("signer")
+                .takes_value(true)
+                .help("Also require the attestation to be signed by this address")))
+        .subcommand(SubCommand::with_name("profile")
+            .about("Manage named verify-from-repo invocations, run with `solana-verify verify <name>`")
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(SubCommand::with_name("add")
+                .about("Save a profile")
+                .arg(Arg::with_name("name")
+                    .required(true)
+                    .help("Name of the profile, e.g. mainnet-phoenix"))
+                .arg(Arg::with_name("program-id")
+                    .long("program-id")
+                    .required(true)
+                    .takes_value(true)
+                    .help("The Program ID of the program to verify"))
+                .arg(Arg::with_name("repo")
+                    .long("repo")
+                    .required(true)
+                    .takes_value(true)
+                    .help("The HTTPS URL of the repo to clone"))
+                .arg(Arg::with_name("commit-hash")
+                    .long("commit-hash")
+                    .takes_value(true)
+                    .help("Commit to verify. The head of the default branch is verified if not set"))
+                .arg(Arg::with_name("mount-path")
+                    .long("mount-path")
+                    .takes_value(true)
+                    .help("Relative path to the root directory or the source code repository from which to build the program"))
+                .arg(Arg::with_name("library-name")
+                    .long("library-name")
+                    .takes_value(true)
+                    .help("Specify the name of the library to build and verify"))
+                .arg(Arg::with_name("base-image")
+                    .short("b")
+                    .long("base-image")
+                    .takes_value(true)
+                    .help("Optionally specify a custom base docker image to use for building"))
+                .arg(Arg::with_name("bpf")
+                    .long("bpf")
+                    .help("If the program requires cargo build-bpf (instead of cargo build-sbf), set this flag"))
+                .arg(Arg::with_name("profile-url")
+                    .long("rpc-url")
+                    .takes_value(true)
+                    .help("RPC endpoint of the profile, used unless --url is given"))
+                .arg(Arg::with_name("keypair")
+                    .short("k")
+                    .long("keypair")
+                    .takes_value(true)
+                    .help("Keypair, or the URL of a remote signing service, uploading the program verification args"))
+                .arg(Arg::with_name("force")
+                    .long("force")
+                    .help("Replace a profile of the same name"))
+                .arg(Arg::with_name("cargo-args")
+                    .multiple(true)
+                    .last(true)
+                    .help("Arguments to pass to the underlying `cargo build-sbf` command")))
+            .subcommand(SubCommand::with_name("list")
+                .about("List the saved profiles"))
+            .subcommand(SubCommand::with_name("remove")
+                .about("Remove a profile")
+                .arg(Arg::with_name("name")
+                    .required(true)
+                    .help("Name of the profile"))))
+        .subcommand(SubCommand::with_name("verify")
+            .about("Runs verify-from-repo with the arguments saved in a profile")
+            .arg(Arg::with_name("name")
+                .required(true)
+                .help("Name of the profile"))
             .arg(Arg::with_name("commit-hash")
                 .long("commit-hash")
                 .takes_value(true)
-                .help("Commit hash to checkout. Required to know the correct program snapshot. Will fallback to HEAD if not provided"))
+                .help("Verify this commit instead of the one saved in the profile"))
+            .arg(Arg::with_name("current-dir")
+                .long("current-dir")
+                .help("Verify in current directory"))
+            .arg(Arg::with_name("skip-prompt")
+                .short("y")
+                .long("skip-prompt")
+                .help("Skip the prompt to write verify data on chain without user confirmation"))
+            .arg(Arg::with_name("report")
+                .long("report")
+                .takes_value(true)
+                .help("Write a JSON report of the build, including the dependency tree and a CycloneDX SBOM of the crates the program was built with, to this file. Compressed with zstd if it ends in .zst")))
+        .subcommand(SubCommand::with_name("verify-from-archive")
+            .about("Builds and verifies a program from a source archive or a crates.io release instead of a git repository")
+            .arg(Arg::with_name("archive-url")
+                .long("archive-url")
+                .takes_value(true)
+                .requires("archive-sha256")
+                .help("URL of a .tar.gz source snapshot"))
+            .arg(Arg::with_name("archive-sha256")
+                .long("archive-sha256")
+                .takes_value(true)
+                .requires("archive-url")
+                .help("Expected SHA-256 of the archive, recorded in the PDA"))
+            .arg(Arg::with_name("crate")
+                .long("crate")
+                .takes_value(true)
+                .requires("crate-version")
+                .help("Name of a crate published on crates.io"))
+            .arg(Arg::with_name("crate-version")
+                .long("crate-version")
+                .takes_value(true)
+                .requires("crate")
+                .help("Version of the crate to verify"))
+            .arg(Arg::with_name("from-pda")
+                .long("from-pda")
+                .takes_value(true)
+                .value_name("SIGNER")
+                .help("Rebuild the archive and build arguments this signer uploaded for the program, without uploading"))
+            .group(ArgGroup::with_name("source")
+                .args(&["archive-url", "crate", "from-pda"])
+                .required(true))
             .arg(Arg::with_name("program-id")
                 .long("program-id")
                 .required(true)
                 .takes_value(true)
                 .help("The Program ID of the program to verify"))
+            .arg(Arg::with_name("mount-path")
+                .long("mount-path")
+                .takes_value(true)
+                .default_value("")
+                .help("Relative path to the root directory of the archive from which to build the program"))
             .arg(Arg::with_name("base-image")
                 .short("b")
                 .long("base-image")
@@ -281,65 +735,736 @@ async fn main() -> anyhow::Result<()> {
             .arg(Arg::with_name("bpf")
                 .long("bpf")
                 .help("If the program requires cargo build-bpf (instead of cargo build-sbf), set this flag"))
+            .arg(Arg::with_name("skip-prompt")
+                .short("y")
+                .long("skip-prompt")
+                .help("Skip the prompt to write verify data on chain without user confirmation"))
+            .arg(Arg::with_name("keypair")
+                .short("k")
+                .long("keypair")
+                .takes_value(true)
+                .help("Optionally specify a keypair, or the URL of a remote signing service, to use for uploading the program verification args"))
+            .arg(Arg::with_name("signer-name")
+                .long("signer-name")
+                .takes_value(true)
+                .conflicts_with("keypair")
+                .help("Sign with a signer named in the [signers] section of solana-verify.toml, e.g. deployer"))
             .arg(Arg::with_name("cargo-args")
                 .multiple(true)
                 .last(true)
                 .help("Arguments to pass to the underlying `cargo build-sbf` command")))
-        .subcommand(SubCommand::with_name("close")
-            .about("Close the otter-verify PDA account associated with the given program ID")
+        .subcommand(SubCommand::with_name("find-commit")
+            .about("Builds the commits of a repository until one reproduces the on-chain program")
             .arg(Arg::with_name("program-id")
                 .long("program-id")
                 .required(true)
                 .takes_value(true)
-                .help("The address of the program to close the PDA")))
-            .arg(Arg::with_name("export")
-                .long("export")
-                .required(false)
-                .help("Print the transaction as base58 for use with Squads"))
-        .subcommand(SubCommand::with_name("list-program-pdas")
-            .about("List all the PDA information associated with a program ID. Requires custom RPC endpoint")
-            .arg(Arg::with_name("program-id")
-                .long("program-id")
+                .help("The Program ID of the deployed program"))
+            .arg(Arg::with_name("repo")
+                .long("repo")
                 .required(true)
-                .takes_value(true)))
-        .subcommand(SubCommand::with_name("get-program-pda")
-            .about("Get uploaded PDA information for a given program ID and signer")
+                .takes_value(true)
+                .help("The HTTPS URL of the repo to clone"))
+            .arg(Arg::with_name("range")
+                .long("range")
+                .takes_value(true)
+                .help("Commits to check, e.g. v1.0..v1.1. Defaults to the commits made before the last deployment"))
+            .arg(Arg::with_name("max-commits")
+                .long("max-commits")
+                .takes_value(true)
+                .help("Stop after building this many commits"))
+            .arg(Arg::with_name("mount-path")
+                .long("mount-path")
+                .takes_value(true)
+                .default_value("")
+                .help("Relative path to the root directory or the source code repository from which to build the program"))
+            .arg(Arg::with_name("base-image")
+                .short("b")
+                .long("base-image")
+                .takes_value(true)
+                .help("Optionally specify a custom base docker image to use for building"))
+            .arg(Arg::with_name("library-name")
+                .long("library-name")
+                .takes_value(true)
+                .help("Specify the name of the library to build"))
+            .arg(Arg::with_name("bpf")
+                .long("bpf")
+                .help("If the program requires cargo build-bpf (instead of cargo build-sbf), set this flag"))
+            .arg(Arg::with_name("cargo-args")
+                .multiple(true)
+                .last(true)
+                .help("Arguments to pass to the underlying `cargo build-sbf` command")))
+        .subcommand(SubCommand::with_name("compare-commits")
+            .about("Builds two revisions of a repository and reports which, if either, matches the on-chain program")
             .arg(Arg::with_name("program-id")
                 .long("program-id")
                 .required(true)
                 .takes_value(true)
-            )
-            .arg(Arg::with_name("signer")
-                .short("s")
-                .long("signer")
-                .required(false)
+                .help("The Program ID of the deployed program"))
+            .arg(Arg::with_name("repo")
+                .long("repo")
+                .required(true)
                 .takes_value(true)
-                .help("Signer to get the PDA for")
-            )
-        )
-        .subcommand(SubCommand::with_name("remote")
-            .about("Send a command to a remote machine")
-        .setting(AppSettings::SubcommandRequiredElseHelp)
-            .subcommand(SubCommand::with_name("get-status")
-                .about("Get the verification status of a program")
-                .arg(Arg::with_name("program-id")
-                    .long("program-id")
-                    .required(true)
-                    .takes_value(true)
-                    .help("The program address to fetch verification status for")))
-
-            .subcommand(SubCommand::with_name("get-job")
-                .about("Get the status of a verification job")
-                .arg(Arg::with_name("job-id")
-                    .long("job-id")
-                    .required(true)
-                    .takes_value(true)))
-            .subcommand(SubCommand::with_name("submit-job")
-                .about("Submit a verification job with with on-chain information")
-                .arg(Arg::with_name("program-id")
-                    .long("program-id")
-                    .required(true)
-                    .takes_value(true))
+                .help("The HTTPS URL of the repo to clone"))
+            .arg(Arg::with_name("commit-a")
+                .long("commit-a")
+                .required(true)
+                .takes_value(true)
+                .help("First commit, tag or branch to build, e.g. the release tag"))
+            .arg(Arg::with_name("commit-b")
+                .long("commit-b")
+                .required(true)
+                .takes_value(true)
+                .help("Second commit, tag or branch to build, e.g. a hotfix branch"))
+            .arg(Arg::with_name("mount-path")
+                .long("mount-path")
+                .takes_value(true)
+                .default_value("")
+                .help("Relative path to the root directory or the source code repository from which to build the program"))
+            .arg(Arg::with_name("base-image")
+                .short("b")
+                .long("base-image")
+                .takes_value(true)
+                .help("Optionally specify a custom base docker image to use for building"))
+            .arg(Arg::with_name("library-name")
+                .long("library-name")
+                .takes_value(true)
+                .help("Specify the name of the library to build"))
+            .arg(Arg::with_name("bpf")
+                .long("bpf")
+                .help("If the program requires cargo build-bpf (instead of cargo build-sbf), set this flag"))
+            .arg(Arg::with_name("cargo-args")
+                .multiple(true)
+                .last(true)
+                .help("Arguments to pass to the underlying `cargo build-sbf` command")))
+        .subcommand(SubCommand::with_name("upload-pda")
+            .about("Upload verification params saved by `verify-from-repo --write-params` without rebuilding")
+            .arg(Arg::with_name("from-file")
+                .long("from-file")
+                .required(true)
+                .takes_value(true)
+                .help("Path to the verified params file"))
+            .arg(Arg::with_name("allow-unsigned")
+                .long("allow-unsigned")
+                .help("Upload the params file even if it is not signed. Unsigned files could have been written by anyone"))
+            .arg(Arg::with_name("program-id")
+                .long("program-id")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Upload the params for this program instead of the one in the file. Repeat to upload for several programs deployed from the same build"))
+            .arg(Arg::with_name("skip-prompt")
+                .short("y")
+                .long("skip-prompt")
+                .help("Skip the prompt to write verify data on chain without user confirmation"))
+            .arg(Arg::with_name("keypair")
+                .short("k")
+                .long("keypair")
+                .takes_value(true)
+                .help("Optionally specify a keypair, or the URL of a remote signing service, to use for uploading the program verification args"))
+            .arg(Arg::with_name("signer-name")
+                .long("signer-name")
+                .takes_value(true)
+                .conflicts_with("keypair")
+                .help("Sign with a signer named in the [signers] section of solana-verify.toml, e.g. deployer")))
+        .subcommand(SubCommand::with_name("upload-only")
+            .about("Upload verification params without building the program, from a verified params file, another signer's PDA or explicitly trusted arguments")
+            .arg(Arg::with_name("program-id")
+                .long("program-id")
+                .required(true)
+                .takes_value(true)
+                .help("The Program ID of the program to upload the params for"))
+            .arg(Arg::with_name("from-artifact")
+                .long("from-artifact")
+                .takes_value(true)
+                .help("Params file written by `verify-from-repo --write-params`. It is only uploaded if it matches the on-chain program"))
+            .arg(Arg::with_name("allow-unsigned")
+                .long("allow-unsigned")
+                .requires("from-artifact")
+                .help("Upload the params file even if it is not signed. Unsigned files could have been written by anyone"))
+            .arg(Arg::with_name("from-pda")
+                .long("from-pda")
+                .takes_value(true)
+                .value_name("SIGNER")
+                .help("Copy the params this signer uploaded for the program"))
+            .arg(Arg::with_name("repo-url")
+                .long("repo-url")
+                .takes_value(true)
+                .requires("commit-hash")
+                .help("Upload these params as given, without building them. The repository is cloned to infer the build arguments"))
+            .group(ArgGroup::with_name("source")
+                .args(&["from-artifact", "from-pda", "repo-url"])
+                .required(true))
+            .arg(Arg::with_name("commit-hash")
+                .long("commit-hash")
+                .takes_value(true)
+                .requires("repo-url")
+                .help("Commit of the trusted params"))
+            .arg(Arg::with_name("mount-path")
+                .long("mount-path")
+                .takes_value(true)
+                .default_value("")
+                .help("Relative path to the root directory or the source code repository from which to build the program"))
+            .arg(Arg::with_name("base-image")
+                .short("b")
+                .long("base-image")
+                .takes_value(true)
+                .help("Optionally specify a custom base docker image to use for building"))
+            .arg(Arg::with_name("library-name")
+                .long("library-name")
+                .takes_value(true)
+                .help("Specify the name of the library to build and verify"))
+            .arg(Arg::with_name("bpf")
+                .long("bpf")
+                .help("If the program requires cargo build-bpf (instead of cargo build-sbf), set this flag"))
+            .arg(Arg::with_name("skip-prompt")
+                .short("y")
+                .long("skip-prompt")
+                .help("Skip the prompt to write verify data on chain without user confirmation"))
+            .arg(Arg::with_name("keypair")
+                .short("k")
+                .long("keypair")
+                .takes_value(true)
+                .help("Optionally specify a keypair, or the URL of a remote signing service, to use for uploading the program verification args"))
+            .arg(Arg::with_name("signer-name")
+                .long("signer-name")
+                .takes_value(true)
+                .conflicts_with("keypair")
+                .help("Sign with a signer named in the [signers] section of solana-verify.toml, e.g. deployer"))
+            .arg(Arg::with_name("cargo-args")
+                .multiple(true)
+                .last(true)
+                .help("Arguments to pass to the underlying `cargo build-sbf` command")))
+        .subcommand(SubCommand::with_name("publish-release")
+            .about("Attach the verified program and its verification metadata to a GitHub release")
+            .arg(Arg::with_name("tag")
+                .long("tag")
+                .required(true)
+                .takes_value(true)
+                .help("Release tag, created at the verified commit if the release does not exist"))
+            .arg(Arg::with_name("executable")
+                .short("e")
+                .long("executable")
+                .required(true)
+                .takes_value(true)
+                .help("Path to the verified executable"))
+            .arg(Arg::with_name("params-file")
+                .long("params-file")
+                .required(true)
+                .takes_value(true)
+                .help("Path to the verified params file written by `verify-from-repo --write-params`"))
+            .arg(Arg::with_name("uploader")
+                .long("uploader")
+                .takes_value(true)
+                .help("Address that uploaded the verification PDA. Defaults to the signer of the params file"))
+            .arg(Arg::with_name("token")
+                .long("token")
+                .takes_value(true)
+                .help("GitHub token with write access to the repository. Defaults to $GITHUB_TOKEN")))
+        .subcommand(SubCommand::with_name("record-in-repo")
+            .about("Open a pull request recording a successful verification and its verify command in the verified repository")
+            .arg(Arg::with_name("params-file")
+                .long("params-file")
+                .required(true)
+                .takes_value(true)
+                .help("Path to the verified params file written by `verify-from-repo --write-params`"))
+            .arg(Arg::with_name("file")
+                .long("file")
+                .takes_value(true)
+                .default_value(VERIFIED_BUILDS_FILE)
+                .help("Path of the file in the repository to record the verification in"))
+            .arg(Arg::with_name("branch")
+                .long("branch")
+                .takes_value(true)
+                .help("Branch to commit to. Defaults to solana-verify/<program id>"))
+            .arg(Arg::with_name("base")
+                .long("base")
+                .takes_value(true)
+                .help("Branch to open the pull request against. Defaults to the default branch of the repository"))
+            .arg(Arg::with_name("token")
+                .long("token")
+                .takes_value(true)
+                .help("GitHub token with write access to the repository. Defaults to $GITHUB_TOKEN")))
+        .subcommand(SubCommand::with_name("push-artifact")
+            .about("Push the verified program and its attestation to an OCI registry with oras")
+            .arg(Arg::with_name("repository")
+                .long("repository")
+                .required(true)
+                .takes_value(true)
+                .help("OCI repository to push to, e.g. ghcr.io/org/verified-programs. The tag is <program id>-<commit>"))
+            .arg(Arg::with_name("executable")
+                .short("e")
+                .long("executable")
+                .required(true)
+                .takes_value(true)
+                .help("Path to the verified executable"))
+            .arg(Arg::with_name("params-file")
+                .long("params-file")
+                .required(true)
+                .takes_value(true)
+                .help("Path to the verified params file written by `verify-from-repo --write-params`"))
+            .arg(Arg::with_name("report")
+                .long("report")
+                .takes_value(true)
+                .help("Verification report written by `verify-from-repo --report`, whose SBOM is pushed alongside the attestation")))
+        .subcommand(SubCommand::with_name("fetch-artifact")
+            .about("Fetch a verified program pushed with push-artifact and check it against its attestation")
+            .arg(Arg::with_name("repository")
+                .long("repository")
+                .required(true)
+                .takes_value(true)
+                .help("OCI repository the artifact was pushed to"))
+            .arg(Arg::with_name("program-id")
+                .long("program-id")
+                .required(true)
+                .takes_value(true)
+                .help("Program id the artifact was verified for"))
+            .arg(Arg::with_name("commit-hash")
+                .long("commit-hash")
+                .takes_value(true)
+                .default_value("")
+                .hide_default_value(true)
+                .help("Commit the artifact was verified at"))
+            .arg(Arg::with_name("output-dir")
+                .short("o")
+                .long("output-dir")
+                .takes_value(true)
+                .default_value(".")
+                .help("Directory to write the program and attestation to")))
+        .subcommand(SubCommand::with_name("deploy-verified")
+            .about("Build the program verifiably, write it to a buffer and export the upgrade transaction for the program authority")
+            .arg(Arg::with_name("program-id")
+                .long("program-id")
+                .required(true)
+                .takes_value(true)
+                .help("The Program ID of the program to upgrade"))
+            .arg(Arg::with_name("mount-directory")
+                .long("mount-directory")
+                .takes_value(true)
+                .help("Path of the program's workspace, defaults to the current directory"))
+            .arg(Arg::with_name("library-name")
+                .long("library-name")
+                .required(true)
+                .takes_value(true)
+                .help("Which binary file to build and deploy"))
+            .arg(Arg::with_name("base-image")
+                .short("b")
+                .long("base-image")
+                .takes_value(true)
+                .help("Optionally specify a custom base docker image to use for building"))
+            .arg(Arg::with_name("bpf")
+                .long("bpf")
+                .help("If the program requires cargo build-bpf (instead of cargo build-sbf), set this flag"))
+            .arg(Arg::with_name("buffer")
+                .long("buffer")
+                .takes_value(true)
+                .help("Keypair of the buffer to write the build to, defaults to a new buffer"))
+            .arg(Arg::with_name("keypair")
+                .short("k")
+                .long("keypair")
+                .takes_value(true)
+                .help("Keypair paying for and writing the buffer, defaults to the Solana CLI config"))
+            .arg(Arg::with_name("upgrade-authority")
+                .long("upgrade-authority")
+                .takes_value(true)
+                .help("Authority signing the upgrade, e.g. a multisig vault. Defaults to the program's upgrade authority"))
+            .arg(Arg::with_name("encoding")
+                .long("encoding")
+                .takes_value(true)
+                .default_value("base58")
+                .possible_values(&["base58", "base64"])
+                .help("The encoding to use for the transaction"))
+            .arg(Arg::with_name("cargo-args")
+                .multiple(true)
+                .last(true)
+                .help("Arguments to pass to the underlying `cargo build-sbf` command")))
+        .subcommand(SubCommand::with_name("export-pda-tx")
+            .about("Export the transaction as base58 for use with Squads")
+            .arg(Arg::with_name("uploader")
+                .long("uploader")
+                .takes_value(true)
+                .required_unless("signer-name")
+                .help("Specifies an address to use for uploading the program verification args (should be the program authority)"))
+            .arg(Arg::with_name("signer-name")
+                .long("signer-name")
+                .takes_value(true)
+                .conflicts_with("uploader")
+                .help("Upload as a signer named in the [signers] section of solana-verify.toml, e.g. dao-vault"))
+            .arg(Arg::with_name("encoding")
+                .long("encoding")
+                .takes_value(true)
+                .default_value("base58")
+                .possible_values(&["base58", "base64", "squads-json", "spl-gov-json"])
+                .help("The encoding to use for the transaction. The JSON encodings list the instructions in the format the Squads app and Realms accept"))
+            .arg(Arg::with_name("blockhash")
+                .long("blockhash")
+                .takes_value(true)
+                .default_value("none")
+                .possible_values(&["none", "latest", "nonce"])
+                .help("Recent blockhash of a base58 or base64 transaction: none for wallets that set it, the latest blockhash, which expires after about 90 seconds, or the value of a durable nonce"))
+            .arg(Arg::with_name("nonce-account")
+                .long("nonce-account")
+                .takes_value(true)
+                .required_if("blockhash", "nonce")
+                .help("Durable nonce account providing the blockhash"))
+            .arg(Arg::with_name("nonce-authority")
+                .long("nonce-authority")
+                .takes_value(true)
+                .requires("nonce-account")
+                .help("Authority of the nonce account. Defaults to the uploader"))
+            .arg(Arg::with_name("dry-run")
+                .long("dry-run")
+                .help("Simulate the transaction with the uploader as fee payer and print the PDA address, rent and fee instead of exporting it"))
+            .arg(Arg::with_name("mount-path")
+                .long("mount-path")
+                .takes_value(true)
+                .default_value("")
+                .help("Relative path to the root directory or the source code repository from which to build the program"))
+            .arg(Arg::with_name("repo-url")
+                .required(true)
+                .help("The HTTPS URL of the repo to clone"))
+            .arg(Arg::with_name("commit-hash")
+                .long("commit-hash")
+                .takes_value(true)
+                .help("Commit hash to checkout. Required to know the correct program snapshot. Will fallback to HEAD if not provided"))
+            .arg(Arg::with_name("program-id")
+                .long("program-id")
+                .required(true)
+                .takes_value(true)
+                .help("The Program ID of the program to verify"))
+            .arg(Arg::with_name("base-image")
+                .short("b")
+                .long("base-image")
+                .takes_value(true)
+                .help("Optionally specify a custom base docker image to use for building"))
+            .arg(Arg::with_name("library-name")
+                .long("library-name")
+                .takes_value(true)
+                .help("Specify the name of the library to build and verify"))
+            .arg(Arg::with_name("bpf")
+                .long("bpf")
+                .help("If the program requires cargo build-bpf (instead of cargo build-sbf), set this flag"))
+            .arg(Arg::with_name("cargo-args")
+                .multiple(true)
+                .last(true)
+                .help("Arguments to pass to the underlying `cargo build-sbf` command")))
+        .subcommand(SubCommand::with_name("partial-sign")
+            .about("Sign the upload of the PDA of a program authority as fee payer and write it to a file, for the authority to countersign later without the build environment")
+            .arg(Arg::with_name("uploader")
+                .long("uploader")
+                .takes_value(true)
+                .required_unless("signer-name")
+                .help("Address of the program authority that countersigns the upload"))
+            .arg(Arg::with_name("signer-name")
+                .long("signer-name")
+                .takes_value(true)
+                .conflicts_with("uploader")
+                .help("Upload as a signer named in the [signers] section of solana-verify.toml"))
+            .arg(Arg::with_name("keypair")
+                .short("k")
+                .long("keypair")
+                .takes_value(true)
+                .help("Keypair of the fee payer. Defaults to the Solana CLI config"))
+            .arg(Arg::with_name("blockhash")
+                .long("blockhash")
+                .takes_value(true)
+                .default_value("latest")
+                .possible_values(&["latest", "nonce"])
+                .help("The latest blockhash, leaving about 90 seconds to countersign, or the value of a durable nonce, valid until the nonce is advanced"))
+            .arg(Arg::with_name("nonce-account")
+                .long("nonce-account")
+                .takes_value(true)
+                .required_if("blockhash", "nonce")
+                .help("Durable nonce account providing the blockhash, advanced by the fee payer"))
+            .arg(Arg::with_name("out")
+                .long("out")
+                .takes_value(true)
+                .default_value(DEFAULT_PARTIAL_UPLOAD)
+                .help("File to write the partially signed upload to"))
+            .arg(Arg::with_name("mount-path")
+                .long("mount-path")
+                .takes_value(true)
+                .default_value("")
+                .help("Relative path to the root directory or the source code repository from which to build the program"))
+            .arg(Arg::with_name("repo-url")
+                .required(true)
+                .help("The HTTPS URL of the repo to clone"))
+            .arg(Arg::with_name("commit-hash")
+                .long("commit-hash")
+                .takes_value(true)
+                .help("Commit hash to checkout. Required to know the correct program snapshot. Will fallback to HEAD if not provided"))
+            .arg(Arg::with_name("program-id")
+                .long("program-id")
+                .required(true)
+                .takes_value(true)
+                .help("The Program ID of the program to verify"))
+            .arg(Arg::with_name("base-image")
+                .short("b")
+                .long("base-image")
+                .takes_value(true)
+                .help("Optionally specify a custom base docker image to use for building"))
+            .arg(Arg::with_name("library-name")
+                .long("library-name")
+                .takes_value(true)
+                .help("Specify the name of the library to build and verify"))
+            .arg(Arg::with_name("bpf")
+                .long("bpf")
+                .help("If the program requires cargo build-bpf (instead of cargo build-sbf), set this flag"))
+            .arg(Arg::with_name("cargo-args")
+                .multiple(true)
+                .last(true)
+                .help("Arguments to pass to the underlying `cargo build-sbf` command")))
+        .subcommand(SubCommand::with_name("countersign")
+            .about("Check an upload written by partial-sign, add the signature of the program authority and send it")
+            .arg(Arg::with_name("upload")
+                .required(true)
+                .help("File written by partial-sign"))
+            .arg(Arg::with_name("keypair")
+                .short("k")
+                .long("keypair")
+                .takes_value(true)
+                .help("Keypair, or the URL of a remote signing service, of the program authority. Defaults to the Solana CLI config"))
+            .arg(Arg::with_name("signer-name")
+                .long("signer-name")
+                .takes_value(true)
+                .conflicts_with("keypair")
+                .help("Sign with a signer named in the [signers] section of solana-verify.toml"))
+            .arg(Arg::with_name("skip-prompt")
+                .short("y")
+                .long("skip-prompt")
+                .help("Countersign without confirmation")))
+        .subcommand(SubCommand::with_name("refresh-tx")
+            .about("Re-encode a transaction exported by export-pda-tx with a fresh blockhash, without rebuilding the repo")
+            .arg(Arg::with_name("transaction")
+                .required(true)
+                .help("The base58 or base64 transaction, or a file containing it")))
+        .subcommand(SubCommand::with_name("wait-for-authority-tx")
+            .about("Wait for the governance proposal uploading the PDA exported with `export-pda-tx` to be executed, then submit the remote verification job")
+            .arg(Arg::with_name("program-id")
+                .long("program-id")
+                .required(true)
+                .takes_value(true)
+                .help("The Program ID of the program to verify"))
+            .arg(Arg::with_name("proposal")
+                .long("proposal")
+                .required(true)
+                .takes_value(true)
+                .help("Address of the Squads or spl-governance proposal executing the PDA transaction"))
+            .arg(Arg::with_name("uploader")
+                .long("uploader")
+                .takes_value(true)
+                .help("Address uploading the PDA. Defaults to the program's upgrade authority"))
+            .arg(Arg::with_name("poll-interval")
+                .long("poll-interval")
+                .takes_value(true)
+                .default_value("30")
+                .help("Seconds between checks of the proposal and the verification status")))
+        .subcommand(SubCommand::with_name("close")
+            .about("Close the otter-verify PDA account associated with the given program ID")
+            .arg(Arg::with_name("program-id")
+                .long("program-id")
+                .required(true)
+                .takes_value(true)
+                .help("The address of the program to close the PDA"))
+            .arg(Arg::with_name("keypair")
+                .short("k")
+                .long("keypair")
+                .takes_value(true)
+                .help("Optionally specify the keypair that uploaded the PDA"))
+            .arg(Arg::with_name("signer-name")
+                .long("signer-name")
+                .takes_value(true)
+                .conflicts_with("keypair")
+                .help("Sign with a signer named in the [signers] section of solana-verify.toml, e.g. deployer")))
+            .arg(Arg::with_name("export")
+                .long("export")
+                .required(false)
+                .help("Print the transaction as base58 for use with Squads"))
+        .subcommand(SubCommand::with_name("revoke")
+            .about("Mark the verification uploaded by the signer for a program ID as revoked")
+            .arg(Arg::with_name("program-id")
+                .long("program-id")
+                .required(true)
+                .takes_value(true)
+                .help("The address of the program whose verification should no longer be trusted"))
+            .arg(Arg::with_name("reason")
+                .long("reason")
+                .takes_value(true)
+                .help("Optional reason recorded on chain, e.g. a vulnerable release"))
+            .arg(Arg::with_name("skip-prompt")
+                .short("y")
+                .long("skip-prompt")
+                .help("Skip the confirmation prompt"))
+            .arg(Arg::with_name("keypair")
+                .short("k")
+                .long("keypair")
+                .takes_value(true)
+                .help("Optionally specify the keypair that uploaded the verification"))
+            .arg(Arg::with_name("signer-name")
+                .long("signer-name")
+                .takes_value(true)
+                .conflicts_with("keypair")
+                .help("Sign with a signer named in the [signers] section of solana-verify.toml, e.g. deployer")))
+        .subcommand(SubCommand::with_name("inspect")
+            .about("Summarize what is known about a deployed program without building it")
+            .arg(Arg::with_name("program-id")
+                .long("program-id")
+                .required(true)
+                .takes_value(true)
+                .help("Program ID of the program to inspect")))
+        .subcommand(SubCommand::with_name("list-program-pdas")
+            .about("List all the PDA information associated with a program ID. Requires custom RPC endpoint")
+            .arg(Arg::with_name("program-id")
+                .long("program-id")
+                .required(true)
+                .takes_value(true))
+            .arg(Arg::with_name("signer")
+                .long("signer")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Only list the PDAs uploaded by this signer. Other PDAs are not fetched, which keeps the command usable on rate-limited endpoints")))
+        .subcommand(SubCommand::with_name("list-uploader-pdas")
+            .about("List the programs verified by an uploader and whether each verification is still current. Requires custom RPC endpoint")
+            .arg(Arg::with_name("signer")
+                .long("signer")
+                .required(true)
+                .takes_value(true)
+                .help("Address of the uploader")))
+        .subcommand(SubCommand::with_name("get-program-pda")
+            .about("Get uploaded PDA information for a given program ID and signer")
+            .arg(Arg::with_name("program-id")
+                .long("program-id")
+                .required(true)
+                .takes_value(true)
+            )
+            .arg(Arg::with_name("signer")
+                .short("s")
+                .long("signer")
+                .required(false)
+                .takes_value(true)
+                .help("Signer to get the PDA for")
+            )
+            .arg(Arg::with_name("api-only")
+                .long("api-only")
+                .help("Read the verification from the verify.osec.io API instead of an RPC")
+            )
+        )
+        .subcommand(SubCommand::with_name("status")
+            .about("Get the verification a consumer should trust for a program: authority PDA, then OtterSec PDA, then others")
+            .arg(Arg::with_name("program-id")
+                .long("program-id")
+                .required(true)
+                .takes_value(true)
+                .help("The program address to resolve the trusted verification for"))
+            .arg(Arg::with_name("api-only")
+                .long("api-only")
+                .help("Read the status from the verify.osec.io API instead of an RPC")))
+        .subcommand(SubCommand::with_name("badge")
+            .about("Generate an SVG badge and shields.io endpoint JSON showing the current verification status of a program")
+            .arg(Arg::with_name("program-id")
+                .long("program-id")
+                .required(true)
+                .takes_value(true)
+                .help("The program address to generate the badge for"))
+            .arg(Arg::with_name("out")
+                .long("out")
+                .takes_value(true)
+                .default_value("badge.svg")
+                .help("Path of the SVG badge. The endpoint JSON is written next to it with a .json extension")))
+        .subcommand(SubCommand::with_name("daemon")
+            .about("Run indefinitely, re-verifying the programs of a config file on a schedule and serving their status over HTTP")
+            .arg(Arg::with_name("config")
+                .long("config")
+                .required(true)
+                .takes_value(true)
+                .help("JSON file listing the programs and repositories to watch. Reloaded on SIGHUP")))
+        .subcommand(SubCommand::with_name("example")
+            .about("Scaffold example programs to check the verification setup with")
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(SubCommand::with_name("new")
+                .about("Create a hello world program with a Makefile that builds it verifiably, deploys it to devnet and verifies it")
+                .arg(Arg::with_name("dir")
+                    .required(true)
+                    .help("Directory to create the program in. Must not exist or be empty"))
+                .arg(Arg::with_name("name")
+                    .long("name")
+                    .takes_value(true)
+                    .help("Name of the program crate. Defaults to the name of the directory"))))
+        .subcommand(SubCommand::with_name("serve")
+            .about("Run a JSON-RPC 2.0 service over HTTP (POST /) to submit verifications, query their status and get on-chain program hashes")
+            .arg(Arg::with_name("listen")
+                .long("listen")
+                .takes_value(true)
+                .default_value(DEFAULT_SERVE_LISTEN)
+                .help("Address to listen on. Listening beyond localhost requires SVB_SERVE_TOKEN to be set, clients then send it as a bearer token")))
+        .subcommand(SubCommand::with_name("cache")
+            .about("Inspect and clean up the docker images and containers created by solana-verify")
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(SubCommand::with_name("stats")
+                .about("Show the disk usage of the build images and leftover containers"))
+            .subcommand(SubCommand::with_name("prune")
+                .about("Remove leftover containers and build images according to the given policy")
+                .arg(Arg::with_name("older-than-days")
+                    .long("older-than-days")
+                    .takes_value(true)
+                    .help("Remove build images created more than this many days ago"))
+                .arg(Arg::with_name("max-size-gb")
+                    .long("max-size-gb")
+                    .takes_value(true)
+                    .help("Remove the oldest build images until the remaining ones fit in this many gigabytes"))))
+        .subcommand(SubCommand::with_name("registry")
+            .about("Query the verification registry of a program")
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(SubCommand::with_name("history")
+                .about("Export the timeline of a program's verifications: every write of its PDAs on chain and the verifications recorded by the remote API")
+                .arg(Arg::with_name("program-id")
+                    .long("program-id")
+                    .required(true)
+                    .takes_value(true))
+                .arg(Arg::with_name("out")
+                    .long("out")
+                    .required(true)
+                    .takes_value(true)
+                    .value_name("FILE")
+                    .help("Output file, written as CSV, JSON Lines or Parquet depending on its .csv, .jsonl or .parquet extension"))))
+        .subcommand(SubCommand::with_name("remote")
+            .about("Send a command to a remote machine")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(SubCommand::with_name("get-status")
+                .about("Get the verification status of a program")
+                .arg(Arg::with_name("program-id")
+                    .long("program-id")
+                    .required(true)
+                    .takes_value(true)
+                    .help("The program address to fetch verification status for"))
+                .arg(Arg::with_name("signer")
+                    .short("s")
+                    .long("signer")
+                    .takes_value(true)
+                    .help("Only show the verification status uploaded by this signer"))
+                .arg(Arg::with_name("only-verified")
+                    .long("only-verified")
+                    .help("Only show verification records whose hashes match"))
+                .arg(Arg::with_name("json")
+                    .long("json")
+                    .help("Print the verification status as JSON")))
+
+            .subcommand(SubCommand::with_name("get-job")
+                .about("Get the status of a verification job")
+                .arg(Arg::with_name("job-id")
+                    .long("job-id")
+                    .required(true)
+                    .takes_value(true))
+                .arg(Arg::with_name("json")
+                    .long("json")
+                    .help("Print the job status, timings and failure category as JSON")))
+            .subcommand(SubCommand::with_name("submit-job")
+                .about("Submit a verification job with with on-chain information")
+                .arg(Arg::with_name("program-id")
+                    .long("program-id")
+                    .required(true)
+                    .takes_value(true))
                 .arg(Arg::with_name("uploader")
                     .long("uploader")
                     .required(true)
@@ -348,26 +1473,180 @@ async fn main() -> anyhow::Result<()> {
         )
         .get_matches();
 
-    let connection = resolve_rpc_url(matches.value_of("url").map(|s| s.to_string()))?;
+    init_logging(Verbosity::from_flags(
+        matches.occurrences_of("verbose"),
+        matches.is_present("quiet"),
+    ));
+
+    // Credentials given on the command line or in the environment are scrubbed from output
+    register_env_secrets();
+    let args = std::env::args().collect::<Vec<_>>();
+    for (i, arg) in args.iter().enumerate() {
+        // Options are either followed by their value or given as `--option=value`
+        let (option, value) = match arg.split_once('=') {
+            Some((option, value)) if option.starts_with("--") => (Some(option), value),
+            _ => (
+                args.get(i.wrapping_sub(1)).map(String::as_str),
+                arg.as_str(),
+            ),
+        };
+        match option {
+            Some("--token") => register_secret(value),
+            Some("-u") | Some("--url") => register_rpc_url(value),
+            Some("--cross-check-rpcs") => value.split(',').for_each(register_rpc_url),
+            Some("--rpc-header") => register_rpc_header(value),
+            _ => register_url(value),
+        }
+    }
+
+    match matches.value_of("container-runtime") {
+        Some(runtime) => set_container_runtime(ContainerRuntime::from_str(runtime)?),
+        None => {
+            if let Some(runtime) = ContainerRuntime::from_env()? {
+                set_container_runtime(runtime);
+            }
+        }
+    }
+    if let Some(docker_context) = matches.value_of("docker-context") {
+        set_docker_context(docker_context)?;
+    }
+    if let Some(docker_version) = matches.value_of("require-docker-version") {
+        set_required_docker_version(docker_version)?;
+    }
+    let endpoint_config = EndpointConfig::resolve(
+        &EndpointsFile::load(Path::new(signers::CONFIG_FILE_NAME))?,
+        matches.value_of("url"),
+        get_cli_config_url().as_deref(),
+        matches
+            .values_of("cross-check-rpcs")
+            .map(|urls| urls.map(|url| url.to_string()).collect()),
+        matches.value_of("remote-api-url"),
+    );
+    endpoint_config.register_secrets();
+    set_endpoints(endpoint_config);
+    if let Some(path) = matches.value_of("support-bundle") {
+        set_support_bundle_path(path);
+    }
+    if let Some(volume) = matches.value_of("build-cache") {
+        set_build_cache(volume);
+    }
+    if let Some(recipients) = matches.values_of("encrypt-to") {
+        set_encryption_recipients(
+            recipients
+                .map(Recipient::from_str)
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        )?;
+    }
+    if let Some(headers) = matches.values_of("rpc-header") {
+        set_rpc_headers(
+            headers
+                .map(RpcHeader::from_str)
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        );
+    }
+    if let Some(dir) = matches.value_of("record-fixtures") {
+        record_fixtures(Path::new(dir))?;
+    }
+    if let Some(dir) = matches.value_of("replay-fixtures") {
+        replay_fixtures(Path::new(dir))?;
+    }
+    if let Some(budget) = matches.value_of("rpc-budget") {
+        set_rpc_budget(
+            budget
+                .parse::<u64>()
+                .map_err(|e| anyhow!("Invalid --rpc-budget: {}", e))?,
+        );
+    }
+    if let Some(limit) = matches.value_of("compute-unit-limit") {
+        set_compute_unit_limit(
+            limit
+                .parse::<u32>()
+                .map_err(|e| anyhow!("Invalid --compute-unit-limit: {}", e))?,
+        );
+    }
+    if matches.value_of("compute-unit-price") == Some(AUTO_COMPUTE_UNIT_PRICE) {
+        let percentile = matches
+            .value_of("compute-unit-price-percentile")
+            .unwrap()
+            .parse::<u8>()
+            .ok()
+            .filter(|percentile| (1..=100).contains(percentile))
+            .ok_or_else(|| anyhow!("--compute-unit-price-percentile must be from 1 to 100"))?;
+        set_auto_compute_unit_price(percentile);
+    }
+    if matches.is_present("yes") {
+        set_prompt_answer(true);
+    } else if matches.is_present("no") {
+        set_prompt_answer(false);
+    }
+    set_image_fallback(ImageFallback::from_str(
+        matches.value_of("image-fallback").unwrap(),
+    )?);
+    set_replace_policy(ReplacePolicy::from_str(
+        matches.value_of("replace-policy").unwrap(),
+    )?);
+    set_git_backend(GitBackend::from_str(
+        matches.value_of("git-backend").unwrap(),
+    )?);
+
+    let commitment = CommitmentConfig {
+        commitment: CommitmentLevel::from_str(matches.value_of("commitment").unwrap())?,
+    };
+    let connection = get_client(None, commitment);
+    register_rpc_url(&connection.url());
     let res = match matches.subcommand() {
         ("build", Some(sub_m)) => {
-            let mount_directory = sub_m.value_of("mount-directory").map(|s| s.to_string());
-            let library_name = sub_m.value_of("library-name").map(|s| s.to_string());
-            let base_image = sub_m.value_of("base-image").map(|s| s.to_string());
-            let bpf_flag = sub_m.is_present("bpf");
-            let cargo_args = sub_m
-                .values_of("cargo-args")
-                .unwrap_or_default()
-                .map(|s| s.to_string())
-                .collect();
-            build(
-                mount_directory,
+            let mount_directory = sub_m.value_of_os("mount-directory").map(PathBuf::from);
+            let root = match &mount_directory {
+                Some(mount_directory) => mount_directory.clone(),
+                None => std::env::current_dir()?,
+            };
+            let ManifestProgram {
                 library_name,
+                mount_path,
                 base_image,
-                bpf_flag,
+                bpf: bpf_flag,
                 cargo_args,
-                &mut container_id,
-            )
+            } = apply_project_config(
+                &root,
+                None,
+                ManifestProgram {
+                    library_name: sub_m.value_of("library-name").map(|s| s.to_string()),
+                    mount_path: String::new(),
+                    base_image: sub_m.value_of("base-image").map(|s| s.to_string()),
+                    bpf: bpf_arg(sub_m),
+                    cargo_args: sub_m
+                        .values_of("cargo-args")
+                        .unwrap_or_default()
+                        .map(|s| s.to_string())
+                        .collect(),
+                },
+            )?;
+            let bpf_flag = bpf_flag.unwrap_or_default();
+            // The mount path of verify.toml is relative to the directory holding it
+            let mount_directory = if mount_path.is_empty() {
+                mount_directory
+            } else {
+                Some(root.join(mount_path))
+            };
+            if sub_m.is_present("copy-source") {
+                build_from_copy(
+                    mount_directory,
+                    library_name,
+                    base_image,
+                    bpf_flag,
+                    cargo_args,
+                )
+            } else {
+                build(
+                    mount_directory,
+                    library_name,
+                    base_image,
+                    bpf_flag,
+                    cargo_args,
+                )
+            }
+            .map(|_| ())
         }
         ("verify-from-image", Some(sub_m)) => {
             let executable_path = sub_m.value_of("executable-path-in-image").unwrap();
@@ -378,10 +1657,11 @@ async fn main() -> anyhow::Result<()> {
                 executable_path.to_string(),
                 image.to_string(),
                 matches.value_of("url").map(|s| s.to_string()),
+                commitment,
                 Pubkey::try_from(program_id)?,
                 current_dir,
-                &mut temp_dir,
-                &mut container_id,
+                sub_m.value_of("repo-url"),
+                sub_m.value_of("commit-hash"),
             )
         }
         ("get-executable-hash", Some(sub_m)) => {
@@ -390,151 +1670,863 @@ async fn main() -> anyhow::Result<()> {
             println!("{}", program_hash);
             Ok(())
         }
+        ("check-features", Some(sub_m)) => {
+            let filepath = sub_m.value_of("filepath").unwrap();
+            let program = std::fs::read(filepath)
+                .map_err(|e| anyhow!("Failed to read {}: {}", filepath, e))?;
+            if print_cluster_features(&connection, &program)? {
+                Ok(())
+            } else {
+                Err(anyhow!(
+                    "Program uses syscalls that are not active on the cluster"
+                ))
+            }
+        }
         ("get-buffer-hash", Some(sub_m)) => {
+            let url = matches.value_of("url").map(|s| s.to_string());
+            let buffer_hash = match sub_m.value_of("account") {
+                Some(account) => {
+                    let data_offset = sub_m
+                        .value_of("data-offset")
+                        .unwrap_or("0")
+                        .parse::<usize>()
+                        .map_err(|e| anyhow!("Invalid --data-offset: {}", e))?;
+                    let data_length = sub_m
+                        .value_of("data-length")
+                        .map(|s| s.parse::<usize>())
+                        .transpose()
+                        .map_err(|e| anyhow!("Invalid --data-length: {}", e))?;
+                    get_account_executable_hash(
+                        url,
+                        commitment,
+                        Pubkey::try_from(account)?,
+                        data_offset,
+                        data_length,
+                    )?
+                }
+                None => get_buffer_hash(
+                    url,
+                    commitment,
+                    Pubkey::try_from(sub_m.value_of("buffer-address").unwrap())?,
+                )?,
+            };
+            println!("{}", buffer_hash);
+            Ok(())
+        }
+        ("verify-idl", Some(sub_m)) => {
+            let program_id = sub_m.value_of("program-id").unwrap();
+            verify_idl(
+                &connection,
+                Pubkey::try_from(program_id)?,
+                sub_m.value_of("signer").map(|s| s.to_string()),
+            )
+            .await
+        }
+        ("compare-buffer", Some(sub_m)) => {
             let buffer_address = sub_m.value_of("buffer-address").unwrap();
-            let buffer_hash = get_buffer_hash(
-                matches.value_of("url").map(|s| s.to_string()),
-                Pubkey::try_from(buffer_address)?,
+            compare_buffer(
+                &connection,
+                &Pubkey::try_from(buffer_address)?,
+                sub_m.value_of("executable").unwrap(),
             )?;
-            println!("{}", buffer_hash);
             Ok(())
         }
         ("get-program-hash", Some(sub_m)) => {
             let program_id = sub_m.value_of("program-id").unwrap();
-            let program_hash = get_program_hash(&connection, Pubkey::try_from(program_id)?)?;
+            let program_id = Pubkey::try_from(program_id)?;
+            let program_hash = get_program_hash(&connection, program_id)?;
             println!("{}", program_hash);
+            print_program_data_info(&connection, program_id);
+            if sub_m.is_present("check-pdas") {
+                check_pda_coverage(&connection, program_id, &program_hash).await?;
+            }
             Ok(())
         }
         ("verify-from-repo", Some(sub_m)) => {
-            let skip_build = sub_m.is_present("skip-build");
+            if sub_m.is_present("skip-build") {
+                return Err(anyhow!("--skip-build has been replaced by `solana-verify upload-only`, which uploads params from a verified params file (--from-artifact), another signer's PDA (--from-pda) or explicitly trusted arguments (--repo-url and --commit-hash)"));
+            }
+            let phases = VerifyPhases {
+                skip_clone: sub_m.value_of("skip-clone").map(PathBuf::from),
+                copy_source: sub_m.is_present("copy-source"),
+                skip_hash_check: sub_m.is_present("skip-hash-check"),
+                only_build: sub_m.is_present("only-build"),
+                audit_deps: sub_m.is_present("audit-deps"),
+                downgrade_lockfile: sub_m.is_present("downgrade-lockfile"),
+                attest: sub_m.is_present("attest"),
+                attestation_path: sub_m.value_of("attestation-out").map(PathBuf::from),
+                buffer: sub_m
+                    .value_of("buffer")
+                    .map(Pubkey::from_str)
+                    .transpose()
+                    .map_err(|e| anyhow!("Invalid --buffer: {}", e))?,
+                explain_mismatch: sub_m.is_present("explain-mismatch"),
+                dry_run: sub_m.is_present("dry-run"),
+            };
             let remote = sub_m.is_present("remote");
             let mount_path = sub_m.value_of("mount-path").map(|s| s.to_string()).unwrap();
             let repo_url = sub_m.value_of("repo-url").map(|s| s.to_string()).unwrap();
-            let program_id = sub_m.value_of("program-id").unwrap();
             let base_image = sub_m.value_of("base-image").map(|s| s.to_string());
             let library_name = sub_m.value_of("library-name").map(|s| s.to_string());
-            let bpf_flag = sub_m.is_present("bpf");
+            let bpf_flag = bpf_arg(sub_m);
             let current_dir = sub_m.is_present("current-dir");
             let skip_prompt = sub_m.is_present("skip-prompt");
-            let path_to_keypair = sub_m.value_of("keypair").map(|s| s.to_string());
-            let compute_unit_price = matches
-                .value_of("compute-unit-price")
-                .unwrap()
-                .parse::<u64>()
-                .unwrap_or(100000);
+            let path_to_keypair = keypair_arg(sub_m)?;
+            let params_file = sub_m.value_of("write-params").map(|s| s.to_string());
+            let stale_rebuilds = if sub_m.is_present("rebuild-if-stale") {
+                MAX_STALE_REBUILDS
+            } else {
+                0
+            };
+            let keep_temp_on_failure = sub_m.is_present("keep-temp-on-failure");
+            let report_path = sub_m.value_of("report").map(|s| s.to_string());
+            let compute_unit_price = compute_unit_price_arg(&matches)?;
             let cargo_args: Vec<String> = sub_m
                 .values_of("cargo-args")
                 .unwrap_or_default()
                 .map(|s| s.to_string())
                 .collect();
 
-            let commit_hash = get_commit_hash(sub_m, &repo_url)?;
+            let commit_hash = match &phases.skip_clone {
+                Some(checkout) => {
+                    get_checkout_commit_hash(checkout, sub_m.value_of("commit-hash"))?
+                }
+                None => get_commit_hash(sub_m.value_of("commit-hash"), &repo_url).await?,
+            };
+
+            if sub_m.is_present("local-validator") {
+                return verify_on_local_validator(
+                    repo_url,
+                    Some(commit_hash),
+                    mount_path,
+                    library_name,
+                    base_image,
+                    bpf_flag.unwrap_or_default(),
+                    cargo_args,
+                )
+                .await;
+            }
 
-            println!("Skipping prompt: {}", skip_prompt);
+            let program_id = sub_m.value_of("program-id").unwrap();
+            debug!("Skipping prompt: {}", skip_prompt);
             verify_from_repo(
-                remote,
-                mount_path,
                 &connection,
+                VerifyOptions {
+                    repo_url,
+                    commit_hash: Some(commit_hash),
+                    program_id: Pubkey::try_from(program_id)?,
+                    mount_path,
+                    library_name,
+                    base_image,
+                    bpf: bpf_flag,
+                    cargo_args,
+                    remote,
+                    current_dir,
+                    skip_prompt,
+                    keypair: path_to_keypair,
+                    compute_unit_price,
+                    phases,
+                    stale_rebuilds,
+                    keep_temp_on_failure,
+                    params_file,
+                    report_path,
+                },
+            )
+            .await
+            .map(|outcome| outcome.print())
+        }
+        ("verify-all", Some(sub_m)) => {
+            let manifest = VerifyManifest::load(Path::new(sub_m.value_of("manifest").unwrap()))?;
+            let repo_url = sub_m
+                .value_of("repo-url")
+                .or(manifest.repo_url.as_deref())
+                .ok_or_else(|| anyhow!("Pass the repository URL or set repo-url in the manifest"))?
+                .to_string();
+            let commit_hash = get_commit_hash(
+                sub_m
+                    .value_of("commit-hash")
+                    .or(manifest.commit_hash.as_deref()),
+                &repo_url,
+            )
+            .await?;
+            let results = verify_all(
+                &manifest,
+                &repo_url,
+                commit_hash,
+                &connection,
+                sub_m.is_present("current-dir"),
+            )?;
+            verify_all::print_summary(&results);
+            let failed = results.iter().filter(|result| !result.matches()).count();
+            if failed > 0 {
+                Err(anyhow!("{} program(s) did not verify", failed))
+            } else {
+                Ok(())
+            }
+        }
+        ("verify-suite", Some(sub_m)) => {
+            let manifest = SuiteManifest::load(Path::new(sub_m.value_of("manifest").unwrap()))?;
+            let results =
+                verify_suite(&manifest, &connection, sub_m.is_present("current-dir")).await?;
+            print_suite_summary(&results);
+            if let Some(report) = sub_m.value_of("report") {
+                let written = write_suite_report(&results, Path::new(report))?;
+                info!("Trust report written to {}", written.display());
+            }
+            let failed = results
+                .iter()
+                .filter(|result| !result.verification.matches())
+                .count();
+            if failed > 0 {
+                Err(anyhow!("{} program(s) of the suite did not verify", failed))
+            } else {
+                Ok(())
+            }
+        }
+        ("compare-repos", Some(sub_m)) => {
+            let commit = sub_m.value_of("commit");
+            let program = ManifestProgram {
+                library_name: sub_m.value_of("library-name").map(|s| s.to_string()),
+                mount_path: sub_m.value_of("mount-path").unwrap().to_string(),
+                base_image: sub_m.value_of("base-image").map(|s| s.to_string()),
+                bpf: sub_m.is_present("bpf").then_some(true),
+                cargo_args: sub_m
+                    .values_of("cargo-args")
+                    .unwrap_or_default()
+                    .map(|s| s.to_string())
+                    .collect(),
+            };
+            let comparison = compare_repos(
+                sub_m.value_of("program-id").unwrap(),
+                (
+                    sub_m.value_of("repo-a").unwrap(),
+                    sub_m.value_of("commit-a").or(commit),
+                ),
+                (
+                    sub_m.value_of("repo-b").unwrap(),
+                    sub_m.value_of("commit-b").or(commit),
+                ),
+                &program,
+                &connection,
+                sub_m.is_present("current-dir"),
+            )
+            .await?;
+            print_comparison(&comparison);
+            if comparison.a.verification.matches() || comparison.b.verification.matches() {
+                Ok(())
+            } else {
+                Err(anyhow!(
+                    "Neither repository reproduces the on-chain program"
+                ))
+            }
+        }
+        ("verify-attestation", Some(sub_m)) => {
+            let attestation = Attestation::load(Path::new(sub_m.value_of("attestation").unwrap()))?;
+            attestation.print();
+            attestation.verify()?;
+            if let Some(signer) = sub_m.value_of("signer") {
+                if signer != attestation.signer {
+                    return Err(anyhow!(
+                        "The attestation is signed by {}, not {}",
+                        attestation.signer,
+                        signer
+                    ));
+                }
+            }
+            println!("Signature is valid ✅");
+            Ok(())
+        }
+        ("profile", Some(sub_m)) => {
+            let store_path = ProfileStore::default_path()?;
+            match sub_m.subcommand() {
+                ("add", Some(sub_m)) => {
+                    let profile = Profile {
+                        program_id: Pubkey::from_str(sub_m.value_of("program-id").unwrap())?
+                            .to_string(),
+                        repo_url: sub_m.value_of("repo").unwrap().to_string(),
+                        commit_hash: sub_m.value_of("commit-hash").map(|s| s.to_string()),
+                        mount_path: sub_m.value_of("mount-path").map(|s| s.to_string()),
+                        library_name: sub_m.value_of("library-name").map(|s| s.to_string()),
+                        base_image: sub_m.value_of("base-image").map(|s| s.to_string()),
+                        bpf: sub_m.is_present("bpf"),
+                        cargo_args: sub_m
+                            .values_of("cargo-args")
+                            .unwrap_or_default()
+                            .map(|s| s.to_string())
+                            .collect(),
+                        url: sub_m.value_of("profile-url").map(|s| s.to_string()),
+                        keypair: sub_m.value_of("keypair").map(|s| s.to_string()),
+                    };
+                    add_profile(
+                        &store_path,
+                        sub_m.value_of("name").unwrap(),
+                        profile,
+                        sub_m.is_present("force"),
+                    )
+                }
+                ("list", Some(_)) => list_profiles(&store_path),
+                ("remove", Some(sub_m)) => {
+                    remove_profile(&store_path, sub_m.value_of("name").unwrap())
+                }
+                _ => unreachable!(),
+            }
+        }
+        ("verify", Some(sub_m)) => {
+            let name = sub_m.value_of("name").unwrap();
+            let profile = ProfileStore::load(&ProfileStore::default_path()?)?
+                .get(name)?
+                .clone();
+            let profile_connection = match (&profile.url, matches.value_of("url")) {
+                (Some(url), None) => {
+                    let profile_connection = get_client(Some(url.clone()), commitment);
+                    register_rpc_url(&profile_connection.url());
+                    Some(profile_connection)
+                }
+                _ => None,
+            };
+            let connection = profile_connection.as_ref().unwrap_or(&connection);
+            println!(
+                "Verifying profile {} against {}",
+                name,
+                redact_rpc_url(&connection.url())
+            );
+
+            let commit_hash = get_commit_hash(
+                sub_m
+                    .value_of("commit-hash")
+                    .or(profile.commit_hash.as_deref()),
+                &profile.repo_url,
+            )
+            .await?;
+            let compute_unit_price = compute_unit_price_arg(&matches)?;
+            verify_from_repo(
+                connection,
+                VerifyOptions {
+                    repo_url: profile.repo_url,
+                    commit_hash: Some(commit_hash),
+                    program_id: Pubkey::from_str(&profile.program_id)?,
+                    mount_path: profile.mount_path.unwrap_or_default(),
+                    library_name: profile.library_name,
+                    base_image: profile.base_image,
+                    bpf: profile.bpf.then_some(true),
+                    cargo_args: profile.cargo_args,
+                    current_dir: sub_m.is_present("current-dir"),
+                    skip_prompt: sub_m.is_present("skip-prompt"),
+                    keypair: profile.keypair,
+                    compute_unit_price,
+                    report_path: sub_m.value_of("report").map(|s| s.to_string()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .map(|outcome| outcome.print())
+        }
+        ("verify-from-archive", Some(sub_m)) => {
+            let source = match (sub_m.value_of("archive-url"), sub_m.value_of("from-pda")) {
+                (Some(url), _) => ArchiveSource::Url {
+                    url: url.to_string(),
+                    sha256: sub_m.value_of("archive-sha256").unwrap().to_string(),
+                },
+                (None, Some(signer)) => ArchiveSource::Pda(Pubkey::from_str(signer)?),
+                (None, None) => ArchiveSource::Crate {
+                    name: sub_m.value_of("crate").unwrap().to_string(),
+                    version: sub_m.value_of("crate-version").unwrap().to_string(),
+                },
+            };
+            let compute_unit_price = compute_unit_price_arg(&matches)?;
+            verify_from_archive(
+                &connection,
+                source,
+                Pubkey::from_str(sub_m.value_of("program-id").unwrap())?,
+                sub_m.value_of("mount-path").unwrap().to_string(),
+                sub_m.value_of("library-name").map(|s| s.to_string()),
+                sub_m.value_of("base-image").map(|s| s.to_string()),
+                sub_m.is_present("bpf"),
+                sub_m
+                    .values_of("cargo-args")
+                    .unwrap_or_default()
+                    .map(|s| s.to_string())
+                    .collect(),
+                sub_m.is_present("skip-prompt"),
+                keypair_arg(sub_m)?,
+                compute_unit_price,
+            )
+            .await
+        }
+        ("find-commit", Some(sub_m)) => {
+            let program_id = sub_m.value_of("program-id").unwrap();
+            let repo_url = sub_m.value_of("repo").unwrap();
+            let max_commits = sub_m
+                .value_of("max-commits")
+                .map(|s| s.parse::<usize>())
+                .transpose()
+                .map_err(|e| anyhow!("Invalid --max-commits: {}", e))?;
+            let cargo_args: Vec<String> = sub_m
+                .values_of("cargo-args")
+                .unwrap_or_default()
+                .map(|s| s.to_string())
+                .collect();
+            find_commit(
+                &connection,
+                Pubkey::try_from(program_id)?,
                 repo_url,
-                Some(commit_hash),
+                sub_m.value_of("range"),
+                max_commits,
+                sub_m.value_of("mount-path").unwrap(),
+                sub_m.value_of("library-name").map(|s| s.to_string()),
+                sub_m.value_of("base-image").map(|s| s.to_string()),
+                sub_m.is_present("bpf"),
+                cargo_args,
+            )
+            .await?;
+            Ok(())
+        }
+        ("compare-commits", Some(sub_m)) => {
+            let program_id = sub_m.value_of("program-id").unwrap();
+            let cargo_args: Vec<String> = sub_m
+                .values_of("cargo-args")
+                .unwrap_or_default()
+                .map(|s| s.to_string())
+                .collect();
+            compare_commits(
+                &connection,
                 Pubkey::try_from(program_id)?,
-                base_image,
-                library_name,
-                bpf_flag,
+                sub_m.value_of("repo").unwrap(),
+                [
+                    sub_m.value_of("commit-a").unwrap(),
+                    sub_m.value_of("commit-b").unwrap(),
+                ],
+                sub_m.value_of("mount-path").unwrap(),
+                sub_m.value_of("library-name").map(|s| s.to_string()),
+                sub_m.value_of("base-image").map(|s| s.to_string()),
+                sub_m.is_present("bpf"),
                 cargo_args,
-                current_dir,
+            )
+        }
+        ("deploy-verified", Some(sub_m)) => {
+            let program_id = sub_m.value_of("program-id").unwrap();
+            let upgrade_authority = sub_m
+                .value_of("upgrade-authority")
+                .map(Pubkey::from_str)
+                .transpose()?;
+            let encoding = match sub_m.value_of("encoding").unwrap() {
+                "base58" => UiTransactionEncoding::Base58,
+                "base64" => UiTransactionEncoding::Base64,
+                _ => unreachable!(),
+            };
+            let cargo_args = sub_m
+                .values_of("cargo-args")
+                .unwrap_or_default()
+                .map(|s| s.to_string())
+                .collect();
+            deploy_verified(
+                &connection,
+                Pubkey::try_from(program_id)?,
+                sub_m.value_of_os("mount-directory").map(PathBuf::from),
+                sub_m.value_of("library-name").unwrap().to_string(),
+                sub_m.value_of("base-image").map(|s| s.to_string()),
+                sub_m.is_present("bpf"),
+                cargo_args,
+                sub_m.value_of("buffer").map(|s| s.to_string()),
+                sub_m.value_of("keypair").map(|s| s.to_string()),
+                upgrade_authority,
+                encoding,
+            )
+        }
+        ("close", Some(sub_m)) => {
+            let program_id = sub_m.value_of("program-id").unwrap();
+            let compute_unit_price = compute_unit_price_arg(&matches)?;
+            process_close(
+                Pubkey::try_from(program_id)?,
+                &connection,
+                keypair_arg(sub_m)?,
+                compute_unit_price,
+            )
+            .await
+        }
+        ("upload-pda", Some(sub_m)) => {
+            let params_file = sub_m.value_of("from-file").unwrap();
+            let program_ids = sub_m
+                .values_of("program-id")
+                .unwrap_or_default()
+                .map(Pubkey::from_str)
+                .collect::<Result<Vec<_>, _>>()?;
+            let skip_prompt = sub_m.is_present("skip-prompt");
+            let path_to_keypair = keypair_arg(sub_m)?;
+            let compute_unit_price = compute_unit_price_arg(&matches)?;
+            upload_params_file(
+                params_file,
+                program_ids,
+                &connection,
                 skip_prompt,
                 path_to_keypair,
                 compute_unit_price,
-                skip_build,
-                &mut container_id,
-                &mut temp_dir,
-                &check_signal,
+                sub_m.is_present("allow-unsigned"),
             )
             .await
         }
-        ("close", Some(sub_m)) => {
+        ("upload-only", Some(sub_m)) => {
+            let program_id = Pubkey::from_str(sub_m.value_of("program-id").unwrap())?;
+            let source = if let Some(path) = sub_m.value_of("from-artifact") {
+                UploadSource::Artifact {
+                    path: path.to_string(),
+                    allow_unsigned: sub_m.is_present("allow-unsigned"),
+                }
+            } else if let Some(signer) = sub_m.value_of("from-pda") {
+                UploadSource::Pda(Pubkey::from_str(signer)?)
+            } else {
+                UploadSource::TrustedArgs {
+                    repo_url: sub_m.value_of("repo-url").unwrap().to_string(),
+                    commit_hash: sub_m.value_of("commit-hash").unwrap().to_string(),
+                    mount_path: sub_m.value_of("mount-path").unwrap().to_string(),
+                    library_name: sub_m.value_of("library-name").map(|s| s.to_string()),
+                    base_image: sub_m.value_of("base-image").map(|s| s.to_string()),
+                    bpf_flag: sub_m.is_present("bpf"),
+                    cargo_args: sub_m
+                        .values_of("cargo-args")
+                        .unwrap_or_default()
+                        .map(|s| s.to_string())
+                        .collect(),
+                }
+            };
+            let compute_unit_price = compute_unit_price_arg(&matches)?;
+            upload_only(
+                &connection,
+                program_id,
+                source,
+                sub_m.is_present("skip-prompt"),
+                keypair_arg(sub_m)?,
+                compute_unit_price,
+            )
+            .await
+        }
+        ("publish-release", Some(sub_m)) => {
+            let token = match sub_m.value_of("token") {
+                Some(token) => token.to_string(),
+                None => std::env::var("GITHUB_TOKEN").map_err(|_| {
+                    anyhow!("Pass --token or set GITHUB_TOKEN to publish a release")
+                })?,
+            };
+            let uploader = sub_m
+                .value_of("uploader")
+                .map(Pubkey::from_str)
+                .transpose()?;
+            publish_release(
+                &connection,
+                sub_m.value_of("tag").unwrap(),
+                sub_m.value_of("executable").unwrap(),
+                sub_m.value_of("params-file").unwrap(),
+                uploader,
+                &token,
+            )
+            .await
+        }
+        ("record-in-repo", Some(sub_m)) => {
+            let token = match sub_m.value_of("token") {
+                Some(token) => token.to_string(),
+                None => std::env::var("GITHUB_TOKEN").map_err(|_| {
+                    anyhow!("Pass --token or set GITHUB_TOKEN to record the verification")
+                })?,
+            };
+            record_in_repo(
+                sub_m.value_of("params-file").unwrap(),
+                sub_m.value_of("branch").map(|s| s.to_string()),
+                sub_m.value_of("base").map(|s| s.to_string()),
+                sub_m.value_of("file").unwrap(),
+                &token,
+            )
+            .await
+        }
+        ("push-artifact", Some(sub_m)) => push_artifact(
+            sub_m.value_of("repository").unwrap(),
+            sub_m.value_of("executable").unwrap(),
+            sub_m.value_of("params-file").unwrap(),
+            sub_m.value_of("report"),
+        )
+        .map(|_| ()),
+        ("fetch-artifact", Some(sub_m)) => fetch_artifact(
+            sub_m.value_of("repository").unwrap(),
+            sub_m.value_of("program-id").unwrap(),
+            sub_m.value_of("commit-hash").unwrap(),
+            sub_m.value_of("output-dir").unwrap(),
+        )
+        .map(|_| ()),
+        ("revoke", Some(sub_m)) => {
             let program_id = sub_m.value_of("program-id").unwrap();
-            let compute_unit_price = matches
-                .value_of("compute-unit-price")
-                .unwrap()
-                .parse::<u64>()
-                .unwrap_or(100000);
-            process_close(
+            let reason = sub_m.value_of("reason").map(|s| s.to_string());
+            let skip_prompt = sub_m.is_present("skip-prompt");
+            let path_to_keypair = keypair_arg(sub_m)?;
+            let compute_unit_price = compute_unit_price_arg(&matches)?;
+            process_revoke(
                 Pubkey::try_from(program_id)?,
                 &connection,
+                skip_prompt,
+                path_to_keypair,
+                reason,
                 compute_unit_price,
             )
             .await
         }
         ("export-pda-tx", Some(sub_m)) => {
-            let uploader = sub_m.value_of("uploader").unwrap();
+            let uploader = signers::resolve_address(
+                sub_m.value_of("uploader"),
+                sub_m.value_of("signer-name"),
+            )?;
             let mount_path = sub_m.value_of("mount-path").map(|s| s.to_string()).unwrap();
             let repo_url = sub_m.value_of("repo-url").map(|s| s.to_string()).unwrap();
             let program_id = sub_m.value_of("program-id").unwrap();
             let base_image = sub_m.value_of("base-image").map(|s| s.to_string());
-            let library_name = sub_m.value_of("library-name").map(|s| s.to_string());
-            let bpf_flag = sub_m.is_present("bpf");
-            let encoding = sub_m.value_of("encoding").unwrap();
-
-            let encoding: UiTransactionEncoding = match encoding {
-                "base58" => UiTransactionEncoding::Base58,
-                "base64" => UiTransactionEncoding::Base64,
-                _ => {
-                    return Err(anyhow!("Unsupported encoding: {}", encoding));
-                }
-            };
+            let library_name = sub_m.value_of("library-name").map(|s| s.to_string());
+            let bpf_flag = sub_m.is_present("bpf");
+            let encoding = TxExportFormat::from_str(sub_m.value_of("encoding").unwrap())?;
+            let blockhash = BlockhashSource::new(
+                sub_m.value_of("blockhash").unwrap(),
+                sub_m
+                    .value_of("nonce-account")
+                    .map(Pubkey::from_str)
+                    .transpose()?,
+                sub_m
+                    .value_of("nonce-authority")
+                    .map(Pubkey::from_str)
+                    .transpose()?
+                    .unwrap_or(uploader),
+            )?;
+            blockhash.check_format(encoding)?;
 
-            let compute_unit_price = matches
-                .value_of("compute-unit-price")
-                .unwrap()
-                .parse::<u64>()
-                .unwrap_or(100000);
+            let compute_unit_price = compute_unit_price_arg(&matches)?;
 
-            let commit_hash = get_commit_hash(sub_m, &repo_url)?;
+            let commit_hash = get_commit_hash(sub_m.value_of("commit-hash"), &repo_url).await?;
             let cargo_args: Vec<String> = sub_m
                 .values_of("cargo-args")
                 .unwrap_or_default()
                 .map(|s| s.to_string())
                 .collect();
 
-            let connection = resolve_rpc_url(matches.value_of("url").map(|s| s.to_string()))?;
-            println!("Using connection url: {}", connection.url());
+            let connection = get_client(None, commitment);
+            info!(
+                "Using connection url: {}",
+                redact_rpc_url(&connection.url())
+            );
 
             export_pda_tx(
                 &connection,
                 Pubkey::try_from(program_id)?,
-                Pubkey::try_from(uploader)?,
+                uploader,
                 repo_url,
                 commit_hash,
                 mount_path,
                 library_name,
                 base_image,
                 bpf_flag,
-                &mut temp_dir,
                 encoding,
+                blockhash,
                 cargo_args,
                 compute_unit_price,
+                sub_m.is_present("dry-run"),
             )
             .await
         }
+        ("partial-sign", Some(sub_m)) => {
+            let authority = signers::resolve_address(
+                sub_m.value_of("uploader"),
+                sub_m.value_of("signer-name"),
+            )?;
+            let fee_payer = get_signer_from_path_or_config(
+                sub_m.value_of("keypair").map(|s| s.to_string()).as_ref(),
+            )?;
+            let blockhash = BlockhashSource::new(
+                sub_m.value_of("blockhash").unwrap(),
+                sub_m
+                    .value_of("nonce-account")
+                    .map(Pubkey::from_str)
+                    .transpose()?,
+                fee_payer.try_pubkey()?,
+            )?;
+            let program_id = Pubkey::from_str(sub_m.value_of("program-id").unwrap())?;
+            let repo_url = sub_m.value_of("repo-url").unwrap().to_string();
+            let commit_hash = get_commit_hash(sub_m.value_of("commit-hash"), &repo_url).await?;
+            let compute_unit_price = compute_unit_price_arg(&matches)?;
+
+            let input_params = resolve_upload_params(
+                &connection,
+                program_id,
+                repo_url,
+                commit_hash,
+                sub_m.value_of("mount-path").unwrap().to_string(),
+                sub_m.value_of("library-name").map(|s| s.to_string()),
+                sub_m.value_of("base-image").map(|s| s.to_string()),
+                sub_m.is_present("bpf"),
+                sub_m
+                    .values_of("cargo-args")
+                    .unwrap_or_default()
+                    .map(|s| s.to_string())
+                    .collect(),
+            )
+            .await?;
+            let upload = partial_sign(
+                &connection,
+                &input_params,
+                program_id,
+                authority,
+                fee_payer.as_ref(),
+                blockhash,
+                compute_unit_price,
+            )?;
+            let path = upload.write(sub_m.value_of("out").unwrap())?;
+            println!(
+                "Partially signed upload written to {}. Send it to {} to run `solana-verify countersign {}`",
+                path.display(),
+                authority,
+                path.display()
+            );
+            match upload.last_valid_block_height {
+                Some(height) => println!(
+                    "The upload expires at block height {}, in about 90 seconds",
+                    height
+                ),
+                None => println!("The upload is valid until the nonce account is advanced"),
+            }
+            Ok(())
+        }
+        ("countersign", Some(sub_m)) => {
+            let upload = PartiallySignedUpload::read(sub_m.value_of("upload").unwrap())?;
+            let authority = get_signer_from_path_or_config(keypair_arg(sub_m)?.as_ref())?;
+            countersign(
+                &connection,
+                &upload,
+                authority.as_ref(),
+                sub_m.is_present("skip-prompt"),
+            )?;
+            Ok(())
+        }
+        ("refresh-tx", Some(sub_m)) => {
+            let transaction = sub_m.value_of("transaction").unwrap();
+            let transaction = if Path::new(transaction).is_file() {
+                std::fs::read_to_string(transaction)?
+            } else {
+                transaction.to_string()
+            };
+            println!("{}", refresh_transaction(&connection, &transaction)?);
+            Ok(())
+        }
+        ("inspect", Some(sub_m)) => {
+            let program_id = sub_m.value_of("program-id").unwrap();
+            inspect(&connection, Pubkey::try_from(program_id)?).await
+        }
+        ("list-uploader-pdas", Some(sub_m)) => {
+            let signer = sub_m.value_of("signer").unwrap();
+            list_uploader_pdas(Pubkey::from_str(signer)?, &connection).await
+        }
         ("list-program-pdas", Some(sub_m)) => {
             let program_id = sub_m.value_of("program-id").unwrap();
-            list_program_pdas(Pubkey::try_from(program_id)?, &connection).await
+            let signers = sub_m
+                .values_of("signer")
+                .unwrap_or_default()
+                .map(Pubkey::from_str)
+                .collect::<Result<Vec<_>, _>>()?;
+            list_program_pdas(Pubkey::try_from(program_id)?, &signers, &connection).await
         }
         ("get-program-pda", Some(sub_m)) => {
             let program_id = sub_m.value_of("program-id").unwrap();
             let signer = sub_m.value_of("signer").map(|s| s.to_string());
-            print_program_pda(Pubkey::try_from(program_id)?, signer, &connection).await
+            if sub_m.is_present("api-only") {
+                let signer = signer.as_deref().map(Pubkey::from_str).transpose()?;
+                get_remote_status(Pubkey::try_from(program_id)?, signer, false, false).await
+            } else {
+                print_program_pda(Pubkey::try_from(program_id)?, signer, &connection).await
+            }
+        }
+        ("status", Some(sub_m)) => {
+            let program_id = sub_m.value_of("program-id").unwrap();
+            if sub_m.is_present("api-only") {
+                get_api_status(Pubkey::try_from(program_id)?).await
+            } else {
+                print_trusted_verification(Pubkey::try_from(program_id)?, &connection).await
+            }
+        }
+        ("badge", Some(sub_m)) => {
+            let program_id = sub_m.value_of("program-id").unwrap();
+            generate_badge(
+                Pubkey::try_from(program_id)?,
+                Path::new(sub_m.value_of("out").unwrap()),
+            )
+            .await
+        }
+        ("daemon", Some(sub_m)) => {
+            run_daemon(&connection, Path::new(sub_m.value_of("config").unwrap())).await
+        }
+        ("example", Some(sub_m)) => match sub_m.subcommand() {
+            ("new", Some(sub_m)) => new_example(
+                Path::new(sub_m.value_of("dir").unwrap()),
+                sub_m.value_of("name"),
+            )
+            .map(|_| ()),
+            _ => unreachable!(),
+        },
+        ("serve", Some(sub_m)) => run_service(&connection, sub_m.value_of("listen").unwrap()).await,
+        ("cache", Some(sub_m)) => match sub_m.subcommand() {
+            ("stats", Some(_)) => print_cache_stats(),
+            ("prune", Some(sub_m)) => {
+                let older_than = sub_m
+                    .value_of("older-than-days")
+                    .map(|days| days.parse::<u32>())
+                    .transpose()
+                    .map_err(|e| anyhow!("Invalid --older-than-days: {}", e))?
+                    .map(|days| chrono::Duration::days(days.into()));
+                let max_size = sub_m
+                    .value_of("max-size-gb")
+                    .map(|gb| match gb.parse::<f64>() {
+                        Ok(gb) if gb.is_finite() && gb > 0.0 => Ok((gb * 1e9) as u64),
+                        Ok(_) => Err(anyhow!(
+                            "Invalid --max-size-gb {}: expected a positive size",
+                            gb
+                        )),
+                        Err(e) => Err(anyhow!("Invalid --max-size-gb {}: {}", gb, e)),
+                    })
+                    .transpose()?;
+                prune_cache(older_than, max_size)
+            }
+            _ => unreachable!(),
+        },
+        ("registry", Some(sub_m)) => match sub_m.subcommand() {
+            ("history", Some(sub_m)) => {
+                let program_id = Pubkey::from_str(sub_m.value_of("program-id").unwrap())?;
+                let out = Path::new(sub_m.value_of("out").unwrap());
+                // Fail on an unsupported format before fetching the history
+                HistoryFormat::from_path(out)?;
+                let events = collect_history(&connection, &program_id).await?;
+                let written = write_history(&events, out)?;
+                info!("Wrote {} event(s) to {}", events.len(), written.display());
+                Ok(())
+            }
+            _ => unreachable!(),
+        },
+        ("wait-for-authority-tx", Some(sub_m)) => {
+            let uploader = sub_m
+                .value_of("uploader")
+                .map(Pubkey::from_str)
+                .transpose()?;
+            let poll_interval = sub_m.value_of("poll-interval").unwrap().parse::<u64>()?;
+            wait_for_authority_tx(
+                &connection,
+                Pubkey::from_str(sub_m.value_of("program-id").unwrap())?,
+                Pubkey::from_str(sub_m.value_of("proposal").unwrap())?,
+                uploader,
+                std::time::Duration::from_secs(poll_interval),
+            )
+            .await
         }
         ("remote", Some(sub_m)) => match sub_m.subcommand() {
             ("get-status", Some(sub_m)) => {
                 let program_id = sub_m.value_of("program-id").unwrap();
-                get_remote_status(Pubkey::try_from(program_id)?).await
+                let signer = sub_m.value_of("signer").map(Pubkey::try_from).transpose()?;
+                let only_verified = sub_m.is_present("only-verified");
+                let json = sub_m.is_present("json");
+                get_remote_status(Pubkey::try_from(program_id)?, signer, only_verified, json).await
             }
             ("get-job", Some(sub_m)) => {
                 let job_id = sub_m.value_of("job-id").unwrap();
-                get_remote_job(job_id).await
+                get_remote_job(job_id, sub_m.is_present("json")).await
             }
             ("submit-job", Some(sub_m)) => {
                 let program_id = sub_m.value_of("program-id").unwrap();
@@ -557,965 +2549,51 @@ async fn main() -> anyhow::Result<()> {
     };
 
     handle.close();
-    res
-}
-
-pub fn get_client(url: Option<String>) -> RpcClient {
-    let config = match CONFIG_FILE.as_ref() {
-        Some(config_file) => Config::load(config_file).unwrap_or_else(|_| {
-            println!("Failed to load config file: {}", config_file);
-            Config::default()
-        }),
-        None => Config::default(),
-    };
-    let url = &get_network(&url.unwrap_or(config.json_rpc_url)).to_string();
-    RpcClient::new(url)
-}
-
-fn get_commit_hash_from_remote(repo_url: &str) -> anyhow::Result<String> {
-    // Fetch the symbolic reference of the default branch
-    let output = Command::new("git")
-        .arg("ls-remote")
-        .arg("--symref")
-        .arg(repo_url)
-        .output()
-        .map_err(|e| anyhow::anyhow!("Failed to run git ls-remote: {}", e))?;
-
-    if !output.status.success() {
-        return Err(anyhow::anyhow!(
-            "Failed to fetch default branch information: {}",
-            String::from_utf8_lossy(&output.stderr)
-        ));
+    // Everything to clean up, including the containers and directories of other threads,
+    // is in the cleanup registry
+    if interrupted() {
+        warn!("Received interrupt signal, cleaning up...");
+        cleanup_all();
+        std::process::exit(130);
     }
-
-    // Find out if the branch is called master or main
-    let output_str = String::from_utf8(output.stdout)?;
-    let default_branch = output_str
-        .lines()
-        .find_map(|line| {
-            if line.starts_with("ref: refs/heads/") {
-                Some(
-                    line.trim_start_matches("ref: refs/heads/")
-                        .split_whitespace()
-                        .next()?
-                        .to_string(),
-                )
-            } else {
-                None
-            }
-        })
-        .ok_or_else(|| {
-            anyhow::anyhow!(
-                "Unable to determine default branch from remote repository '{}'",
-                repo_url
-            )
-        })?;
-
-    println!("Default branch detected: {}", default_branch);
-
-    // Fetch the latest commit hash for the default branch
-    let hash_output = Command::new("git")
-        .arg("ls-remote")
-        .arg(repo_url)
-        .arg(&default_branch)
-        .output()
-        .map_err(|e| anyhow::anyhow!("Failed to fetch commit hash for default branch: {}", e))?;
-
-    if !hash_output.status.success() {
-        return Err(anyhow::anyhow!(
-            "Failed to fetch commit hash: {}",
-            String::from_utf8_lossy(&hash_output.stderr)
-        ));
-    }
-
-    // Parse and return the commit hash
-    String::from_utf8(hash_output.stdout)?
-        .split_whitespace()
-        .next()
-        .map(|s| s.to_string())
-        .ok_or_else(|| anyhow::anyhow!("Failed to parse commit hash from git ls-remote output"))
-}
-
-pub fn get_binary_hash(program_data: Vec<u8>) -> String {
-    let buffer = program_data
-        .into_iter()
-        .rev()
-        .skip_while(|&x| x == 0)
-        .collect::<Vec<_>>()
-        .into_iter()
-        .rev()
-        .collect::<Vec<_>>();
-    sha256::digest(&buffer[..])
-}
-
-pub fn get_file_hash(filepath: &str) -> Result<String, std::io::Error> {
-    let mut f = std::fs::File::open(filepath)?;
-    let metadata = std::fs::metadata(filepath)?;
-    let mut buffer = vec![0; metadata.len() as usize];
-    f.read_exact(&mut buffer)?;
-    Ok(get_binary_hash(buffer))
-}
-
-pub fn get_buffer_hash(url: Option<String>, buffer_address: Pubkey) -> anyhow::Result<String> {
-    let client = get_client(url);
-    let offset = UpgradeableLoaderState::size_of_buffer_metadata();
-    let account_data = client.get_account_data(&buffer_address)?[offset..].to_vec();
-    let program_hash = get_binary_hash(account_data);
-    Ok(program_hash)
-}
-
-pub fn get_program_hash(client: &RpcClient, program_id: Pubkey) -> anyhow::Result<String> {
-    // First check if the program account exists
-    if client.get_account(&program_id).is_err() {
-        return Err(anyhow!("Program {} is not deployed", program_id));
-    }
-
-    let program_buffer =
-        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id()).0;
-
-    // Then check if the program data account exists
-    match client.get_account_data(&program_buffer) {
-        Ok(data) => {
-            let offset = UpgradeableLoaderState::size_of_programdata_metadata();
-            let account_data = data[offset..].to_vec();
-            let program_hash = get_binary_hash(account_data);
-            Ok(program_hash)
-        }
-        Err(_) => Err(anyhow!(
-            "Could not find program data for {}. This could mean:\n\
-             1. The program is not deployed\n\
-             2. The program is not upgradeable\n\
-             3. The program was deployed with a different loader",
-            program_id
-        )),
-    }
-}
-
-pub fn get_genesis_hash(client: &RpcClient) -> anyhow::Result<String> {
-    let genesis_hash = client.get_genesis_hash()?;
-    Ok(genesis_hash.to_string())
-}
-
-pub fn get_docker_resource_limits() -> Option<(String, String)> {
-    let memory = std::env::var("SVB_DOCKER_MEMORY_LIMIT").ok();
-    let cpus = std::env::var("SVB_DOCKER_CPU_LIMIT").ok();
-    if memory.is_some() || cpus.is_some() {
-        println!(
-            "Using docker resource limits: memory: {:?}, cpus: {:?}",
-            memory, cpus
-        );
-    } else {
-        // Print message to user that they can set these environment variables to limit docker resources
-        println!("No Docker resource limits are set.");
-        println!("You can set the SVB_DOCKER_MEMORY_LIMIT and SVB_DOCKER_CPU_LIMIT environment variables to limit Docker resources.");
-        println!("For example: SVB_DOCKER_MEMORY_LIMIT=2g SVB_DOCKER_CPU_LIMIT=2.");
+    print_rpc_usage();
+    if let Err(err) = &res {
+        let mut rpc_urls = vec![connection.url()];
+        rpc_urls.extend(endpoints().cross_check_rpcs.clone());
+        write_support_bundle(err, &rpc_urls);
     }
-    memory.zip(cpus)
+    let res = res.map_err(|err| anyhow!("{}", redact(&format!("{:#}", err))));
+    clear_secrets();
+    res
 }
-
-pub fn build(
-    mount_directory: Option<String>,
-    library_name: Option<String>,
-    base_image: Option<String>,
-    bpf_flag: bool,
-    cargo_args: Vec<String>,
-    container_id_opt: &mut Option<String>,
-) -> anyhow::Result<()> {
-    let mut mount_path = mount_directory.unwrap_or(
-        std::env::current_dir()?
-            .as_os_str()
-            .to_str()
-            .ok_or_else(|| anyhow::Error::msg("Invalid path string"))?
-            .to_string(),
-    );
-    mount_path = mount_path.trim_end_matches('/').to_string();
-    println!("Mounting path: {}", mount_path);
-
-    let lockfile = format!("{}/Cargo.lock", mount_path);
-    if !std::path::Path::new(&lockfile).exists() {
-        println!("Mount directory must contain a Cargo.lock file");
-        return Err(anyhow!(format!("No lockfile found at {}", lockfile)));
-    }
-
-    let build_command = if bpf_flag { "build-bpf" } else { "build-sbf" };
-
-    let (major, minor, patch) = get_pkg_version_from_cargo_lock("solana-program", &lockfile)?;
-
-    let mut solana_version: Option<String> = None;
-    let  image: String = base_image.unwrap_or_else(|| {
-        if bpf_flag {
-            // Use this for backwards compatibility with anchor verified builds
-            solana_version = Some("v1.13.5".to_string());
-            "projectserum/build@sha256:75b75eab447ebcca1f471c98583d9b5d82c4be122c470852a022afcf9c98bead".to_string()
-        } else if let Some(digest) = IMAGE_MAP.get(&(major, minor, patch)) {
-                println!("Found docker image for Solana version {}.{}.{}", major, minor, patch);
-                solana_version = Some(format!("v{}.{}.{}", major, minor, patch));
-                format!("solanafoundation/solana-verifiable-build@{}", digest)
-            } else {
-                println!("Unable to find docker image for Solana version {}.{}.{}", major, minor, patch);
-                let prev = IMAGE_MAP.range(..(major, minor, patch)).next_back();
-                let next = IMAGE_MAP.range((major, minor, patch)..).next();
-                let (version, digest) = if let Some((version, digest)) = prev {
-                    (version, digest)
-                } else if let Some((version, digest)) = next {
-                    (version, digest)
-                } else {
-                    println!("Unable to find backup docker image for Solana version {}.{}.{}", major, minor, patch);
-                    std::process::exit(1);
-                };
-                println!("Using backup docker image for Solana version {}.{}.{}", version.0, version.1, version.2);
-                solana_version = Some(format!("v{}.{}.{}", version.0, version.1, version.2));
-                format!("solanafoundation/solana-verifiable-build@{}", digest)
-            }
-    });
-
-    let mut manifest_path = None;
-
-    let relative_build_path = std::process::Command::new("find")
-        .args([&mount_path, "-name", "Cargo.toml"])
-        .output()
-        .map_err(|e| {
-            anyhow::format_err!(
-                "Failed to find Cargo.toml files in root directory: {}",
-                e.to_string()
+/// Price given with `--compute-unit-price`. 0 in auto mode, where each transaction is priced
+/// once its accounts are known.
+fn compute_unit_price_arg(matches: &ArgMatches) -> anyhow::Result<u64> {
+    match matches.value_of("compute-unit-price").unwrap() {
+        AUTO_COMPUTE_UNIT_PRICE => Ok(0),
+        price => price.parse::<u64>().map_err(|e| {
+            anyhow!(
+                "Invalid --compute-unit-price {}: expected micro-lamports or {}: {}",
+                price,
+                AUTO_COMPUTE_UNIT_PRICE,
+                e
             )
-        })
-        .and_then(|output| {
-            for p in String::from_utf8(output.stdout)?.split("\n") {
-                match get_lib_name_from_cargo_toml(p) {
-                    Ok(name) => {
-                        if name == library_name.clone().unwrap_or_default() {
-                            manifest_path = Some(p.to_string().replace(&mount_path, ""));
-                            return Ok(p
-                                .to_string()
-                                .replace("Cargo.toml", "")
-                                .replace(&mount_path, ""));
-                        }
-                    }
-                    Err(_) => {
-                        continue;
-                    }
-                }
-            }
-            Err(anyhow!("No Cargo.toml files found"))
-        })
-        .unwrap_or_else(|_| "".to_string());
-
-    let workdir = std::process::Command::new("docker")
-        .args(["run", "--rm", &image, "pwd"])
-        .stderr(Stdio::inherit())
-        .output()
-        .map_err(|e| anyhow::format_err!("Failed to get workdir: {}", e.to_string()))
-        .and_then(|output| parse_output(output.stdout))?;
-
-    println!("Workdir: {}", workdir);
-
-    let build_path = format!("{}/{}", workdir, relative_build_path);
-    println!("Building program at {}", build_path);
-
-    let manifest_path_filter = manifest_path
-        .clone()
-        .map(|m| vec!["--manifest-path".to_string(), format!("{}/{}", workdir, m)])
-        .unwrap_or_else(Vec::new);
-
-    if manifest_path.is_some() {
-        println!(
-            "Building manifest path: {}/{}",
-            workdir,
-            manifest_path.unwrap()
-        );
-    }
-
-    // change directory to program/build dir
-    let mount_params = format!("{}:{}", mount_path, workdir);
-    let container_id = {
-        let mut cmd = std::process::Command::new("docker");
-        cmd.args(["run", "--rm", "-v", &mount_params, "-dit"]);
-        cmd.stderr(Stdio::inherit());
-
-        if let Some((memory_limit, cpu_limit)) = get_docker_resource_limits() {
-            cmd.arg("--memory")
-                .arg(memory_limit)
-                .arg("--cpus")
-                .arg(cpu_limit);
-        }
-
-        let output = cmd
-            .args([&image, "bash"])
-            .output()
-            .map_err(|e| anyhow!("Docker build failed: {}", e.to_string()))?;
-
-        parse_output(output.stdout)?
-    };
-
-    // Set the container id so we can kill it later if the process is interrupted
-    container_id_opt.replace(container_id.clone());
-
-    // Solana v1.17 uses Rust 1.73, which defaults to the sparse registry, making
-    // this fetch unnecessary, but requires us to omit the "frozen" argument
-    let locked_args = if major == 1 && minor < 17 {
-        // First, we resolve the dependencies and cache them in the Docker container
-        // ARM processors running Linux have a bug where the build fails if the dependencies are not preloaded.
-        // Running the build without the pre-fetch will cause the container to run out of memory.
-        // This is a workaround for that issue.
-        std::process::Command::new("docker")
-            .args(["exec", &container_id])
-            .args([
-                "cargo",
-                "--config",
-                "net.git-fetch-with-cli=true",
-                "fetch",
-                "--locked",
-            ])
-            .stderr(Stdio::inherit())
-            .stdout(Stdio::inherit())
-            .output()?;
-        println!("Finished fetching build dependencies");
-
-        ["--frozen", "--locked"].as_slice()
-    } else {
-        // To be totally safe, force the build to use the sparse registry
-        [
-            "--config",
-            "registries.crates-io.protocol=\"sparse\"",
-            "--locked",
-        ]
-        .as_slice()
-    };
-
-    std::process::Command::new("docker")
-        .args(["exec", "-w", &build_path, &container_id])
-        .args(["cargo", build_command])
-        .args(["--"])
-        .args(locked_args)
-        .args(manifest_path_filter)
-        .args(cargo_args)
-        .stderr(Stdio::inherit())
-        .stdout(Stdio::inherit())
-        .output()?;
-
-    println!("Finished building program");
-    println!("Program Solana version: v{}.{}.{}", major, minor, patch);
-
-    if let Some(solana_version) = solana_version {
-        println!("Docker image Solana version: {}", solana_version);
-    }
-
-    if let Some(program_name) = library_name {
-        let executable_path = std::process::Command::new("find")
-            .args([
-                &format!("{}/target/deploy", mount_path),
-                "-name",
-                &format!("{}.so", program_name),
-            ])
-            .output()
-            .map_err(|e| anyhow!("Failed to find program: {}", e.to_string()))
-            .and_then(|output| parse_output(output.stdout))?;
-        let executable_hash = get_file_hash(&executable_path)?;
-        println!("{}", executable_hash);
-    }
-    std::process::Command::new("docker")
-        .args(["kill", &container_id])
-        .output()?;
-    Ok(())
-}
-
-pub fn verify_from_image(
-    executable_path: String,
-    image: String,
-    network: Option<String>,
-    program_id: Pubkey,
-    current_dir: bool,
-    temp_dir: &mut Option<String>,
-    container_id_opt: &mut Option<String>,
-) -> anyhow::Result<()> {
-    println!(
-        "Verifying image: {:?}, on network {:?} against program ID {}",
-        image, network, program_id
-    );
-    println!("Executable path in container: {:?}", executable_path);
-    println!(" ");
-
-    let workdir = std::process::Command::new("docker")
-        .args(["run", "--rm", &image, "pwd"])
-        .stderr(Stdio::inherit())
-        .output()
-        .map_err(|e| anyhow::format_err!("Failed to get workdir: {}", e.to_string()))
-        .and_then(|output| parse_output(output.stdout))?;
-
-    println!("Workdir: {}", workdir);
-
-    let container_id = {
-        let mut cmd = std::process::Command::new("docker");
-        cmd.args(["run", "--rm", "-dit"]);
-        cmd.stderr(Stdio::inherit());
-
-        if let Some((memory_limit, cpu_limit)) = get_docker_resource_limits() {
-            cmd.arg("--memory")
-                .arg(memory_limit)
-                .arg("--cpus")
-                .arg(cpu_limit);
-        }
-
-        let output = cmd
-            .args([&image])
-            .output()
-            .map_err(|e| anyhow!("Docker build failed: {}", e.to_string()))?;
-        parse_output(output.stdout)?
-    };
-
-    container_id_opt.replace(container_id.clone());
-
-    let uuid = Uuid::new_v4().to_string();
-
-    // Create a temporary directory to clone the repo into
-    let verify_dir = if current_dir {
-        format!(
-            "{}/.{}",
-            std::env::current_dir()?
-                .as_os_str()
-                .to_str()
-                .ok_or_else(|| anyhow::Error::msg("Invalid path string"))?,
-            uuid.clone()
-        )
-    } else {
-        "/tmp".to_string()
-    };
-
-    temp_dir.replace(verify_dir.clone());
-
-    let program_filepath = format!("{}/program.so", verify_dir);
-    std::process::Command::new("docker")
-        .args([
-            "cp",
-            format!("{}:{}/{}", container_id, workdir, executable_path).as_str(),
-            program_filepath.as_str(),
-        ])
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
-        .output()
-        .map_err(|e| anyhow::format_err!("Failed to copy executable file {}", e.to_string()))?;
-
-    let executable_hash: String = get_file_hash(program_filepath.as_str())?;
-    let client = get_client(network);
-    let program_buffer =
-        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id()).0;
-    let offset = UpgradeableLoaderState::size_of_programdata_metadata();
-    let account_data = &client.get_account_data(&program_buffer)?[offset..];
-    let program_hash = get_binary_hash(account_data.to_vec());
-    println!("Executable hash: {}", executable_hash);
-    println!("Program hash: {}", program_hash);
-
-    // Cleanup docker and rm file
-    std::process::Command::new("docker")
-        .args(["kill", container_id.as_str()])
-        .stderr(Stdio::inherit())
-        .output()
-        .map_err(|e| anyhow::format_err!("Docker kill failed: {}", e.to_string()))?;
-
-    std::process::Command::new("rm")
-        .args([program_filepath])
-        .stderr(Stdio::inherit())
-        .output()
-        .map_err(|e| {
-            anyhow::format_err!("Failed to remove temp program file: {}", e.to_string())
-        })?;
-
-    if program_hash != executable_hash {
-        println!("Executable hash mismatch");
-        return Err(anyhow::Error::msg("Executable hash mismatch"));
-    } else {
-        println!("Executable matches on-chain program data ✅");
-    }
-    Ok(())
-}
-
-fn build_args(
-    relative_mount_path: &str,
-    library_name_opt: Option<String>,
-    verify_tmp_root_path: &str,
-    base_image: Option<String>,
-    bpf_flag: bool,
-    cargo_args: Vec<String>,
-) -> anyhow::Result<(Vec<String>, String, String)> {
-    let mut args: Vec<String> = Vec::new();
-    if !relative_mount_path.is_empty() {
-        args.push("--mount-path".to_string());
-        args.push(relative_mount_path.to_string());
-    }
-    // Get the absolute build path to the solana program directory to build inside docker
-    let mount_path = PathBuf::from(verify_tmp_root_path).join(relative_mount_path);
-
-    args.push("--library-name".to_string());
-    let library_name = match library_name_opt.clone() {
-        Some(p) => p,
-        None => {
-            std::process::Command::new("find")
-                .args([mount_path.to_str().unwrap(), "-name", "Cargo.toml"])
-                .output()
-                .map_err(|e| {
-                    anyhow::format_err!(
-                        "Failed to find Cargo.toml files in root directory: {}",
-                        e.to_string()
-                    )
-                })
-                .and_then(|output| {
-                    let mut options = vec![];
-                    for path in String::from_utf8(output.stdout)?.split("\n") {
-                        match get_lib_name_from_cargo_toml(path) {
-                            Ok(name) => {
-                                options.push(name);
-                            }
-                            Err(_) => {
-                                continue;
-                            }
-                        }
-                    }
-                    if options.len() != 1 {
-                        println!(
-                            "Found multiple possible targets in root directory: {:?}",
-                            options
-                        );
-                        println!(
-                            "Please explicitly specify the target with the --library-name <name> option",
-                        );
-                        Err(anyhow::format_err!(
-                            "Failed to find unique Cargo.toml file in root directory"
-                        ))
-                    } else {
-                        Ok(options[0].clone())
-                    }
-                })?
-        }
-    };
-    args.push(library_name.clone());
-
-    if let Some(base_image) = &base_image {
-        args.push("--base-image".to_string());
-        args.push(base_image.clone());
-    }
-
-    if bpf_flag {
-        args.push("--bpf".to_string());
-    }
-
-    if !cargo_args.is_empty() {
-        args.push("--".to_string());
-        for arg in &cargo_args {
-            args.push(arg.clone());
-        }
-    }
-
-    Ok((args, mount_path.to_str().unwrap().to_string(), library_name))
-}
-
-fn clone_repo_and_checkout(
-    repo_url: &str,
-    current_dir: bool,
-    base_name: &str,
-    commit_hash: Option<String>,
-    temp_dir_opt: &mut Option<String>,
-) -> anyhow::Result<(String, String)> {
-    let uuid = Uuid::new_v4().to_string();
-
-    // Create a temporary directory to clone the repo into
-    let verify_dir = if current_dir {
-        format!(
-            "{}/.{}",
-            std::env::current_dir()?
-                .as_os_str()
-                .to_str()
-                .ok_or_else(|| anyhow::Error::msg("Invalid path string"))?,
-            uuid.clone()
-        )
-    } else {
-        format!("/tmp/solana-verify/{}", uuid)
-    };
-
-    temp_dir_opt.replace(verify_dir.clone());
-
-    let verify_tmp_root_path = format!("{}/{}", verify_dir, base_name);
-    println!("Cloning repo into: {}", verify_tmp_root_path);
-
-    std::process::Command::new("git")
-        .args(["clone", repo_url, &verify_tmp_root_path])
-        .stdout(Stdio::inherit())
-        .output()?;
-
-    if let Some(commit_hash) = commit_hash.as_ref() {
-        let result = std::process::Command::new("git")
-            .args(["-C", &verify_tmp_root_path])
-            .args(["checkout", commit_hash])
-            .output()
-            .map_err(|e| anyhow!("Failed to checkout commit hash: {:?}", e));
-        if result.is_ok() {
-            println!("Checked out commit hash: {}", commit_hash);
-        } else {
-            std::process::Command::new("rm")
-                .args(["-rf", verify_dir.as_str()])
-                .output()?;
-            Err(anyhow!("Encountered error in git setup: {:?}", result))?;
-        }
+        }),
     }
-
-    Ok((verify_tmp_root_path, verify_dir))
-}
-
-fn get_basename(repo_url: &str) -> anyhow::Result<String> {
-    let base_name = std::process::Command::new("basename")
-        .arg(repo_url)
-        .output()
-        .map_err(|e| anyhow!("Failed to get basename of repo_url: {:?}", e))
-        .and_then(|output| parse_output(output.stdout))?;
-    Ok(base_name)
 }
 
-#[allow(clippy::too_many_arguments)]
-pub async fn verify_from_repo(
-    remote: bool,
-    relative_mount_path: String,
-    connection: &RpcClient,
-    repo_url: String,
-    commit_hash: Option<String>,
-    program_id: Pubkey,
-    base_image: Option<String>,
-    library_name_opt: Option<String>,
-    bpf_flag: bool,
-    cargo_args: Vec<String>,
-    current_dir: bool,
-    skip_prompt: bool,
-    path_to_keypair: Option<String>,
-    compute_unit_price: u64,
-    mut skip_build: bool,
-    container_id_opt: &mut Option<String>,
-    temp_dir_opt: &mut Option<String>,
-    check_signal: &dyn Fn(&mut Option<String>, &mut Option<String>),
-) -> anyhow::Result<()> {
-    // Set skip_build to true if remote is true
-    skip_build |= remote;
-
-    // Get source code from repo_url
-    let base_name = get_basename(&repo_url)?;
-
-    check_signal(container_id_opt, temp_dir_opt);
-
-    let (verify_tmp_root_path, verify_dir) = clone_repo_and_checkout(
-        &repo_url,
-        current_dir,
-        &base_name,
-        commit_hash.clone(),
-        temp_dir_opt,
-    )?;
-
-    check_signal(container_id_opt, temp_dir_opt);
-
-    let (args, mount_path, library_name) = build_args(
-        &relative_mount_path,
-        library_name_opt.clone(),
-        &verify_tmp_root_path,
-        base_image.clone(),
-        bpf_flag,
-        cargo_args.clone(),
-    )?;
-    println!("Build path: {:?}", mount_path);
-    println!("Verifying program: {}", library_name);
-
-    check_signal(container_id_opt, temp_dir_opt);
-
-    let result: Result<(String, String), anyhow::Error> = if !skip_build {
-        build_and_verify_repo(
-            mount_path,
-            base_image.clone(),
-            bpf_flag,
-            library_name.clone(),
-            connection,
-            program_id,
-            cargo_args.clone(),
-            container_id_opt,
-        )
+/// `--bpf` or `--no-bpf`, unset without either so the verify.toml of the repository applies.
+fn bpf_arg(sub_m: &ArgMatches) -> Option<bool> {
+    if sub_m.is_present("bpf") {
+        Some(true)
+    } else if sub_m.is_present("no-bpf") {
+        Some(false)
     } else {
-        Ok(("skipped".to_string(), "skipped".to_string()))
-    };
-
-    // Cleanup no matter the result
-    std::process::Command::new("rm")
-        .args(["-rf", &verify_dir])
-        .output()?;
-
-    // Handle the result
-    match result {
-        Ok((build_hash, program_hash)) => {
-            if !skip_build {
-                println!("Executable Program Hash from repo: {}", build_hash);
-                println!("On-chain Program Hash: {}", program_hash);
-            }
-
-            if skip_build || build_hash == program_hash {
-                if skip_build {
-                    println!("Skipping local build and writing verify data on chain");
-                } else {
-                    println!("Program hash matches ✅");
-                }
-
-                upload_program_verification_data(
-                    repo_url.clone(),
-                    &commit_hash.clone(),
-                    args.iter().map(|s| s.to_string()).collect(),
-                    program_id,
-                    connection,
-                    skip_prompt,
-                    path_to_keypair.clone(),
-                    compute_unit_price,
-                )
-                .await?;
-
-                if remote {
-                    check_signal(container_id_opt, temp_dir_opt);
-                    let genesis_hash = get_genesis_hash(connection)?;
-                    if genesis_hash != MAINNET_GENESIS_HASH {
-                        return Err(anyhow!("Remote verification only works with mainnet. Please omit the --remote flag to verify locally."));
-                    }
-
-                    let uploader = get_address_from_keypair_or_config(path_to_keypair.as_ref())?;
-                    println!(
-                        "Sending verify command to remote machine with uploader: {}",
-                        &uploader
-                    );
-                    println!(
-                        "\nPlease note that if the desired uploader is not the provided keypair, you will need to run `solana-verify remote submit-job --program-id {} --uploader <uploader-address>.\n",
-                        &program_id,
-                    );
-                    send_job_with_uploader_to_remote(connection, &program_id, &uploader).await?;
-                }
-
-                Ok(())
-            } else {
-                println!("Program hashes do not match ❌");
-                println!("Executable Program Hash from repo: {}", build_hash);
-                println!("On-chain Program Hash: {}", program_hash);
-                Ok(())
-            }
-        }
-        Err(e) => Err(anyhow!("Error verifying program: {:?}", e)),
-    }
-}
-
-#[allow(clippy::too_many_arguments)]
-pub fn build_and_verify_repo(
-    mount_path: String,
-    base_image: Option<String>,
-    bpf_flag: bool,
-    library_name: String,
-    connection: &RpcClient,
-    program_id: Pubkey,
-    cargo_args: Vec<String>,
-    container_id_opt: &mut Option<String>,
-) -> anyhow::Result<(String, String)> {
-    // Build the code using the docker container
-    let executable_filename = format!("{}.so", &library_name);
-    build(
-        Some(mount_path.clone()),
-        Some(library_name),
-        base_image,
-        bpf_flag,
-        cargo_args,
-        container_id_opt,
-    )?;
-
-    // Get the hash of the build
-    let executable_path = std::process::Command::new("find")
-        .args([
-            &format!("{}/target/deploy", mount_path),
-            "-name",
-            executable_filename.as_str(),
-        ])
-        .output()
-        .map_err(|e| anyhow::format_err!("Failed to find executable file {}", e.to_string()))
-        .and_then(|output| parse_output(output.stdout))?;
-    println!("Executable file found at path: {:?}", executable_path);
-    let build_hash = get_file_hash(&executable_path)?;
-
-    // Get the hash of the deployed program
-    println!(
-        "Fetching on-chain program data for program ID: {}",
-        program_id,
-    );
-    let program_hash = get_program_hash(connection, program_id)?;
-
-    Ok((build_hash, program_hash))
-}
-
-pub fn parse_output(output: Vec<u8>) -> anyhow::Result<String> {
-    let parsed_output = String::from_utf8(output)?
-        .strip_suffix("\n")
-        .ok_or_else(|| anyhow!("Failed to parse output"))?
-        .to_string();
-    Ok(parsed_output)
-}
-
-pub fn get_pkg_version_from_cargo_lock(
-    package_name: &str,
-    cargo_lock_file: &str,
-) -> anyhow::Result<(u32, u32, u32)> {
-    let lockfile = Lockfile::load(cargo_lock_file)?;
-    let res = lockfile
-        .packages
-        .iter()
-        .filter(|pkg| pkg.name.to_string() == *package_name)
-        .filter_map(|pkg| {
-            let version = pkg.version.clone().to_string();
-            let version_parts: Vec<&str> = version.split(".").collect();
-            if version_parts.len() == 3 {
-                let major = version_parts[0].parse::<u32>().unwrap_or(0);
-                let minor = version_parts[1].parse::<u32>().unwrap_or(0);
-                let patch = version_parts[2].parse::<u32>().unwrap_or(0);
-                return Some((major, minor, patch));
-            }
-            None
-        })
-        .next()
-        .ok_or_else(|| anyhow!("Failed to parse solana-program version from Cargo.lock"))?;
-    Ok(res)
-}
-
-pub fn get_lib_name_from_cargo_toml(cargo_toml_file: &str) -> anyhow::Result<String> {
-    let manifest = Manifest::from_path(cargo_toml_file)?;
-    let lib = manifest
-        .lib
-        .ok_or_else(|| anyhow!("Failed to parse lib from Cargo.toml"))?;
-    lib.name
-        .ok_or_else(|| anyhow!("Failed to parse lib name from Cargo.toml"))
-}
-
-pub fn get_pkg_name_from_cargo_toml(cargo_toml_file: &str) -> Option<String> {
-    let manifest = Manifest::from_path(cargo_toml_file).ok()?;
-    let pkg = manifest.package?;
-    Some(pkg.name)
-}
-
-pub fn print_build_params(pubkey: &Pubkey, build_params: &OtterBuildParams) {
-    println!("----------------------------------------------------------------");
-    println!("Address: {:?}", pubkey);
-    println!("----------------------------------------------------------------");
-    println!("{}", build_params);
-}
-
-pub async fn list_program_pdas(program_id: Pubkey, client: &RpcClient) -> anyhow::Result<()> {
-    let pdas = get_all_pdas_available(client, &program_id).await?;
-    for (pda, build_params) in pdas {
-        print_build_params(&pda, &build_params);
+        None
     }
-    Ok(())
-}
-
-pub async fn print_program_pda(
-    program_id: Pubkey,
-    signer: Option<String>,
-    client: &RpcClient,
-) -> anyhow::Result<()> {
-    let (pda, build_params) = get_program_pda(client, &program_id, signer).await?;
-    print_build_params(&pda, &build_params);
-    Ok(())
 }
 
-pub fn get_commit_hash(sub_m: &ArgMatches, repo_url: &str) -> anyhow::Result<String> {
-    let commit_hash = sub_m
-        .value_of("commit-hash")
-        .map(String::from)
-        .or_else(|| {
-            get_commit_hash_from_remote(repo_url).ok() // Dynamically determine commit hash from remote
-        })
-        .ok_or_else(|| {
-            anyhow::anyhow!("Commit hash must be provided or inferred from the remote repository")
-        })?;
-
-    println!("Commit hash from remote: {}", commit_hash);
-    Ok(commit_hash)
-}
-
-#[allow(clippy::too_many_arguments)]
-async fn export_pda_tx(
-    connection: &RpcClient,
-    program_id: Pubkey,
-    uploader: Pubkey,
-    repo_url: String,
-    commit_hash: String,
-    mount_path: String,
-    library_name: Option<String>,
-    base_image: Option<String>,
-    bpf_flag: bool,
-    temp_dir: &mut Option<String>,
-    encoding: UiTransactionEncoding,
-    cargo_args: Vec<String>,
-    compute_unit_price: u64,
-) -> anyhow::Result<()> {
-    let last_deployed_slot = get_last_deployed_slot(connection, &program_id.to_string())
-        .await
-        .map_err(|err| anyhow!("Unable to get last deployed slot: {}", err))?;
-
-    let (temp_root_path, verify_dir) = clone_repo_and_checkout(
-        &repo_url,
-        true,
-        &get_basename(&repo_url)?,
-        Some(commit_hash.clone()),
-        temp_dir,
-    )?;
-
-    let input_params = InputParams {
-        version: env!("CARGO_PKG_VERSION").to_string(),
-        git_url: repo_url,
-        commit: commit_hash.clone(),
-        args: build_args(
-            &mount_path,
-            library_name.clone(),
-            &temp_root_path,
-            base_image.clone(),
-            bpf_flag,
-            cargo_args,
-        )?
-        .0,
-        deployed_slot: last_deployed_slot,
-    };
-
-    std::process::Command::new("rm")
-        .args(["-rf", &verify_dir])
-        .output()?;
-
-    let (pda, _) = find_build_params_pda(&program_id, &uploader);
-
-    // check if account already exists
-    let instruction = match connection.get_account(&pda) {
-        Ok(account_info) => {
-            if !account_info.data.is_empty() {
-                println!("PDA already exists, creating update transaction");
-                OtterVerifyInstructions::Update
-            } else {
-                println!("PDA does not exist, creating initialize transaction");
-                OtterVerifyInstructions::Initialize
-            }
-        }
-        Err(_) => OtterVerifyInstructions::Initialize,
-    };
-
-    let tx = compose_transaction(
-        &input_params,
-        uploader,
-        pda,
-        program_id,
-        instruction,
-        compute_unit_price,
-    );
-
-    // serialize the transaction to base58
-    match encoding {
-        UiTransactionEncoding::Base58 => {
-            println!("{}", bs58::encode(serialize(&tx)?).into_string());
-        }
-        UiTransactionEncoding::Base64 => {
-            println!("{}", BASE64_STANDARD.encode(serialize(&tx)?));
-        }
-        _ => unreachable!(),
-    }
-
-    Ok(())
+/// Keypair given with `--keypair` or `--signer-name`, none to use the Solana CLI config.
+fn keypair_arg(sub_m: &ArgMatches) -> anyhow::Result<Option<String>> {
+    signers::resolve_keypair(sub_m.value_of("keypair"), sub_m.value_of("signer-name"))
 }
@@ -0,0 +1,340 @@
+use anyhow::anyhow;
+use goblin::elf::{section_header::SHT_NOBITS, Elf};
+use regex::bytes::Regex;
+use sha2::{Digest, Sha256};
+use std::{collections::BTreeSet, fmt};
+
+/// Section of an executable, compared by size and content.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SectionSummary {
+    pub name: String,
+    pub size: u64,
+    pub hash: String,
+}
+
+/// What an executable reveals about how it was built.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ElfSummary {
+    pub sections: Vec<SectionSummary>,
+    /// Offset the ELF ends at, data past it is not part of the program
+    pub elf_len: usize,
+    /// Non-zero bytes after the end of the ELF
+    pub trailing_bytes: usize,
+    /// Solana and Anchor crates with their versions, from the registry paths of panic locations
+    pub crates: BTreeSet<String>,
+    /// Commits of the rustc that built the program, from the paths of the standard library
+    pub rustc_commits: BTreeSet<String>,
+    /// Compiler and linker versions of the .comment section
+    pub comments: BTreeSet<String>,
+}
+
+impl ElfSummary {
+    pub fn parse(bytes: &[u8]) -> anyhow::Result<ElfSummary> {
+        let elf = Elf::parse(bytes).map_err(|e| anyhow!("Failed to parse program: {}", e))?;
+        let mut summary = ElfSummary {
+            elf_len: elf.header.e_shoff as usize
+                + elf.header.e_shnum as usize * elf.header.e_shentsize as usize,
+            ..Default::default()
+        };
+
+        for header in &elf.section_headers {
+            let name = elf
+                .shdr_strtab
+                .get_at(header.sh_name)
+                .unwrap_or_default()
+                .to_string();
+            let data = if header.sh_type == SHT_NOBITS {
+                &[][..]
+            } else {
+                let start = header.sh_offset as usize;
+                let end = start.saturating_add(header.sh_size as usize);
+                summary.elf_len = summary.elf_len.max(end);
+                bytes.get(start..end).unwrap_or_default()
+            };
+            if name == ".comment" {
+                summary.comments.extend(
+                    data.split(|&b| b == 0)
+                        .filter(|comment| !comment.is_empty())
+                        .map(|comment| String::from_utf8_lossy(comment).trim().to_string()),
+                );
+            }
+            if !name.is_empty() {
+                summary.sections.push(SectionSummary {
+                    name,
+                    size: header.sh_size,
+                    hash: hex::encode(Sha256::digest(data)),
+                });
+            }
+        }
+
+        summary.trailing_bytes = bytes
+            .get(summary.elf_len..)
+            .unwrap_or_default()
+            .iter()
+            .filter(|&&b| b != 0)
+            .count();
+
+        let crates = Regex::new(r"/((?:solana|anchor)-[a-z0-9_-]+-\d+\.\d+\.\d+[0-9A-Za-z.+-]*)/")?;
+        summary.crates = crates
+            .captures_iter(bytes)
+            .map(|captures| String::from_utf8_lossy(&captures[1]).to_string())
+            .collect();
+        let rustc = Regex::new(r"/rustc/([0-9a-f]{40})/")?;
+        summary.rustc_commits = rustc
+            .captures_iter(bytes)
+            .map(|captures| String::from_utf8_lossy(&captures[1]).to_string())
+            .collect();
+
+        Ok(summary)
+    }
+
+    fn section(&self, name: &str) -> Option<&SectionSummary> {
+        self.sections.iter().find(|section| section.name == name)
+    }
+}
+
+fn join(items: &BTreeSet<String>) -> String {
+    if items.is_empty() {
+        "none found".to_string()
+    } else {
+        items.iter().cloned().collect::<Vec<_>>().join(", ")
+    }
+}
+
+/// Comparison of a local build with the on-chain program it does not match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MismatchReport {
+    pub local: ElfSummary,
+    pub on_chain: ElfSummary,
+}
+
+impl MismatchReport {
+    pub fn new(local: &[u8], on_chain: &[u8]) -> anyhow::Result<MismatchReport> {
+        Ok(MismatchReport {
+            local: ElfSummary::parse(local)
+                .map_err(|e| anyhow!("Unable to parse the local build: {}", e))?,
+            on_chain: ElfSummary::parse(on_chain)
+                .map_err(|e| anyhow!("Unable to parse the on-chain program: {}", e))?,
+        })
+    }
+
+    /// Names of the sections whose size or content differ, or that only one side has.
+    pub fn differing_sections(&self) -> Vec<String> {
+        let mut names = vec![];
+        for section in self.local.sections.iter().chain(&self.on_chain.sections) {
+            if names.contains(&section.name) {
+                continue;
+            }
+            if self.local.section(&section.name) != self.on_chain.section(&section.name) {
+                names.push(section.name.clone());
+            }
+        }
+        names
+    }
+
+    /// Explanations of the mismatch, most specific first.
+    pub fn likely_causes(&self) -> Vec<String> {
+        let (local, on_chain) = (&self.local, &self.on_chain);
+        let differing = self.differing_sections();
+        let mut causes = vec![];
+
+        if differing.is_empty() {
+            for (side, summary) in [("on-chain program", on_chain), ("local build", local)] {
+                if summary.trailing_bytes > 0 {
+                    causes.push(format!(
+                        "Trailing padding: the {} has {} non-zero byte(s) after the end of the ELF, e.g. left over from an earlier, larger deployment. The programs are otherwise identical",
+                        side, summary.trailing_bytes
+                    ));
+                }
+            }
+            return causes;
+        }
+
+        let toolchain_differs = (!local.rustc_commits.is_empty()
+            && !on_chain.rustc_commits.is_empty()
+            && local.rustc_commits != on_chain.rustc_commits)
+            || (!local.comments.is_empty()
+                && !on_chain.comments.is_empty()
+                && local.comments != on_chain.comments);
+        if toolchain_differs {
+            causes.push(
+                "Toolchain mismatch: the programs were built by different compilers. Build with the image of the Solana version the program was deployed with (--base-image)".to_string(),
+            );
+        }
+
+        let only_local = local.crates.difference(&on_chain.crates).cloned().collect();
+        let only_on_chain = on_chain.crates.difference(&local.crates).cloned().collect();
+        if !local.crates.is_empty()
+            && !on_chain.crates.is_empty()
+            && local.crates != on_chain.crates
+        {
+            causes.push(format!(
+                "Dependency versions differ: {} locally, {} on chain. The program was likely deployed from another Cargo.lock or commit",
+                join(&only_local),
+                join(&only_on_chain)
+            ));
+        }
+
+        if causes.is_empty() {
+            let code_differs = differing.iter().any(|name| name == ".text");
+            if code_differs {
+                causes.push(
+                    "Feature flags or source: with the same toolchain and dependencies the code still differs. The program was likely built with other features or cargo args (--features, --no-default-features), as another library, or from another commit".to_string(),
+                );
+            } else {
+                causes.push(format!(
+                    "Embedded data: the code is identical but {} differ, e.g. paths or strings embedded by a build outside the verifiable image or from another mount path",
+                    differing.join(", ")
+                ));
+            }
+        }
+        causes
+    }
+}
+
+impl fmt::Display for MismatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Mismatch explanation:")?;
+        writeln!(
+            f,
+            "  {:<24} {:>12} {:>12}  status",
+            "section", "local", "on-chain"
+        )?;
+        let mut names: Vec<&str> = self
+            .local
+            .sections
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect();
+        for section in &self.on_chain.sections {
+            if !names.contains(&section.name.as_str()) {
+                names.push(&section.name);
+            }
+        }
+        let size = |section: Option<&SectionSummary>| {
+            section.map_or("-".to_string(), |section| section.size.to_string())
+        };
+        for name in names {
+            let (local, on_chain) = (self.local.section(name), self.on_chain.section(name));
+            let status = match (local, on_chain) {
+                (Some(local), Some(on_chain)) if local == on_chain => "same",
+                (Some(_), Some(_)) => "differs",
+                (Some(_), None) => "only local",
+                _ => "only on-chain",
+            };
+            writeln!(
+                f,
+                "  {:<24} {:>12} {:>12}  {}",
+                name,
+                size(local),
+                size(on_chain),
+                status
+            )?;
+        }
+        writeln!(
+            f,
+            "  Trailing bytes after the ELF: {} local, {} on-chain",
+            self.local.trailing_bytes, self.on_chain.trailing_bytes
+        )?;
+        writeln!(f, "  Local crates: {}", join(&self.local.crates))?;
+        writeln!(f, "  On-chain crates: {}", join(&self.on_chain.crates))?;
+        writeln!(f, "  Local rustc: {}", join(&self.local.rustc_commits))?;
+        writeln!(
+            f,
+            "  On-chain rustc: {}",
+            join(&self.on_chain.rustc_commits)
+        )?;
+        writeln!(f, "  Local compiler: {}", join(&self.local.comments))?;
+        writeln!(f, "  On-chain compiler: {}", join(&self.on_chain.comments))?;
+        writeln!(f, "Likely causes:")?;
+        let causes = self.likely_causes();
+        if causes.is_empty() {
+            writeln!(f, "  none identified")?;
+        }
+        for cause in causes {
+            writeln!(f, "  - {}", cause)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(sections: &[(&str, &str)], crates: &[&str], rustc: &[&str]) -> ElfSummary {
+        ElfSummary {
+            sections: sections
+                .iter()
+                .map(|(name, hash)| SectionSummary {
+                    name: name.to_string(),
+                    size: 64,
+                    hash: hash.to_string(),
+                })
+                .collect(),
+            crates: crates.iter().map(|c| c.to_string()).collect(),
+            rustc_commits: rustc.iter().map(|c| c.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_likely_causes() {
+        let local = summary(
+            &[(".text", "a"), (".rodata", "b")],
+            &["solana-program-1.18.26"],
+            &["aaaa"],
+        );
+
+        let report = MismatchReport {
+            local: local.clone(),
+            on_chain: summary(
+                &[(".text", "c"), (".rodata", "b")],
+                &["solana-program-1.18.22"],
+                &["bbbb"],
+            ),
+        };
+        assert_eq!(report.differing_sections(), vec![".text"]);
+        let causes = report.likely_causes();
+        assert!(causes[0].starts_with("Toolchain mismatch"));
+        assert!(
+            causes[1].contains("solana-program-1.18.26 locally, solana-program-1.18.22 on chain")
+        );
+
+        let report = MismatchReport {
+            local: local.clone(),
+            on_chain: summary(
+                &[(".text", "c"), (".rodata", "b")],
+                &["solana-program-1.18.26"],
+                &["aaaa"],
+            ),
+        };
+        assert!(report.likely_causes()[0].starts_with("Feature flags"));
+
+        let report = MismatchReport {
+            local: local.clone(),
+            on_chain: summary(
+                &[(".text", "a"), (".rodata", "d")],
+                &["solana-program-1.18.26"],
+                &["aaaa"],
+            ),
+        };
+        assert!(report.likely_causes()[0].contains(".rodata differ"));
+
+        let mut on_chain = local.clone();
+        on_chain.trailing_bytes = 12;
+        let report = MismatchReport { local, on_chain };
+        assert!(
+            report.likely_causes()[0].starts_with("Trailing padding: the on-chain program has 12")
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_non_elf() {
+        assert!(ElfSummary::parse(b"not an elf").is_err());
+        assert!(MismatchReport::new(b"not an elf", b"not an elf")
+            .unwrap_err()
+            .to_string()
+            .contains("local build"));
+    }
+}
@@ -0,0 +1,206 @@
+use anyhow::anyhow;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use uuid::Uuid;
+
+use crate::cleanup::{remove_dir, track_temp_dir};
+use crate::get_file_hash;
+use crate::logging::command_output;
+use crate::params_file::VerifiedParamsFile;
+use crate::report::VerificationReport;
+use crate::sbom::SBOM_MEDIA_TYPE;
+
+pub const ARTIFACT_TYPE: &str = "application/vnd.solana.verified-program.v1";
+const PROGRAM_MEDIA_TYPE: &str = "application/vnd.solana.program.v1";
+const ATTESTATION_MEDIA_TYPE: &str = "application/vnd.solana.verification.v1+json";
+
+const PROGRAM_FILE_NAME: &str = "program.so";
+const ATTESTATION_FILE_NAME: &str = "verification.json";
+const SBOM_FILE_NAME: &str = "sbom.cdx.json";
+
+/// Reference of the artifact for a program verified at a commit, e.g.
+/// `ghcr.io/org/verified:<program id>-<commit>`.
+pub fn artifact_reference(repository: &str, program_id: &str, commit: &str) -> String {
+    if commit.is_empty() {
+        format!("{}:{}", repository.trim_end_matches('/'), program_id)
+    } else {
+        format!(
+            "{}:{}-{}",
+            repository.trim_end_matches('/'),
+            program_id,
+            commit
+        )
+    }
+}
+
+fn oras_command(args: &[&str], current_dir: impl AsRef<Path>) -> anyhow::Result<()> {
+    let status = Command::new("oras")
+        .args(args)
+        .current_dir(current_dir.as_ref())
+        .stdout(command_output())
+        .stderr(command_output())
+        .status()
+        .map_err(|e| {
+            anyhow!(
+                "Failed to run oras, make sure it is installed (https://oras.land): {}",
+                e
+            )
+        })?;
+
+    if !status.success() {
+        return Err(anyhow!("oras {} failed", args[0]));
+    }
+    Ok(())
+}
+
+fn create_artifact_dir() -> anyhow::Result<PathBuf> {
+    let dir = std::env::temp_dir()
+        .join("solana-verify")
+        .join(Uuid::new_v4().to_string());
+    std::fs::create_dir_all(&dir)?;
+    track_temp_dir(&dir);
+    Ok(dir)
+}
+
+/// Pushes the verified program and its signed params file as an OCI artifact tagged by
+/// program id and commit, with the SBOM of the build if a report is given.
+pub fn push_artifact(
+    repository: &str,
+    executable_path: &str,
+    params_path: &str,
+    report_path: Option<&str>,
+) -> anyhow::Result<String> {
+    let params_file = VerifiedParamsFile::read(params_path)?;
+    params_file.verify_signature()?;
+
+    let executable_hash = get_file_hash(executable_path)?;
+    if executable_hash != params_file.executable_hash {
+        return Err(anyhow!(
+            "Hash of {} ({}) does not match the verified hash {}",
+            executable_path,
+            executable_hash,
+            params_file.executable_hash
+        ));
+    }
+
+    let sbom = match report_path {
+        Some(report_path) => {
+            let report = VerificationReport::read(report_path)?;
+            if report.executable_hash != params_file.executable_hash {
+                return Err(anyhow!(
+                    "Report {} is for executable {}, not the verified {}",
+                    report_path,
+                    report.executable_hash,
+                    params_file.executable_hash
+                ));
+            }
+            Some(
+                report
+                    .sbom
+                    .ok_or_else(|| anyhow!("Report {} has no SBOM", report_path))?,
+            )
+        }
+        None => None,
+    };
+
+    let reference = artifact_reference(repository, &params_file.program_id, &params_file.commit);
+
+    // oras names the layers after the paths it is given, so stage the files under fixed names
+    let artifact_dir = create_artifact_dir()?;
+    std::fs::copy(executable_path, artifact_dir.join(PROGRAM_FILE_NAME))?;
+    // Layers are published as is, so they are written without --encrypt-to
+    std::fs::write(
+        artifact_dir.join(ATTESTATION_FILE_NAME),
+        serde_json::to_string_pretty(&params_file)?,
+    )?;
+    if let Some(sbom) = &sbom {
+        std::fs::write(
+            artifact_dir.join(SBOM_FILE_NAME),
+            serde_json::to_string_pretty(sbom)?,
+        )?;
+    }
+
+    let source_annotation = format!("org.opencontainers.image.source={}", params_file.git_url);
+    let revision_annotation = format!("org.opencontainers.image.revision={}", params_file.commit);
+    let program_layer = format!("{}:{}", PROGRAM_FILE_NAME, PROGRAM_MEDIA_TYPE);
+    let attestation_layer = format!("{}:{}", ATTESTATION_FILE_NAME, ATTESTATION_MEDIA_TYPE);
+    let sbom_layer = format!("{}:{}", SBOM_FILE_NAME, SBOM_MEDIA_TYPE);
+    let mut args = vec![
+        "push",
+        &reference,
+        "--artifact-type",
+        ARTIFACT_TYPE,
+        "--annotation",
+        &source_annotation,
+        "--annotation",
+        &revision_annotation,
+        &program_layer,
+        &attestation_layer,
+    ];
+    if sbom.is_some() {
+        args.push(&sbom_layer);
+    }
+    let result = oras_command(&args, &artifact_dir);
+    let _ = remove_dir(&artifact_dir);
+    result?;
+
+    println!("Pushed verified program to {}", reference);
+    Ok(reference)
+}
+
+/// Pulls the artifact for `program_id` at `commit` into `output_dir` and checks that the
+/// program matches the hash recorded in its attestation.
+pub fn fetch_artifact(
+    repository: &str,
+    program_id: &str,
+    commit: &str,
+    output_dir: &str,
+) -> anyhow::Result<VerifiedParamsFile> {
+    let reference = artifact_reference(repository, program_id, commit);
+    std::fs::create_dir_all(output_dir)?;
+    oras_command(&["pull", &reference, "--output", "."], output_dir)?;
+
+    let params_file =
+        VerifiedParamsFile::read(&format!("{}/{}", output_dir, ATTESTATION_FILE_NAME))?;
+    if let Some(signer) = params_file.verify_signature()? {
+        println!("Attestation signed by {}", signer);
+    }
+    if params_file.program_id != program_id {
+        return Err(anyhow!(
+            "Attestation is for program {}, expected {}",
+            params_file.program_id,
+            program_id
+        ));
+    }
+
+    let executable_path = format!("{}/{}", output_dir, PROGRAM_FILE_NAME);
+    let executable_hash = get_file_hash(&executable_path)?;
+    if executable_hash != params_file.executable_hash {
+        return Err(anyhow!(
+            "Fetched program hash {} does not match the attested hash {}",
+            executable_hash,
+            params_file.executable_hash
+        ));
+    }
+
+    println!("Fetched {} into {}", reference, output_dir);
+    println!("Executable Hash: {}", executable_hash);
+    Ok(params_file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_artifact_reference() {
+        assert_eq!(
+            artifact_reference("ghcr.io/org/verified/", "Prog111", "abc123"),
+            "ghcr.io/org/verified:Prog111-abc123"
+        );
+        assert_eq!(
+            artifact_reference("ghcr.io/org/verified", "Prog111", ""),
+            "ghcr.io/org/verified:Prog111"
+        );
+    }
+}
@@ -0,0 +1,345 @@
+use anyhow::anyhow;
+use log::info;
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    collections::BTreeMap,
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{endpoints::Cluster, paths::find_cargo_tomls, verify_all::ManifestProgram};
+
+/// Verification defaults a program crate declares in its Cargo.toml, so that they are reviewed
+/// with its source and read at the commit being verified:
+///
+/// ```toml
+/// [package.metadata.solana-verify]
+/// mount-path = "."
+/// features = ["mainnet"]
+///
+/// [package.metadata.solana-verify.program-id]
+/// mainnet = "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY"
+/// devnet = "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SolanaVerifyMetadata {
+    /// Program IDs by cluster
+    #[serde(default)]
+    pub program_id: BTreeMap<String, String>,
+    /// Relative to the root of the workspace of the crate, which is the default
+    pub mount_path: Option<String>,
+    /// Features the program must be built with
+    #[serde(default)]
+    pub features: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CargoManifest {
+    package: Option<CargoPackage>,
+    lib: Option<CargoLib>,
+    workspace: Option<toml::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CargoPackage {
+    name: String,
+    #[serde(default)]
+    metadata: Option<CargoPackageMetadata>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CargoLib {
+    name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CargoPackageMetadata {
+    #[serde(rename = "solana-verify")]
+    solana_verify: Option<SolanaVerifyMetadata>,
+}
+
+impl SolanaVerifyMetadata {
+    fn validate(&self, manifest_path: &Path) -> anyhow::Result<()> {
+        let invalid = |reason: String| {
+            anyhow!(
+                "Invalid [package.metadata.solana-verify] in {}: {}",
+                manifest_path.display(),
+                reason
+            )
+        };
+        for (cluster, program_id) in &self.program_id {
+            if Cluster::from_moniker(cluster).is_none() {
+                return Err(invalid(format!(
+                    "unknown cluster {}, expected one of mainnet, mainnet-beta, devnet, testnet or localnet",
+                    cluster
+                )));
+            }
+            Pubkey::from_str(program_id)
+                .map_err(|e| invalid(format!("invalid program ID {}: {}", program_id, e)))?;
+        }
+        if let Some(mount_path) = &self.mount_path {
+            let escapes = Path::new(mount_path)
+                .components()
+                .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir));
+            if escapes {
+                return Err(invalid(format!(
+                    "mount-path {} must be relative to the workspace and stay inside it",
+                    mount_path
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Cluster the program is deployed at `program_id` on, if any.
+    pub fn cluster_of(&self, program_id: &Pubkey) -> Option<&str> {
+        self.program_id
+            .iter()
+            .find(|(_, id)| **id == program_id.to_string())
+            .map(|(cluster, _)| cluster.as_str())
+    }
+}
+
+/// A crate of a checkout declaring verification defaults.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgramCrate {
+    pub manifest_path: PathBuf,
+    pub library_name: String,
+    /// Directory of the Cargo.toml of the workspace the crate belongs to
+    pub workspace_root: PathBuf,
+    pub metadata: SolanaVerifyMetadata,
+}
+
+fn read_manifest(path: &Path) -> Option<CargoManifest> {
+    toml::from_str(&std::fs::read_to_string(path).ok()?).ok()
+}
+
+/// Closest directory from the crate at `crate_dir` up to `root` whose Cargo.toml declares a
+/// workspace, else the directory of the crate.
+fn find_workspace_root(root: &Path, crate_dir: &Path) -> PathBuf {
+    crate_dir
+        .ancestors()
+        .take_while(|dir| dir.starts_with(root))
+        .find(|dir| {
+            read_manifest(&dir.join("Cargo.toml"))
+                .is_some_and(|manifest| manifest.workspace.is_some())
+        })
+        .unwrap_or(crate_dir)
+        .to_path_buf()
+}
+
+impl ProgramCrate {
+    /// Build settings of the crate, with the mount path relative to `root`.
+    pub fn defaults(&self, root: &Path) -> anyhow::Result<ManifestProgram> {
+        let mount_path = self
+            .workspace_root
+            .join(self.metadata.mount_path.as_deref().unwrap_or_default());
+        let mount_path = mount_path.strip_prefix(root).map_err(|_| {
+            anyhow!(
+                "The workspace of {} is outside of {}",
+                self.manifest_path.display(),
+                root.display()
+            )
+        })?;
+        Ok(ManifestProgram {
+            library_name: Some(self.library_name.clone()),
+            mount_path: mount_path
+                .to_string_lossy()
+                .trim_end_matches('/')
+                .to_string(),
+            ..Default::default()
+        })
+    }
+}
+
+/// The crate below `root` that declares `program_id` in its
+/// `[package.metadata.solana-verify]`, if any.
+pub fn find_program_crate(
+    root: &Path,
+    program_id: &Pubkey,
+) -> anyhow::Result<Option<ProgramCrate>> {
+    let mut crates = vec![];
+    for manifest_path in find_cargo_tomls(root) {
+        let Some(manifest) = read_manifest(&manifest_path) else {
+            continue;
+        };
+        let Some(package) = manifest.package else {
+            continue;
+        };
+        let Some(metadata) = package.metadata.and_then(|metadata| metadata.solana_verify) else {
+            continue;
+        };
+        // Crates of other programs are not checked, so that their metadata cannot fail this one
+        if metadata.cluster_of(program_id).is_none() {
+            continue;
+        }
+        metadata.validate(&manifest_path)?;
+        let crate_dir = manifest_path.parent().unwrap_or(root).to_path_buf();
+        crates.push(ProgramCrate {
+            library_name: manifest
+                .lib
+                .and_then(|lib| lib.name)
+                .unwrap_or_else(|| package.name.replace('-', "_")),
+            workspace_root: find_workspace_root(root, &crate_dir),
+            manifest_path,
+            metadata,
+        });
+    }
+    match crates.len() {
+        0 => Ok(None),
+        1 => Ok(crates.pop()),
+        _ => Err(anyhow!(
+            "Several crates declare program {} in their [package.metadata.solana-verify]: {}",
+            program_id,
+            crates
+                .iter()
+                .map(|program_crate| program_crate.manifest_path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Adds `--features` for the features of `required` the cargo args do not enable yet.
+pub fn add_required_features(mut cargo_args: Vec<String>, required: &[String]) -> Vec<String> {
+    let mut enabled = vec![];
+    let mut args = cargo_args.iter();
+    while let Some(arg) = args.next() {
+        let features = if arg == "--features" || arg == "-F" {
+            args.next().map(String::as_str)
+        } else {
+            arg.strip_prefix("--features=")
+                .or_else(|| arg.strip_prefix("-F"))
+        };
+        if let Some(features) = features {
+            enabled.extend(
+                features
+                    .split([',', ' '])
+                    .filter(|feature| !feature.is_empty())
+                    .map(str::to_string),
+            );
+        }
+    }
+    let missing: Vec<&str> = required
+        .iter()
+        .filter(|feature| !enabled.contains(feature))
+        .map(String::as_str)
+        .collect();
+    if !missing.is_empty() {
+        cargo_args.push("--features".to_string());
+        cargo_args.push(missing.join(","));
+    }
+    cargo_args
+}
+
+/// Completes the build settings with the `[package.metadata.solana-verify]` of the crate of
+/// the checkout at `root` declaring `program_id`, and adds the features it requires.
+pub fn apply_package_metadata(
+    root: &Path,
+    program_id: &Pubkey,
+    settings: ManifestProgram,
+) -> anyhow::Result<ManifestProgram> {
+    let Some(program_crate) = find_program_crate(root, program_id)? else {
+        return Ok(settings);
+    };
+    info!(
+        "Using the verification settings of {} ({} program ID)",
+        program_crate.manifest_path.display(),
+        program_crate
+            .metadata
+            .cluster_of(program_id)
+            .unwrap_or_default()
+    );
+    let mut settings = settings.with_defaults(&program_crate.defaults(root)?);
+    if settings.library_name.as_deref() == Some(program_crate.library_name.as_str()) {
+        settings.cargo_args =
+            add_required_features(settings.cargo_args, &program_crate.metadata.features);
+    }
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_program_crate() {
+        let program_id = Pubkey::new_unique();
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let program_dir = root.join("programs/amm");
+        std::fs::create_dir_all(&program_dir).unwrap();
+        std::fs::write(
+            root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"programs/*\"]\n",
+        )
+        .unwrap();
+        std::fs::write(
+            program_dir.join("Cargo.toml"),
+            format!(
+                "[package]\nname = \"acme-amm\"\nversion = \"0.1.0\"\n\n[package.metadata.solana-verify]\nfeatures = [\"mainnet\"]\n\n[package.metadata.solana-verify.program-id]\nmainnet-beta = \"{}\"\n",
+                program_id
+            ),
+        )
+        .unwrap();
+
+        let settings = apply_package_metadata(
+            root,
+            &program_id,
+            ManifestProgram {
+                cargo_args: vec!["--features".to_string(), "logging".to_string()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(settings.library_name.as_deref(), Some("acme_amm"));
+        assert_eq!(settings.mount_path, "");
+        assert_eq!(
+            settings.cargo_args,
+            vec!["--features", "logging", "--features", "mainnet"]
+        );
+        assert!(find_program_crate(root, &Pubkey::new_unique())
+            .unwrap()
+            .is_none());
+
+        // Invalid metadata of another program does not get in the way
+        let other_dir = root.join("programs/oracle");
+        std::fs::create_dir_all(&other_dir).unwrap();
+        std::fs::write(
+            other_dir.join("Cargo.toml"),
+            "[package]\nname = \"oracle\"\n\n[package.metadata.solana-verify.program-id]\nfoonet = \"nope\"\n",
+        )
+        .unwrap();
+        assert!(find_program_crate(root, &program_id).unwrap().is_some());
+
+        std::fs::write(
+            program_dir.join("Cargo.toml"),
+            format!(
+                "[package]\nname = \"acme-amm\"\n\n[package.metadata.solana-verify]\nmount-path = \"../outside\"\n\n[package.metadata.solana-verify.program-id]\nmainnet = \"{}\"\n",
+                program_id
+            ),
+        )
+        .unwrap();
+        assert!(find_program_crate(root, &program_id).is_err());
+    }
+
+    #[test]
+    fn test_add_required_features() {
+        let args = |args: &[&str]| args.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let required = args(&["mainnet", "no-log"]);
+        assert_eq!(
+            add_required_features(args(&["--features=mainnet"]), &required),
+            args(&["--features=mainnet", "--features", "no-log"])
+        );
+        assert_eq!(
+            add_required_features(args(&["-F", "no-log,mainnet"]), &required),
+            args(&["-F", "no-log,mainnet"])
+        );
+        assert_eq!(
+            add_required_features(vec![], &required),
+            args(&["--features", "mainnet,no-log"])
+        );
+    }
+}
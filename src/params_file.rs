@@ -0,0 +1,157 @@
+use anyhow::anyhow;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{pubkey::Pubkey, signature::Signature, signer::Signer};
+use std::{path::PathBuf, str::FromStr};
+
+use crate::compression::{read_artifact, write_artifact};
+use crate::solana_program::InputParams;
+
+/// Verification params saved after a successful hash match so that they can be uploaded
+/// later, e.g. by the program authority, without rebuilding the program.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifiedParamsFile {
+    pub program_id: String,
+    pub version: String,
+    pub git_url: String,
+    pub commit: String,
+    pub args: Vec<String>,
+    pub deployed_slot: u64,
+    pub executable_hash: String,
+    /// Address of the machine's keypair that signed the file, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl VerifiedParamsFile {
+    pub fn new(
+        program_id: &Pubkey,
+        input_params: &InputParams,
+        executable_hash: String,
+    ) -> VerifiedParamsFile {
+        VerifiedParamsFile {
+            program_id: program_id.to_string(),
+            version: input_params.version.clone(),
+            git_url: input_params.git_url.clone(),
+            commit: input_params.commit.clone(),
+            args: input_params.args.clone(),
+            deployed_slot: input_params.deployed_slot,
+            executable_hash,
+            signer: None,
+            signature: None,
+        }
+    }
+
+    /// The bytes covered by the signature: the file contents without the signature fields.
+    fn signed_message(&self) -> anyhow::Result<Vec<u8>> {
+        let unsigned = VerifiedParamsFile {
+            signer: None,
+            signature: None,
+            ..self.clone()
+        };
+        Ok(serde_json::to_vec(&unsigned)?)
+    }
+
+    pub fn sign(&mut self, signer: &dyn Signer) -> anyhow::Result<()> {
+        let signature = signer.try_sign_message(&self.signed_message()?)?;
+        self.signer = Some(signer.try_pubkey()?.to_string());
+        self.signature = Some(signature.to_string());
+        Ok(())
+    }
+
+    /// Checks the signature if the file is signed and returns the signer.
+    pub fn verify_signature(&self) -> anyhow::Result<Option<Pubkey>> {
+        match (&self.signer, &self.signature) {
+            (Some(signer), Some(signature)) => {
+                let signer = Pubkey::from_str(signer)?;
+                let signature = Signature::from_str(signature)?;
+                if signature.verify(signer.as_ref(), &self.signed_message()?) {
+                    Ok(Some(signer))
+                } else {
+                    Err(anyhow!("Invalid signature on verified params file"))
+                }
+            }
+            (None, None) => Ok(None),
+            _ => Err(anyhow!(
+                "Verified params file must contain both a signer and a signature"
+            )),
+        }
+    }
+
+    /// Signer of a file about to be uploaded. Anyone could have written an unsigned file, so
+    /// one is only accepted with `allow_unsigned`.
+    pub fn check_signature(&self, allow_unsigned: bool) -> anyhow::Result<Option<Pubkey>> {
+        match self.verify_signature()? {
+            Some(signer) => {
+                info!("Params file signed by {}", signer);
+                Ok(Some(signer))
+            }
+            None if allow_unsigned => {
+                warn!("Uploading the params of an unsigned file (--allow-unsigned)");
+                Ok(None)
+            }
+            None => Err(anyhow!(
+                "The params file is not signed. Write it with a keypair available to sign it, or pass --allow-unsigned to upload it anyway"
+            )),
+        }
+    }
+
+    pub fn program_id(&self) -> anyhow::Result<Pubkey> {
+        Ok(Pubkey::from_str(&self.program_id)?)
+    }
+
+    pub fn input_params(&self) -> InputParams {
+        InputParams {
+            version: self.version.clone(),
+            git_url: self.git_url.clone(),
+            commit: self.commit.clone(),
+            args: self.args.clone(),
+            deployed_slot: self.deployed_slot,
+        }
+    }
+
+    /// Writes the params as JSON, zstd-compressed if `path` ends in `.zst`. Returns the path
+    /// written, which ends in `.age` if the params were encrypted.
+    pub fn write(&self, path: &str) -> anyhow::Result<PathBuf> {
+        write_artifact(path, serde_json::to_string_pretty(self)?.as_bytes())
+            .map_err(|e| anyhow!("Failed to write verified params: {}", e))
+    }
+
+    pub fn read(path: &str) -> anyhow::Result<VerifiedParamsFile> {
+        let contents =
+            read_artifact(path).map_err(|e| anyhow!("Failed to read verified params: {}", e))?;
+        Ok(serde_json::from_slice(&contents)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::Keypair;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let keypair = Keypair::new();
+        let input_params = InputParams {
+            version: "0.4.1".to_string(),
+            git_url: "https://github.com/Ellipsis-Labs/phoenix-v1".to_string(),
+            commit: "abc".to_string(),
+            args: vec!["--library-name".to_string(), "phoenix".to_string()],
+            deployed_slot: 42,
+        };
+        let mut file =
+            VerifiedParamsFile::new(&Pubkey::new_unique(), &input_params, "hash".to_string());
+        assert_eq!(file.verify_signature().unwrap(), None);
+        assert!(file.check_signature(false).is_err());
+        assert_eq!(file.check_signature(true).unwrap(), None);
+
+        file.sign(&keypair).unwrap();
+        assert_eq!(file.check_signature(false).unwrap(), Some(keypair.pubkey()));
+        assert_eq!(file.verify_signature().unwrap(), Some(keypair.pubkey()));
+
+        file.deployed_slot = 43;
+        assert!(file.verify_signature().is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use anyhow::anyhow;
+use git2::{Repository, StatusOptions};
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+/// Never copied into a build from a directory that is not a git repository
+const COPY_EXCLUDES: [&str; 4] = [".git", "target", "node_modules", ".env"];
+
+/// Recursively finds every Cargo.toml below `root`, without following symlinks.
+pub fn find_cargo_tomls(root: &Path) -> Vec<PathBuf> {
+    let mut manifests = vec![];
+    let mut directories = vec![root.to_path_buf()];
+    while let Some(directory) = directories.pop() {
+        let Ok(entries) = std::fs::read_dir(&directory) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                directories.push(entry.path());
+            } else if entry.file_name() == "Cargo.toml" {
+                manifests.push(entry.path());
+            }
+        }
+    }
+    manifests.sort();
+    manifests
+}
+
+/// Files below `root`, relative to it, skipping `COPY_EXCLUDES`.
+fn walk_files(root: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let mut directories = vec![PathBuf::new()];
+    while let Some(directory) = directories.pop() {
+        for entry in std::fs::read_dir(root.join(&directory))? {
+            let entry = entry?;
+            if COPY_EXCLUDES
+                .iter()
+                .any(|exclude| entry.file_name() == *exclude)
+            {
+                continue;
+            }
+            let path = directory.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                directories.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Files of `root` that belong in a build, relative to it: the files git tracks, or everything
+/// but build output and secrets outside a git repository.
+pub fn list_source_files(root: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let Ok(repo) = Repository::discover(root) else {
+        return walk_files(root);
+    };
+    let Some(workdir) = repo.workdir().map(Path::to_path_buf) else {
+        return walk_files(root);
+    };
+    // Paths are relative to the root of the repository, which may be above `root`
+    let prefix = root
+        .canonicalize()?
+        .strip_prefix(workdir.canonicalize()?)
+        .map_err(|_| anyhow!("{} is outside of its git repository", root.display()))?
+        .to_path_buf();
+
+    let mut options = StatusOptions::new();
+    options.include_unmodified(true).include_untracked(false);
+    let mut files = vec![];
+    for entry in repo.statuses(Some(&mut options))?.iter() {
+        let path = PathBuf::from(String::from_utf8(entry.path_bytes().to_vec())?);
+        let Ok(path) = path.strip_prefix(&prefix) else {
+            continue;
+        };
+        let path = path.to_path_buf();
+        let full_path = root.join(&path);
+        if full_path.is_dir() {
+            // Submodules are listed as a single entry
+            files.extend(
+                walk_files(&full_path)?
+                    .into_iter()
+                    .map(|file| path.join(file)),
+            );
+        } else if full_path.exists() {
+            // Tracked files deleted from the working tree are still listed
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Copies the source files of `root` into `dest`, leaving out what git does not track. Returns the
+/// number of files copied.
+pub fn copy_source(root: &Path, dest: &Path) -> anyhow::Result<usize> {
+    let files = list_source_files(root)?;
+    for file in &files {
+        let dest_file = dest.join(file);
+        if let Some(parent) = dest_file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(root.join(file), &dest_file)
+            .map_err(|e| anyhow!("Failed to copy {}: {}", file.display(), e))?;
+    }
+    Ok(files.len())
+}
+
+/// Builds the value of a docker `--mount` flag binding `source` to `target`. Unlike `-v`,
+/// the source may contain `:`, and quoting the field keeps `,` and `"` intact.
+pub fn bind_mount_arg(source: &Path, target: &Path) -> OsString {
+    fn quoted(key: &str, value: &Path) -> OsString {
+        let mut field = OsString::from("\"");
+        field.push(key);
+        field.push("=");
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::{OsStrExt, OsStringExt};
+            let escaped = value
+                .as_os_str()
+                .as_bytes()
+                .iter()
+                .flat_map(|&b| if b == b'"' { vec![b'"', b'"'] } else { vec![b] })
+                .collect::<Vec<_>>();
+            field.push(OsString::from_vec(escaped));
+        }
+        #[cfg(not(unix))]
+        field.push(value.to_string_lossy().replace('"', "\"\""));
+        field.push("\"");
+        field
+    }
+
+    let mut arg = OsString::from("type=bind,");
+    arg.push(quoted("source", source));
+    arg.push(",");
+    arg.push(quoted("target", target));
+    arg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bind_mount_arg() {
+        assert_eq!(
+            bind_mount_arg(Path::new("/home/me/my \"docs\", v1:2"), Path::new("/build")),
+            OsString::from("type=bind,\"source=/home/me/my \"\"docs\"\", v1:2\",\"target=/build\"")
+        );
+    }
+
+    #[test]
+    fn test_list_source_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for dir in ["src", "target/deploy", "node_modules/pkg"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in [
+            "Cargo.toml",
+            "src/lib.rs",
+            "target/deploy/a.so",
+            "node_modules/pkg/index.js",
+            ".env",
+        ] {
+            std::fs::write(root.join(file), "").unwrap();
+        }
+        let expected = vec![PathBuf::from("Cargo.toml"), PathBuf::from("src/lib.rs")];
+        assert_eq!(walk_files(root).unwrap(), expected);
+
+        let repo = Repository::init(root).unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\nnode_modules/\n.env\n").unwrap();
+        let mut index = repo.index().unwrap();
+        for file in [".gitignore", "Cargo.toml", "src/lib.rs"] {
+            index.add_path(Path::new(file)).unwrap();
+        }
+        index.write().unwrap();
+        // Untracked files are local, they are not part of the source
+        std::fs::write(root.join("notes.txt"), "").unwrap();
+        let mut expected = expected;
+        expected.insert(0, PathBuf::from(".gitignore"));
+        assert_eq!(list_source_files(root).unwrap(), expected);
+        assert_eq!(
+            list_source_files(&root.join("src")).unwrap(),
+            vec![PathBuf::from("lib.rs")]
+        );
+    }
+
+    #[test]
+    fn test_find_cargo_tomls() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let program = root.join("programs").join("mon programme é");
+        std::fs::create_dir_all(&program).unwrap();
+        std::fs::write(root.join("Cargo.toml"), "").unwrap();
+        std::fs::write(program.join("Cargo.toml"), "").unwrap();
+        std::fs::write(program.join("lib.rs"), "").unwrap();
+
+        assert_eq!(
+            find_cargo_tomls(root),
+            vec![root.join("Cargo.toml"), program.join("Cargo.toml")]
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+/// A saved `verify-from-repo` invocation, run with `solana-verify verify <name>`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    pub program_id: String,
+    pub repo_url: String,
+    /// Verified commit, the head of the default branch if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mount_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub library_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_image: Option<String>,
+    #[serde(default)]
+    pub bpf: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cargo_args: Vec<String>,
+    /// RPC url, used unless `--url` is given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Keypair uploading the verification PDA
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keypair: Option<String>,
+}
+
+/// Profiles by name, stored as JSON.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ProfileStore {
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl ProfileStore {
+    /// `~/.config/solana-verify/profiles.json`
+    pub fn default_path() -> anyhow::Result<PathBuf> {
+        let home = std::env::var_os("HOME")
+            .ok_or_else(|| anyhow!("Unable to locate the profile store: HOME is not set"))?;
+        Ok(PathBuf::from(home)
+            .join(".config")
+            .join("solana-verify")
+            .join("profiles.json"))
+    }
+
+    /// Loads the store at `path`, which is empty if the file does not exist yet.
+    pub fn load(path: &Path) -> anyhow::Result<ProfileStore> {
+        if !path.exists() {
+            return Ok(ProfileStore::default());
+        }
+        let contents = std::fs::read_to_string(path)?;
+        serde_json::from_str(&contents)
+            .map_err(|e| anyhow!("Invalid profile store {}: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> anyhow::Result<&Profile> {
+        self.profiles.get(name).ok_or_else(|| {
+            anyhow!(
+                "No profile named {}, add one with `solana-verify profile add {}`",
+                name,
+                name
+            )
+        })
+    }
+}
+
+/// Saves `profile` as `name`, replacing a profile of the same name only if `force` is set.
+pub fn add_profile(path: &Path, name: &str, profile: Profile, force: bool) -> anyhow::Result<()> {
+    let mut store = ProfileStore::load(path)?;
+    if store.profiles.contains_key(name) && !force {
+        return Err(anyhow!(
+            "Profile {} already exists, pass --force to replace it",
+            name
+        ));
+    }
+    store.profiles.insert(name.to_string(), profile);
+    store.save(path)?;
+    println!("Saved profile {} to {}", name, path.display());
+    Ok(())
+}
+
+pub fn remove_profile(path: &Path, name: &str) -> anyhow::Result<()> {
+    let mut store = ProfileStore::load(path)?;
+    if store.profiles.remove(name).is_none() {
+        return Err(anyhow!("No profile named {}", name));
+    }
+    store.save(path)?;
+    println!("Removed profile {}", name);
+    Ok(())
+}
+
+pub fn list_profiles(path: &Path) -> anyhow::Result<()> {
+    let store = ProfileStore::load(path)?;
+    if store.profiles.is_empty() {
+        println!("No profiles in {}", path.display());
+        return Ok(());
+    }
+    for (name, profile) in &store.profiles {
+        println!(
+            "{}: {} from {}{}",
+            name,
+            profile.program_id,
+            profile.repo_url,
+            profile
+                .commit_hash
+                .as_ref()
+                .map(|commit| format!(" at {}", commit))
+                .unwrap_or_default()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("solana-verify").join("profiles.json");
+        let profile = Profile {
+            program_id: "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY".to_string(),
+            repo_url: "https://github.com/Ellipsis-Labs/phoenix-v1".to_string(),
+            commit_hash: None,
+            mount_path: None,
+            library_name: Some("phoenix".to_string()),
+            base_image: None,
+            bpf: false,
+            cargo_args: vec![],
+            url: Some("https://api.mainnet-beta.solana.com".to_string()),
+            keypair: None,
+        };
+
+        add_profile(&path, "mainnet-phoenix", profile.clone(), false).unwrap();
+        assert!(add_profile(&path, "mainnet-phoenix", profile.clone(), false).is_err());
+        assert_eq!(
+            ProfileStore::load(&path)
+                .unwrap()
+                .get("mainnet-phoenix")
+                .unwrap(),
+            &profile
+        );
+
+        remove_profile(&path, "mainnet-phoenix").unwrap();
+        assert!(ProfileStore::load(&path)
+            .unwrap()
+            .get("mainnet-phoenix")
+            .is_err());
+    }
+}
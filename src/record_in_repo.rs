@@ -0,0 +1,305 @@
+use anyhow::anyhow;
+use base64::{prelude::BASE64_STANDARD, Engine};
+use log::info;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::github_release::{github_client, parse_github_repo, GITHUB_API_URL};
+use crate::params_file::VerifiedParamsFile;
+
+/// File in the verified repository listing how each of its programs was verified
+pub const VERIFIED_BUILDS_FILE: &str = "verified-builds.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedBuild {
+    pub program_id: String,
+    pub commit: String,
+    pub executable_hash: String,
+    /// Command reproducing the verification
+    pub command: String,
+    pub solana_verify_version: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct VerifiedBuilds {
+    pub builds: Vec<RecordedBuild>,
+}
+
+impl RecordedBuild {
+    pub fn new(params_file: &VerifiedParamsFile) -> RecordedBuild {
+        let mut command = vec![
+            "solana-verify".to_string(),
+            "verify-from-repo".to_string(),
+            params_file.git_url.clone(),
+            "--program-id".to_string(),
+            params_file.program_id.clone(),
+            "--commit-hash".to_string(),
+            params_file.commit.clone(),
+        ];
+        command.extend(params_file.args.iter().cloned());
+        RecordedBuild {
+            program_id: params_file.program_id.clone(),
+            commit: params_file.commit.clone(),
+            executable_hash: params_file.executable_hash.clone(),
+            command: command
+                .iter()
+                .map(|arg| shell_quote(arg))
+                .collect::<Vec<_>>()
+                .join(" "),
+            solana_verify_version: params_file.version.clone(),
+        }
+    }
+}
+
+fn shell_quote(arg: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "-_./:=@,+".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Adds `build` to the contents of a verified builds file, replacing the previous build of
+/// the same program.
+pub fn record_build(existing: Option<&str>, build: RecordedBuild) -> anyhow::Result<String> {
+    let mut verified_builds: VerifiedBuilds = match existing {
+        Some(existing) => serde_json::from_str(existing)
+            .map_err(|e| anyhow!("Failed to parse {}: {}", VERIFIED_BUILDS_FILE, e))?,
+        None => VerifiedBuilds::default(),
+    };
+    verified_builds
+        .builds
+        .retain(|recorded| recorded.program_id != build.program_id);
+    verified_builds.builds.push(build);
+    verified_builds
+        .builds
+        .sort_by(|a, b| a.program_id.cmp(&b.program_id));
+    Ok(serde_json::to_string_pretty(&verified_builds)? + "\n")
+}
+
+#[derive(Debug, Deserialize)]
+struct Repository {
+    default_branch: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitRef {
+    object: GitObject,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitObject {
+    sha: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct FileContents {
+    sha: String,
+    content: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PullRequest {
+    html_url: String,
+}
+
+async fn github_json<T: serde::de::DeserializeOwned>(
+    request: reqwest::RequestBuilder,
+    action: &str,
+) -> anyhow::Result<T> {
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("Failed to {}: {}", action, response.text().await?));
+    }
+    Ok(response.json().await?)
+}
+
+/// Creates `branch` from `base` unless it already exists.
+async fn create_branch(
+    client: &Client,
+    repo_api_url: &str,
+    branch: &str,
+    base: &str,
+) -> anyhow::Result<()> {
+    let base_ref: GitRef = github_json(
+        client.get(format!("{}/git/ref/heads/{}", repo_api_url, base)),
+        &format!("look up branch {}", base),
+    )
+    .await?;
+    let response = client
+        .post(format!("{}/git/refs", repo_api_url))
+        .json(&serde_json::json!({
+            "ref": format!("refs/heads/{}", branch),
+            "sha": base_ref.object.sha,
+        }))
+        .send()
+        .await?;
+    match response.status() {
+        status if status.is_success() => println!("Created branch {}", branch),
+        StatusCode::UNPROCESSABLE_ENTITY => info!("Updating existing branch {}", branch),
+        _ => {
+            return Err(anyhow!(
+                "Failed to create branch {}: {}",
+                branch,
+                response.text().await?
+            ))
+        }
+    }
+    Ok(())
+}
+
+async fn get_file(
+    client: &Client,
+    repo_api_url: &str,
+    path: &str,
+    branch: &str,
+) -> anyhow::Result<Option<(String, String)>> {
+    let response = client
+        .get(format!("{}/contents/{}", repo_api_url, path))
+        .query(&[("ref", branch)])
+        .send()
+        .await?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !response.status().is_success() {
+        return Err(anyhow!(
+            "Failed to read {}: {}",
+            path,
+            response.text().await?
+        ));
+    }
+    let file: FileContents = response.json().await?;
+    let content: String = file.content.split_whitespace().collect();
+    Ok(Some((
+        String::from_utf8(BASE64_STANDARD.decode(content)?)?,
+        file.sha,
+    )))
+}
+
+/// Records the verification in `params_path` in the verified builds file of the verified
+/// repository, committing it to `branch` and opening a pull request against `base`, the
+/// default branch if not given.
+pub async fn record_in_repo(
+    params_path: &str,
+    branch: Option<String>,
+    base: Option<String>,
+    path: &str,
+    token: &str,
+) -> anyhow::Result<()> {
+    let params_file = VerifiedParamsFile::read(params_path)?;
+    params_file.verify_signature()?;
+    let (owner, repo) = parse_github_repo(&params_file.git_url)?;
+    let repo_api_url = format!("{}/repos/{}/{}", GITHUB_API_URL, owner, repo);
+    let client = github_client(token)?;
+
+    let base = match base {
+        Some(base) => base,
+        None => {
+            let repository: Repository = github_json(
+                client.get(&repo_api_url),
+                &format!("look up {}/{}", owner, repo),
+            )
+            .await?;
+            repository.default_branch
+        }
+    };
+    let branch = branch.unwrap_or_else(|| format!("solana-verify/{}", params_file.program_id));
+    create_branch(&client, &repo_api_url, &branch, &base).await?;
+
+    let existing = get_file(&client, &repo_api_url, path, &branch).await?;
+    let build = RecordedBuild::new(&params_file);
+    println!("Verify command: {}", build.command);
+    let content = record_build(
+        existing.as_ref().map(|(content, _)| content.as_str()),
+        build,
+    )?;
+    if existing.as_ref().map(|(content, _)| content) == Some(&content) {
+        println!("{} already records this verification", path);
+    } else {
+        let mut body = serde_json::json!({
+            "message": format!(
+                "Record verified build of {} at {}",
+                params_file.program_id, params_file.commit
+            ),
+            "content": BASE64_STANDARD.encode(&content),
+            "branch": branch,
+        });
+        if let Some((_, sha)) = &existing {
+            body["sha"] = sha.as_str().into();
+        }
+        let _: serde_json::Value = github_json(
+            client
+                .put(format!("{}/contents/{}", repo_api_url, path))
+                .json(&body),
+            &format!("write {}", path),
+        )
+        .await?;
+        println!("Committed {} to {}", path, branch);
+    }
+
+    let response = client
+        .post(format!("{}/pulls", repo_api_url))
+        .json(&serde_json::json!({
+            "title": format!("Record verified build of {}", params_file.program_id),
+            "head": branch,
+            "base": base,
+            "body": format!(
+                "Records how program `{}` was verified at commit `{}` so the build can be reproduced from the repository.",
+                params_file.program_id, params_file.commit
+            ),
+        }))
+        .send()
+        .await?;
+    if response.status() == StatusCode::UNPROCESSABLE_ENTITY {
+        println!("A pull request from {} is already open", branch);
+    } else if !response.status().is_success() {
+        return Err(anyhow!(
+            "Failed to open a pull request: {}",
+            response.text().await?
+        ));
+    } else {
+        let pull_request: PullRequest = response.json().await?;
+        println!("Opened pull request {}", pull_request.html_url);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(program_id: &str, commit: &str) -> RecordedBuild {
+        RecordedBuild {
+            program_id: program_id.to_string(),
+            commit: commit.to_string(),
+            executable_hash: "hash".to_string(),
+            command: "solana-verify verify-from-repo".to_string(),
+            solana_verify_version: "0.4.0".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_record_build() {
+        let content = record_build(None, build("B", "1")).unwrap();
+        let content = record_build(Some(&content), build("A", "1")).unwrap();
+        let content = record_build(Some(&content), build("B", "2")).unwrap();
+
+        let recorded: VerifiedBuilds = serde_json::from_str(&content).unwrap();
+        assert_eq!(recorded.builds, vec![build("A", "1"), build("B", "2")]);
+        assert!(record_build(Some("not json"), build("A", "1")).is_err());
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("--library-name"), "--library-name");
+        assert_eq!(
+            shell_quote("https://github.com/Ellipsis-Labs/phoenix-v1"),
+            "https://github.com/Ellipsis-Labs/phoenix-v1"
+        );
+        assert_eq!(shell_quote("a b"), "'a b'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote(""), "''");
+    }
+}
@@ -0,0 +1,132 @@
+use anyhow::anyhow;
+use base64::{prelude::BASE64_STANDARD, Engine};
+use log::info;
+use reqwest::Client;
+use serde::Deserialize;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::Signature,
+    signer::{Signer, SignerError},
+};
+use std::{str::FromStr, time::Duration};
+
+use crate::block_on;
+
+/// Bearer token sent to the remote signer, if set
+pub const REMOTE_SIGNER_TOKEN_ENV: &str = "SOLANA_VERIFY_SIGNER_TOKEN";
+
+/// Whether a `--keypair` value designates a remote signer rather than a local keypair.
+pub fn is_remote_signer(path: &str) -> bool {
+    path.starts_with("https://") || path.starts_with("http://")
+}
+
+#[derive(Debug, Deserialize)]
+struct PubkeyResponse {
+    pubkey: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SignatureResponse {
+    signature: String,
+}
+
+/// Signs with a key held by an external signing service, e.g. a KMS or custody provider,
+/// for keys that cannot be exported to a keypair file. The service is called over HTTP:
+///
+/// - `GET <url>/pubkey` returns `{"pubkey": "<base58>"}`
+/// - `POST <url>/sign` with `{"pubkey": "<base58>", "message": "<base64>"}` returns
+///   `{"signature": "<base58>"}`
+#[derive(Debug)]
+pub struct RemoteSigner {
+    url: String,
+    client: Client,
+    pubkey: Pubkey,
+}
+
+impl RemoteSigner {
+    /// Connects to the signing service at `url` and fetches the public key it signs for.
+    pub fn connect(url: &str) -> anyhow::Result<RemoteSigner> {
+        let url = url.trim_end_matches('/').to_string();
+        let client = Client::builder().timeout(Duration::from_secs(60)).build()?;
+        let pubkey_url = format!("{}/pubkey", url);
+        let response: PubkeyResponse = block_on(async {
+            let mut request = client.get(&pubkey_url);
+            if let Ok(token) = std::env::var(REMOTE_SIGNER_TOKEN_ENV) {
+                request = request.bearer_auth(token);
+            }
+            request.send().await?.error_for_status()?.json().await
+        })?
+        .map_err(|e| {
+            anyhow!(
+                "Failed to get the public key of remote signer {}: {}",
+                url,
+                e
+            )
+        })?;
+        let pubkey = Pubkey::from_str(&response.pubkey).map_err(|e| {
+            anyhow!(
+                "Remote signer {} returned an invalid public key: {}",
+                url,
+                e
+            )
+        })?;
+        info!("Using remote signer {} at {}", pubkey, url);
+        Ok(RemoteSigner {
+            url,
+            client,
+            pubkey,
+        })
+    }
+
+    fn sign(&self, message: &[u8]) -> anyhow::Result<Signature> {
+        let response: SignatureResponse = block_on(async {
+            let mut request =
+                self.client
+                    .post(format!("{}/sign", self.url))
+                    .json(&serde_json::json!({
+                        "pubkey": self.pubkey.to_string(),
+                        "message": BASE64_STANDARD.encode(message),
+                    }));
+            if let Ok(token) = std::env::var(REMOTE_SIGNER_TOKEN_ENV) {
+                request = request.bearer_auth(token);
+            }
+            request.send().await?.error_for_status()?.json().await
+        })??;
+        let signature = Signature::from_str(&response.signature)?;
+        if !signature.verify(self.pubkey.as_ref(), message) {
+            return Err(anyhow!(
+                "Remote signer returned a signature that is not valid for {}",
+                self.pubkey
+            ));
+        }
+        Ok(signature)
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.pubkey)
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        self.sign(message)
+            .map_err(|e| SignerError::Custom(format!("Remote signer {} failed: {}", self.url, e)))
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_remote_signer() {
+        assert!(is_remote_signer("https://signer.example.com/v1"));
+        assert!(is_remote_signer("http://localhost:8080"));
+        assert!(!is_remote_signer("~/.config/solana/id.json"));
+        assert!(!is_remote_signer("usb://ledger"));
+    }
+}
@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    audit::DependencyAudit,
+    build_cache::BuildCacheUse,
+    compression::{read_artifact, write_artifact},
+    diagnostics::BuildDiagnostics,
+    resource_usage::ResourceUsage,
+    sbom::Sbom,
+};
+
+/// Output of `cargo tree --locked` captured in the build container, so the dependency closure
+/// of a verified binary can be audited without rebuilding it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DependencySnapshot {
+    pub sha256: String,
+    pub cargo_tree: String,
+}
+
+impl DependencySnapshot {
+    pub fn new(cargo_tree: String) -> DependencySnapshot {
+        DependencySnapshot {
+            sha256: hex::encode(Sha256::digest(cargo_tree.as_bytes())),
+            cargo_tree,
+        }
+    }
+}
+
+/// Outcome of `verify-from-repo`, written with `--report`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerificationReport {
+    pub version: String,
+    pub program_id: String,
+    pub repo_url: String,
+    pub commit: String,
+    pub args: Vec<String>,
+    pub executable_hash: String,
+    pub on_chain_hash: String,
+    pub matches: bool,
+    /// Docker image the program was built in, which may be a fallback for its Solana version
+    pub image: String,
+    pub dependencies: Option<DependencySnapshot>,
+    /// CycloneDX SBOM of the crates the program was built with
+    #[serde(default)]
+    pub sbom: Option<Sbom>,
+    /// RustSec advisories matching the dependencies, with `--audit-deps`
+    #[serde(default)]
+    pub audit: Option<DependencyAudit>,
+    /// Compiler warnings and errors of the build
+    pub diagnostics: Option<BuildDiagnostics>,
+    /// Time, memory and CPU the build took
+    #[serde(default)]
+    pub resource_usage: Option<ResourceUsage>,
+    /// Compile cache the build ran with
+    pub build_cache: Option<BuildCacheUse>,
+}
+
+impl VerificationReport {
+    /// Writes the report as JSON, zstd-compressed if `path` ends in `.zst`.
+    pub fn write(&self, path: &str) -> anyhow::Result<()> {
+        let path = write_artifact(path, serde_json::to_string_pretty(self)?.as_bytes())?;
+        println!("Verification report written to {}", path.display());
+        Ok(())
+    }
+
+    /// Reads a report written by `write`.
+    pub fn read(path: &str) -> anyhow::Result<VerificationReport> {
+        let contents = read_artifact(path)?;
+        serde_json::from_slice(&contents)
+            .map_err(|e| anyhow::anyhow!("Invalid verification report {}: {}", path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dependency_snapshot_digest() {
+        let snapshot = DependencySnapshot::new("phoenix v0.1.0\n".to_string());
+        assert_eq!(
+            snapshot.sha256,
+            hex::encode(Sha256::digest(b"phoenix v0.1.0\n"))
+        );
+        assert_ne!(
+            snapshot.sha256,
+            DependencySnapshot::new("phoenix v0.1.1\n".to_string()).sha256
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{process::Stdio, time::Duration};
+
+use crate::docker::docker_command;
+use crate::humanize::{format_bytes, format_duration};
+
+const MIB: u64 = 1024 * 1024;
+
+/// Headroom added to the peak memory of a build when suggesting a limit
+const MEMORY_HEADROOM: f64 = 1.25;
+
+/// Resources a build container used, read from its cgroup once the build finished.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    pub duration_secs: u64,
+    /// Not reported by kernels older than 5.19 on cgroup v2
+    pub peak_memory_bytes: Option<u64>,
+    pub cpu_seconds: Option<f64>,
+    /// Processes of the build killed for exceeding the memory limit
+    pub oom_kills: u64,
+}
+
+fn read_container_file(container_id: &str, path: &str) -> Option<String> {
+    docker_command()
+        .args(["exec", container_id, "cat", path])
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Value of `key` in a cgroup file of `key value` lines, such as cpu.stat or memory.events.
+fn parse_keyed_value(contents: &str, key: &str) -> Option<u64> {
+    contents.lines().find_map(|line| {
+        let (name, value) = line.split_once(' ')?;
+        (name == key).then(|| value.trim().parse().ok()).flatten()
+    })
+}
+
+/// Memory limit to suggest for `SVB_DOCKER_MEMORY_LIMIT`, the peak plus some headroom
+/// rounded up to 512 MiB, in docker's format.
+// `is_multiple_of` needs Rust 1.87
+#[allow(clippy::manual_is_multiple_of)]
+pub fn suggest_memory_limit(peak_memory_bytes: u64) -> String {
+    let megabytes = (peak_memory_bytes as f64 * MEMORY_HEADROOM / MIB as f64).ceil() as u64;
+    let megabytes = megabytes.div_ceil(512).max(1) * 512;
+    if megabytes % 1024 == 0 {
+        format!("{}g", megabytes / 1024)
+    } else {
+        format!("{}m", megabytes)
+    }
+}
+
+impl ResourceUsage {
+    /// Reads the usage of the container, on cgroup v2 or v1.
+    pub fn collect(container_id: &str, duration: Duration) -> ResourceUsage {
+        let read = |path: &str| read_container_file(container_id, path);
+        let peak_memory_bytes = read("/sys/fs/cgroup/memory.peak")
+            .or_else(|| read("/sys/fs/cgroup/memory/memory.max_usage_in_bytes"))
+            .and_then(|peak| peak.trim().parse().ok());
+        let cpu_seconds = read("/sys/fs/cgroup/cpu.stat")
+            .and_then(|stat| parse_keyed_value(&stat, "usage_usec"))
+            .map(|usec| usec as f64 / 1e6)
+            .or_else(|| {
+                read("/sys/fs/cgroup/cpuacct/cpuacct.usage")
+                    .and_then(|nsec| nsec.trim().parse::<u64>().ok())
+                    .map(|nsec| nsec as f64 / 1e9)
+            });
+        let oom_kills = read("/sys/fs/cgroup/memory.events")
+            .or_else(|| read("/sys/fs/cgroup/memory/memory.oom_control"))
+            .and_then(|events| parse_keyed_value(&events, "oom_kill"))
+            .unwrap_or_default();
+        ResourceUsage {
+            duration_secs: duration.as_secs(),
+            peak_memory_bytes,
+            cpu_seconds,
+            oom_kills,
+        }
+    }
+
+    pub fn print_summary(&self) {
+        info!(
+            "Build took {}",
+            format_duration(Duration::from_secs(self.duration_secs))
+        );
+        if let Some(cpu_seconds) = self.cpu_seconds {
+            info!(
+                "Build CPU time: {}",
+                format_duration(Duration::from_secs_f64(cpu_seconds))
+            );
+        }
+        if let Some(peak) = self.peak_memory_bytes {
+            info!("Build peak memory: {}", format_bytes(peak));
+            info!(
+                "Suggested limit for this build: SVB_DOCKER_MEMORY_LIMIT={}",
+                suggest_memory_limit(peak)
+            );
+        }
+        if self.oom_kills > 0 {
+            warn!(
+                "{} build process(es) were killed for running out of memory, raise SVB_DOCKER_MEMORY_LIMIT or the memory of the docker host",
+                self.oom_kills
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_keyed_value() {
+        let cpu_stat = "usage_usec 540123456\nuser_usec 500000000\nsystem_usec 40123456\n";
+        assert_eq!(parse_keyed_value(cpu_stat, "usage_usec"), Some(540123456));
+        let events = "low 0\nhigh 0\nmax 12\noom 1\noom_kill 1\n";
+        assert_eq!(parse_keyed_value(events, "oom_kill"), Some(1));
+        assert_eq!(parse_keyed_value(events, "oom_group_kill"), None);
+    }
+
+    #[test]
+    fn test_suggest_memory_limit() {
+        assert_eq!(suggest_memory_limit(1600 * MIB), "2g");
+        assert_eq!(suggest_memory_limit(2000 * MIB), "2560m");
+        assert_eq!(suggest_memory_limit(1), "512m");
+    }
+}
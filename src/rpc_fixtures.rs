@@ -0,0 +1,253 @@
+use anyhow::anyhow;
+use async_trait::async_trait;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use solana_client::{
+    client_error::{ClientErrorKind, Result as ClientResult},
+    rpc_request::RpcRequest,
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+use solana_rpc_client::http_sender::HttpSender;
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::File,
+    io::Write,
+    path::Path,
+    sync::{Mutex, OnceLock},
+};
+
+use crate::rpc_headers::http_sender;
+
+/// File of a fixture directory holding one recorded RPC exchange per line
+pub const FIXTURE_FILE: &str = "rpc.jsonl";
+
+/// Fixture file responses are appended to, set with `--record-fixtures`
+static RECORDING: OnceLock<Mutex<File>> = OnceLock::new();
+/// Recorded responses served instead of the network, set with `--replay-fixtures`
+static REPLAY: OnceLock<Mutex<ReplayState>> = OnceLock::new();
+
+/// One RPC request of a recorded run and what the node answered.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Exchange {
+    method: String,
+    params: serde_json::Value,
+    #[serde(flatten)]
+    outcome: Outcome,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Outcome {
+    Result(serde_json::Value),
+    /// Errors are replayed as their message only
+    Error(String),
+}
+
+fn exchange_key(method: &str, params: &serde_json::Value) -> String {
+    format!("{} {}", method, params)
+}
+
+/// Recorded outcomes by request, in the order they were received.
+#[derive(Debug, Default)]
+struct ReplayState {
+    outcomes: HashMap<String, VecDeque<Outcome>>,
+}
+
+impl ReplayState {
+    fn parse(contents: &str) -> anyhow::Result<ReplayState> {
+        let mut state = ReplayState::default();
+        for (number, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let exchange: Exchange = serde_json::from_str(line)
+                .map_err(|e| anyhow!("Invalid fixture on line {}: {}", number + 1, e))?;
+            state
+                .outcomes
+                .entry(exchange_key(&exchange.method, &exchange.params))
+                .or_default()
+                .push_back(exchange.outcome);
+        }
+        Ok(state)
+    }
+
+    /// Next outcome recorded for the request. The last one is repeated once the others are
+    /// consumed, since replays may poll more often than the recorded run.
+    fn next(&mut self, key: &str) -> Option<Outcome> {
+        let outcomes = self.outcomes.get_mut(key)?;
+        if outcomes.len() > 1 {
+            outcomes.pop_front()
+        } else {
+            outcomes.front().cloned()
+        }
+    }
+}
+
+/// Records every RPC response of the run to `dir`, replacing an earlier recording.
+pub fn record_fixtures(dir: &Path) -> anyhow::Result<()> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| anyhow!("Failed to create {}: {}", dir.display(), e))?;
+    let path = dir.join(FIXTURE_FILE);
+    let file =
+        File::create(&path).map_err(|e| anyhow!("Failed to create {}: {}", path.display(), e))?;
+    let _ = RECORDING.set(Mutex::new(file));
+    Ok(())
+}
+
+/// Answers every RPC request of the run from the recording in `dir`, without network access.
+pub fn replay_fixtures(dir: &Path) -> anyhow::Result<()> {
+    let path = dir.join(FIXTURE_FILE);
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|e| anyhow!("Failed to read fixtures {}: {}", path.display(), e))?;
+    let _ = REPLAY.set(Mutex::new(ReplayState::parse(&contents)?));
+    Ok(())
+}
+
+/// Sends requests to the node and appends each exchange to the fixture file.
+struct RecordingSender {
+    inner: HttpSender,
+}
+
+#[async_trait]
+impl RpcSender for RecordingSender {
+    async fn send(
+        &self,
+        request: RpcRequest,
+        params: serde_json::Value,
+    ) -> ClientResult<serde_json::Value> {
+        let result = self.inner.send(request, params.clone()).await;
+        let exchange = Exchange {
+            method: request.to_string(),
+            params,
+            outcome: match &result {
+                Ok(value) => Outcome::Result(value.clone()),
+                Err(err) => Outcome::Error(err.to_string()),
+            },
+        };
+        if let Some(file) = RECORDING.get() {
+            let line = serde_json::to_string(&exchange).unwrap_or_default();
+            if let Err(err) = writeln!(file.lock().unwrap(), "{}", line) {
+                warn!("failed to record RPC fixture: {}", err);
+            }
+        }
+        result
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.inner.get_transport_stats()
+    }
+
+    fn url(&self) -> String {
+        self.inner.url()
+    }
+}
+
+/// Answers requests from the replayed fixtures.
+struct ReplaySender {
+    url: String,
+}
+
+#[async_trait]
+impl RpcSender for ReplaySender {
+    async fn send(
+        &self,
+        request: RpcRequest,
+        params: serde_json::Value,
+    ) -> ClientResult<serde_json::Value> {
+        let key = exchange_key(&request.to_string(), &params);
+        let outcome = REPLAY
+            .get()
+            .and_then(|state| state.lock().unwrap().next(&key));
+        match outcome {
+            Some(Outcome::Result(value)) => Ok(value),
+            Some(Outcome::Error(message)) => Err(ClientErrorKind::Custom(message).into()),
+            None => Err(ClientErrorKind::Custom(format!(
+                "No recorded response to {} in the replayed fixtures",
+                key
+            ))
+            .into()),
+        }
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        RpcTransportStats::default()
+    }
+
+    fn url(&self) -> String {
+        self.url.clone()
+    }
+}
+
+/// Sender for requests to `url`, recording or replaying them if fixtures are enabled.
+pub fn fixture_sender(url: String) -> Box<dyn RpcSender + Send + Sync> {
+    if REPLAY.get().is_some() {
+        Box::new(ReplaySender { url })
+    } else if RECORDING.get().is_some() {
+        Box::new(RecordingSender {
+            inner: http_sender(url),
+        })
+    } else {
+        Box::new(http_sender(url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_state() {
+        let recorded = [
+            Exchange {
+                method: "getSlot".to_string(),
+                params: serde_json::json!([{"commitment": "confirmed"}]),
+                outcome: Outcome::Result(serde_json::json!(100)),
+            },
+            Exchange {
+                method: "getSlot".to_string(),
+                params: serde_json::json!([{"commitment": "confirmed"}]),
+                outcome: Outcome::Result(serde_json::json!(101)),
+            },
+            Exchange {
+                method: "getBalance".to_string(),
+                params: serde_json::json!(["11111111111111111111111111111111"]),
+                outcome: Outcome::Error("node is behind".to_string()),
+            },
+        ];
+        let contents = recorded
+            .iter()
+            .map(|exchange| serde_json::to_string(exchange).unwrap() + "\n")
+            .collect::<String>();
+        assert!(contents.starts_with(
+            r#"{"method":"getSlot","params":[{"commitment":"confirmed"}],"result":100}"#
+        ));
+
+        let mut state = ReplayState::parse(&contents).unwrap();
+        let slot = exchange_key("getSlot", &serde_json::json!([{"commitment": "confirmed"}]));
+        assert_eq!(
+            state.next(&slot),
+            Some(Outcome::Result(serde_json::json!(100)))
+        );
+        assert_eq!(
+            state.next(&slot),
+            Some(Outcome::Result(serde_json::json!(101)))
+        );
+        assert_eq!(
+            state.next(&slot),
+            Some(Outcome::Result(serde_json::json!(101)))
+        );
+        assert_eq!(
+            state.next(&exchange_key(
+                "getBalance",
+                &serde_json::json!(["11111111111111111111111111111111"])
+            )),
+            Some(Outcome::Error("node is behind".to_string()))
+        );
+        assert_eq!(
+            state.next(&exchange_key("getSlot", &serde_json::json!([]))),
+            None
+        );
+
+        assert!(ReplayState::parse("not json\n").is_err());
+    }
+}
@@ -0,0 +1,122 @@
+use anyhow::anyhow;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use solana_rpc_client::http_sender::HttpSender;
+use std::{str::FromStr, sync::OnceLock, time::Duration};
+
+/// Headers sent with RPC requests, set with `--rpc-header`
+static RPC_HEADERS: OnceLock<Vec<RpcHeader>> = OnceLock::new();
+
+/// Timeout of RPC requests, the default of `HttpSender`
+const RPC_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A header required by an RPC provider, e.g. for API key authentication. Written
+/// `NAME: VALUE` to send it to every endpoint, or `HOST=NAME: VALUE` to send it only to the
+/// endpoint at HOST, so a key for one provider does not leak to the others.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcHeader {
+    pub host: Option<String>,
+    pub name: HeaderName,
+    pub value: HeaderValue,
+}
+
+impl FromStr for RpcHeader {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let (name, value) = s
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Invalid RPC header {:?}, expected NAME: VALUE", s))?;
+        // Header names cannot contain '=', so one separates the host
+        let (host, name) = match name.split_once('=') {
+            Some((host, name)) => (Some(host.trim().to_lowercase()), name),
+            None => (None, name),
+        };
+        let mut value = HeaderValue::from_str(value.trim())
+            .map_err(|e| anyhow!("Invalid value of RPC header {}: {}", name.trim(), e))?;
+        value.set_sensitive(true);
+        Ok(RpcHeader {
+            host,
+            name: HeaderName::from_str(name.trim())
+                .map_err(|e| anyhow!("Invalid RPC header name {:?}: {}", name.trim(), e))?,
+            value,
+        })
+    }
+}
+
+pub fn set_rpc_headers(headers: Vec<RpcHeader>) {
+    let _ = RPC_HEADERS.set(headers);
+}
+
+/// Headers to send to the RPC endpoint at `url`.
+pub fn rpc_headers_for(url: &str) -> HeaderMap {
+    let host = reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_lowercase));
+    let mut headers = HeaderMap::new();
+    for header in RPC_HEADERS.get().into_iter().flatten() {
+        if header.host.is_none() || header.host == host {
+            headers.append(header.name.clone(), header.value.clone());
+        }
+    }
+    headers
+}
+
+/// HTTP sender for `url` with the headers configured for it.
+pub fn http_sender(url: String) -> HttpSender {
+    let extra_headers = rpc_headers_for(&url);
+    if extra_headers.is_empty() {
+        return HttpSender::new(url);
+    }
+    let mut headers = HttpSender::default_headers();
+    headers.extend(extra_headers);
+    match reqwest::Client::builder()
+        .default_headers(headers)
+        .timeout(RPC_TIMEOUT)
+        .pool_idle_timeout(RPC_TIMEOUT)
+        .build()
+    {
+        Ok(client) => HttpSender::new_with_client(url, client),
+        Err(err) => {
+            println!(
+                "Warning: unable to send the RPC headers, sending requests without them: {}",
+                err
+            );
+            HttpSender::new(url)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rpc_header() {
+        let header = RpcHeader::from_str("x-api-key: abc:123").unwrap();
+        assert_eq!(header.host, None);
+        assert_eq!(header.name, "x-api-key");
+        assert_eq!(header.value, "abc:123");
+        assert!(header.value.is_sensitive());
+
+        let scoped = RpcHeader::from_str("Mainnet.Helius-RPC.com=Authorization: Bearer t").unwrap();
+        assert_eq!(scoped.host.as_deref(), Some("mainnet.helius-rpc.com"));
+        assert_eq!(scoped.name, "authorization");
+
+        assert!(RpcHeader::from_str("x-api-key").is_err());
+        assert!(RpcHeader::from_str("bad header: value").is_err());
+    }
+
+    #[test]
+    fn test_rpc_headers_for() {
+        set_rpc_headers(vec![
+            RpcHeader::from_str("x-client: solana-verify").unwrap(),
+            RpcHeader::from_str("rpc.example.com=x-api-key: secret").unwrap(),
+        ]);
+        let headers = rpc_headers_for("https://rpc.example.com/v1");
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers["x-api-key"], "secret");
+        let headers = rpc_headers_for("https://api.mainnet-beta.solana.com");
+        assert_eq!(headers.len(), 1);
+        assert!(!headers.contains_key("x-api-key"));
+    }
+}
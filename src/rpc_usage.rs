@@ -0,0 +1,112 @@
+use async_trait::async_trait;
+use solana_client::{
+    client_error::{ClientErrorKind, Result as ClientResult},
+    rpc_client::{RpcClient, RpcClientConfig},
+    rpc_request::RpcRequest,
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+use solana_sdk::commitment_config::CommitmentConfig;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    OnceLock,
+};
+
+use crate::humanize::{format_bytes, format_number};
+use crate::rpc_fixtures::fixture_sender;
+
+/// Maximum number of RPC calls of a run, set with `--rpc-budget`
+static RPC_BUDGET: OnceLock<u64> = OnceLock::new();
+
+static RPC_CALLS: AtomicU64 = AtomicU64::new(0);
+static BYTES_SENT: AtomicU64 = AtomicU64::new(0);
+static BYTES_RECEIVED: AtomicU64 = AtomicU64::new(0);
+
+pub fn set_rpc_budget(budget: u64) {
+    let _ = RPC_BUDGET.set(budget);
+}
+
+/// Sends requests and accounts for them in the usage of the run. Sizes are those of the
+/// JSON bodies, which is what metered RPC providers bill on.
+struct MeteredSender {
+    inner: Box<dyn RpcSender + Send + Sync>,
+}
+
+#[async_trait]
+impl RpcSender for MeteredSender {
+    async fn send(
+        &self,
+        request: RpcRequest,
+        params: serde_json::Value,
+    ) -> ClientResult<serde_json::Value> {
+        if let Some(budget) = RPC_BUDGET.get() {
+            if RPC_CALLS.load(Ordering::Relaxed) >= *budget {
+                return Err(ClientErrorKind::Custom(format!(
+                    "RPC budget of {} calls exhausted before {}, raise --rpc-budget to continue",
+                    budget, request
+                ))
+                .into());
+            }
+        }
+        RPC_CALLS.fetch_add(1, Ordering::Relaxed);
+        BYTES_SENT.fetch_add(
+            request
+                .build_request_json(0, params.clone())
+                .to_string()
+                .len() as u64,
+            Ordering::Relaxed,
+        );
+
+        let response = self.inner.send(request, params).await?;
+        BYTES_RECEIVED.fetch_add(response.to_string().len() as u64, Ordering::Relaxed);
+        Ok(response)
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.inner.get_transport_stats()
+    }
+
+    fn url(&self) -> String {
+        self.inner.url()
+    }
+}
+
+/// RPC client whose requests count towards the usage reported at the end of the run.
+pub fn new_rpc_client<U: ToString>(url: U, commitment: CommitmentConfig) -> RpcClient {
+    RpcClient::new_sender(
+        MeteredSender {
+            inner: fixture_sender(url.to_string()),
+        },
+        RpcClientConfig::with_commitment(commitment),
+    )
+}
+
+/// Prints the RPC calls made during the run, if any.
+pub fn print_rpc_usage() {
+    let calls = RPC_CALLS.load(Ordering::Relaxed);
+    if calls == 0 {
+        return;
+    }
+    let budget = RPC_BUDGET
+        .get()
+        .map(|budget| format!(" of a budget of {}", format_number(*budget)))
+        .unwrap_or_default();
+    println!(
+        "RPC usage: {} call(s){}, {} sent, {} received",
+        format_number(calls),
+        budget,
+        format_bytes(BYTES_SENT.load(Ordering::Relaxed)),
+        format_bytes(BYTES_RECEIVED.load(Ordering::Relaxed))
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024), "3.0 MiB");
+    }
+}
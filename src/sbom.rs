@@ -0,0 +1,324 @@
+use anyhow::anyhow;
+use cargo_lock::{Lockfile, Package};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub const SBOM_MEDIA_TYPE: &str = "application/vnd.cyclonedx+json";
+
+const SPEC_VERSION: &str = "1.5";
+
+/// What `cargo metadata` resolved for a package, which Cargo.lock does not record.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResolvedPackage {
+    pub features: Vec<String>,
+    pub license: Option<String>,
+}
+
+/// A CycloneDX software bill of materials of a build, listing every crate of its Cargo.lock
+/// with its checksum and the features it was compiled with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Sbom {
+    pub bom_format: String,
+    pub spec_version: String,
+    pub serial_number: String,
+    pub version: u32,
+    pub metadata: SbomMetadata,
+    pub components: Vec<Component>,
+    pub dependencies: Vec<ComponentDependencies>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SbomMetadata {
+    pub timestamp: String,
+    pub tools: Vec<Tool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub component: Option<Component>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tool {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Component {
+    #[serde(rename = "type")]
+    pub component_type: String,
+    #[serde(rename = "bom-ref")]
+    pub bom_ref: String,
+    pub name: String,
+    pub version: String,
+    pub purl: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hashes: Vec<Hash>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub licenses: Vec<License>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub properties: Vec<Property>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hash {
+    pub alg: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct License {
+    pub expression: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Property {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComponentDependencies {
+    #[serde(rename = "ref")]
+    pub dependency_ref: String,
+    pub depends_on: Vec<String>,
+}
+
+fn package_key(name: &str, version: &str) -> String {
+    format!("{}@{}", name, version)
+}
+
+/// Package URL of a crate. Crates not from crates.io are qualified with their source.
+fn purl(package: &Package) -> String {
+    let purl = format!("pkg:cargo/{}@{}", package.name, package.version);
+    match &package.source {
+        Some(source) if !source.is_default_registry() => {
+            format!("{}?repository_url={}", purl, source.url())
+        }
+        _ => purl,
+    }
+}
+
+/// Reads the features and licenses of the resolved packages from the output of
+/// `cargo metadata --format-version 1`, keyed by `name@version`.
+pub fn parse_cargo_metadata(metadata: &str) -> anyhow::Result<BTreeMap<String, ResolvedPackage>> {
+    #[derive(Deserialize)]
+    struct Metadata {
+        packages: Vec<MetadataPackage>,
+        resolve: Option<Resolve>,
+    }
+    #[derive(Deserialize)]
+    struct MetadataPackage {
+        id: String,
+        name: String,
+        version: String,
+        license: Option<String>,
+    }
+    #[derive(Deserialize)]
+    struct Resolve {
+        nodes: Vec<Node>,
+    }
+    #[derive(Deserialize)]
+    struct Node {
+        id: String,
+        #[serde(default)]
+        features: Vec<String>,
+    }
+
+    let metadata: Metadata = serde_json::from_str(metadata)
+        .map_err(|e| anyhow!("Unexpected output from cargo metadata: {}", e))?;
+    let features = metadata
+        .resolve
+        .map(|resolve| {
+            resolve
+                .nodes
+                .into_iter()
+                .map(|node| (node.id, node.features))
+                .collect::<BTreeMap<_, _>>()
+        })
+        .unwrap_or_default();
+    Ok(metadata
+        .packages
+        .into_iter()
+        .map(|package| {
+            let resolved = ResolvedPackage {
+                features: features.get(&package.id).cloned().unwrap_or_default(),
+                license: package.license,
+            };
+            (package_key(&package.name, &package.version), resolved)
+        })
+        .collect())
+}
+
+impl Sbom {
+    /// Lists the packages of `lockfile`, annotated with what cargo resolved for them.
+    /// `program` is the crate the executable was built from.
+    pub fn new(
+        lockfile: &Lockfile,
+        resolved: &BTreeMap<String, ResolvedPackage>,
+        program: Option<&str>,
+    ) -> Sbom {
+        let components = lockfile
+            .packages
+            .iter()
+            .map(|package| {
+                let resolved = resolved
+                    .get(&package_key(
+                        package.name.as_str(),
+                        &package.version.to_string(),
+                    ))
+                    .cloned()
+                    .unwrap_or_default();
+                Component {
+                    component_type: "library".to_string(),
+                    bom_ref: purl(package),
+                    name: package.name.to_string(),
+                    version: package.version.to_string(),
+                    purl: purl(package),
+                    hashes: package
+                        .checksum
+                        .iter()
+                        .map(|checksum| Hash {
+                            alg: "SHA-256".to_string(),
+                            content: checksum.to_string(),
+                        })
+                        .collect(),
+                    licenses: resolved
+                        .license
+                        .into_iter()
+                        .map(|expression| License { expression })
+                        .collect(),
+                    properties: if resolved.features.is_empty() {
+                        vec![]
+                    } else {
+                        vec![Property {
+                            name: "cargo:features".to_string(),
+                            value: resolved.features.join(","),
+                        }]
+                    },
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let dependencies = lockfile
+            .packages
+            .iter()
+            .map(|package| ComponentDependencies {
+                dependency_ref: purl(package),
+                depends_on: package
+                    .dependencies
+                    .iter()
+                    .filter_map(|dependency| {
+                        lockfile
+                            .packages
+                            .iter()
+                            .find(|package| dependency.matches(package))
+                            .map(purl)
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        // The program crate is the subject of the SBOM rather than one of its components
+        let (subject, components): (Vec<_>, Vec<_>) =
+            components.into_iter().partition(|component| {
+                program.is_some_and(|program| {
+                    component.name.replace('-', "_") == program.replace('-', "_")
+                })
+            });
+        let component = subject.into_iter().next().map(|component| Component {
+            component_type: "application".to_string(),
+            ..component
+        });
+
+        Sbom {
+            bom_format: "CycloneDX".to_string(),
+            spec_version: SPEC_VERSION.to_string(),
+            serial_number: format!("urn:uuid:{}", uuid::Uuid::new_v4()),
+            version: 1,
+            metadata: SbomMetadata {
+                timestamp: chrono::Utc::now().to_rfc3339(),
+                tools: vec![Tool {
+                    name: "solana-verify".to_string(),
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                }],
+                component,
+            },
+            components,
+            dependencies,
+        }
+    }
+
+    /// Writes the SBOM as JSON, zstd-compressed if `path` ends in `.zst`.
+    pub fn write(&self, path: &str) -> anyhow::Result<()> {
+        let path = crate::compression::write_artifact(
+            path,
+            serde_json::to_string_pretty(self)?.as_bytes(),
+        )?;
+        println!("SBOM written to {}", path.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "phoenix-v1"
+version = "0.2.4"
+dependencies = [
+ "borsh",
+]
+
+[[package]]
+name = "borsh"
+version = "0.10.3"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "4114279215a005bc675e386011e594e1d9b800918cea18fcadadcce864a2046b"
+"#;
+
+    const METADATA: &str = r#"{
+        "packages": [
+            {"id": "borsh 0.10.3 (registry+https://github.com/rust-lang/crates.io-index)", "name": "borsh", "version": "0.10.3", "license": "MIT OR Apache-2.0"},
+            {"id": "phoenix-v1 0.2.4 (path+file:///build)", "name": "phoenix-v1", "version": "0.2.4", "license": null}
+        ],
+        "resolve": {"nodes": [
+            {"id": "borsh 0.10.3 (registry+https://github.com/rust-lang/crates.io-index)", "features": ["default", "std"]},
+            {"id": "phoenix-v1 0.2.4 (path+file:///build)", "features": []}
+        ]}
+    }"#;
+
+    #[test]
+    fn test_sbom_from_lockfile() {
+        let lockfile = Lockfile::from_str(LOCKFILE).unwrap();
+        let resolved = parse_cargo_metadata(METADATA).unwrap();
+        let sbom = Sbom::new(&lockfile, &resolved, Some("phoenix_v1"));
+
+        let program = sbom.metadata.component.as_ref().unwrap();
+        assert_eq!(program.component_type, "application");
+        assert_eq!(program.purl, "pkg:cargo/phoenix-v1@0.2.4");
+
+        assert_eq!(sbom.components.len(), 1);
+        let borsh = &sbom.components[0];
+        assert_eq!(borsh.purl, "pkg:cargo/borsh@0.10.3");
+        assert_eq!(
+            borsh.hashes[0].content,
+            "4114279215a005bc675e386011e594e1d9b800918cea18fcadadcce864a2046b"
+        );
+        assert_eq!(borsh.licenses[0].expression, "MIT OR Apache-2.0");
+        assert_eq!(borsh.properties[0].value, "default,std");
+
+        assert_eq!(
+            sbom.dependencies[0].depends_on,
+            vec!["pkg:cargo/borsh@0.10.3".to_string()]
+        );
+        let json = serde_json::to_value(&sbom).unwrap();
+        assert_eq!(json["bomFormat"], "CycloneDX");
+        assert_eq!(json["components"][0]["bom-ref"], "pkg:cargo/borsh@0.10.3");
+    }
+}
@@ -0,0 +1,489 @@
+use anyhow::anyhow;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io::{BufRead, BufReader, Read},
+    net::{TcpListener, TcpStream},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use crate::api::sleep_unless_interrupted;
+use crate::cleanup::{check_interrupted, interrupted};
+use crate::daemon::{
+    check_program, set_connection_timeouts, write_json_response, ProgramStatus, WatchedProgram,
+};
+use crate::get_program_hash;
+use crate::git_provider::GitRemote;
+use crate::rpc_usage::new_rpc_client;
+
+pub const DEFAULT_SERVE_LISTEN: &str = "127.0.0.1:8080";
+
+/// Largest request body accepted, verification requests are a few hundred bytes
+const MAX_BODY_LEN: usize = 1 << 20;
+
+/// Jobs kept for status queries. Once reached, the oldest finished job is forgotten for each
+/// new one, and submissions are refused while every job is still pending.
+const MAX_JOBS: usize = 1000;
+
+/// Verifications waiting for the build worker. Submissions beyond it are refused.
+const MAX_QUEUED_JOBS: usize = 100;
+
+/// Connections handled at once, later ones are closed until a handler finishes
+const MAX_CONNECTIONS: usize = 32;
+
+/// Bearer token clients must send, required to listen beyond localhost
+const TOKEN_ENV: &str = "SVB_SERVE_TOKEN";
+
+// JSON-RPC 2.0 error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+/// A verification submitted to the service.
+#[derive(Debug, Clone, Serialize)]
+pub struct VerificationJob {
+    pub job_id: String,
+    pub state: JobState,
+    pub submitted_at: String,
+    pub request: WatchedProgram,
+    /// Outcome of the build, set once the job completes or fails
+    pub result: Option<ProgramStatus>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JobParams {
+    job_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProgramParams {
+    program_id: String,
+}
+
+/// Jobs of the service, shared between the request handlers and the build worker.
+#[derive(Default)]
+pub struct ServiceState {
+    jobs: Mutex<BTreeMap<String, VerificationJob>>,
+    queue: Mutex<VecDeque<String>>,
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl ToString) -> RpcError {
+        RpcError {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl ServiceState {
+    /// Queues a verification and returns its job.
+    pub fn submit(&self, request: WatchedProgram) -> anyhow::Result<VerificationJob> {
+        Pubkey::from_str(&request.program_id)
+            .map_err(|e| anyhow!("Invalid program id {}: {}", request.program_id, e))?;
+        if request.repo_url.is_empty() {
+            return Err(anyhow!("repo_url is required"));
+        }
+        // Only remotes: local paths and file:// URLs would build files of this machine, and a
+        // leading dash would be read by git as an option
+        if request.repo_url.starts_with('-') {
+            return Err(anyhow!("Invalid repository url {}", request.repo_url));
+        }
+        GitRemote::parse(&request.repo_url)?;
+        let mut jobs = self.jobs.lock().unwrap();
+        let queued = self.queue.lock().unwrap().len();
+        if queued >= MAX_QUEUED_JOBS {
+            return Err(anyhow!("{} verifications are queued, retry later", queued));
+        }
+        if jobs.len() >= MAX_JOBS {
+            let oldest_finished = jobs
+                .values()
+                .filter(|job| matches!(job.state, JobState::Completed | JobState::Failed))
+                .min_by(|a, b| a.submitted_at.cmp(&b.submitted_at))
+                .map(|job| job.job_id.clone())
+                .ok_or_else(|| anyhow!("{} jobs are pending, retry later", jobs.len()))?;
+            jobs.remove(&oldest_finished);
+        }
+        let job = VerificationJob {
+            job_id: uuid::Uuid::new_v4().to_string(),
+            state: JobState::Queued,
+            submitted_at: chrono::Utc::now().to_rfc3339(),
+            request,
+            result: None,
+        };
+        jobs.insert(job.job_id.clone(), job.clone());
+        self.queue.lock().unwrap().push_back(job.job_id.clone());
+        Ok(job)
+    }
+
+    pub fn job(&self, job_id: &str) -> Option<VerificationJob> {
+        self.jobs.lock().unwrap().get(job_id).cloned()
+    }
+
+    /// Takes the oldest queued job and marks it running.
+    fn next_job(&self) -> Option<VerificationJob> {
+        let job_id = self.queue.lock().unwrap().pop_front()?;
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.get_mut(&job_id)?;
+        job.state = JobState::Running;
+        Some(job.clone())
+    }
+
+    fn finish_job(&self, job_id: &str, state: JobState, result: ProgramStatus) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(job_id) {
+            job.state = state;
+            job.result = Some(result);
+        }
+    }
+}
+
+fn parse_params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e))
+}
+
+/// Runs one JSON-RPC method. `connection` is only needed by `getProgramHash`.
+fn call(
+    method: &str,
+    params: Value,
+    state: &ServiceState,
+    connection: &RpcClient,
+) -> Result<Value, RpcError> {
+    match method {
+        "submitVerification" => {
+            let job = state
+                .submit(parse_params(params)?)
+                .map_err(|e| RpcError::new(INVALID_PARAMS, e))?;
+            Ok(json!(job))
+        }
+        "getVerificationStatus" => {
+            let JobParams { job_id } = parse_params(params)?;
+            state
+                .job(&job_id)
+                .map(|job| json!(job))
+                .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("Unknown job {}", job_id)))
+        }
+        "listVerifications" => Ok(json!(state
+            .jobs
+            .lock()
+            .unwrap()
+            .values()
+            .collect::<Vec<_>>())),
+        "getProgramHash" => {
+            let ProgramParams { program_id } = parse_params(params)?;
+            let program_id =
+                Pubkey::from_str(&program_id).map_err(|e| RpcError::new(INVALID_PARAMS, e))?;
+            let hash = get_program_hash(connection, program_id)
+                .map_err(|e| RpcError::new(SERVER_ERROR, e))?;
+            Ok(json!({ "program_id": program_id.to_string(), "hash": hash }))
+        }
+        _ => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("Method not found: {}", method),
+        )),
+    }
+}
+
+/// Answers a JSON-RPC 2.0 request body.
+fn handle_rpc(body: &str, state: &ServiceState, connection: &RpcClient) -> Value {
+    let request: Value = match serde_json::from_str(body) {
+        Ok(request) => request,
+        Err(e) => return rpc_response(Value::Null, Err(RpcError::new(PARSE_ERROR, e))),
+    };
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let result = match request.get("method").and_then(Value::as_str) {
+        Some(method) => call(
+            method,
+            request.get("params").cloned().unwrap_or(Value::Null),
+            state,
+            connection,
+        ),
+        None => Err(RpcError::new(INVALID_REQUEST, "Missing method")),
+    };
+    rpc_response(id, result)
+}
+
+fn rpc_response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": error.code, "message": error.message },
+        }),
+    }
+}
+
+fn handle_connection(
+    mut stream: TcpStream,
+    state: &ServiceState,
+    connection: &RpcClient,
+    token: Option<&str>,
+) -> anyhow::Result<()> {
+    set_connection_timeouts(&stream)?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut content_length = 0;
+    let mut authorization = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse()?;
+            } else if name.eq_ignore_ascii_case("authorization") {
+                authorization = Some(value.trim().to_string());
+            }
+        }
+    }
+
+    let authorized = match token {
+        Some(token) => authorization.as_deref() == Some(format!("Bearer {}", token).as_str()),
+        None => true,
+    };
+    let (code, body) = match request_line.split_whitespace().collect::<Vec<_>>()[..] {
+        _ if !authorized => (401, r#"{"error":"unauthorized"}"#.to_string()),
+        ["POST", "/", ..] if content_length <= MAX_BODY_LEN => {
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body)?;
+            let response = handle_rpc(&String::from_utf8_lossy(&body), state, connection);
+            (200, response.to_string())
+        }
+        ["POST", "/", ..] => (400, r#"{"error":"request too large"}"#.to_string()),
+        ["POST", ..] => (404, r#"{"error":"not found"}"#.to_string()),
+        _ => (405, r#"{"error":"method not allowed"}"#.to_string()),
+    };
+    write_json_response(&mut stream, code, &body)
+}
+
+/// Serves verification over JSON-RPC 2.0 (`POST /`) until interrupted. Submitted jobs are
+/// built one at a time with the pipeline of `verify-from-repo`.
+///
+/// When `SVB_SERVE_TOKEN` is set, requests must carry it as a bearer token. It is required
+/// to listen on anything but a loopback address, since the service builds what it is sent.
+pub async fn run_service(connection: &RpcClient, listen: &str) -> anyhow::Result<()> {
+    let token = std::env::var(TOKEN_ENV)
+        .ok()
+        .filter(|token| !token.is_empty());
+    let state = Arc::new(ServiceState::default());
+    let listener =
+        TcpListener::bind(listen).map_err(|e| anyhow!("Failed to listen on {}: {}", listen, e))?;
+    if token.is_none() && !listener.local_addr()?.ip().is_loopback() {
+        return Err(anyhow!(
+            "Refusing to listen on {} without authentication. Set {} to the token clients must send, or listen on a loopback address",
+            listen,
+            TOKEN_ENV
+        ));
+    }
+    info!("Verification service listening on http://{}", listen);
+    let handler_state = Arc::clone(&state);
+    let handler_connection = Arc::new(new_rpc_client(connection.url(), connection.commitment()));
+    let handler_token = Arc::new(token);
+    let open_connections = Arc::new(AtomicUsize::new(0));
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if open_connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                open_connections.fetch_sub(1, Ordering::SeqCst);
+                warn!(
+                    "dropping a connection, {} are already open",
+                    MAX_CONNECTIONS
+                );
+                continue;
+            }
+            let state = Arc::clone(&handler_state);
+            let connection = Arc::clone(&handler_connection);
+            let token = Arc::clone(&handler_token);
+            let open_connections = Arc::clone(&open_connections);
+            std::thread::spawn(move || {
+                if let Err(err) = handle_connection(stream, &state, &connection, token.as_deref()) {
+                    error!("Request failed: {}", err);
+                }
+                open_connections.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+    });
+
+    while !interrupted() {
+        let Some(job) = state.next_job() else {
+            sleep_unless_interrupted(Duration::from_secs(1)).await;
+            continue;
+        };
+        info!("Job {}: verifying {}", job.job_id, job.request.program_id);
+        match check_program(connection, &job.request, None) {
+            Ok(status) => {
+                info!(
+                    "Job {}: {}",
+                    job.job_id,
+                    if status.is_verified {
+                        "verified ✅"
+                    } else {
+                        "not verified ❌"
+                    }
+                );
+                state.finish_job(&job.job_id, JobState::Completed, status);
+            }
+            Err(err) => {
+                error!("Job {}: failed: {}", job.job_id, err);
+                state.finish_job(
+                    &job.job_id,
+                    JobState::Failed,
+                    ProgramStatus {
+                        program_id: job.request.program_id.clone(),
+                        repo_url: job.request.repo_url.clone(),
+                        last_checked_at: Some(chrono::Utc::now().to_rfc3339()),
+                        error: Some(err.to_string()),
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+    }
+    check_interrupted()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rpc(body: Value, state: &ServiceState) -> Value {
+        // Never reached by the methods under test
+        let connection = RpcClient::new("http://127.0.0.1:1".to_string());
+        handle_rpc(&body.to_string(), state, &connection)
+    }
+
+    #[test]
+    fn test_submit_and_query() {
+        let state = ServiceState::default();
+        let response = rpc(
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "submitVerification",
+                "params": {
+                    "program_id": "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY",
+                    "repo_url": "https://github.com/Ellipsis-Labs/phoenix-v1",
+                },
+            }),
+            &state,
+        );
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"]["state"], "queued");
+        let job_id = response["result"]["job_id"].as_str().unwrap().to_string();
+
+        assert_eq!(state.next_job().unwrap().job_id, job_id);
+        assert!(state.next_job().is_none());
+        let response = rpc(
+            json!({
+                "jsonrpc": "2.0",
+                "id": 2,
+                "method": "getVerificationStatus",
+                "params": { "job_id": job_id },
+            }),
+            &state,
+        );
+        assert_eq!(response["result"]["state"], "running");
+    }
+
+    fn request(repo_url: &str) -> WatchedProgram {
+        serde_json::from_value(json!({
+            "program_id": "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY",
+            "repo_url": repo_url,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_job_limit() {
+        let state = ServiceState::default();
+        let request = || request("https://github.com/Ellipsis-Labs/phoenix-v1");
+        let first = state.submit(request()).unwrap();
+        for _ in 1..MAX_QUEUED_JOBS {
+            state.submit(request()).unwrap();
+        }
+        assert!(state.submit(request()).is_err());
+
+        // Finished jobs are kept until MAX_JOBS, then the oldest one is forgotten
+        while let Some(job) = state.next_job() {
+            state.finish_job(&job.job_id, JobState::Completed, ProgramStatus::default());
+        }
+        for _ in MAX_QUEUED_JOBS..MAX_JOBS {
+            let job = state.submit(request()).unwrap();
+            state.next_job().unwrap();
+            state.finish_job(&job.job_id, JobState::Completed, ProgramStatus::default());
+        }
+        assert_eq!(state.jobs.lock().unwrap().len(), MAX_JOBS);
+        state.submit(request()).unwrap();
+        assert!(state.job(&first.job_id).is_none());
+        assert_eq!(state.jobs.lock().unwrap().len(), MAX_JOBS);
+    }
+
+    #[test]
+    fn test_submit_rejects_local_repositories() {
+        let state = ServiceState::default();
+        for repo_url in [
+            "file:///etc/team/program",
+            "/home/user/program",
+            "../program",
+            "--upload-pack=touch x@host:team/program",
+        ] {
+            assert!(state.submit(request(repo_url)).is_err(), "{}", repo_url);
+        }
+        state
+            .submit(request("git@github.com:Ellipsis-Labs/phoenix-v1.git"))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_rpc_errors() {
+        let state = ServiceState::default();
+        let connection = RpcClient::new("http://127.0.0.1:1".to_string());
+        assert_eq!(
+            handle_rpc("{", &state, &connection)["error"]["code"],
+            PARSE_ERROR
+        );
+        let response = rpc(json!({ "id": 1, "method": "deploy" }), &state);
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+        let response = rpc(
+            json!({
+                "id": 1,
+                "method": "submitVerification",
+                "params": { "program_id": "x", "repo_url": "https://github.com/a/b" },
+            }),
+            &state,
+        );
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+        let response = rpc(
+            json!({ "id": 1, "method": "getProgramHash", "params": {} }),
+            &state,
+        );
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+    }
+}
@@ -0,0 +1,162 @@
+use anyhow::anyhow;
+use log::info;
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{remote_signer::is_remote_signer, solana_program::get_address_from_keypair_or_config};
+
+/// Project configuration read from the current directory.
+pub const CONFIG_FILE_NAME: &str = "solana-verify.toml";
+
+/// The `[signers]` section of solana-verify.toml, naming the authorities a team signs with:
+///
+/// ```toml
+/// [signers]
+/// deployer = "~/.config/solana/deployer.json"
+/// dao-vault = "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU"
+/// ```
+///
+/// A signer is a keypair path, the URL of a remote signing service, or the address of an
+/// authority that signs elsewhere, such as a multisig vault.
+#[derive(Debug, Default, Deserialize)]
+pub struct SignersConfig {
+    #[serde(default)]
+    pub signers: BTreeMap<String, String>,
+}
+
+/// What a named signer resolves to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NamedSigner {
+    /// A keypair path or remote signer URL, which can sign transactions
+    Keypair(String),
+    /// An address only, usable where a transaction is exported rather than signed
+    Address(Pubkey),
+}
+
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest).display().to_string(),
+        _ => path.to_string(),
+    }
+}
+
+impl SignersConfig {
+    pub fn parse(contents: &str) -> anyhow::Result<SignersConfig> {
+        toml::from_str(contents).map_err(|e| anyhow!("Invalid {}: {}", CONFIG_FILE_NAME, e))
+    }
+
+    /// Loads the signers of `path`, none if the file does not exist.
+    pub fn load(path: &Path) -> anyhow::Result<SignersConfig> {
+        if !path.exists() {
+            return Ok(SignersConfig::default());
+        }
+        SignersConfig::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn get(&self, name: &str) -> anyhow::Result<NamedSigner> {
+        let value = self.signers.get(name).ok_or_else(|| {
+            anyhow!(
+                "No signer named {} in the [signers] section of {}{}",
+                name,
+                CONFIG_FILE_NAME,
+                if self.signers.is_empty() {
+                    String::new()
+                } else {
+                    format!(
+                        ", known signers: {}",
+                        self.signers.keys().cloned().collect::<Vec<_>>().join(", ")
+                    )
+                }
+            )
+        })?;
+        if is_remote_signer(value) {
+            return Ok(NamedSigner::Keypair(value.clone()));
+        }
+        match Pubkey::from_str(value) {
+            Ok(address) => Ok(NamedSigner::Address(address)),
+            Err(_) => Ok(NamedSigner::Keypair(expand_home(value))),
+        }
+    }
+}
+
+fn load_named(name: &str) -> anyhow::Result<NamedSigner> {
+    SignersConfig::load(Path::new(CONFIG_FILE_NAME))?.get(name)
+}
+
+/// Keypair to sign with: `keypair` if given, else the named signer, else none to fall back to
+/// the Solana CLI config.
+pub fn resolve_keypair(
+    keypair: Option<&str>,
+    signer_name: Option<&str>,
+) -> anyhow::Result<Option<String>> {
+    let Some(name) = signer_name else {
+        return Ok(keypair.map(|s| s.to_string()));
+    };
+    match load_named(name)? {
+        NamedSigner::Keypair(path) => {
+            info!("Signing as {} ({})", name, path);
+            Ok(Some(path))
+        }
+        NamedSigner::Address(address) => Err(anyhow!(
+            "Signer {} is the address {}, which cannot sign here. Export the transaction with `solana-verify export-pda-tx --signer-name {}` instead",
+            name,
+            address,
+            name
+        )),
+    }
+}
+
+/// Address of `address` if given, else of the named signer.
+pub fn resolve_address(address: Option<&str>, signer_name: Option<&str>) -> anyhow::Result<Pubkey> {
+    if let Some(address) = address {
+        return Pubkey::from_str(address)
+            .map_err(|e| anyhow!("Invalid address {}: {}", address, e));
+    }
+    let name = signer_name.ok_or_else(|| anyhow!("Specify an address or a signer name"))?;
+    match load_named(name)? {
+        NamedSigner::Address(address) => Ok(address),
+        NamedSigner::Keypair(path) => get_address_from_keypair_or_config(Some(&path)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_named_signers() {
+        let config = SignersConfig::parse(
+            r#"
+[signers]
+deployer = "keys/deployer.json"
+dao-vault = "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU"
+ledger = "usb://ledger"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.get("deployer").unwrap(),
+            NamedSigner::Keypair("keys/deployer.json".to_string())
+        );
+        assert_eq!(
+            config.get("dao-vault").unwrap(),
+            NamedSigner::Address(
+                Pubkey::from_str("7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU").unwrap()
+            )
+        );
+        assert_eq!(
+            config.get("ledger").unwrap(),
+            NamedSigner::Keypair("usb://ledger".to_string())
+        );
+        let missing = config.get("treasury").unwrap_err().to_string();
+        assert!(missing.contains("dao-vault, deployer, ledger"));
+
+        assert!(SignersConfig::parse("").unwrap().signers.is_empty());
+        assert!(SignersConfig::parse("[signers]\ndeployer = 1").is_err());
+    }
+}
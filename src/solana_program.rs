@@ -1,30 +1,57 @@
 use anyhow::anyhow;
+use log::{error, info, warn};
 use solana_cli_config::Config;
 use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
     rpc_client::RpcClient,
-    rpc_config::RpcProgramAccountsConfig,
+    rpc_config::{
+        RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSimulateTransactionAccountsConfig,
+        RpcSimulateTransactionConfig,
+    },
     rpc_filter::{Memcmp, RpcFilterType},
+    rpc_request::MAX_MULTIPLE_ACCOUNTS,
 };
 use std::{
-    io::{self, Read, Write},
+    io::{self, IsTerminal, Write},
     str::FromStr,
+    sync::OnceLock,
+    time::{Duration, Instant},
 };
 
 use borsh::{to_vec, BorshDeserialize, BorshSerialize};
 use solana_sdk::{
-    compute_budget::ComputeBudgetInstruction, instruction::AccountMeta, message::Message,
-    pubkey::Pubkey, signature::Keypair, signer::Signer, system_program, transaction::Transaction,
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    compute_budget::{self, ComputeBudgetInstruction},
+    hash::Hash,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    message::Message,
+    packet::PACKET_DATA_SIZE,
+    pubkey::Pubkey,
+    signature::Signature,
+    signer::Signer,
+    system_instruction::SystemError,
+    system_program,
+    transaction::{Transaction, TransactionError},
 };
 
-use solana_account_decoder::UiAccountEncoding;
-use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
+use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_transaction_status::TransactionStatus;
 
-use crate::api::get_last_deployed_slot;
+use crate::api::{get_last_deployed_slot, Backoff, RemoteStatusResponse};
+use crate::humanize::{format_lamports, format_number};
+use crate::logging::is_quiet;
+use crate::remote_signer::{is_remote_signer, RemoteSigner};
+use crate::secrets::{contains_secret, redact, redact_rpc_url, strip_url_credentials};
 
-const OTTER_VERIFY_PROGRAM_ID: Pubkey =
+pub const OTTER_VERIFY_PROGRAM_ID: Pubkey =
     solana_sdk::pubkey!("verifycLy8mB96wd9wqq3WDXQwM4oU6r42Th37Db9fC");
 const OTTER_SIGNER: &str = "9VWiUUhgNoRwTH5NVehYJEDwcotwYX3VgW4MChiHPAqU";
 
+/// First build argument of a PDA that has been revoked by its signer. Any following
+/// argument is the reason given for the revocation.
+pub const REVOKED_MARKER: &str = "--revoked";
+
 #[derive(BorshDeserialize, BorshSerialize, Debug)]
 pub struct OtterBuildParams {
     pub address: Pubkey,
@@ -36,8 +63,29 @@ pub struct OtterBuildParams {
     pub deployed_slot: u64,
     bump: u8,
 }
+impl OtterBuildParams {
+    pub fn is_revoked(&self) -> bool {
+        self.args.first().map(String::as_str) == Some(REVOKED_MARKER)
+    }
+
+    pub fn revocation_reason(&self) -> Option<&str> {
+        if self.is_revoked() {
+            self.args.get(1).map(String::as_str)
+        } else {
+            None
+        }
+    }
+}
+
 impl std::fmt::Display for OtterBuildParams {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_revoked() {
+            writeln!(
+                f,
+                "⚠️  REVOKED: the signer no longer vouches for this verification ({})",
+                self.revocation_reason().unwrap_or("no reason given")
+            )?;
+        }
         writeln!(f, "Program Id: {}", self.address)?;
         writeln!(f, "Signer: {}", self.signer)?;
         writeln!(f, "Git Url: {}", self.git_url)?;
@@ -49,14 +97,170 @@ impl std::fmt::Display for OtterBuildParams {
     }
 }
 
-pub fn prompt_user_input(message: &str) -> bool {
-    let mut buffer = [0; 1];
+/// Answer given with `--yes` / `--no`, used for every prompt instead of reading stdin
+static PROMPT_ANSWER: OnceLock<bool> = OnceLock::new();
+
+pub fn set_prompt_answer(answer: bool) {
+    let _ = PROMPT_ANSWER.set(answer);
+}
+
+pub fn prompt_user_input(message: &str) -> anyhow::Result<bool> {
     print!("{}", message);
+    if let Some(answer) = PROMPT_ANSWER.get() {
+        println!("{}", if *answer { "y" } else { "n" });
+        return Ok(*answer);
+    }
     let _ = io::stdout().flush();
-    io::stdin()
-        .read_exact(&mut buffer)
-        .expect("Unable to read user input");
-    matches!(buffer[0] as char, 'Y' | 'y')
+
+    let stdin = io::stdin();
+    let is_terminal = stdin.is_terminal();
+    let mut buffer = String::new();
+    let read = stdin
+        .read_line(&mut buffer)
+        .map_err(|e| anyhow!("Unable to read user input: {}", e))?;
+
+    // Input may still be piped in, but without a terminal nobody is left to answer once it runs out
+    if read == 0 {
+        println!();
+        return Err(if is_terminal {
+            anyhow!("No answer given to the prompt")
+        } else {
+            anyhow!("Unable to prompt for confirmation: stdin is not a terminal. Pass --yes or --no to answer prompts non-interactively")
+        });
+    }
+    Ok(matches!(
+        buffer.trim_start().chars().next(),
+        Some('Y' | 'y')
+    ))
+}
+
+/// Prompt for a change nobody asked for, such as an upload with other params, which `--yes`
+/// does not accept: it is declined unless answered at the terminal.
+pub fn prompt_user_confirmation(message: &str) -> anyhow::Result<bool> {
+    if PROMPT_ANSWER.get().is_some() {
+        println!("{}n (answer it interactively to accept)", message);
+        return Ok(false);
+    }
+    prompt_user_input(message)
+}
+
+/// What to do when a program already has verification PDAs uploaded by other signers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplacePolicy {
+    Never,
+    Always,
+    Prompt,
+}
+
+impl FromStr for ReplacePolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "never" => Ok(ReplacePolicy::Never),
+            "always" => Ok(ReplacePolicy::Always),
+            "prompt" => Ok(ReplacePolicy::Prompt),
+            _ => Err(anyhow!("Invalid replace policy: {}", s)),
+        }
+    }
+}
+
+/// Policy given with `--replace-policy`, applied to every upload
+static REPLACE_POLICY: OnceLock<ReplacePolicy> = OnceLock::new();
+
+pub fn set_replace_policy(policy: ReplacePolicy) {
+    let _ = REPLACE_POLICY.set(policy);
+}
+
+/// Whether to create our own PDA next to other signers' PDAs, or `None` if the user must be
+/// asked. Skipping prompts keeps the previous behaviour of always creating one.
+fn resolve_replace_policy(policy: ReplacePolicy, skip_prompt: bool) -> Option<bool> {
+    match policy {
+        ReplacePolicy::Never => Some(false),
+        ReplacePolicy::Always => Some(true),
+        ReplacePolicy::Prompt if skip_prompt => Some(true),
+        ReplacePolicy::Prompt => None,
+    }
+}
+
+/// Verification PDAs of a program uploaded by anyone but `signer`. Falls back to looking up
+/// the upgrade authority's and OtterSec's PDAs when the RPC can't list program accounts.
+async fn get_other_signer_pdas(
+    connection: &RpcClient,
+    program_address: &Pubkey,
+    signer: &Pubkey,
+) -> Vec<(Pubkey, OtterBuildParams)> {
+    let pdas = match get_all_pdas_available(connection, program_address).await {
+        Ok(pdas) => pdas,
+        Err(_) => {
+            let authority = get_program_upgrade_authority(connection, program_address)
+                .ok()
+                .flatten();
+            let otter_signer = Pubkey::from_str(OTTER_SIGNER).unwrap();
+            authority
+                .into_iter()
+                .chain([otter_signer])
+                .filter_map(|other| {
+                    let pda = find_build_params_pda(program_address, &other).0;
+                    let account = connection.get_account(&pda).ok()?;
+                    let build_params =
+                        OtterBuildParams::try_from_slice(account.data.get(8..)?).ok()?;
+                    Some((pda, build_params))
+                })
+                .collect()
+        }
+    };
+    pdas.into_iter()
+        .filter(|(_, build_params)| build_params.signer != *signer)
+        .collect()
+}
+
+/// Shows the other signers' PDAs and decides whether to create a PDA for our signer as well.
+async fn confirm_new_pda(
+    connection: &RpcClient,
+    program_address: &Pubkey,
+    other_pdas: &[(Pubkey, OtterBuildParams)],
+    skip_prompt: bool,
+) -> anyhow::Result<bool> {
+    let authority = get_program_upgrade_authority(connection, program_address)
+        .ok()
+        .flatten();
+    println!(
+        "Program {} already has verification PDAs uploaded by other signers:",
+        program_address
+    );
+    for (pda, build_params) in other_pdas {
+        println!(
+            "  {} by {} ({}): {} at {}",
+            pda,
+            build_params.signer,
+            get_verification_source(&build_params.signer, authority),
+            build_params.git_url,
+            if build_params.commit.is_empty() {
+                "HEAD"
+            } else {
+                &build_params.commit
+            },
+        );
+    }
+
+    let policy = REPLACE_POLICY
+        .get()
+        .copied()
+        .unwrap_or(ReplacePolicy::Prompt);
+    let create = match resolve_replace_policy(policy, skip_prompt) {
+        Some(create) => create,
+        None => {
+            prompt_user_input("Do you want to upload a new PDA for your signer as well? (y/n) ")?
+        }
+    };
+    if !create {
+        info!(
+            "Not uploading a new PDA for {} (replace policy: {:?})",
+            program_address, policy
+        );
+    }
+    Ok(create)
 }
 
 #[derive(BorshSerialize, BorshDeserialize)]
@@ -68,7 +272,14 @@ pub struct InputParams {
     pub deployed_slot: u64,
 }
 
-#[derive(PartialEq)]
+impl InputParams {
+    /// Parameters of the data of an Initialize or Update instruction.
+    pub fn from_ix_data(data: &[u8]) -> Option<InputParams> {
+        InputParams::deserialize(&mut data.get(8..)?).ok()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum OtterVerifyInstructions {
     Initialize,
     Update,
@@ -83,49 +294,80 @@ impl OtterVerifyInstructions {
             OtterVerifyInstructions::Close => vec![98, 165, 201, 177, 108, 65, 206, 96],
         }
     }
+
+    /// Instruction whose data is `data`, recognized by its discriminant.
+    pub fn from_ix_data(data: &[u8]) -> Option<OtterVerifyInstructions> {
+        [
+            OtterVerifyInstructions::Initialize,
+            OtterVerifyInstructions::Update,
+            OtterVerifyInstructions::Close,
+        ]
+        .into_iter()
+        .find(|ix| data.starts_with(&ix.get_discriminant()))
+    }
 }
 
-fn create_ix_data(params: &InputParams, ix: &OtterVerifyInstructions) -> Vec<u8> {
-    let mut data = ix.get_discriminant(); // Discriminant for the instruction
+fn create_ix_data(params: &InputParams, ix: &OtterVerifyInstructions) -> anyhow::Result<Vec<u8>> {
+    // Discriminant for the instruction
+    let mut data = ix.get_discriminant();
+    // Credentials used to clone a private repository must not end up on-chain. Arguments are
+    // rebuilt from as they are, so one carrying a credential is refused rather than rewritten
+    if let Some(arg) = params.args.iter().find(|arg| contains_secret(arg)) {
+        return Err(anyhow!(
+            "Refusing to upload build argument {} on chain, it contains a credential",
+            redact(arg)
+        ));
+    }
+    let params = InputParams {
+        version: params.version.clone(),
+        git_url: strip_url_credentials(&params.git_url),
+        commit: params.commit.clone(),
+        args: params.args.clone(),
+        deployed_slot: params.deployed_slot,
+    };
     let params_data = to_vec(&params).expect("Unable to serialize params");
     data.extend_from_slice(&params_data);
-    data
+    Ok(data)
 }
 
-fn get_keypair_from_path(path: &str) -> anyhow::Result<Keypair> {
-    solana_clap_utils::keypair::keypair_from_path(&Default::default(), path, "keypair", false)
-        .map_err(|err| anyhow!("Unable to get signer from path: {}", err))
+/// Signer for a keypair path, or for a remote signing service if `path` is its URL.
+fn get_signer_from_path(path: &str) -> anyhow::Result<Box<dyn Signer>> {
+    if is_remote_signer(path) {
+        return Ok(Box::new(RemoteSigner::connect(path)?));
+    }
+    let keypair =
+        solana_clap_utils::keypair::keypair_from_path(&Default::default(), path, "keypair", false)
+            .map_err(|err| anyhow!("Unable to get signer from path: {}", err))?;
+    Ok(Box::new(keypair))
 }
 
-fn get_user_config() -> anyhow::Result<(Keypair, RpcClient)> {
+/// Signer of the Solana CLI config.
+fn get_user_signer() -> anyhow::Result<Box<dyn Signer>> {
     let config_file = solana_cli_config::CONFIG_FILE
         .as_ref()
         .ok_or_else(|| anyhow!("Unable to get config file path"))?;
     let cli_config: Config = Config::load(config_file)?;
 
-    let signer = get_keypair_from_path(&cli_config.keypair_path)?;
-
-    let rpc_client = RpcClient::new(cli_config.json_rpc_url.clone());
-    Ok((signer, rpc_client))
+    get_signer_from_path(&cli_config.keypair_path)
 }
 
-pub fn compose_transaction(
+fn create_otter_verify_ix(
     params: &InputParams,
     signer_pubkey: Pubkey,
     pda_account: Pubkey,
     program_address: Pubkey,
     instruction: OtterVerifyInstructions,
-    compute_unit_price: u64,
-) -> Transaction {
+) -> anyhow::Result<Instruction> {
     let ix_data = if instruction != OtterVerifyInstructions::Close {
-        create_ix_data(params, &instruction)
+        create_ix_data(params, &instruction)?
     } else {
         instruction.get_discriminant()
     };
 
     let mut accounts_meta_vec = vec![
         AccountMeta::new(pda_account, false),
-        AccountMeta::new_readonly(signer_pubkey, true),
+        // Writable so that it can pay for the PDA when another account pays the fee
+        AccountMeta::new(signer_pubkey, true),
         AccountMeta::new_readonly(program_address, false),
     ];
 
@@ -133,22 +375,208 @@ pub fn compose_transaction(
         accounts_meta_vec.push(AccountMeta::new_readonly(system_program::ID, false));
     }
 
-    let ix = solana_sdk::instruction::Instruction::new_with_bytes(
+    Ok(Instruction::new_with_bytes(
         OTTER_VERIFY_PROGRAM_ID,
         &ix_data,
         accounts_meta_vec,
-    );
+    ))
+}
+
+pub fn compose_instructions(
+    params: &InputParams,
+    signer_pubkey: Pubkey,
+    pda_account: Pubkey,
+    program_address: Pubkey,
+    instruction: OtterVerifyInstructions,
+    compute_unit_price: u64,
+) -> anyhow::Result<Vec<Instruction>> {
+    let ix = create_otter_verify_ix(
+        params,
+        signer_pubkey,
+        pda_account,
+        program_address,
+        instruction,
+    )?;
 
-    let message = if compute_unit_price > 0 {
+    if compute_unit_price > 0 {
         // Add compute budget instruction for priority fees only if price > 0
         let compute_budget_ix =
             ComputeBudgetInstruction::set_compute_unit_price(compute_unit_price);
-        Message::new(&[compute_budget_ix, ix], Some(&signer_pubkey))
+        Ok(vec![compute_budget_ix, ix])
     } else {
-        Message::new(&[ix], Some(&signer_pubkey))
-    };
+        Ok(vec![ix])
+    }
+}
+
+pub fn compose_transaction(
+    params: &InputParams,
+    signer_pubkey: Pubkey,
+    pda_account: Pubkey,
+    program_address: Pubkey,
+    instruction: OtterVerifyInstructions,
+    compute_unit_price: u64,
+) -> anyhow::Result<Transaction> {
+    let instructions = compose_instructions(
+        params,
+        signer_pubkey,
+        pda_account,
+        program_address,
+        instruction,
+        compute_unit_price,
+    )?;
+    Ok(Transaction::new_unsigned(Message::new(
+        &instructions,
+        Some(&signer_pubkey),
+    )))
+}
+
+/// Limit given with `--compute-unit-limit`, used instead of simulating each transaction
+static COMPUTE_UNIT_LIMIT: OnceLock<u32> = OnceLock::new();
+
+/// Headroom over the simulated units, which vary slightly with the state the transaction
+/// lands on
+const COMPUTE_UNIT_MARGIN_PERCENT: u64 = 20;
+/// Units of the set_compute_unit_limit instruction, which is not part of the simulation
+const SET_COMPUTE_UNIT_LIMIT_UNITS: u64 = 150;
+const MAX_COMPUTE_UNIT_LIMIT: u64 = 1_400_000;
+
+/// Value of `--compute-unit-price` that prices each transaction from recent fees
+pub const AUTO_COMPUTE_UNIT_PRICE: &str = "auto";
+/// Percentile of recent prioritization fees paid with `--compute-unit-price auto`
+static COMPUTE_UNIT_PRICE_PERCENTILE: OnceLock<u8> = OnceLock::new();
+/// Price in auto mode when recent prioritization fees are unavailable
+const DEFAULT_COMPUTE_UNIT_PRICE: u64 = 100_000;
+/// Least price paid in auto mode. Accounts nobody contends for, such as a new PDA, report
+/// mostly zero fees, which would send no priority fee at all
+const MIN_AUTO_COMPUTE_UNIT_PRICE: u64 = 10_000;
+/// Most accounts getRecentPrioritizationFees accepts
+const MAX_PRIORITIZATION_FEE_ACCOUNTS: usize = 128;
+
+pub fn set_compute_unit_limit(limit: u32) {
+    let _ = COMPUTE_UNIT_LIMIT.set(limit);
+}
+
+pub fn set_auto_compute_unit_price(percentile: u8) {
+    let _ = COMPUTE_UNIT_PRICE_PERCENTILE.set(percentile);
+}
+
+fn fee_percentile(mut fees: Vec<u64>, percentile: u8) -> Option<u64> {
+    if fees.is_empty() {
+        return None;
+    }
+    fees.sort_unstable();
+    let rank = (fees.len() * percentile as usize).div_ceil(100).max(1);
+    Some(fees[rank - 1])
+}
+
+/// Price at `percentile` of the fees paid in the slots where any were, at least
+/// `MIN_AUTO_COMPUTE_UNIT_PRICE`. Slots without a priority fee say nothing of congestion.
+fn auto_price_from_fees(fees: Vec<u64>, percentile: u8) -> u64 {
+    let paid = fees.into_iter().filter(|fee| *fee > 0).collect();
+    fee_percentile(paid, percentile)
+        .unwrap_or_default()
+        .max(MIN_AUTO_COMPUTE_UNIT_PRICE)
+}
+
+/// Price at `percentile` of the prioritization fees recently paid to use the otter-verify
+/// program and write the accounts `instructions` write, such as the PDA and its signer, as
+/// fees are set per account.
+fn auto_compute_unit_price(
+    connection: &RpcClient,
+    instructions: &[Instruction],
+    percentile: u8,
+) -> anyhow::Result<u64> {
+    let mut accounts = vec![OTTER_VERIFY_PROGRAM_ID];
+    for meta in instructions.iter().flat_map(|ix| &ix.accounts) {
+        if meta.is_writable && !accounts.contains(&meta.pubkey) {
+            accounts.push(meta.pubkey);
+        }
+    }
+    accounts.truncate(MAX_PRIORITIZATION_FEE_ACCOUNTS);
+    let fees: Vec<u64> = connection
+        .get_recent_prioritization_fees(&accounts)?
+        .into_iter()
+        .map(|fee| fee.prioritization_fee)
+        .collect();
+    if fees.is_empty() {
+        return Err(anyhow!("the RPC returned no recent fees"));
+    }
+    Ok(auto_price_from_fees(fees, percentile))
+}
+
+fn compute_unit_limit_with_margin(units_consumed: u64) -> u32 {
+    let limit =
+        (units_consumed + SET_COMPUTE_UNIT_LIMIT_UNITS) * (100 + COMPUTE_UNIT_MARGIN_PERCENT) / 100;
+    limit.min(MAX_COMPUTE_UNIT_LIMIT) as u32
+}
+
+fn simulate_compute_units(
+    connection: &RpcClient,
+    instructions: &[Instruction],
+    payer: &Pubkey,
+) -> anyhow::Result<u64> {
+    let simulation = connection
+        .simulate_transaction_with_config(
+            &Transaction::new_unsigned(Message::new(instructions, Some(payer))),
+            RpcSimulateTransactionConfig {
+                sig_verify: false,
+                replace_recent_blockhash: true,
+                commitment: Some(connection.commitment()),
+                ..Default::default()
+            },
+        )?
+        .value;
+    if let Some(err) = simulation.err {
+        return Err(anyhow!("the simulation failed: {}", err));
+    }
+    simulation
+        .units_consumed
+        .ok_or_else(|| anyhow!("the RPC did not report the units consumed"))
+}
 
-    Transaction::new_unsigned(message)
+/// Prepends the compute budget to `instructions`. With `--compute-unit-price auto`, a price
+/// at a percentile of recent fees. Then a compute unit limit: the one given with
+/// `--compute-unit-limit`, else the units a simulation consumed with a margin. The default
+/// limit is kept if the simulation fails.
+pub fn with_compute_budget(
+    connection: &RpcClient,
+    mut instructions: Vec<Instruction>,
+    payer: &Pubkey,
+) -> Vec<Instruction> {
+    if let Some(&percentile) = COMPUTE_UNIT_PRICE_PERCENTILE.get() {
+        let price = auto_compute_unit_price(connection, &instructions, percentile)
+            .unwrap_or_else(|err| {
+                warn!(
+                    "Unable to fetch recent prioritization fees, using {} micro-lamports per compute unit: {}",
+                    format_number(DEFAULT_COMPUTE_UNIT_PRICE),
+                    err
+                );
+                DEFAULT_COMPUTE_UNIT_PRICE
+            });
+        info!(
+            "Priority fee: {} micro-lamports per compute unit (percentile {} of recent fees)",
+            format_number(price),
+            percentile
+        );
+        if price > 0 {
+            instructions.insert(0, ComputeBudgetInstruction::set_compute_unit_price(price));
+        }
+    }
+    let limit = match COMPUTE_UNIT_LIMIT.get() {
+        Some(limit) => *limit,
+        None => match simulate_compute_units(connection, &instructions, payer) {
+            Ok(units) => compute_unit_limit_with_margin(units),
+            Err(err) => {
+                warn!(
+                    "Unable to estimate the compute units of the transaction, keeping the default limit: {}",
+                    err
+                );
+                return instructions;
+            }
+        },
+    };
+    instructions.insert(0, ComputeBudgetInstruction::set_compute_unit_limit(limit));
+    instructions
 }
 
 fn process_otter_verify_ixs(
@@ -160,64 +588,390 @@ fn process_otter_verify_ixs(
     path_to_keypair: Option<String>,
     compute_unit_price: u64,
 ) -> anyhow::Result<()> {
-    let user_config = get_user_config()?;
-    let signer = if let Some(path_to_keypair) = path_to_keypair {
-        get_keypair_from_path(&path_to_keypair)?
-    } else {
-        user_config.0
-    };
+    let signer = get_signer_from_path_or_config(path_to_keypair.as_ref())?;
     let connection = rpc_client;
+    let initializing = instruction == OtterVerifyInstructions::Initialize;
 
-    let mut tx = compose_transaction(
-        params,
-        signer.pubkey(),
-        pda_account,
-        program_address,
-        instruction,
-        compute_unit_price,
+    let instructions = with_compute_budget(
+        connection,
+        compose_instructions(
+            params,
+            signer.pubkey(),
+            pda_account,
+            program_address,
+            instruction,
+            compute_unit_price,
+        )?,
+        &signer.pubkey(),
     );
 
-    tx.sign(&[&signer], connection.get_latest_blockhash()?);
-
-    let tx_id = connection
-        .send_and_confirm_transaction_with_spinner(&tx)
-        .map_err(|err| {
-            println!("{:?}", err);
-            anyhow!("Failed to send transaction to the network.")
-        })?;
+    let message = Message::new(&instructions, Some(&signer.pubkey()));
+    let tx_id = send_with_retry(connection, message, signer.as_ref()).map_err(|err| {
+        if initializing && is_account_already_in_use(&err) {
+            return PdaAlreadyInitializedError { pda: pda_account }.into();
+        }
+        error!("{:?}", err);
+        anyhow!("Failed to send transaction to the network.")
+    })?;
     println!("Program uploaded successfully. Transaction ID: {}", tx_id);
     Ok(())
 }
 
-pub fn resolve_rpc_url(url: Option<String>) -> anyhow::Result<RpcClient> {
-    let connection = match url.as_deref() {
-        Some("m") => RpcClient::new("https://api.mainnet-beta.solana.com"),
-        Some("d") => RpcClient::new("https://api.devnet.solana.com"),
-        Some("t") => RpcClient::new("https://api.testnet.solana.com"),
-        Some("l") => RpcClient::new("http://localhost:8899"),
-        Some(url) => RpcClient::new(url),
-        None => {
-            if let Ok(cli_config) = get_user_config() {
-                cli_config.1
-            } else {
-                RpcClient::new("https://api.mainnet-beta.solana.com")
+/// What an upload would write, found by simulating its transaction instead of sending it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UploadPreview {
+    pub program_id: Pubkey,
+    pub pda: Pubkey,
+    pub instruction: OtterVerifyInstructions,
+    /// Lamports moved into the PDA to keep it rent exempt, unknown if the simulation failed
+    pub rent: Option<u64>,
+    pub fee: u64,
+    pub units_consumed: Option<u64>,
+    /// Why the transaction would fail, none if it would succeed
+    pub error: Option<String>,
+    pub logs: Vec<String>,
+}
+
+impl std::fmt::Display for UploadPreview {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Dry run, the transaction was simulated but not sent:")?;
+        writeln!(f, "  Program: {}", self.program_id)?;
+        writeln!(f, "  PDA: {}", self.pda)?;
+        writeln!(f, "  Instruction: {:?}", self.instruction)?;
+        match self.rent {
+            Some(rent) => writeln!(f, "  Rent: {}", format_lamports(rent))?,
+            None => writeln!(f, "  Rent: unknown, the simulation failed")?,
+        }
+        writeln!(f, "  Fee: {}", format_lamports(self.fee))?;
+        if let Some(units) = self.units_consumed {
+            writeln!(f, "  Compute units: {}", format_number(units))?;
+        }
+        match &self.error {
+            Some(error) => writeln!(f, "  Simulation failed: {}", error)?,
+            None => writeln!(f, "  Simulation succeeded")?,
+        }
+        if self.error.is_some() {
+            for log in &self.logs {
+                writeln!(f, "    {}", log)?;
             }
         }
+        Ok(())
+    }
+}
+
+impl UploadPreview {
+    /// Prints the preview, and fails if the upload would.
+    pub fn print(self) -> anyhow::Result<UploadPreview> {
+        print!("{}", self);
+        match &self.error {
+            Some(error) => Err(anyhow!("The upload would fail: {}", error)),
+            None => Ok(self),
+        }
+    }
+}
+
+/// Simulates the upload `instructions` paid by `payer` and reads the rent it would move into
+/// `pda`. Signatures are not verified, so `payer` may be a multisig vault.
+pub fn simulate_upload(
+    connection: &RpcClient,
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    program_id: Pubkey,
+    pda: Pubkey,
+    instruction: OtterVerifyInstructions,
+) -> anyhow::Result<UploadPreview> {
+    let balance = connection
+        .get_account(&pda)
+        .map(|account| account.lamports)
+        .unwrap_or_default();
+    let mut message = Message::new(instructions, Some(payer));
+    message.recent_blockhash = connection.get_latest_blockhash()?;
+    let fee = connection.get_fee_for_message(&message)?;
+
+    let simulation = connection
+        .simulate_transaction_with_config(
+            &Transaction::new_unsigned(message),
+            RpcSimulateTransactionConfig {
+                sig_verify: false,
+                replace_recent_blockhash: true,
+                commitment: Some(connection.commitment()),
+                accounts: Some(RpcSimulateTransactionAccountsConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    addresses: vec![pda.to_string()],
+                }),
+                ..Default::default()
+            },
+        )
+        .map_err(|err| anyhow!("Failed to simulate the upload: {}", err))?
+        .value;
+    let error = simulation.err.map(|err| err.to_string());
+    let rent = match error {
+        Some(_) => None,
+        None => simulation
+            .accounts
+            .and_then(|accounts| accounts.into_iter().next().flatten())
+            .map(|account| account.lamports.saturating_sub(balance)),
+    };
+    Ok(UploadPreview {
+        program_id,
+        pda,
+        instruction,
+        rent,
+        fee,
+        units_consumed: simulation.units_consumed,
+        error,
+        logs: simulation.logs.unwrap_or_default(),
+    })
+}
+
+/// Composes the upload `upload_program_verification_data` would send and prints its
+/// simulation, without sending it.
+#[allow(clippy::too_many_arguments)]
+pub async fn preview_program_verification_data(
+    git_url: String,
+    commit: &Option<String>,
+    args: Vec<String>,
+    program_address: Pubkey,
+    connection: &RpcClient,
+    path_to_keypair: Option<String>,
+    compute_unit_price: u64,
+    deployed_slot: Option<u64>,
+) -> anyhow::Result<UploadPreview> {
+    let deployed_slot = match deployed_slot {
+        Some(deployed_slot) => deployed_slot,
+        None => get_last_deployed_slot(connection, &program_address.to_string())
+            .await
+            .map_err(|err| anyhow!("Unable to get last deployed slot: {}", err))?,
+    };
+    let input_params = InputParams {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_url,
+        commit: commit.clone().unwrap_or_default(),
+        args,
+        deployed_slot,
+    };
+    let signer_pubkey = get_address_from_keypair_or_config(path_to_keypair.as_ref())?;
+    let pda = find_build_params_pda(&program_address, &signer_pubkey).0;
+    let instruction = if connection.get_account(&pda).is_ok() {
+        OtterVerifyInstructions::Update
+    } else {
+        OtterVerifyInstructions::Initialize
     };
+    let instructions = with_compute_budget(
+        connection,
+        compose_instructions(
+            &input_params,
+            signer_pubkey,
+            pda,
+            program_address,
+            instruction,
+            compute_unit_price,
+        )?,
+        &signer_pubkey,
+    );
+    let preview = simulate_upload(
+        connection,
+        &instructions,
+        &signer_pubkey,
+        program_address,
+        pda,
+        instruction,
+    )?;
+    preview.print()
+}
 
-    Ok(connection)
+/// Returned when initializing a PDA that was created in the meantime, e.g. by a concurrent
+/// upload with the same signer.
+#[derive(Debug)]
+pub struct PdaAlreadyInitializedError {
+    pub pda: Pubkey,
 }
 
-pub fn get_address_from_keypair_or_config(
+impl std::fmt::Display for PdaAlreadyInitializedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "PDA {} was initialized by another upload while this one was in progress",
+            self.pda
+        )
+    }
+}
+
+impl std::error::Error for PdaAlreadyInitializedError {}
+
+/// Whether a transaction failed because an account it creates already exists.
+fn is_account_already_in_use(err: &ClientError) -> bool {
+    matches!(
+        err.get_transaction_error(),
+        Some(TransactionError::InstructionError(_, InstructionError::Custom(code)))
+            if code == SystemError::AccountAlreadyInUse as u32
+    )
+}
+
+/// Attempts at sending a transaction before giving up on transient RPC failures
+const MAX_SEND_ATTEMPTS: u32 = 5;
+/// Longest wait for a sent transaction to land, past the minute or so a blockhash lives, so
+/// that an unreachable RPC fails the upload instead of polling forever
+const MAX_LANDING_WAIT: Duration = Duration::from_secs(120);
+
+/// Whether sending a transaction may succeed on retry. Transactions the cluster executed and
+/// rejected fail the same way again, except for a blockhash that expired before they landed.
+fn is_transient_send_error(err: &ClientError) -> bool {
+    match err.get_transaction_error() {
+        Some(tx_err) => tx_err == TransactionError::BlockhashNotFound,
+        None => !matches!(err.kind(), ClientErrorKind::SigningError(_)),
+    }
+}
+
+/// First of the `sent` signatures whose transaction landed with the required commitment, and
+/// how it was executed.
+fn find_landed(
+    sent: &[Signature],
+    statuses: &[Option<TransactionStatus>],
+    commitment: CommitmentConfig,
+) -> Option<(Signature, Option<TransactionError>)> {
+    sent.iter()
+        .zip(statuses)
+        .find_map(|(signature, status)| match status {
+            Some(status) if status.satisfies_commitment(commitment) => {
+                Some((*signature, status.err.clone()))
+            }
+            _ => None,
+        })
+}
+
+/// Sends `tx` and waits for its confirmation, with a spinner unless the run is quiet.
+#[allow(clippy::result_large_err)]
+pub fn send_and_confirm(
+    connection: &RpcClient,
+    tx: &Transaction,
+) -> Result<Signature, ClientError> {
+    if is_quiet() {
+        connection.send_and_confirm_transaction(tx)
+    } else {
+        connection.send_and_confirm_transaction_with_spinner(tx)
+    }
+}
+
+/// Signs `message` with a recent blockhash and sends it, retrying transient failures such as
+/// dropped connections or expired blockhashes. A transaction whose confirmation failed may
+/// still land, so its signature is polled with backoff until its blockhash expires before the
+/// message is signed again with a fresh one.
+#[allow(clippy::result_large_err)]
+fn send_with_retry(
+    connection: &RpcClient,
+    message: Message,
+    signer: &dyn Signer,
+) -> Result<Signature, ClientError> {
+    let mut sent = vec![];
+    let mut backoff = Backoff::new(Duration::from_secs(2), Duration::from_secs(30));
+    for attempt in 1..=MAX_SEND_ATTEMPTS {
+        let err = match connection.get_latest_blockhash() {
+            Ok(blockhash) => {
+                let mut tx = Transaction::new_unsigned(message.clone());
+                tx.try_sign(&[signer], blockhash)?;
+                sent.push(tx.signatures[0]);
+                match send_and_confirm(connection, &tx) {
+                    Ok(signature) => return Ok(signature),
+                    Err(err) if !is_transient_send_error(&err) => return Err(err),
+                    Err(err) => {
+                        if let Some((signature, tx_err)) =
+                            wait_for_landing(connection, &sent, &blockhash, &mut backoff)?
+                        {
+                            return match tx_err {
+                                None => Ok(signature),
+                                Some(tx_err) => Err(tx_err.into()),
+                            };
+                        }
+                        err
+                    }
+                }
+            }
+            Err(err) => {
+                std::thread::sleep(backoff.next_delay());
+                err
+            }
+        };
+        if attempt == MAX_SEND_ATTEMPTS {
+            return Err(err);
+        }
+        warn!(
+            "Sending the transaction failed ({}), retrying with a fresh blockhash ({}/{})",
+            err,
+            attempt + 1,
+            MAX_SEND_ATTEMPTS
+        );
+        backoff.reset();
+    }
+    unreachable!("the last attempt returns")
+}
+
+/// Polls the status of the `sent` signatures until one lands or `blockhash`, the most recent
+/// they were signed with, expires. Fails if the RPC cannot tell within `MAX_LANDING_WAIT`.
+#[allow(clippy::result_large_err)]
+fn wait_for_landing(
+    connection: &RpcClient,
+    sent: &[Signature],
+    blockhash: &Hash,
+    backoff: &mut Backoff,
+) -> Result<Option<(Signature, Option<TransactionError>)>, ClientError> {
+    let commitment = connection.commitment();
+    let deadline = Instant::now() + MAX_LANDING_WAIT;
+    loop {
+        if let Ok(statuses) = connection.get_signature_statuses(sent) {
+            if let Some(landed) = find_landed(sent, &statuses.value, commitment) {
+                return Ok(Some(landed));
+            }
+        }
+        // An unknown answer keeps polling, the blockhash lives for about a minute
+        match connection.is_blockhash_valid(blockhash, CommitmentConfig::processed()) {
+            Ok(false) => return Ok(None),
+            Err(err) if Instant::now() >= deadline => return Err(err),
+            Ok(true) if Instant::now() >= deadline => return Ok(None),
+            _ => {}
+        }
+        std::thread::sleep(backoff.next_delay());
+    }
+}
+
+/// Shows what a PDA created by a concurrent upload holds and asks to overwrite it.
+fn confirm_update_after_race(
+    connection: &RpcClient,
+    err: &PdaAlreadyInitializedError,
+    skip_prompt: bool,
+) -> anyhow::Result<bool> {
+    println!("{}", err);
+    let account = connection
+        .get_account(&err.pda)
+        .map_err(|e| anyhow!("Unable to fetch PDA {}: {}", err.pda, e))?;
+    let data = account
+        .data
+        .get(8..)
+        .ok_or_else(|| anyhow!("PDA {} is too small to hold build params", err.pda))?;
+    match OtterBuildParams::try_from_slice(data) {
+        Ok(build_params) => print!("It now contains:\n{}", build_params),
+        Err(_) => println!("Unable to parse its build params"),
+    }
+    Ok(skip_prompt
+        || prompt_user_input("Do you want to overwrite it with the verified params? (y/n) ")?)
+}
+
+pub fn get_signer_from_path_or_config(
     path_to_keypair: Option<&String>,
-) -> anyhow::Result<Pubkey> {
+) -> anyhow::Result<Box<dyn Signer>> {
     if let Some(path_to_keypair) = path_to_keypair {
-        Ok(get_keypair_from_path(path_to_keypair)?.pubkey())
+        get_signer_from_path(path_to_keypair)
     } else {
-        Ok(get_user_config()?.0.pubkey())
+        Ok(get_user_signer()?)
     }
 }
 
+pub fn get_address_from_keypair_or_config(
+    path_to_keypair: Option<&String>,
+) -> anyhow::Result<Pubkey> {
+    Ok(get_signer_from_path_or_config(path_to_keypair)?.try_pubkey()?)
+}
+
 #[allow(clippy::too_many_arguments)]
 pub async fn upload_program_verification_data(
     git_url: String,
@@ -228,22 +982,22 @@ pub async fn upload_program_verification_data(
     skip_prompt: bool,
     path_to_keypair: Option<String>,
     compute_unit_price: u64,
+    deployed_slot: Option<u64>,
 ) -> anyhow::Result<()> {
     if skip_prompt
         || prompt_user_input(
             "Do you want to upload the program verification to the Solana Blockchain? (y/n) ",
-        )
+        )?
     {
-        println!("Uploading the program verification params to the Solana blockchain...");
-
-        let signer_pubkey: Pubkey = get_address_from_keypair_or_config(path_to_keypair.as_ref())?;
+        info!("Uploading the program verification params to the Solana blockchain...");
 
-        // let rpc_url = connection.url();
-        println!("Using connection url: {}", connection.url());
-
-        let last_deployed_slot = get_last_deployed_slot(connection, &program_address.to_string())
-            .await
-            .map_err(|err| anyhow!("Unable to get last deployed slot: {}", err))?;
+        // Use the slot of the deployment that was verified if it is known
+        let last_deployed_slot = match deployed_slot {
+            Some(deployed_slot) => deployed_slot,
+            None => get_last_deployed_slot(connection, &program_address.to_string())
+                .await
+                .map_err(|err| anyhow!("Unable to get last deployed slot: {}", err))?,
+        };
 
         let input_params = InputParams {
             version: env!("CARGO_PKG_VERSION").to_string(),
@@ -253,72 +1007,323 @@ pub async fn upload_program_verification_data(
             deployed_slot: last_deployed_slot,
         };
 
-        // Possible PDA-1: Signer is current signer then we can update the program
-        let pda_account_1 = find_build_params_pda(&program_address, &signer_pubkey).0;
-
-        // Possible PDA-2: signer is otter signer
-        let otter_signer = Pubkey::from_str(OTTER_SIGNER)?;
-        let pda_account_2 = find_build_params_pda(&program_address, &otter_signer).0;
-
-        if connection.get_account(&pda_account_1).is_ok() {
-            println!("Program already uploaded by the current signer. Updating the program.");
-            process_otter_verify_ixs(
-                &input_params,
-                pda_account_1,
-                program_address,
-                OtterVerifyInstructions::Update,
-                connection,
-                path_to_keypair,
-                compute_unit_price,
-            )?;
-        } else if connection.get_account(&pda_account_2).is_ok() {
-            let wanna_create_new_pda = skip_prompt || prompt_user_input(
-                "Program already uploaded by another signer. Do you want to upload a new program? (Y/n)"
-            );
-            if wanna_create_new_pda {
-                process_otter_verify_ixs(
-                    &input_params,
-                    pda_account_1,
-                    program_address,
-                    OtterVerifyInstructions::Initialize,
-                    connection,
-                    path_to_keypair,
-                    compute_unit_price,
-                )?;
-            }
-            return Ok(());
-        } else {
-            // Else Create new PDA and upload the program
-            process_otter_verify_ixs(
-                &input_params,
-                pda_account_1,
-                program_address,
-                OtterVerifyInstructions::Initialize,
-                connection,
-                path_to_keypair,
-                compute_unit_price,
-            )?;
-        }
+        upload_input_params(
+            &input_params,
+            program_address,
+            connection,
+            skip_prompt,
+            path_to_keypair,
+            compute_unit_price,
+        )
+        .await?;
     } else {
-        println!("Exiting without uploading the program.");
+        info!("Exiting without uploading the program.");
     }
 
     Ok(())
 }
 
-pub fn find_build_params_pda(program_id: &Pubkey, signer: &Pubkey) -> (Pubkey, u8) {
-    let seeds: &[&[u8]; 3] = &[b"otter_verify", &signer.to_bytes(), &program_id.to_bytes()];
-    Pubkey::find_program_address(seeds, &OTTER_VERIFY_PROGRAM_ID)
+/// Returned when the program was redeployed after the params about to be uploaded were
+/// resolved, meaning they would describe a superseded deployment.
+#[derive(Debug)]
+pub struct StaleDeploymentError {
+    pub program_id: Pubkey,
+    pub recorded_slot: u64,
+    pub current_slot: u64,
+}
+
+impl std::fmt::Display for StaleDeploymentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Program {} was redeployed at slot {} after it was verified against the deployment at slot {}. Verify the program again before uploading.",
+            self.program_id, self.current_slot, self.recorded_slot
+        )
+    }
+}
+
+impl std::error::Error for StaleDeploymentError {}
+
+/// Refuses params recorded for a deployment other than the program's current one.
+fn ensure_current_deployment(
+    program_id: Pubkey,
+    input_params: &InputParams,
+    current_slot: u64,
+) -> Result<(), StaleDeploymentError> {
+    if current_slot != input_params.deployed_slot {
+        return Err(StaleDeploymentError {
+            program_id,
+            recorded_slot: input_params.deployed_slot,
+            current_slot,
+        });
+    }
+    Ok(())
+}
+
+/// Writes already resolved verification params to the signer's PDA, initializing it if needed.
+pub async fn upload_input_params(
+    input_params: &InputParams,
+    program_address: Pubkey,
+    connection: &RpcClient,
+    skip_prompt: bool,
+    path_to_keypair: Option<String>,
+    compute_unit_price: u64,
+) -> anyhow::Result<()> {
+    let current_slot = get_last_deployed_slot(connection, &program_address.to_string())
+        .await
+        .map_err(|err| anyhow!("Unable to get last deployed slot: {}", err))?;
+    ensure_current_deployment(program_address, input_params, current_slot)?;
+
+    let signer_pubkey: Pubkey = get_address_from_keypair_or_config(path_to_keypair.as_ref())?;
+
+    info!(
+        "Using connection url: {}",
+        redact_rpc_url(&connection.url())
+    );
+
+    // Possible PDA-1: Signer is current signer then we can update the program
+    let pda_account_1 = find_build_params_pda(&program_address, &signer_pubkey).0;
+
+    if connection.get_account(&pda_account_1).is_ok() {
+        info!("Program already uploaded by the current signer. Updating the program.");
+        process_otter_verify_ixs(
+            input_params,
+            pda_account_1,
+            program_address,
+            OtterVerifyInstructions::Update,
+            connection,
+            path_to_keypair,
+            compute_unit_price,
+        )?;
+    } else {
+        // Possible PDA-2: uploaded by other signers, such as the authority or OtterSec
+        let other_pdas = get_other_signer_pdas(connection, &program_address, &signer_pubkey).await;
+        if !other_pdas.is_empty()
+            && !confirm_new_pda(connection, &program_address, &other_pdas, skip_prompt).await?
+        {
+            return Ok(());
+        }
+
+        // Create new PDA and upload the program
+        let result = process_otter_verify_ixs(
+            input_params,
+            pda_account_1,
+            program_address,
+            OtterVerifyInstructions::Initialize,
+            connection,
+            path_to_keypair.clone(),
+            compute_unit_price,
+        );
+        match result
+            .as_ref()
+            .err()
+            .and_then(|err| err.downcast_ref::<PdaAlreadyInitializedError>())
+        {
+            Some(err) => {
+                if confirm_update_after_race(connection, err, skip_prompt)? {
+                    process_otter_verify_ixs(
+                        input_params,
+                        pda_account_1,
+                        program_address,
+                        OtterVerifyInstructions::Update,
+                        connection,
+                        path_to_keypair,
+                        compute_unit_price,
+                    )?;
+                } else {
+                    info!("Keeping the existing PDA.");
+                }
+            }
+            None => result?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Packs instructions into as few transactions as fit in a packet, keeping their order.
+/// Room is left for the compute budget each transaction is given before it is sent.
+fn pack_instructions(
+    instructions: Vec<Instruction>,
+    payer: &Pubkey,
+    compute_unit_price: u64,
+) -> anyhow::Result<Vec<Vec<Instruction>>> {
+    let new_batch = || {
+        if compute_unit_price > 0 {
+            vec![ComputeBudgetInstruction::set_compute_unit_price(
+                compute_unit_price,
+            )]
+        } else {
+            vec![]
+        }
+    };
+    let fits = |batch: &[Instruction]| {
+        let mut batch = batch.to_vec();
+        batch.push(ComputeBudgetInstruction::set_compute_unit_limit(0));
+        if COMPUTE_UNIT_PRICE_PERCENTILE.get().is_some() {
+            batch.push(ComputeBudgetInstruction::set_compute_unit_price(0));
+        }
+        let tx = Transaction::new_unsigned(Message::new(&batch, Some(payer)));
+        bincode::serialized_size(&tx).is_ok_and(|size| size as usize <= PACKET_DATA_SIZE)
+    };
+
+    let mut messages = vec![];
+    let mut batch = new_batch();
+    let mut batch_len = 0;
+    for instruction in instructions {
+        batch.push(instruction);
+        if fits(&batch) {
+            batch_len += 1;
+            continue;
+        }
+
+        let instruction = batch.pop().unwrap();
+        if batch_len == 0 {
+            return Err(anyhow!(
+                "Verification params are too large for a transaction"
+            ));
+        }
+        messages.push(batch);
+        batch = new_batch();
+        batch.push(instruction);
+        batch_len = 1;
+        if !fits(&batch) {
+            return Err(anyhow!(
+                "Verification params are too large for a transaction"
+            ));
+        }
+    }
+    if batch_len > 0 {
+        messages.push(batch);
+    }
+    Ok(messages)
+}
+
+/// Writes the verification params of several programs, e.g. a router and its instances
+/// deployed from the same source, with a single confirmation and as few transactions as
+/// possible. Each program's params carry its own deployed slot.
+pub async fn upload_input_params_batch(
+    params: Vec<(Pubkey, InputParams)>,
+    connection: &RpcClient,
+    skip_prompt: bool,
+    path_to_keypair: Option<String>,
+    compute_unit_price: u64,
+) -> anyhow::Result<()> {
+    let signer = get_signer_from_path_or_config(path_to_keypair.as_ref())?;
+
+    info!(
+        "Using connection url: {}",
+        redact_rpc_url(&connection.url())
+    );
+    // Refuse the whole batch before anything is sent, as a single upload would
+    for (program_address, input_params) in &params {
+        let current_slot = get_last_deployed_slot(connection, &program_address.to_string())
+            .await
+            .map_err(|err| {
+                anyhow!(
+                    "Unable to get last deployed slot of {}: {}",
+                    program_address,
+                    err
+                )
+            })?;
+        ensure_current_deployment(*program_address, input_params, current_slot)?;
+    }
+
+    let mut instructions = vec![];
+    let mut included = vec![];
+    for (program_address, input_params) in params {
+        let pda_account = find_build_params_pda(&program_address, &signer.pubkey()).0;
+        let instruction = if connection.get_account(&pda_account).is_ok() {
+            info!("{}: updating existing PDA {}", program_address, pda_account);
+            OtterVerifyInstructions::Update
+        } else {
+            let other_pdas =
+                get_other_signer_pdas(connection, &program_address, &signer.pubkey()).await;
+            if !other_pdas.is_empty()
+                && !confirm_new_pda(connection, &program_address, &other_pdas, skip_prompt).await?
+            {
+                continue;
+            }
+            info!("{}: creating PDA {}", program_address, pda_account);
+            OtterVerifyInstructions::Initialize
+        };
+        instructions.push(create_otter_verify_ix(
+            &input_params,
+            signer.pubkey(),
+            pda_account,
+            program_address,
+            instruction,
+        )?);
+        included.push((program_address, input_params));
+    }
+
+    if instructions.is_empty() {
+        info!("Nothing to upload.");
+        return Ok(());
+    }
+    let instructions_len = instructions.len();
+    let batches = pack_instructions(instructions, &signer.pubkey(), compute_unit_price)?;
+    if !skip_prompt
+        && !prompt_user_input(&format!(
+            "Do you want to upload the verification of {} programs in {} transaction(s)? (y/n) ",
+            instructions_len,
+            batches.len()
+        ))?
+    {
+        info!("Exiting without uploading the programs.");
+        return Ok(());
+    }
+
+    let mut uploaded = 0;
+    for batch in batches {
+        // Batches hold the instructions in order, after the compute budget ones
+        let count = batch
+            .iter()
+            .filter(|ix| ix.program_id != compute_budget::id())
+            .count();
+        let batch = with_compute_budget(connection, batch, &signer.pubkey());
+        let message = Message::new(&batch, Some(&signer.pubkey()));
+        match send_with_retry(connection, message, signer.as_ref()) {
+            Ok(tx_id) => {
+                println!("Programs uploaded successfully. Transaction ID: {}", tx_id);
+                uploaded += count;
+            }
+            Err(err) if is_account_already_in_use(&err) => {
+                // Checking the PDAs again turns the ones created concurrently into updates
+                warn!(
+                    "Some PDAs were initialized by another upload while this one was in progress"
+                );
+                let remaining = included.split_off(uploaded);
+                return Box::pin(upload_input_params_batch(
+                    remaining,
+                    connection,
+                    skip_prompt,
+                    path_to_keypair,
+                    compute_unit_price,
+                ))
+                .await;
+            }
+            Err(err) => {
+                error!("{:?}", err);
+                return Err(anyhow!("Failed to send transaction to the network."));
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn find_build_params_pda(program_id: &Pubkey, signer: &Pubkey) -> (Pubkey, u8) {
+    let seeds: &[&[u8]; 3] = &[b"otter_verify", &signer.to_bytes(), &program_id.to_bytes()];
+    Pubkey::find_program_address(seeds, &OTTER_VERIFY_PROGRAM_ID)
 }
 
 pub async fn process_close(
     program_address: Pubkey,
     connection: &RpcClient,
+    path_to_keypair: Option<String>,
     compute_unit_price: u64,
 ) -> anyhow::Result<()> {
-    let user_config = get_user_config()?;
-    let signer = user_config.0;
-    let signer_pubkey = signer.pubkey();
+    let signer_pubkey = get_address_from_keypair_or_config(path_to_keypair.as_ref())?;
 
     let last_deployed_slot = get_last_deployed_slot(connection, &program_address.to_string())
         .await
@@ -339,7 +1344,7 @@ pub async fn process_close(
             program_address,
             OtterVerifyInstructions::Close,
             connection,
-            None,
+            path_to_keypair,
             compute_unit_price,
         )?;
     } else {
@@ -354,6 +1359,62 @@ pub async fn process_close(
     Ok(())
 }
 
+pub async fn process_revoke(
+    program_address: Pubkey,
+    connection: &RpcClient,
+    skip_prompt: bool,
+    path_to_keypair: Option<String>,
+    reason: Option<String>,
+    compute_unit_price: u64,
+) -> anyhow::Result<()> {
+    let signer_pubkey = get_address_from_keypair_or_config(path_to_keypair.as_ref())?;
+    let (pda_account, build_params) = get_program_pda(
+        connection,
+        &program_address,
+        Some(signer_pubkey.to_string()),
+    )
+    .await?;
+
+    if build_params.is_revoked() {
+        println!(
+            "The verification for {} is already revoked.",
+            program_address
+        );
+        return Ok(());
+    }
+
+    println!(
+        "Revoking verification of {} at commit {}",
+        build_params.git_url, build_params.commit
+    );
+    if !skip_prompt
+        && !prompt_user_input("Do you want to mark this verification as revoked? (y/n) ")?
+    {
+        info!("Exiting without revoking the verification.");
+        return Ok(());
+    }
+
+    // Keep the repository and commit so that consumers can tell what was revoked
+    let mut args = vec![REVOKED_MARKER.to_string()];
+    args.extend(reason);
+
+    process_otter_verify_ixs(
+        &InputParams {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git_url: build_params.git_url,
+            commit: build_params.commit,
+            args,
+            deployed_slot: build_params.deployed_slot,
+        },
+        pda_account,
+        program_address,
+        OtterVerifyInstructions::Update,
+        connection,
+        path_to_keypair,
+        compute_unit_price,
+    )
+}
+
 pub async fn get_program_pda(
     client: &RpcClient,
     program_id: &Pubkey,
@@ -362,18 +1423,13 @@ pub async fn get_program_pda(
     let signer_pubkey = if let Some(signer_pubkey) = signer_pubkey {
         Pubkey::from_str(&signer_pubkey)?
     } else {
-        get_user_config()?.0.pubkey()
+        get_user_signer()?.pubkey()
     };
 
     let pda = find_build_params_pda(program_id, &signer_pubkey).0;
     let account = client
-        .get_account_with_commitment(
-            &pda,
-            CommitmentConfig {
-                commitment: CommitmentLevel::Confirmed,
-            },
-        )
-        .unwrap();
+        .get_account_with_commitment(&pda, client.commitment())
+        .map_err(|err| anyhow!("Unable to fetch PDA {}: {}", pda, err))?;
     if let Some(account) = account.value {
         Ok((
             pda,
@@ -393,33 +1449,699 @@ pub async fn get_all_pdas_available(
     client: &RpcClient,
     program_id_pubkey: &Pubkey,
 ) -> anyhow::Result<Vec<(Pubkey, OtterBuildParams)>> {
+    get_pdas_matching(client, 8, program_id_pubkey)
+}
+
+/// Every PDA uploaded by `signer`, one per program it verified.
+pub async fn get_all_pdas_by_signer(
+    client: &RpcClient,
+    signer: &Pubkey,
+) -> anyhow::Result<Vec<(Pubkey, OtterBuildParams)>> {
+    // The signer follows the discriminator and the program address
+    get_pdas_matching(client, 8 + 32, signer)
+}
+
+/// Headers of the PDAs uploaded for a program, to filter them before fetching them in full.
+pub fn get_program_pda_headers(
+    client: &RpcClient,
+    program_id: &Pubkey,
+) -> anyhow::Result<Vec<PdaHeader>> {
+    get_pda_headers_matching(client, 8, program_id)
+}
+
+fn get_pdas_matching(
+    client: &RpcClient,
+    offset: usize,
+    pubkey: &Pubkey,
+) -> anyhow::Result<Vec<(Pubkey, OtterBuildParams)>> {
+    let headers = get_pda_headers_matching(client, offset, pubkey)?;
+    fetch_pdas(
+        client,
+        &headers.iter().map(|header| header.pda).collect::<Vec<_>>(),
+    )
+}
+
+/// Fixed-size prefix of a PDA: the discriminator, then the program address and the signer
+const PDA_HEADER_LEN: usize = 8 + 32 + 32;
+
+/// Program and signer of a PDA, read without its variable-size params.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PdaHeader {
+    pub pda: Pubkey,
+    pub address: Pubkey,
+    pub signer: Pubkey,
+}
+
+impl PdaHeader {
+    fn parse(pda: Pubkey, data: &[u8]) -> Option<PdaHeader> {
+        let data = data.get(8..PDA_HEADER_LEN)?;
+        Some(PdaHeader {
+            pda,
+            address: Pubkey::try_from(&data[..32]).ok()?,
+            signer: Pubkey::try_from(&data[32..]).ok()?,
+        })
+    }
+}
+
+/// Headers of the PDAs holding `pubkey` at `offset`. Only the fixed-size prefix of each
+/// account is transferred, so listing programs with many uploads stays cheap.
+fn get_pda_headers_matching(
+    client: &RpcClient,
+    offset: usize,
+    pubkey: &Pubkey,
+) -> anyhow::Result<Vec<PdaHeader>> {
     let filter = vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
-        8,
-        &program_id_pubkey.to_bytes(),
+        offset,
+        &pubkey.to_bytes(),
     ))];
 
     let config = RpcProgramAccountsConfig {
         filters: Some(filter),
-        account_config: solana_client::rpc_config::RpcAccountInfoConfig {
+        account_config: RpcAccountInfoConfig {
             encoding: Some(UiAccountEncoding::Base64),
-            data_slice: None,
-            commitment: Some(CommitmentConfig {
-                commitment: CommitmentLevel::Confirmed,
+            data_slice: Some(UiDataSliceConfig {
+                offset: 0,
+                length: PDA_HEADER_LEN,
             }),
+            commitment: Some(client.commitment()),
             min_context_slot: None,
         },
         with_context: None,
     };
 
     let accounts = client.get_program_accounts_with_config(&OTTER_VERIFY_PROGRAM_ID, config)?;
+    Ok(accounts
+        .into_iter()
+        .filter_map(|(pda, account)| PdaHeader::parse(pda, &account.data))
+        .collect())
+}
+
+/// Fetches and parses `pdas` in batches of as many accounts as one request returns, skipping
+/// the ones that were closed or cannot be parsed.
+pub fn fetch_pdas(
+    client: &RpcClient,
+    pdas: &[Pubkey],
+) -> anyhow::Result<Vec<(Pubkey, OtterBuildParams)>> {
+    let mut fetched = vec![];
+    for chunk in pdas.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let accounts = client
+            .get_multiple_accounts_with_commitment(chunk, client.commitment())
+            .map_err(|err| anyhow!("Unable to fetch PDAs: {}", err))?
+            .value;
+        for (pda, account) in chunk.iter().zip(accounts) {
+            let build_params = account
+                .and_then(|account| OtterBuildParams::try_from_slice(account.data.get(8..)?).ok());
+            if let Some(build_params) = build_params {
+                fetched.push((*pda, build_params));
+            }
+        }
+    }
+    Ok(fetched)
+}
+
+/// Whether a PDA still describes the program deployed on-chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PdaFreshness {
+    /// Uploaded for the current deployment
+    Fresh,
+    /// The program was redeployed at this slot after the PDA was uploaded
+    Stale(u64),
+    /// The program has been closed or is not upgradeable
+    NoProgramData,
+    Revoked,
+}
+
+impl PdaFreshness {
+    pub fn classify(build_params: &OtterBuildParams, program_slot: Option<u64>) -> PdaFreshness {
+        if build_params.is_revoked() {
+            return PdaFreshness::Revoked;
+        }
+        match program_slot {
+            // PDAs uploaded before deployed slots were recorded hold 0
+            Some(slot) if slot > build_params.deployed_slot => PdaFreshness::Stale(slot),
+            Some(_) => PdaFreshness::Fresh,
+            None => PdaFreshness::NoProgramData,
+        }
+    }
+}
+
+impl std::fmt::Display for PdaFreshness {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PdaFreshness::Fresh => write!(f, "fresh"),
+            PdaFreshness::Stale(slot) => write!(f, "stale, redeployed at slot {}", slot),
+            PdaFreshness::NoProgramData => write!(f, "no program data"),
+            PdaFreshness::Revoked => write!(f, "revoked"),
+        }
+    }
+}
+
+/// Whether the remote verifier confirmed that a PDA's build matches the deployed program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PdaCoverage {
+    /// The build of the PDA's commit matches the current on-chain hash
+    Covered,
+    /// The build was confirmed against an earlier deployment with this hash
+    OtherDeployment(String),
+    /// The remote verifier has not confirmed the build, or at another commit
+    Unconfirmed,
+    Revoked,
+}
+
+impl PdaCoverage {
+    pub fn classify(
+        build_params: &OtterBuildParams,
+        statuses: &[RemoteStatusResponse],
+        program_hash: &str,
+    ) -> PdaCoverage {
+        if build_params.is_revoked() {
+            return PdaCoverage::Revoked;
+        }
+        let mut confirmed = statuses.iter().filter(|status| {
+            status.is_verified
+                && status.signer == build_params.signer.to_string()
+                && status.commit == build_params.commit
+        });
+        // A confirmation of the current deployment wins over those of earlier ones
+        let confirmed = confirmed
+            .clone()
+            .find(|status| status.on_chain_hash == program_hash)
+            .or_else(|| confirmed.next());
+        match confirmed {
+            Some(status) if status.on_chain_hash == program_hash => PdaCoverage::Covered,
+            Some(status) => PdaCoverage::OtherDeployment(status.on_chain_hash.clone()),
+            None => PdaCoverage::Unconfirmed,
+        }
+    }
+}
+
+impl std::fmt::Display for PdaCoverage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PdaCoverage::Covered => write!(f, "covers the current deployment ✅"),
+            PdaCoverage::OtherDeployment(hash) => {
+                write!(f, "confirmed for an earlier deployment with hash {}", hash)
+            }
+            PdaCoverage::Unconfirmed => write!(f, "not confirmed by the remote verifier"),
+            PdaCoverage::Revoked => write!(f, "revoked"),
+        }
+    }
+}
 
-    let mut pdas = vec![];
-    for account in accounts {
-        let otter_build_params = OtterBuildParams::try_from_slice(&account.1.data[8..]);
-        if let Ok(otter_build_params) = otter_build_params {
-            pdas.push((account.0, otter_build_params));
+/// Who uploaded the verification PDA that a consumer should trust for a program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerificationSource {
+    /// Uploaded by the current upgrade authority of the program
+    Authority,
+    /// Uploaded by the OtterSec signer used by the remote verifier
+    OtterSec,
+    /// Uploaded by any other signer
+    Other,
+}
+
+impl std::fmt::Display for VerificationSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerificationSource::Authority => write!(f, "program upgrade authority"),
+            VerificationSource::OtterSec => write!(f, "OtterSec signer"),
+            VerificationSource::Other => write!(f, "third-party signer"),
         }
     }
+}
+
+/// The single verification PDA that should be believed for a program, following the
+/// trust order: upgrade authority PDA, then the OtterSec signer PDA, then any other uploader.
+#[derive(Debug)]
+pub struct VerificationVerdict {
+    pub pda: Pubkey,
+    pub source: VerificationSource,
+    pub build_params: OtterBuildParams,
+}
+
+impl std::fmt::Display for VerificationVerdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Trusted uploader: {}", self.source)?;
+        writeln!(f, "PDA: {}", self.pda)?;
+        write!(f, "{}", self.build_params)
+    }
+}
+
+pub fn get_program_upgrade_authority(
+    client: &RpcClient,
+    program_id: &Pubkey,
+) -> anyhow::Result<Option<Pubkey>> {
+    let program_data_address =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id()).0;
+    let data = client.get_account_data(&program_data_address)?;
+    parse_upgrade_authority(program_id, &data)
+}
+
+/// Upgrade authority of a program, or `None` if it has no program data account, as for
+/// programs of the non-upgradeable loaders. Other RPC failures are returned.
+fn get_upgrade_authority_if_upgradeable(
+    client: &RpcClient,
+    program_id: &Pubkey,
+) -> anyhow::Result<Option<Pubkey>> {
+    let program_data_address =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id()).0;
+    match client
+        .get_account_with_commitment(&program_data_address, client.commitment())?
+        .value
+    {
+        Some(account) => parse_upgrade_authority(program_id, &account.data),
+        None => Ok(None),
+    }
+}
+
+fn parse_upgrade_authority(program_id: &Pubkey, data: &[u8]) -> anyhow::Result<Option<Pubkey>> {
+    let metadata_len = UpgradeableLoaderState::size_of_programdata_metadata();
+    if data.len() < metadata_len {
+        return Err(anyhow!(
+            "Program data account for {} is too small",
+            program_id
+        ));
+    }
+    match bincode::deserialize::<UpgradeableLoaderState>(&data[..metadata_len])? {
+        UpgradeableLoaderState::ProgramData {
+            upgrade_authority_address,
+            ..
+        } => Ok(upgrade_authority_address),
+        _ => Err(anyhow!("Account for {} is not program data", program_id)),
+    }
+}
 
-    Ok(pdas)
+/// Classifies the signer of a verification PDA against the program's upgrade authority.
+pub fn get_verification_source(signer: &Pubkey, authority: Option<Pubkey>) -> VerificationSource {
+    classify_signer(signer, authority, Pubkey::from_str(OTTER_SIGNER).unwrap())
+}
+
+fn classify_signer(
+    signer: &Pubkey,
+    authority: Option<Pubkey>,
+    otter_signer: Pubkey,
+) -> VerificationSource {
+    if Some(*signer) == authority {
+        VerificationSource::Authority
+    } else if *signer == otter_signer {
+        VerificationSource::OtterSec
+    } else {
+        VerificationSource::Other
+    }
+}
+
+/// Resolves the canonical verification for `program_id`, or `None` if nobody uploaded one.
+pub async fn resolve_trusted_verification(
+    client: &RpcClient,
+    program_id: &Pubkey,
+) -> anyhow::Result<Option<VerificationVerdict>> {
+    let pdas = get_all_pdas_available(client, program_id).await?;
+    // A program without upgradeable program data simply has no authority to prefer, but an
+    // RPC failure must not make another signer trusted in its place
+    let authority = get_upgrade_authority_if_upgradeable(client, program_id)?;
+    let otter_signer = Pubkey::from_str(OTTER_SIGNER)?;
+    Ok(select_trusted_verification(pdas, authority, otter_signer))
+}
+
+fn select_trusted_verification(
+    pdas: Vec<(Pubkey, OtterBuildParams)>,
+    authority: Option<Pubkey>,
+    otter_signer: Pubkey,
+) -> Option<VerificationVerdict> {
+    pdas.into_iter()
+        .map(|(pda, build_params)| {
+            let source = classify_signer(&build_params.signer, authority, otter_signer);
+            VerificationVerdict {
+                pda,
+                source,
+                build_params,
+            }
+        })
+        // Lower rank wins; among other uploaders prefer the most recent deployment
+        .min_by_key(|verdict| {
+            let rank = match verdict.source {
+                VerificationSource::Authority => 0,
+                VerificationSource::OtterSec => 1,
+                VerificationSource::Other => 2,
+            };
+            (rank, std::cmp::Reverse(verdict.build_params.deployed_slot))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_transaction_status::TransactionConfirmationStatus;
+
+    #[test]
+    fn test_ensure_current_deployment() {
+        let program_id = Pubkey::new_unique();
+        let input_params = InputParams {
+            version: "0.1.0".to_string(),
+            git_url: "https://github.com/Ellipsis-Labs/phoenix-v1".to_string(),
+            commit: "".to_string(),
+            args: vec![],
+            deployed_slot: 100,
+        };
+        assert!(ensure_current_deployment(program_id, &input_params, 100).is_ok());
+
+        let err = ensure_current_deployment(program_id, &input_params, 150).unwrap_err();
+        assert_eq!(err.program_id, program_id);
+        assert_eq!(err.recorded_slot, 100);
+        assert_eq!(err.current_slot, 150);
+    }
+
+    #[test]
+    fn test_pda_freshness() {
+        let mut build_params = build_params_with_args(vec![]);
+        build_params.deployed_slot = 100;
+        assert_eq!(
+            PdaFreshness::classify(&build_params, Some(100)),
+            PdaFreshness::Fresh
+        );
+        assert_eq!(
+            PdaFreshness::classify(&build_params, Some(150)),
+            PdaFreshness::Stale(150)
+        );
+        assert_eq!(
+            PdaFreshness::classify(&build_params, None),
+            PdaFreshness::NoProgramData
+        );
+        let revoked = build_params_with_args(vec![REVOKED_MARKER.to_string()]);
+        assert_eq!(
+            PdaFreshness::classify(&revoked, Some(100)),
+            PdaFreshness::Revoked
+        );
+    }
+
+    #[test]
+    fn test_pda_coverage() {
+        let mut build_params = build_params_with_args(vec![]);
+        build_params.commit = "abc123".to_string();
+        let status = |commit: &str, on_chain_hash: &str, is_verified| RemoteStatusResponse {
+            signer: build_params.signer.to_string(),
+            is_verified,
+            on_chain_hash: on_chain_hash.to_string(),
+            executable_hash: on_chain_hash.to_string(),
+            repo_url: build_params.git_url.clone(),
+            commit: commit.to_string(),
+            last_verified_at: "2024-10-01T00:00:00Z".to_string(),
+        };
+
+        assert_eq!(
+            PdaCoverage::classify(&build_params, &[status("abc123", "hash", true)], "hash"),
+            PdaCoverage::Covered
+        );
+        assert_eq!(
+            PdaCoverage::classify(
+                &build_params,
+                &[
+                    status("abc123", "old", true),
+                    status("abc123", "hash", true)
+                ],
+                "hash"
+            ),
+            PdaCoverage::Covered
+        );
+        assert_eq!(
+            PdaCoverage::classify(&build_params, &[status("abc123", "old", true)], "hash"),
+            PdaCoverage::OtherDeployment("old".to_string())
+        );
+        assert_eq!(
+            PdaCoverage::classify(&build_params, &[status("abc123", "hash", false)], "hash"),
+            PdaCoverage::Unconfirmed
+        );
+        assert_eq!(
+            PdaCoverage::classify(&build_params, &[status("def456", "hash", true)], "hash"),
+            PdaCoverage::Unconfirmed
+        );
+        assert_eq!(
+            PdaCoverage::classify(&build_params, &[], "hash"),
+            PdaCoverage::Unconfirmed
+        );
+    }
+
+    fn build_params_with_args(args: Vec<String>) -> OtterBuildParams {
+        OtterBuildParams {
+            address: Pubkey::new_unique(),
+            signer: Pubkey::new_unique(),
+            version: "0.4.1".to_string(),
+            git_url: "https://github.com/Ellipsis-Labs/phoenix-v1".to_string(),
+            commit: "".to_string(),
+            args,
+            deployed_slot: 0,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn test_is_account_already_in_use() {
+        let in_use = ClientError::from(TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(SystemError::AccountAlreadyInUse as u32),
+        ));
+        assert!(is_account_already_in_use(&in_use));
+
+        let other = ClientError::from(TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(6000),
+        ));
+        assert!(!is_account_already_in_use(&other));
+        assert!(!is_account_already_in_use(&ClientError::from(
+            TransactionError::BlockhashNotFound
+        )));
+    }
+
+    #[test]
+    fn test_resolve_replace_policy() {
+        assert_eq!(
+            resolve_replace_policy(ReplacePolicy::Never, true),
+            Some(false)
+        );
+        assert_eq!(
+            resolve_replace_policy(ReplacePolicy::Always, false),
+            Some(true)
+        );
+        assert_eq!(
+            resolve_replace_policy(ReplacePolicy::Prompt, true),
+            Some(true)
+        );
+        assert_eq!(resolve_replace_policy(ReplacePolicy::Prompt, false), None);
+        assert!("sometimes".parse::<ReplacePolicy>().is_err());
+    }
+
+    #[test]
+    fn test_pack_instructions() {
+        let payer = Pubkey::new_unique();
+        let instructions = |count: usize, arg_len: usize| {
+            (0..count)
+                .map(|_| {
+                    let params = InputParams {
+                        version: "0.4.1".to_string(),
+                        git_url: "https://github.com/Ellipsis-Labs/phoenix-v1".to_string(),
+                        commit: "a".repeat(40),
+                        args: vec!["x".repeat(arg_len)],
+                        deployed_slot: 1,
+                    };
+                    create_otter_verify_ix(
+                        &params,
+                        payer,
+                        Pubkey::new_unique(),
+                        Pubkey::new_unique(),
+                        OtterVerifyInstructions::Initialize,
+                    )
+                    .unwrap()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let batches = pack_instructions(instructions(3, 10), &payer, 100).unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].len(), 4);
+
+        let batches = pack_instructions(instructions(5, 300), &payer, 0).unwrap();
+        assert!(batches.len() > 1);
+        assert_eq!(batches.iter().map(Vec::len).sum::<usize>(), 5);
+
+        assert!(pack_instructions(instructions(1, 2000), &payer, 0).is_err());
+    }
+
+    #[test]
+    fn test_compute_unit_limit_with_margin() {
+        assert_eq!(compute_unit_limit_with_margin(10_000), 12_180);
+        assert_eq!(compute_unit_limit_with_margin(0), 180);
+        assert_eq!(
+            compute_unit_limit_with_margin(2_000_000),
+            MAX_COMPUTE_UNIT_LIMIT as u32
+        );
+    }
+
+    #[test]
+    fn test_fee_percentile() {
+        let fees = vec![0, 0, 5_000, 1_000, 0, 20_000, 2_000, 0, 0, 10_000];
+        assert_eq!(fee_percentile(fees.clone(), 50), Some(0));
+        assert_eq!(fee_percentile(fees.clone(), 75), Some(5_000));
+        assert_eq!(fee_percentile(fees.clone(), 90), Some(10_000));
+        assert_eq!(fee_percentile(fees, 100), Some(20_000));
+        assert_eq!(fee_percentile(vec![], 75), None);
+
+        // Mostly zero fees, as reported for accounts nobody contends for
+        let mut fees = vec![0; 140];
+        fees.extend([50_000, 80_000, 200_000, 1_000]);
+        assert_eq!(auto_price_from_fees(fees, 75), 80_000);
+        assert_eq!(
+            auto_price_from_fees(vec![0; 150], 75),
+            MIN_AUTO_COMPUTE_UNIT_PRICE
+        );
+        assert_eq!(
+            auto_price_from_fees(vec![0, 0, 500], 50),
+            MIN_AUTO_COMPUTE_UNIT_PRICE
+        );
+    }
+
+    #[test]
+    fn test_pda_header() {
+        let (signer, deployed_slot) = (Pubkey::new_unique(), 100);
+        let (pda, build_params) = build_params_from_signer(signer, deployed_slot);
+        let mut data = vec![0; 8];
+        data.extend(to_vec(&build_params).unwrap());
+
+        let header = PdaHeader::parse(pda, &data[..PDA_HEADER_LEN]).unwrap();
+        assert_eq!(header.pda, pda);
+        assert_eq!(header.address, build_params.address);
+        assert_eq!(header.signer, signer);
+        assert_eq!(PdaHeader::parse(pda, &data[..PDA_HEADER_LEN - 1]), None);
+    }
+
+    #[test]
+    fn test_upload_preview() {
+        let mut preview = UploadPreview {
+            program_id: Pubkey::new_unique(),
+            pda: Pubkey::new_unique(),
+            instruction: OtterVerifyInstructions::Initialize,
+            rent: Some(2_853_120),
+            fee: 5_000,
+            units_consumed: Some(12_000),
+            error: None,
+            logs: vec!["Program log: Instruction: Initialize".to_string()],
+        };
+        let printed = preview.to_string();
+        assert!(printed.contains(&format!("PDA: {}", preview.pda)));
+        assert!(printed.contains("Rent: 0.00285312 SOL (2,853,120 lamports)"));
+        assert!(printed.contains("Fee: 0.000005 SOL (5,000 lamports)"));
+        assert!(printed.contains("Simulation succeeded"));
+        assert!(!printed.contains("Program log"));
+
+        preview.rent = None;
+        preview.error = Some("insufficient funds for fee".to_string());
+        let printed = preview.to_string();
+        assert!(printed.contains("Rent: unknown"));
+        assert!(printed.contains("Simulation failed: insufficient funds for fee"));
+        assert!(printed.contains("Program log: Instruction: Initialize"));
+    }
+
+    #[test]
+    fn test_revoked_marker() {
+        let params =
+            build_params_with_args(vec!["--library-name".to_string(), "phoenix".to_string()]);
+        assert!(!params.is_revoked());
+        assert_eq!(params.revocation_reason(), None);
+
+        let params = build_params_with_args(vec![
+            REVOKED_MARKER.to_string(),
+            "vulnerable release".to_string(),
+        ]);
+        assert!(params.is_revoked());
+        assert_eq!(params.revocation_reason(), Some("vulnerable release"));
+    }
+
+    fn build_params_from_signer(signer: Pubkey, deployed_slot: u64) -> (Pubkey, OtterBuildParams) {
+        let mut params = build_params_with_args(vec![]);
+        params.signer = signer;
+        params.deployed_slot = deployed_slot;
+        (Pubkey::new_unique(), params)
+    }
+
+    #[test]
+    fn test_select_trusted_verification() {
+        let authority = Pubkey::new_unique();
+        let otter_signer = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+
+        let verdict = select_trusted_verification(
+            vec![
+                build_params_from_signer(other, 10),
+                build_params_from_signer(otter_signer, 5),
+                build_params_from_signer(authority, 1),
+            ],
+            Some(authority),
+            otter_signer,
+        )
+        .unwrap();
+        assert_eq!(verdict.source, VerificationSource::Authority);
+
+        let verdict = select_trusted_verification(
+            vec![
+                build_params_from_signer(other, 10),
+                build_params_from_signer(otter_signer, 5),
+            ],
+            Some(authority),
+            otter_signer,
+        )
+        .unwrap();
+        assert_eq!(verdict.source, VerificationSource::OtterSec);
+
+        let newest = Pubkey::new_unique();
+        let verdict = select_trusted_verification(
+            vec![
+                build_params_from_signer(other, 10),
+                build_params_from_signer(newest, 20),
+            ],
+            None,
+            otter_signer,
+        )
+        .unwrap();
+        assert_eq!(verdict.build_params.signer, newest);
+
+        assert!(select_trusted_verification(vec![], None, otter_signer).is_none());
+    }
+
+    #[test]
+    fn test_send_retry_classification() {
+        let io_err: ClientError =
+            std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset").into();
+        assert!(is_transient_send_error(&io_err));
+        let expired: ClientError = TransactionError::BlockhashNotFound.into();
+        assert!(is_transient_send_error(&expired));
+        let tx_err: ClientError = TransactionError::AccountInUse.into();
+        assert!(!is_transient_send_error(&tx_err));
+
+        let status = |confirmation_status, err| TransactionStatus {
+            slot: 1,
+            confirmations: Some(0),
+            status: Ok(()),
+            err,
+            confirmation_status: Some(confirmation_status),
+        };
+        let sent = [Signature::new_unique(), Signature::new_unique()];
+        let statuses = [
+            None,
+            Some(status(TransactionConfirmationStatus::Confirmed, None)),
+        ];
+        assert_eq!(
+            find_landed(&sent, &statuses, CommitmentConfig::confirmed()),
+            Some((sent[1], None))
+        );
+        assert_eq!(
+            find_landed(&sent, &statuses, CommitmentConfig::finalized()),
+            None
+        );
+        let failed = [Some(status(
+            TransactionConfirmationStatus::Finalized,
+            Some(TransactionError::AccountInUse),
+        ))];
+        assert_eq!(
+            find_landed(&sent[..1], &failed, CommitmentConfig::confirmed()),
+            Some((sent[0], Some(TransactionError::AccountInUse)))
+        );
+    }
 }
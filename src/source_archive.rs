@@ -0,0 +1,322 @@
+use anyhow::anyhow;
+use log::info;
+use reqwest::Client;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use uuid::Uuid;
+
+use crate::api::get_last_deployed_slot;
+use crate::cleanup::{remove_dir, track_temp_dir};
+use crate::docker::check_docker_available;
+use crate::solana_program::{get_program_pda, upload_program_verification_data};
+use crate::verify_all::ManifestProgram;
+use crate::{build_and_verify_repo, build_args, get_program_data_slot, get_settled_program_hash};
+
+const CRATES_IO_API_URL: &str = "https://crates.io/api/v1/crates";
+const CRATES_IO_DOWNLOAD_URL: &str = "https://static.crates.io/crates";
+
+/// Build argument recording the digest of the source archive in the PDA
+pub const ARCHIVE_SHA256_ARG: &str = "--archive-sha256";
+
+/// A source snapshot published as an archive rather than as a git commit.
+pub enum ArchiveSource {
+    /// `.tar.gz` at `url`, which must have the given SHA-256
+    Url { url: String, sha256: String },
+    /// Crate published on crates.io, checked against the checksum of its index entry
+    Crate { name: String, version: String },
+    /// Archive and build arguments this signer uploaded for the program, rebuilt to check them
+    Pda(Pubkey),
+}
+
+#[derive(Deserialize)]
+struct CrateVersionResponse {
+    version: CrateVersion,
+}
+
+#[derive(Deserialize)]
+struct CrateVersion {
+    checksum: String,
+}
+
+impl ArchiveSource {
+    /// URL of the archive and the SHA-256 it must have.
+    async fn resolve(&self, client: &Client) -> anyhow::Result<(String, String)> {
+        match self {
+            ArchiveSource::Url { url, sha256 } => Ok((url.clone(), sha256.to_lowercase())),
+            ArchiveSource::Crate { name, version } => {
+                let response = client
+                    .get(format!("{}/{}/{}", CRATES_IO_API_URL, name, version))
+                    .send()
+                    .await?;
+                if !response.status().is_success() {
+                    return Err(anyhow!(
+                        "Failed to look up {} {} on crates.io: {}",
+                        name,
+                        version,
+                        response.text().await?
+                    ));
+                }
+                let checksum = response
+                    .json::<CrateVersionResponse>()
+                    .await?
+                    .version
+                    .checksum;
+                Ok((
+                    format!(
+                        "{}/{}/{}-{}.crate",
+                        CRATES_IO_DOWNLOAD_URL, name, name, version
+                    ),
+                    checksum,
+                ))
+            }
+            ArchiveSource::Pda(_) => Err(anyhow!(
+                "The archive of a PDA is read from its build arguments"
+            )),
+        }
+    }
+}
+
+/// Archive and build settings of the PDA of an archive verification, whose `git_url` is the
+/// archive URL and whose arguments start with its SHA-256.
+fn archive_from_pda(
+    git_url: &str,
+    args: &[String],
+) -> anyhow::Result<(ArchiveSource, ManifestProgram)> {
+    match args {
+        [arg, sha256, build_args @ ..] if arg == ARCHIVE_SHA256_ARG => Ok((
+            ArchiveSource::Url {
+                url: git_url.to_string(),
+                sha256: sha256.clone(),
+            },
+            ManifestProgram::from_build_args(build_args)?,
+        )),
+        _ => Err(anyhow!(
+            "The PDA does not record a source archive, verify it with verify-from-repo"
+        )),
+    }
+}
+
+/// Directory holding the sources of an extracted archive, which is its only top-level
+/// directory if it has one.
+fn archive_root(extract_dir: &Path) -> anyhow::Result<PathBuf> {
+    let entries = std::fs::read_dir(extract_dir)?.collect::<Result<Vec<_>, _>>()?;
+    match entries.as_slice() {
+        [entry] if entry.file_type()?.is_dir() => Ok(entry.path()),
+        _ => Ok(extract_dir.to_path_buf()),
+    }
+}
+
+/// Downloads the archive into `dir`, checks its digest and extracts it. Returns the source
+/// directory, the archive URL and its SHA-256.
+async fn fetch_archive(
+    source: &ArchiveSource,
+    dir: &Path,
+) -> anyhow::Result<(PathBuf, String, String)> {
+    // Proxies and crates.io require a user agent
+    let client = Client::builder()
+        .timeout(Duration::from_secs(600))
+        .user_agent("solana-verify")
+        .build()?;
+    let (url, expected_sha256) = source.resolve(&client).await?;
+    info!("Downloading {}", url);
+    let response = client.get(&url).send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("Failed to download {}: {}", url, response.status()));
+    }
+    let archive = response.bytes().await?;
+
+    let sha256 = hex::encode(Sha256::digest(&archive));
+    if sha256 != expected_sha256 {
+        return Err(anyhow!(
+            "SHA-256 of {} is {}, expected {}",
+            url,
+            sha256,
+            expected_sha256
+        ));
+    }
+    info!("Archive SHA-256: {}", sha256);
+
+    std::fs::create_dir_all(dir)?;
+    let archive_path = dir.join("source.tar.gz");
+    std::fs::write(&archive_path, &archive)?;
+    let extract_dir = dir.join("source");
+    std::fs::create_dir_all(&extract_dir)?;
+    let output = std::process::Command::new("tar")
+        .arg("-xzf")
+        .arg(&archive_path)
+        .arg("-C")
+        .arg(&extract_dir)
+        .output()
+        .map_err(|e| anyhow!("Failed to run tar: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to extract {}: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    Ok((archive_root(&extract_dir)?, url, sha256))
+}
+
+/// Verifies a program against a source archive and uploads the archive URL and digest as
+/// its verification params.
+#[allow(clippy::too_many_arguments)]
+pub async fn verify_from_archive(
+    connection: &RpcClient,
+    source: ArchiveSource,
+    program_id: Pubkey,
+    relative_mount_path: String,
+    library_name: Option<String>,
+    base_image: Option<String>,
+    bpf_flag: bool,
+    cargo_args: Vec<String>,
+    skip_prompt: bool,
+    path_to_keypair: Option<String>,
+    compute_unit_price: u64,
+) -> anyhow::Result<()> {
+    check_docker_available()?;
+
+    let deployed_slot = get_last_deployed_slot(connection, &program_id.to_string())
+        .await
+        .map_err(|err| anyhow!("Unable to get last deployed slot: {}", err))?;
+
+    // A PDA is rebuilt with the settings it records, and only checked
+    let settings = ManifestProgram {
+        library_name,
+        mount_path: relative_mount_path,
+        base_image,
+        bpf: Some(bpf_flag),
+        cargo_args,
+    };
+    let (source, settings, upload) = match source {
+        ArchiveSource::Pda(signer) => {
+            let (pda, params) =
+                get_program_pda(connection, &program_id, Some(signer.to_string())).await?;
+            info!("Rebuilding the archive recorded in {}", pda);
+            let (source, settings) = archive_from_pda(&params.git_url, &params.args)?;
+            (source, settings, false)
+        }
+        source => (source, settings, true),
+    };
+    let ManifestProgram {
+        library_name,
+        mount_path: relative_mount_path,
+        base_image,
+        bpf,
+        cargo_args,
+    } = settings;
+    let bpf_flag = bpf.unwrap_or_default();
+
+    let verify_dir = std::env::temp_dir()
+        .join("solana-verify")
+        .join(Uuid::new_v4().to_string());
+    track_temp_dir(&verify_dir);
+
+    let result = async {
+        let (source_root, url, sha256) = fetch_archive(&source, &verify_dir).await?;
+        let (args, mount_path, library_name) = build_args(
+            &relative_mount_path,
+            library_name,
+            &source_root,
+            base_image.clone(),
+            bpf_flag,
+            cargo_args.clone(),
+        )?;
+        info!("Build path: {}", mount_path.display());
+        info!("Verifying program: {}", library_name);
+
+        let slot_before = get_program_data_slot(connection, program_id)?;
+        let (build_hash, program_hash, _) = build_and_verify_repo(
+            mount_path,
+            base_image,
+            bpf_flag,
+            library_name,
+            connection,
+            program_id,
+            cargo_args,
+        )?;
+        let program_hash =
+            get_settled_program_hash(connection, program_id, program_hash, slot_before)?;
+        Ok::<_, anyhow::Error>((url, sha256, args, build_hash, program_hash))
+    }
+    .await;
+
+    // Cleanup no matter the result
+    remove_dir(&verify_dir)?;
+    let (url, sha256, args, build_hash, program_hash) = result?;
+
+    println!("Executable Program Hash from archive: {}", build_hash);
+    println!("On-chain Program Hash: {}", program_hash);
+    if build_hash != program_hash {
+        println!("Program hashes do not match ❌");
+        return Ok(());
+    }
+    println!("Program hash matches ✅");
+    if !upload {
+        return Ok(());
+    }
+
+    let mut pda_args = vec![ARCHIVE_SHA256_ARG.to_string(), sha256];
+    pda_args.extend(args);
+    upload_program_verification_data(
+        url,
+        &None,
+        pda_args,
+        program_id,
+        connection,
+        skip_prompt,
+        path_to_keypair,
+        compute_unit_price,
+        Some(deployed_slot),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_from_pda() {
+        let args = [
+            ARCHIVE_SHA256_ARG,
+            "ab12",
+            "--library-name",
+            "phoenix",
+            "--bpf",
+            "--",
+            "--features",
+            "mainnet",
+        ]
+        .map(String::from);
+        let (source, program) =
+            archive_from_pda("https://example.com/phoenix.tar.gz", &args).unwrap();
+        assert!(matches!(
+            source,
+            ArchiveSource::Url { url, sha256 } if url == "https://example.com/phoenix.tar.gz" && sha256 == "ab12"
+        ));
+        assert_eq!(program.library_name.as_deref(), Some("phoenix"));
+        assert_eq!(program.bpf, Some(true));
+        assert_eq!(program.cargo_args, ["--features", "mainnet"]);
+
+        assert!(archive_from_pda("https://github.com/acme/amm", &args[2..]).is_err());
+    }
+
+    #[test]
+    fn test_archive_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        std::fs::create_dir_all(dir.join("phoenix-0.1.0").join("src")).unwrap();
+        assert_eq!(archive_root(dir).unwrap(), dir.join("phoenix-0.1.0"));
+
+        std::fs::write(dir.join("Cargo.toml"), "").unwrap();
+        assert_eq!(archive_root(dir).unwrap(), dir);
+    }
+}
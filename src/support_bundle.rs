@@ -0,0 +1,231 @@
+use anyhow::anyhow;
+use log::warn;
+use std::{
+    collections::VecDeque,
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::{Mutex, OnceLock},
+};
+use uuid::Uuid;
+
+use crate::compression::{is_compressed, write_artifact_from};
+use crate::docker::docker_command;
+use crate::secrets::{redact, redact_rpc_header, redact_rpc_url, redact_url};
+
+/// Number of build output lines kept for the bundle
+const MAX_OUTPUT_LINES: usize = 2000;
+
+static SUPPORT_BUNDLE_PATH: OnceLock<String> = OnceLock::new();
+static COMMAND_LOG: Mutex<Vec<String>> = Mutex::new(Vec::new());
+static OUTPUT_TAIL: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+/// `docker logs` of each build container, saved before it is stopped and removed
+static CONTAINER_LOGS: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+/// Writes a support bundle to `path` if the run fails.
+pub fn set_support_bundle_path(path: &str) {
+    let _ = SUPPORT_BUNDLE_PATH.set(path.to_string());
+}
+
+fn enabled() -> bool {
+    SUPPORT_BUNDLE_PATH.get().is_some()
+}
+
+fn redact_arg(option: Option<&str>, value: &str) -> String {
+    match option {
+        Some("-u") | Some("--url") => redact_rpc_url(value),
+        Some("--token") => "***".to_string(),
+        Some("--rpc-header") => redact_rpc_header(value),
+        _ => redact(&redact_url(value)),
+    }
+}
+
+/// The solana-verify command line, with the RPC url given to `--url` redacted as such, and
+/// tokens given to `--token` and values of `--rpc-header` hidden.
+fn redact_args(args: impl Iterator<Item = String>) -> String {
+    let mut previous: Option<String> = None;
+    let mut redacted = vec![];
+    for arg in args {
+        redacted.push(match arg.split_once('=') {
+            Some((option, value)) if option.starts_with("--") => {
+                format!("{}={}", option, redact_arg(Some(option), value))
+            }
+            _ => redact_arg(previous.as_deref(), &arg),
+        });
+        previous = Some(arg);
+    }
+    redacted.join(" ")
+}
+
+/// Records a command run on behalf of the user, with credentials in urls redacted.
+pub fn log_command(cmd: &Command) {
+    if !enabled() {
+        return;
+    }
+    let line = std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|arg| redact_url(&arg.to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join(" ");
+    COMMAND_LOG.lock().unwrap().push(redact(&line));
+}
+
+/// Keeps the last lines of build output.
+pub fn log_output(line: &str) {
+    if !enabled() {
+        return;
+    }
+    let mut output = OUTPUT_TAIL.lock().unwrap();
+    if output.len() == MAX_OUTPUT_LINES {
+        output.pop_front();
+    }
+    output.push_back(redact(line));
+}
+
+/// Saves the last lines of `docker logs` of a container, which is removed once stopped.
+pub fn save_container_logs(container_id: &str) {
+    if !enabled() {
+        return;
+    }
+    let tail = MAX_OUTPUT_LINES.to_string();
+    let logs = match docker_command()
+        .args(["logs", "--tail", &tail, container_id])
+        .output()
+    {
+        Ok(output) => format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        ),
+        Err(err) => format!("Failed to run docker logs: {}\n", err),
+    };
+    CONTAINER_LOGS
+        .lock()
+        .unwrap()
+        .push((container_id.to_string(), redact(&logs)));
+}
+
+fn command_output(program: &str, args: &[&str]) -> String {
+    match Command::new(program).args(args).output() {
+        Ok(output) => format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        ),
+        Err(err) => format!("Failed to run {}: {}\n", program, err),
+    }
+}
+
+fn environment() -> String {
+    let docker = |args: &[&str]| match docker_command().args(args).output() {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).to_string(),
+        Err(err) => format!("Failed to run docker: {}\n", err),
+    };
+    let mut environment = format!(
+        "solana-verify {}\nos: {} {}\ncwd: {}\n\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        std::env::current_dir()
+            .map(|dir| dir.display().to_string())
+            .unwrap_or_default()
+    );
+    environment.push_str(&command_output("uname", &["-a"]));
+    environment.push_str(&command_output("git", &["--version"]));
+    environment.push_str("\n$ docker version\n");
+    environment.push_str(&docker(&["version"]));
+    environment.push_str("\n$ docker info\n");
+    environment.push_str(&docker(&["info"]));
+    environment
+}
+
+/// Writes the support bundle of a failed run, if one was asked for: environment, the
+/// command line, the commands run, the tail of the build output, the logs of the build
+/// containers, the RPC endpoints and the error, as a .tar.gz to attach to a GitHub issue.
+pub fn write_support_bundle(error: &anyhow::Error, rpc_urls: &[String]) {
+    let Some(path) = SUPPORT_BUNDLE_PATH.get() else {
+        return;
+    };
+    let dir = std::env::temp_dir().join(format!("solana-verify-support-{}", Uuid::new_v4()));
+    let result = (|| -> anyhow::Result<PathBuf> {
+        std::fs::create_dir_all(&dir)?;
+        let write = |name: &str, contents: String| std::fs::write(dir.join(name), contents);
+        write("environment.txt", environment())?;
+        write("command.txt", redact_args(std::env::args()))?;
+        write("commands.log", COMMAND_LOG.lock().unwrap().join("\n"))?;
+        write(
+            "build-output.log",
+            Vec::from(OUTPUT_TAIL.lock().unwrap().clone()).join("\n"),
+        )?;
+        for (container_id, logs) in CONTAINER_LOGS.lock().unwrap().iter() {
+            let short_id = &container_id[..container_id.len().min(12)];
+            write(&format!("docker-logs-{}.log", short_id), logs.clone())?;
+        }
+        write(
+            "rpc-endpoints.txt",
+            rpc_urls
+                .iter()
+                .map(|url| redact_rpc_url(url))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )?;
+        write("error.txt", redact(&format!("{:?}", error)))?;
+
+        // A .tar.zst bundle is compressed with an integrity footer, anything else is gzipped
+        let compressed = is_compressed(path);
+        let mut tar = Command::new("tar")
+            .arg(if compressed { "-cf" } else { "-czf" })
+            .arg("-")
+            .arg("-C")
+            .arg(&dir)
+            .arg(".")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        // Streamed, as the build output and container logs can be large
+        let written = tar
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow!("Failed to read the output of tar"))
+            .and_then(|stdout| write_artifact_from(path, stdout));
+        let output = tar.wait_with_output()?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "tar failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        written
+    })();
+    let _ = std::fs::remove_dir_all(&dir);
+
+    match result {
+        Ok(path) => println!(
+            "Support bundle written to {}, attach it to your GitHub issue",
+            path.display()
+        ),
+        Err(err) => warn!("failed to write the support bundle: {}", err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_args() {
+        let args = [
+            "solana-verify",
+            "-u",
+            "https://solana-mainnet.g.alchemy.com/v2/secret",
+            "verify-from-repo",
+            "https://github.com/Ellipsis-Labs/phoenix-v1",
+            "--rpc-header",
+            "x-api-key: secret",
+            "--token=secret",
+        ];
+        assert_eq!(
+            redact_args(args.iter().map(|arg| arg.to_string())),
+            "solana-verify -u https://solana-mainnet.g.alchemy.com/*** verify-from-repo https://github.com/Ellipsis-Labs/phoenix-v1 --rpc-header x-api-key: *** --token=***"
+        );
+    }
+}
@@ -0,0 +1,398 @@
+use anyhow::anyhow;
+use base64::{prelude::BASE64_STANDARD, Engine};
+use bincode::serialize;
+use borsh::{to_vec, BorshSerialize};
+use log::info;
+use serde_json::{json, Value};
+use solana_client::{nonce_utils, rpc_client::RpcClient};
+use solana_sdk::{
+    compute_budget,
+    hash::Hash,
+    instruction::Instruction,
+    message::Message,
+    pubkey::Pubkey,
+    signature::Signature,
+    system_instruction::{self, SystemInstruction},
+    system_program,
+    transaction::Transaction,
+};
+use std::str::FromStr;
+
+/// How `export-pda-tx` prints the transaction for the program authority to sign.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxExportFormat {
+    Base58,
+    Base64,
+    /// Instructions with their account metas, as entered in the Squads app
+    SquadsJson,
+    /// Instructions serialized as spl-governance `InstructionData`, as entered in Realms
+    SplGovJson,
+}
+
+impl FromStr for TxExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "base58" => Ok(TxExportFormat::Base58),
+            "base64" => Ok(TxExportFormat::Base64),
+            "squads-json" => Ok(TxExportFormat::SquadsJson),
+            "spl-gov-json" => Ok(TxExportFormat::SplGovJson),
+            _ => Err(anyhow!("Unsupported encoding: {}", s)),
+        }
+    }
+}
+
+/// Where the recent blockhash of an exported base58 or base64 transaction comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockhashSource {
+    /// An empty blockhash, for wallets that set their own before signing
+    None,
+    /// The latest blockhash of the cluster, which expires after about 90 seconds
+    Latest,
+    /// The value of a durable nonce account, which does not expire until the nonce is
+    /// advanced. The transaction starts by advancing it, signed by `authority`
+    Nonce { account: Pubkey, authority: Pubkey },
+}
+
+impl BlockhashSource {
+    pub fn new(
+        kind: &str,
+        nonce_account: Option<Pubkey>,
+        nonce_authority: Pubkey,
+    ) -> anyhow::Result<BlockhashSource> {
+        match (kind, nonce_account) {
+            ("none", _) => Ok(BlockhashSource::None),
+            ("latest", _) => Ok(BlockhashSource::Latest),
+            ("nonce", Some(account)) => Ok(BlockhashSource::Nonce {
+                account,
+                authority: nonce_authority,
+            }),
+            ("nonce", None) => Err(anyhow!("--blockhash nonce requires --nonce-account")),
+            _ => Err(anyhow!("Unsupported blockhash source: {}", kind)),
+        }
+    }
+
+    /// The JSON formats list instructions without a message, so they carry no blockhash.
+    pub fn check_format(&self, format: TxExportFormat) -> anyhow::Result<()> {
+        match (self, format) {
+            (BlockhashSource::None, _) | (_, TxExportFormat::Base58 | TxExportFormat::Base64) => {
+                Ok(())
+            }
+            _ => Err(anyhow!(
+                "--blockhash only applies to the base58 and base64 encodings"
+            )),
+        }
+    }
+}
+
+/// Recent blockhash of an exported transaction, and the instruction advancing the durable
+/// nonce it was read from.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TxBlockhash {
+    pub blockhash: Hash,
+    pub advance_nonce: Option<Instruction>,
+}
+
+pub fn fetch_nonce(connection: &RpcClient, account: &Pubkey) -> anyhow::Result<Hash> {
+    let account = nonce_utils::get_account(connection, account)
+        .map_err(|e| anyhow!("Unable to read nonce account {}: {}", account, e))?;
+    Ok(nonce_utils::data_from_account(&account)
+        .map_err(|e| anyhow!("Invalid nonce account: {}", e))?
+        .blockhash())
+}
+
+pub fn fetch_blockhash(
+    connection: &RpcClient,
+    source: BlockhashSource,
+) -> anyhow::Result<TxBlockhash> {
+    Ok(match source {
+        BlockhashSource::None => TxBlockhash::default(),
+        BlockhashSource::Latest => TxBlockhash {
+            blockhash: connection.get_latest_blockhash()?,
+            advance_nonce: None,
+        },
+        BlockhashSource::Nonce { account, authority } => TxBlockhash {
+            blockhash: fetch_nonce(connection, &account)?,
+            advance_nonce: Some(system_instruction::advance_nonce_account(
+                &account, &authority,
+            )),
+        },
+    })
+}
+
+/// Borsh layout of spl-governance's `InstructionData`
+#[derive(BorshSerialize)]
+struct GovernanceInstructionData {
+    program_id: [u8; 32],
+    accounts: Vec<GovernanceAccountMetaData>,
+    data: Vec<u8>,
+}
+
+#[derive(BorshSerialize)]
+struct GovernanceAccountMetaData {
+    pubkey: [u8; 32],
+    is_signer: bool,
+    is_writable: bool,
+}
+
+/// Instructions a proposal has to execute. Compute budget instructions are left out, as
+/// they have no effect when invoked from a multisig or governance program.
+fn proposal_instructions(instructions: &[Instruction]) -> Vec<&Instruction> {
+    instructions
+        .iter()
+        .filter(|ix| ix.program_id != compute_budget::id())
+        .collect()
+}
+
+fn squads_instruction(ix: &Instruction) -> Value {
+    json!({
+        "programId": ix.program_id.to_string(),
+        "keys": ix.accounts.iter().map(|meta| json!({
+            "pubkey": meta.pubkey.to_string(),
+            "isSigner": meta.is_signer,
+            "isWritable": meta.is_writable,
+        })).collect::<Vec<_>>(),
+        "data": BASE64_STANDARD.encode(&ix.data),
+    })
+}
+
+fn spl_gov_instruction(ix: &Instruction) -> anyhow::Result<Value> {
+    let instruction_data = GovernanceInstructionData {
+        program_id: ix.program_id.to_bytes(),
+        accounts: ix
+            .accounts
+            .iter()
+            .map(|meta| GovernanceAccountMetaData {
+                pubkey: meta.pubkey.to_bytes(),
+                is_signer: meta.is_signer,
+                is_writable: meta.is_writable,
+            })
+            .collect(),
+        data: ix.data.clone(),
+    };
+    Ok(json!({
+        "programId": ix.program_id.to_string(),
+        "accounts": ix.accounts.iter().map(|meta| json!({
+            "pubkey": meta.pubkey.to_string(),
+            "isSigner": meta.is_signer,
+            "isWritable": meta.is_writable,
+        })).collect::<Vec<_>>(),
+        "data": BASE64_STANDARD.encode(&ix.data),
+        "serializedInstruction": BASE64_STANDARD.encode(to_vec(&instruction_data)?),
+    }))
+}
+
+/// Formats the unsigned transaction of `instructions` paid by `payer` for the program
+/// authority to import and sign.
+pub fn export_transaction(
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    format: TxExportFormat,
+    blockhash: &TxBlockhash,
+) -> anyhow::Result<String> {
+    let tx = || {
+        let instructions = blockhash
+            .advance_nonce
+            .iter()
+            .chain(instructions)
+            .cloned()
+            .collect::<Vec<_>>();
+        Transaction::new_unsigned(Message::new_with_blockhash(
+            &instructions,
+            Some(payer),
+            &blockhash.blockhash,
+        ))
+    };
+    Ok(match format {
+        TxExportFormat::Base58 | TxExportFormat::Base64 => encode_transaction(&tx(), format)?,
+        TxExportFormat::SquadsJson => serde_json::to_string_pretty(&json!({
+            "instructions": proposal_instructions(instructions)
+                .into_iter()
+                .map(squads_instruction)
+                .collect::<Vec<_>>(),
+        }))?,
+        TxExportFormat::SplGovJson => serde_json::to_string_pretty(&json!({
+            "instructions": proposal_instructions(instructions)
+                .into_iter()
+                .map(spl_gov_instruction)
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        }))?,
+    })
+}
+
+fn encode_transaction(tx: &Transaction, format: TxExportFormat) -> anyhow::Result<String> {
+    match format {
+        TxExportFormat::Base58 => Ok(bs58::encode(serialize(tx)?).into_string()),
+        TxExportFormat::Base64 => Ok(BASE64_STANDARD.encode(serialize(tx)?)),
+        _ => Err(anyhow!(
+            "Only base58 and base64 transactions can be encoded"
+        )),
+    }
+}
+
+/// Decodes a transaction exported as base58 or base64, along with its encoding.
+fn decode_transaction(encoded: &str) -> anyhow::Result<(Transaction, TxExportFormat)> {
+    let encoded = encoded.trim();
+    let decoded = bs58::decode(encoded)
+        .into_vec()
+        .ok()
+        .and_then(|bytes| bincode::deserialize(&bytes).ok())
+        .map(|tx| (tx, TxExportFormat::Base58))
+        .or_else(|| {
+            BASE64_STANDARD
+                .decode(encoded)
+                .ok()
+                .and_then(|bytes| bincode::deserialize(&bytes).ok())
+                .map(|tx| (tx, TxExportFormat::Base64))
+        });
+    decoded.ok_or_else(|| anyhow!("Not a base58 or base64 encoded transaction"))
+}
+
+/// The durable nonce account of a transaction, if it starts by advancing one.
+pub fn nonce_account(message: &Message) -> Option<Pubkey> {
+    let ix = message.instructions.first()?;
+    let program_id = message.account_keys.get(ix.program_id_index as usize)?;
+    if *program_id != system_program::id() {
+        return None;
+    }
+    match bincode::deserialize(&ix.data).ok()? {
+        SystemInstruction::AdvanceNonceAccount => message
+            .account_keys
+            .get(*ix.accounts.first()? as usize)
+            .copied(),
+        _ => None,
+    }
+}
+
+/// Replaces the blockhash of an unsigned transaction. Any signature is dropped, as it no
+/// longer matches the message.
+fn with_blockhash(mut tx: Transaction, blockhash: Hash) -> Transaction {
+    tx.message.recent_blockhash = blockhash;
+    tx.signatures = vec![Signature::default(); tx.message.header.num_required_signatures as usize];
+    tx
+}
+
+/// Re-encodes a transaction exported by `export-pda-tx` with a fresh blockhash: the current
+/// value of its durable nonce if it advances one, else the latest blockhash.
+pub fn refresh_transaction(connection: &RpcClient, encoded: &str) -> anyhow::Result<String> {
+    let (tx, format) = decode_transaction(encoded)?;
+    let blockhash = match nonce_account(&tx.message) {
+        Some(account) => {
+            info!("Using the current value of nonce account {}", account);
+            fetch_nonce(connection, &account)?
+        }
+        None => connection.get_latest_blockhash()?,
+    };
+    encode_transaction(&with_blockhash(tx, blockhash), format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{compute_budget::ComputeBudgetInstruction, instruction::AccountMeta};
+
+    fn instructions() -> (Vec<Instruction>, Pubkey) {
+        let payer = Pubkey::new_unique();
+        let ix = Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[1, 2, 3],
+            vec![
+                AccountMeta::new(Pubkey::new_unique(), false),
+                AccountMeta::new_readonly(payer, true),
+            ],
+        );
+        (
+            vec![ComputeBudgetInstruction::set_compute_unit_price(100), ix],
+            payer,
+        )
+    }
+
+    fn export_json(instructions: &[Instruction], payer: &Pubkey, format: TxExportFormat) -> Value {
+        serde_json::from_str(
+            &export_transaction(instructions, payer, format, &TxBlockhash::default()).unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_refresh_blockhash() {
+        let (instructions, payer) = instructions();
+        let nonce = Pubkey::new_unique();
+        let exported = export_transaction(
+            &instructions,
+            &payer,
+            TxExportFormat::Base64,
+            &TxBlockhash {
+                blockhash: Hash::new_unique(),
+                advance_nonce: Some(system_instruction::advance_nonce_account(&nonce, &payer)),
+            },
+        )
+        .unwrap();
+        let (tx, format) = decode_transaction(&exported).unwrap();
+        assert_eq!(format, TxExportFormat::Base64);
+        assert_eq!(nonce_account(&tx.message), Some(nonce));
+
+        let fresh = Hash::new_unique();
+        let refreshed = encode_transaction(&with_blockhash(tx.clone(), fresh), format).unwrap();
+        let (refreshed, _) = decode_transaction(&refreshed).unwrap();
+        assert_eq!(refreshed.message.recent_blockhash, fresh);
+        assert_eq!(refreshed.message.instructions, tx.message.instructions);
+        assert_eq!(refreshed.signatures, vec![Signature::default()]);
+
+        let exported = export_transaction(
+            &instructions,
+            &payer,
+            TxExportFormat::Base58,
+            &TxBlockhash::default(),
+        )
+        .unwrap();
+        let (tx, format) = decode_transaction(&exported).unwrap();
+        assert_eq!(format, TxExportFormat::Base58);
+        assert_eq!(tx.message.recent_blockhash, Hash::default());
+        assert_eq!(nonce_account(&tx.message), None);
+        assert!(decode_transaction("not a transaction").is_err());
+    }
+
+    #[test]
+    fn test_proposal_instructions() {
+        let (instructions, _) = instructions();
+        assert_eq!(proposal_instructions(&instructions), vec![&instructions[1]]);
+    }
+
+    #[test]
+    fn test_spl_gov_serialization() {
+        let (instructions, payer) = instructions();
+        let ix = &instructions[1];
+        let exported = export_json(&instructions, &payer, TxExportFormat::SplGovJson);
+        let serialized = BASE64_STANDARD
+            .decode(
+                exported["instructions"][0]["serializedInstruction"]
+                    .as_str()
+                    .unwrap(),
+            )
+            .unwrap();
+
+        let mut expected = ix.program_id.to_bytes().to_vec();
+        expected.extend(2u32.to_le_bytes());
+        for meta in &ix.accounts {
+            expected.extend(meta.pubkey.to_bytes());
+            expected.extend([meta.is_signer as u8, meta.is_writable as u8]);
+        }
+        expected.extend(3u32.to_le_bytes());
+        expected.extend([1, 2, 3]);
+        assert_eq!(serialized, expected);
+    }
+
+    #[test]
+    fn test_squads_json() {
+        let (instructions, payer) = instructions();
+        let exported = export_json(&instructions, &payer, TxExportFormat::SquadsJson);
+        let instructions = exported["instructions"].as_array().unwrap();
+        assert_eq!(instructions.len(), 1);
+        assert_eq!(instructions[0]["keys"][0]["isWritable"], true);
+        assert_eq!(instructions[0]["keys"][1]["isSigner"], true);
+        assert_eq!(instructions[0]["keys"][1]["isWritable"], false);
+        assert_eq!(instructions[0]["data"], "AQID");
+    }
+}
@@ -0,0 +1,185 @@
+use anyhow::anyhow;
+use log::{info, warn};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::api::get_last_deployed_slot;
+use crate::cleanup::remove_dir;
+use crate::params_file::VerifiedParamsFile;
+use crate::solana_program::{get_program_pda, prompt_user_input, upload_input_params, InputParams};
+use crate::{build_args, clone_repo_and_checkout, get_basename, get_program_hash};
+
+/// Where `upload-only` takes the params it writes on chain from. Each source has to be
+/// given explicitly, so unbuilt or unchecked params are never uploaded by accident.
+pub enum UploadSource {
+    /// Params file written by `verify-from-repo --write-params` after a hash match, which must
+    /// be signed unless `allow_unsigned` is set
+    Artifact { path: String, allow_unsigned: bool },
+    /// PDA of another signer, whose params are copied
+    Pda(Pubkey),
+    /// Params given on the command line and trusted without building them
+    TrustedArgs {
+        repo_url: String,
+        commit_hash: String,
+        mount_path: String,
+        library_name: Option<String>,
+        base_image: Option<String>,
+        bpf_flag: bool,
+        cargo_args: Vec<String>,
+    },
+}
+
+fn input_params_from_artifact(
+    connection: &RpcClient,
+    program_id: Pubkey,
+    path: &str,
+    allow_unsigned: bool,
+) -> anyhow::Result<InputParams> {
+    let params_file = VerifiedParamsFile::read(path)?;
+    params_file.check_signature(allow_unsigned)?;
+
+    // The file may have been written for another program deployed from the same build
+    let program_hash = get_program_hash(connection, program_id)?;
+    if program_hash != params_file.executable_hash {
+        return Err(anyhow!(
+            "{} was verified for a build with hash {}, but the on-chain hash of {} is {}",
+            path,
+            params_file.executable_hash,
+            program_id,
+            program_hash
+        ));
+    }
+    println!("Executable Hash: {}", params_file.executable_hash);
+    Ok(params_file.input_params())
+}
+
+async fn input_params_from_pda(
+    connection: &RpcClient,
+    program_id: Pubkey,
+    signer: Pubkey,
+) -> anyhow::Result<InputParams> {
+    let (pda, params) = get_program_pda(connection, &program_id, Some(signer.to_string())).await?;
+    if params.is_revoked() {
+        return Err(anyhow!(
+            "{} has revoked its verification of {} in {}",
+            signer,
+            program_id,
+            pda
+        ));
+    }
+    info!("Copying the params of {} from {}", signer, pda);
+    Ok(InputParams {
+        version: params.version,
+        git_url: params.git_url,
+        commit: params.commit,
+        args: params.args,
+        deployed_slot: params.deployed_slot,
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn input_params_from_args(
+    connection: &RpcClient,
+    program_id: Pubkey,
+    repo_url: String,
+    commit_hash: String,
+    mount_path: String,
+    library_name: Option<String>,
+    base_image: Option<String>,
+    bpf_flag: bool,
+    cargo_args: Vec<String>,
+) -> anyhow::Result<InputParams> {
+    warn!("these params were not built or compared with the on-chain program");
+    let deployed_slot = get_last_deployed_slot(connection, &program_id.to_string())
+        .await
+        .map_err(|err| anyhow!("Unable to get last deployed slot: {}", err))?;
+
+    // The repository is only cloned to infer the library name and the build arguments
+    let (temp_root_path, verify_dir) = clone_repo_and_checkout(
+        &repo_url,
+        true,
+        &get_basename(&repo_url)?,
+        Some(commit_hash.clone()),
+    )?;
+    let args = build_args(
+        &mount_path,
+        library_name,
+        &temp_root_path,
+        base_image,
+        bpf_flag,
+        cargo_args,
+    );
+    remove_dir(&verify_dir)?;
+
+    Ok(InputParams {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_url: repo_url,
+        commit: commit_hash,
+        args: args?.0,
+        deployed_slot,
+    })
+}
+
+/// Uploads verification params for `program_id` without building the program.
+pub async fn upload_only(
+    connection: &RpcClient,
+    program_id: Pubkey,
+    source: UploadSource,
+    skip_prompt: bool,
+    path_to_keypair: Option<String>,
+    compute_unit_price: u64,
+) -> anyhow::Result<()> {
+    let input_params = match source {
+        UploadSource::Artifact {
+            path,
+            allow_unsigned,
+        } => input_params_from_artifact(connection, program_id, &path, allow_unsigned)?,
+        UploadSource::Pda(signer) => input_params_from_pda(connection, program_id, signer).await?,
+        UploadSource::TrustedArgs {
+            repo_url,
+            commit_hash,
+            mount_path,
+            library_name,
+            base_image,
+            bpf_flag,
+            cargo_args,
+        } => {
+            input_params_from_args(
+                connection,
+                program_id,
+                repo_url,
+                commit_hash,
+                mount_path,
+                library_name,
+                base_image,
+                bpf_flag,
+                cargo_args,
+            )
+            .await?
+        }
+    };
+
+    println!("Program Id: {}", program_id);
+    println!("Git Url: {}", input_params.git_url);
+    println!("Commit: {}", input_params.commit);
+    println!("Args: {:?}", input_params.args);
+
+    if skip_prompt
+        || prompt_user_input(
+            "Do you want to upload the program verification to the Solana Blockchain? (y/n) ",
+        )?
+    {
+        upload_input_params(
+            &input_params,
+            program_id,
+            connection,
+            skip_prompt,
+            path_to_keypair,
+            compute_unit_price,
+        )
+        .await
+    } else {
+        info!("Exiting without uploading the program.");
+        Ok(())
+    }
+}
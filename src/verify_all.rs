@@ -0,0 +1,382 @@
+use anyhow::anyhow;
+use log::info;
+use serde::Deserialize;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::{collections::BTreeMap, path::Path, str::FromStr};
+
+use crate::cleanup::check_interrupted;
+use crate::{
+    build_and_verify_repo, build_args, cleanup::remove_dir, clone_repo_and_checkout,
+    docker::check_docker_available, get_basename,
+};
+
+/// Manifest read by `verify-all` when none is given. Committed at the root of a repository,
+/// it also gives `build` and `verify-from-repo` the settings of their program, with their
+/// flags taking precedence.
+pub const DEFAULT_MANIFEST: &str = "verify.toml";
+
+/// Programs of a workspace to verify from a single checkout:
+///
+/// ```toml
+/// repo-url = "https://github.com/solana-labs/solana-program-library"
+///
+/// [programs.TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA]
+/// library-name = "spl_token"
+/// mount-path = "token/program"
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct VerifyManifest {
+    pub repo_url: Option<String>,
+    pub commit_hash: Option<String>,
+    /// Programs by program ID
+    pub programs: BTreeMap<String, ManifestProgram>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ManifestProgram {
+    pub library_name: Option<String>,
+    /// Relative to the root of the repository
+    #[serde(default)]
+    pub mount_path: String,
+    pub base_image: Option<String>,
+    /// Unset when neither `--bpf` nor `--no-bpf` is given, so a default can apply
+    pub bpf: Option<bool>,
+    #[serde(default)]
+    pub cargo_args: Vec<String>,
+}
+
+impl ManifestProgram {
+    /// Settings given on the command line, completed with `defaults` where they are not set.
+    pub fn with_defaults(self, defaults: &ManifestProgram) -> ManifestProgram {
+        ManifestProgram {
+            library_name: self.library_name.or(defaults.library_name.clone()),
+            mount_path: if self.mount_path.is_empty() {
+                defaults.mount_path.clone()
+            } else {
+                self.mount_path
+            },
+            base_image: self.base_image.or(defaults.base_image.clone()),
+            bpf: self.bpf.or(defaults.bpf),
+            cargo_args: if self.cargo_args.is_empty() {
+                defaults.cargo_args.clone()
+            } else {
+                self.cargo_args
+            },
+        }
+    }
+}
+
+impl ManifestProgram {
+    /// Settings recorded in the build arguments of a PDA, as written by `build_args`.
+    pub fn from_build_args(args: &[String]) -> anyhow::Result<ManifestProgram> {
+        let mut program = ManifestProgram::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .cloned()
+                    .ok_or_else(|| anyhow!("Missing value for {} in the build arguments", arg))
+            };
+            match arg.as_str() {
+                "--mount-path" => program.mount_path = value()?,
+                "--library-name" => program.library_name = Some(value()?),
+                "--base-image" | "-b" => program.base_image = Some(value()?),
+                "--bpf" => program.bpf = Some(true),
+                "--" => {
+                    program.cargo_args = args.cloned().collect();
+                    break;
+                }
+                _ => return Err(anyhow!("Unknown build argument {}", arg)),
+            }
+        }
+        Ok(program)
+    }
+}
+
+impl VerifyManifest {
+    /// Program with `program_id`, else the one building `library_name`, else the only program
+    /// of the manifest if no program ID is given.
+    pub fn find_program(
+        &self,
+        program_id: Option<&str>,
+        library_name: Option<&str>,
+    ) -> Option<&ManifestProgram> {
+        if let Some(program) = program_id.and_then(|program_id| self.programs.get(program_id)) {
+            return Some(program);
+        }
+        if let Some(library_name) = library_name {
+            return self
+                .programs
+                .values()
+                .find(|program| program.library_name.as_deref() == Some(library_name));
+        }
+        match (program_id, self.programs.len()) {
+            (None, 1) => self.programs.values().next(),
+            _ => None,
+        }
+    }
+
+    pub fn parse(contents: &str) -> anyhow::Result<VerifyManifest> {
+        let manifest: VerifyManifest =
+            toml::from_str(contents).map_err(|e| anyhow!("Invalid verify manifest: {}", e))?;
+        if manifest.programs.is_empty() {
+            return Err(anyhow!("The verify manifest lists no [programs]"));
+        }
+        for program_id in manifest.programs.keys() {
+            Pubkey::from_str(program_id)
+                .map_err(|e| anyhow!("Invalid program ID {} in the manifest: {}", program_id, e))?;
+        }
+        Ok(manifest)
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<VerifyManifest> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        VerifyManifest::parse(&contents)
+    }
+}
+
+/// Completes the build settings given on the command line with the defaults of the program in
+/// the verify.toml at the root of `root`, if there is one.
+pub fn apply_project_config(
+    root: &Path,
+    program_id: Option<&str>,
+    settings: ManifestProgram,
+) -> anyhow::Result<ManifestProgram> {
+    let path = root.join(DEFAULT_MANIFEST);
+    if !path.exists() {
+        return Ok(settings);
+    }
+    let config = VerifyManifest::load(&path)?;
+    match config.find_program(program_id, settings.library_name.as_deref()) {
+        Some(defaults) => {
+            info!("Using the build settings of {}", path.display());
+            Ok(settings.with_defaults(defaults))
+        }
+        None => {
+            info!(
+                "{} has no settings for this program, using the command line only",
+                path.display()
+            );
+            Ok(settings)
+        }
+    }
+}
+
+/// Outcome of verifying one program of the manifest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgramVerification {
+    pub program_id: String,
+    pub library_name: Option<String>,
+    /// Executable and on-chain hashes, or why they could not be compared
+    pub hashes: Result<(String, String), String>,
+}
+
+impl ProgramVerification {
+    pub fn matches(&self) -> bool {
+        matches!(&self.hashes, Ok((build_hash, program_hash)) if build_hash == program_hash)
+    }
+}
+
+pub fn print_summary(results: &[ProgramVerification]) {
+    let library_width = results
+        .iter()
+        .filter_map(|result| result.library_name.as_ref().map(String::len))
+        .max()
+        .unwrap_or_default()
+        .max("LIBRARY".len());
+    println!(
+        "{:<44}  {:<library_width$}  {:<8}  EXECUTABLE HASH",
+        "PROGRAM ID", "LIBRARY", "RESULT"
+    );
+    for result in results {
+        let (status, detail) = match &result.hashes {
+            _ if result.matches() => ("match", result.hashes.as_ref().unwrap().0.clone()),
+            Ok((build_hash, program_hash)) => (
+                "mismatch",
+                format!("{} (on chain {})", build_hash, program_hash),
+            ),
+            Err(err) => ("error", err.lines().next().unwrap_or_default().to_string()),
+        };
+        println!(
+            "{:<44}  {:<library_width$}  {:<8}  {}",
+            result.program_id,
+            result.library_name.as_deref().unwrap_or("-"),
+            status,
+            detail
+        );
+    }
+    let verified = results.iter().filter(|result| result.matches()).count();
+    println!("{} of {} program(s) verified", verified, results.len());
+}
+
+/// Builds a program from the checkout at `root` and compares it with the deployed program.
+pub fn verify_program(
+    root: &Path,
+    program_id: &str,
+    program: &ManifestProgram,
+    connection: &RpcClient,
+) -> ProgramVerification {
+    info!("Verifying program {}", program_id);
+    let hashes = build_args(
+        &program.mount_path,
+        program.library_name.clone(),
+        root,
+        program.base_image.clone(),
+        program.bpf.unwrap_or_default(),
+        program.cargo_args.clone(),
+    )
+    .and_then(|(_, mount_path, library_name)| {
+        build_and_verify_repo(
+            mount_path,
+            program.base_image.clone(),
+            program.bpf.unwrap_or_default(),
+            library_name,
+            connection,
+            Pubkey::from_str(program_id)?,
+            program.cargo_args.clone(),
+        )
+    })
+    .map(|(build_hash, program_hash, _)| (build_hash, program_hash))
+    .map_err(|err| format!("{:#}", err));
+    ProgramVerification {
+        program_id: program_id.to_string(),
+        library_name: program.library_name.clone(),
+        hashes,
+    }
+}
+
+/// Clones the repository once and builds every program of the manifest from it, comparing
+/// each build with the deployed program. Nothing is uploaded.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_all(
+    manifest: &VerifyManifest,
+    repo_url: &str,
+    commit_hash: String,
+    connection: &RpcClient,
+    current_dir: bool,
+) -> anyhow::Result<Vec<ProgramVerification>> {
+    check_docker_available()?;
+    let (root, verify_dir) = clone_repo_and_checkout(
+        repo_url,
+        current_dir,
+        &get_basename(repo_url)?,
+        Some(commit_hash),
+    )?;
+
+    let mut results = vec![];
+    for (program_id, program) in &manifest.programs {
+        check_interrupted()?;
+        results.push(verify_program(&root, program_id, program, connection));
+    }
+
+    remove_dir(&verify_dir)?;
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_manifest() {
+        let manifest = VerifyManifest::parse(
+            r#"
+repo-url = "https://github.com/solana-labs/solana-program-library"
+
+[programs.TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA]
+library-name = "spl_token"
+mount-path = "token/program"
+
+[programs.MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr]
+library-name = "spl_memo"
+mount-path = "memo/program"
+cargo-args = ["--features", "no-entrypoint"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(manifest.programs.len(), 2);
+        let memo = &manifest.programs["MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr"];
+        assert_eq!(memo.mount_path, "memo/program");
+        assert_eq!(memo.cargo_args, vec!["--features", "no-entrypoint"]);
+        assert_eq!(memo.bpf, None);
+
+        assert!(VerifyManifest::parse("repo-url = \"x\"\n[programs]\n").is_err());
+        assert!(VerifyManifest::parse("[programs.not-a-key]\nmount-path = \"a\"\n").is_err());
+    }
+
+    #[test]
+    fn test_project_config_defaults() {
+        let config = VerifyManifest::parse(
+            r#"
+[programs.PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY]
+library-name = "phoenix"
+mount-path = "programs/phoenix"
+cargo-args = ["--features", "mainnet"]
+
+[programs.MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr]
+library-name = "spl_memo"
+base-image = "solanafoundation/solana-verifiable-build:1.18.26"
+"#,
+        )
+        .unwrap();
+
+        let phoenix = config
+            .find_program(Some("PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY"), None)
+            .unwrap();
+        assert_eq!(config.find_program(None, Some("phoenix")), Some(phoenix));
+        assert_eq!(config.find_program(None, None), None);
+        assert_eq!(
+            config.find_program(Some("11111111111111111111111111111111"), None),
+            None
+        );
+
+        let cli = ManifestProgram {
+            base_image: Some("custom".to_string()),
+            ..Default::default()
+        };
+        let settings = cli.with_defaults(phoenix);
+        assert_eq!(settings.library_name.as_deref(), Some("phoenix"));
+        assert_eq!(settings.mount_path, "programs/phoenix");
+        assert_eq!(settings.base_image.as_deref(), Some("custom"));
+        assert_eq!(settings.cargo_args, vec!["--features", "mainnet"]);
+
+        let cli = ManifestProgram {
+            mount_path: "other".to_string(),
+            cargo_args: vec!["--no-default-features".to_string()],
+            ..Default::default()
+        };
+        let settings = cli.with_defaults(phoenix);
+        assert_eq!(settings.mount_path, "other");
+        assert_eq!(settings.cargo_args, vec!["--no-default-features"]);
+
+        let bpf = ManifestProgram {
+            bpf: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(
+            ManifestProgram::default().with_defaults(&bpf).bpf,
+            Some(true)
+        );
+        let cli = ManifestProgram {
+            bpf: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(cli.with_defaults(&bpf).bpf, Some(false));
+    }
+
+    #[test]
+    fn test_program_verification_matches() {
+        let result = |hashes| ProgramVerification {
+            program_id: "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA".to_string(),
+            library_name: None,
+            hashes,
+        };
+        assert!(result(Ok(("a".to_string(), "a".to_string()))).matches());
+        assert!(!result(Ok(("a".to_string(), "b".to_string()))).matches());
+        assert!(!result(Err("build failed".to_string())).matches());
+    }
+}
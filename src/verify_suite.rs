@@ -0,0 +1,674 @@
+use anyhow::anyhow;
+use log::info;
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{bpf_loader_upgradeable, pubkey::Pubkey};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::cleanup::check_interrupted;
+use crate::{
+    cleanup::remove_dir,
+    clone_repo_and_checkout,
+    compression::write_artifact,
+    docker::check_docker_available,
+    get_basename, get_commit_hash,
+    solana_program::get_program_upgrade_authority,
+    verify_all::{verify_program, ManifestProgram, ProgramVerification},
+};
+
+/// Manifest read by `verify-suite` when none is given
+pub const DEFAULT_SUITE_MANIFEST: &str = "suite.toml";
+
+/// Token-2022 program, owner of the mints transfer hooks are configured on
+const TOKEN_2022_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+/// Token-2022 accounts are padded to the size of a token account before their account type
+const TOKEN_2022_ACCOUNT_TYPE_OFFSET: usize = 165;
+const TOKEN_2022_MINT_ACCOUNT_TYPE: u8 = 1;
+const TRANSFER_HOOK_EXTENSION_TYPE: u16 = 14;
+
+/// Accounts of the primary program searched for the addresses of its companions
+const MAX_SCANNED_ACCOUNTS: usize = 50;
+/// Addresses looked up per `getMultipleAccounts` request
+const ACCOUNTS_PER_REQUEST: usize = 100;
+
+/// A program and the programs it relies on, verified together:
+///
+/// ```toml
+/// repo-url = "https://github.com/acme/stablecoin"
+///
+/// [primary]
+/// program-id = "Stab1e1111111111111111111111111111111111111"
+/// library-name = "stablecoin"
+///
+/// # Program set as the transfer hook of a Token-2022 mint
+/// [companions.transfer-hook]
+/// transfer-hook-mint = "Mint111111111111111111111111111111111111111"
+/// library-name = "stablecoin_hook"
+/// mount-path = "programs/hook"
+///
+/// # Program whose address is stored in an account, e.g. the config of the primary program
+/// [companions.oracle-proxy]
+/// account = "Conf1g1111111111111111111111111111111111111"
+/// offset = 40
+/// repo-url = "https://github.com/acme/oracle-proxy"
+///
+/// # Without a reference, the one other program whose address, or whose transfer hook mint,
+/// # is stored in an account of the primary program
+/// [companions.vault]
+/// library-name = "stablecoin_vault"
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SuiteManifest {
+    /// Repository of the programs that do not set their own
+    pub repo_url: Option<String>,
+    pub commit_hash: Option<String>,
+    pub primary: SuiteProgram,
+    /// Companion programs by name
+    #[serde(default)]
+    pub companions: BTreeMap<String, SuiteProgram>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SuiteProgram {
+    pub program_id: Option<String>,
+    /// Token-2022 mint whose transfer hook is the program
+    pub transfer_hook_mint: Option<String>,
+    /// Account storing the address of the program at `offset`
+    pub account: Option<String>,
+    pub offset: Option<usize>,
+    pub repo_url: Option<String>,
+    pub commit_hash: Option<String>,
+    #[serde(flatten)]
+    pub build: ManifestProgram,
+}
+
+/// Where the program ID of a suite program comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgramReference {
+    ProgramId(Pubkey),
+    TransferHook {
+        mint: Pubkey,
+    },
+    AccountField {
+        account: Pubkey,
+        offset: usize,
+    },
+    /// Found in the accounts of the primary program
+    Discovered,
+}
+
+impl std::fmt::Display for ProgramReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProgramReference::ProgramId(_) => write!(f, "manifest"),
+            ProgramReference::TransferHook { mint } => write!(f, "transfer hook of mint {}", mint),
+            ProgramReference::AccountField { account, offset } => {
+                write!(f, "account {} at offset {}", account, offset)
+            }
+            ProgramReference::Discovered => write!(f, "accounts of the primary program"),
+        }
+    }
+}
+
+fn parse_pubkey(name: &str, field: &str, value: &str) -> anyhow::Result<Pubkey> {
+    Pubkey::from_str(value).map_err(|e| anyhow!("Invalid {} of {}: {}", field, name, e))
+}
+
+impl SuiteProgram {
+    pub fn reference(&self, name: &str) -> anyhow::Result<ProgramReference> {
+        match (
+            &self.program_id,
+            &self.transfer_hook_mint,
+            &self.account,
+            self.offset,
+        ) {
+            (Some(program_id), None, None, None) => Ok(ProgramReference::ProgramId(parse_pubkey(
+                name,
+                "program-id",
+                program_id,
+            )?)),
+            (None, Some(mint), None, None) => Ok(ProgramReference::TransferHook {
+                mint: parse_pubkey(name, "transfer-hook-mint", mint)?,
+            }),
+            (None, None, Some(account), Some(offset)) => Ok(ProgramReference::AccountField {
+                account: parse_pubkey(name, "account", account)?,
+                offset,
+            }),
+            (None, None, None, None) => Ok(ProgramReference::Discovered),
+            _ => Err(anyhow!(
+                "{} must set at most one of program-id, transfer-hook-mint, or account and offset",
+                name
+            )),
+        }
+    }
+}
+
+impl SuiteManifest {
+    pub fn parse(contents: &str) -> anyhow::Result<SuiteManifest> {
+        let manifest: SuiteManifest =
+            toml::from_str(contents).map_err(|e| anyhow!("Invalid suite manifest: {}", e))?;
+        if !matches!(
+            manifest.primary.reference("the primary program")?,
+            ProgramReference::ProgramId(_)
+        ) {
+            return Err(anyhow!("The primary program must set its program-id"));
+        }
+        for (name, companion) in &manifest.companions {
+            companion.reference(name)?;
+        }
+        Ok(manifest)
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<SuiteManifest> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        SuiteManifest::parse(&contents)
+    }
+
+    /// Programs of the suite with their role, primary first.
+    pub fn programs(&self) -> Vec<(&str, &SuiteProgram)> {
+        let mut programs = vec![("primary", &self.primary)];
+        programs.extend(
+            self.companions
+                .iter()
+                .map(|(name, companion)| (name.as_str(), companion)),
+        );
+        programs
+    }
+}
+
+/// Transfer hook program configured on a Token-2022 mint, if any.
+pub fn parse_transfer_hook(mint_data: &[u8]) -> anyhow::Result<Option<Pubkey>> {
+    if mint_data.get(TOKEN_2022_ACCOUNT_TYPE_OFFSET) != Some(&TOKEN_2022_MINT_ACCOUNT_TYPE) {
+        return Err(anyhow!("Not a Token-2022 mint with extensions"));
+    }
+    let mut tlv = &mint_data[TOKEN_2022_ACCOUNT_TYPE_OFFSET + 1..];
+    while tlv.len() >= 4 {
+        let extension_type = u16::from_le_bytes([tlv[0], tlv[1]]);
+        let len = u16::from_le_bytes([tlv[2], tlv[3]]) as usize;
+        let value = tlv
+            .get(4..4 + len)
+            .ok_or_else(|| anyhow!("Truncated extension {} in the mint", extension_type))?;
+        if extension_type == TRANSFER_HOOK_EXTENSION_TYPE {
+            // The authority that may change the hook, then the hook program
+            let program_id = value
+                .get(32..64)
+                .ok_or_else(|| anyhow!("Truncated transfer hook extension"))?;
+            let program_id = Pubkey::try_from(program_id)?;
+            return Ok((program_id != Pubkey::default()).then_some(program_id));
+        }
+        tlv = &tlv[4 + len..];
+    }
+    Ok(None)
+}
+
+/// Every 32 bytes of `data` that may hold an address, skipping runs of zeros such as padding.
+fn embedded_addresses(data: &[u8]) -> impl Iterator<Item = Pubkey> + '_ {
+    data.windows(32)
+        .filter(|window| window.iter().filter(|byte| **byte == 0).count() < 8)
+        .map(|window| Pubkey::try_from(window).unwrap())
+}
+
+/// Upgradeable programs whose address is stored in the accounts of `primary`, along with the
+/// transfer hook programs of the Token-2022 mints stored there.
+pub fn discover_companions(
+    connection: &RpcClient,
+    primary: Pubkey,
+) -> anyhow::Result<BTreeSet<Pubkey>> {
+    let accounts = connection
+        .get_program_accounts(&primary)
+        .map_err(|e| anyhow!("Failed to fetch the accounts of {}: {}", primary, e))?;
+    let candidates: BTreeSet<Pubkey> = accounts
+        .iter()
+        .take(MAX_SCANNED_ACCOUNTS)
+        .flat_map(|(_, account)| embedded_addresses(&account.data))
+        .filter(|address| *address != primary)
+        .collect();
+    let candidates: Vec<Pubkey> = candidates.into_iter().collect();
+
+    let mut programs = BTreeSet::new();
+    for chunk in candidates.chunks(ACCOUNTS_PER_REQUEST) {
+        let accounts = connection
+            .get_multiple_accounts(chunk)
+            .map_err(|e| anyhow!("Failed to fetch the accounts of {}: {}", primary, e))?;
+        for (address, account) in chunk.iter().zip(accounts) {
+            let Some(account) = account else {
+                continue;
+            };
+            if account.owner == TOKEN_2022_PROGRAM_ID {
+                if let Ok(Some(hook)) = parse_transfer_hook(&account.data) {
+                    programs.insert(hook);
+                }
+            } else if account.executable
+                && account.owner == bpf_loader_upgradeable::id()
+                && *address != TOKEN_2022_PROGRAM_ID
+            {
+                programs.insert(*address);
+            }
+        }
+    }
+    Ok(programs)
+}
+
+/// The program a companion without a reference resolves to: the one discovered program that
+/// no other program of the suite resolves to, if only one companion is left to resolve.
+fn pick_discovered(
+    discovered: &BTreeSet<Pubkey>,
+    taken: &BTreeSet<Pubkey>,
+    unresolved: usize,
+) -> Result<Pubkey, String> {
+    let left: Vec<&Pubkey> = discovered.difference(taken).collect();
+    match (left.as_slice(), unresolved) {
+        ([program_id], 1) => Ok(**program_id),
+        ([], _) => Err(
+            "No other program is stored in the accounts of the primary program, set its program-id"
+                .to_string(),
+        ),
+        _ => Err(format!(
+            "The accounts of the primary program store {} other program(s) ({}) for {} companion(s) without a reference, set their program-id",
+            left.len(),
+            left.iter()
+                .map(|program_id| program_id.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            unresolved
+        )),
+    }
+}
+
+/// Resolves the program ID of every program of the suite, in the order of
+/// `SuiteManifest::programs`. The programs that cannot be resolved are reported individually.
+pub fn resolve_suite(
+    connection: &RpcClient,
+    manifest: &SuiteManifest,
+) -> anyhow::Result<Vec<(ProgramReference, anyhow::Result<Pubkey>)>> {
+    let mut resolved = vec![];
+    for (role, program) in manifest.programs() {
+        let reference = program.reference(role)?;
+        let program_id = match reference {
+            ProgramReference::Discovered => Err(anyhow!("Not resolved")),
+            _ => resolve_reference(connection, reference),
+        };
+        resolved.push((reference, program_id));
+    }
+
+    let unresolved = resolved
+        .iter()
+        .filter(|(reference, _)| *reference == ProgramReference::Discovered)
+        .count();
+    if unresolved == 0 {
+        return Ok(resolved);
+    }
+    let taken: BTreeSet<Pubkey> = resolved
+        .iter()
+        .filter_map(|(_, program_id)| program_id.as_ref().ok().copied())
+        .collect();
+    // The primary program always sets its program ID
+    let primary = *resolved[0].1.as_ref().map_err(|e| anyhow!("{:#}", e))?;
+    let picked = discover_companions(connection, primary)
+        .map_err(|e| format!("{:#}", e))
+        .and_then(|discovered| pick_discovered(&discovered, &taken, unresolved));
+    for (reference, program_id) in &mut resolved {
+        if *reference == ProgramReference::Discovered {
+            *program_id = picked.clone().map_err(|e| anyhow!(e));
+        }
+    }
+    Ok(resolved)
+}
+
+/// Reads the program ID a reference points to from the chain.
+pub fn resolve_reference(
+    connection: &RpcClient,
+    reference: ProgramReference,
+) -> anyhow::Result<Pubkey> {
+    match reference {
+        ProgramReference::ProgramId(program_id) => Ok(program_id),
+        ProgramReference::Discovered => Err(anyhow!(
+            "Companions without a reference are resolved with the whole suite"
+        )),
+        ProgramReference::TransferHook { mint } => {
+            let account = connection
+                .get_account(&mint)
+                .map_err(|e| anyhow!("Failed to fetch mint {}: {}", mint, e))?;
+            if account.owner != TOKEN_2022_PROGRAM_ID {
+                return Err(anyhow!("{} is not a Token-2022 mint", mint));
+            }
+            parse_transfer_hook(&account.data)
+                .map_err(|e| anyhow!("{}: {}", mint, e))?
+                .ok_or_else(|| anyhow!("Mint {} has no transfer hook program", mint))
+        }
+        ProgramReference::AccountField { account, offset } => {
+            let data = connection
+                .get_account_data(&account)
+                .map_err(|e| anyhow!("Failed to fetch account {}: {}", account, e))?;
+            let program_id = data
+                .get(offset..offset + 32)
+                .map(Pubkey::try_from)
+                .transpose()?
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Account {} is {} bytes, too small to hold an address at offset {}",
+                        account,
+                        data.len(),
+                        offset
+                    )
+                })?;
+            if program_id == Pubkey::default() {
+                return Err(anyhow!(
+                    "Account {} holds no address at offset {}",
+                    account,
+                    offset
+                ));
+            }
+            Ok(program_id)
+        }
+    }
+}
+
+/// Verification of one program of the suite.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SuiteVerification {
+    pub role: String,
+    pub resolved_from: String,
+    pub verification: ProgramVerification,
+    /// `immutable`, the upgrade authority, or why it could not be read
+    pub upgrade_authority: String,
+}
+
+/// The part of the report written with `--report`.
+#[derive(Debug, Serialize)]
+struct SuiteReportEntry<'a> {
+    role: &'a str,
+    program_id: &'a str,
+    resolved_from: &'a str,
+    library_name: Option<&'a str>,
+    matches: bool,
+    executable_hash: Option<&'a str>,
+    on_chain_hash: Option<&'a str>,
+    error: Option<&'a str>,
+    upgrade_authority: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct SuiteReport<'a> {
+    verified: bool,
+    programs: Vec<SuiteReportEntry<'a>>,
+}
+
+/// Writes the trust report through `write_artifact`, returning the path written.
+pub fn write_suite_report(results: &[SuiteVerification], path: &Path) -> anyhow::Result<PathBuf> {
+    let report = SuiteReport {
+        verified: results.iter().all(|result| result.verification.matches()),
+        programs: results
+            .iter()
+            .map(|result| {
+                let hashes = result.verification.hashes.as_ref();
+                SuiteReportEntry {
+                    role: &result.role,
+                    program_id: &result.verification.program_id,
+                    resolved_from: &result.resolved_from,
+                    library_name: result.verification.library_name.as_deref(),
+                    matches: result.verification.matches(),
+                    executable_hash: hashes.ok().map(|(build_hash, _)| build_hash.as_str()),
+                    on_chain_hash: hashes.ok().map(|(_, program_hash)| program_hash.as_str()),
+                    error: hashes.err().map(String::as_str),
+                    upgrade_authority: &result.upgrade_authority,
+                }
+            })
+            .collect(),
+    };
+    write_artifact(path, serde_json::to_string_pretty(&report)?.as_bytes())
+}
+
+pub fn print_suite_summary(results: &[SuiteVerification]) {
+    let role_width = results
+        .iter()
+        .map(|result| result.role.len())
+        .max()
+        .unwrap_or_default()
+        .max("ROLE".len());
+    println!(
+        "{:<role_width$}  {:<44}  {:<8}  {:<44}  RESOLVED FROM",
+        "ROLE", "PROGRAM ID", "RESULT", "UPGRADE AUTHORITY"
+    );
+    for result in results {
+        let status = match &result.verification.hashes {
+            _ if result.verification.matches() => "match",
+            Ok(_) => "mismatch",
+            Err(_) => "error",
+        };
+        println!(
+            "{:<role_width$}  {:<44}  {:<8}  {:<44}  {}",
+            result.role,
+            result.verification.program_id,
+            status,
+            result.upgrade_authority,
+            result.resolved_from
+        );
+        if let Err(err) = &result.verification.hashes {
+            println!("  {}", err.lines().next().unwrap_or_default());
+        }
+    }
+    let verified = results
+        .iter()
+        .filter(|result| result.verification.matches())
+        .count();
+    if verified == results.len() {
+        println!("Suite verified: all {} program(s) match ✅", results.len());
+    } else {
+        println!(
+            "Suite not verified: {} of {} program(s) match ❌",
+            verified,
+            results.len()
+        );
+    }
+}
+
+/// Resolves the companions of the primary program on chain, then builds every program of
+/// the suite, cloning each repository and commit once, and compares each build with its
+/// deployed program. Nothing is uploaded.
+pub async fn verify_suite(
+    manifest: &SuiteManifest,
+    connection: &RpcClient,
+    current_dir: bool,
+) -> anyhow::Result<Vec<SuiteVerification>> {
+    check_docker_available()?;
+
+    // Checkouts by repository and requested commit, removed whether or not the suite completes
+    let mut checkouts = BTreeMap::new();
+    let results = verify_suite_programs(manifest, connection, current_dir, &mut checkouts).await;
+    for (_, verify_dir) in checkouts.values() {
+        remove_dir(verify_dir)?;
+    }
+    results
+}
+
+async fn verify_suite_programs(
+    manifest: &SuiteManifest,
+    connection: &RpcClient,
+    current_dir: bool,
+    checkouts: &mut BTreeMap<(String, Option<String>), (PathBuf, PathBuf)>,
+) -> anyhow::Result<Vec<SuiteVerification>> {
+    let resolved = resolve_suite(connection, manifest)?;
+    let mut results = vec![];
+    for ((role, program), (reference, program_id)) in manifest.programs().into_iter().zip(resolved)
+    {
+        check_interrupted()?;
+        let program_id = match program_id {
+            Ok(program_id) => program_id,
+            Err(err) => {
+                results.push(SuiteVerification {
+                    role: role.to_string(),
+                    resolved_from: reference.to_string(),
+                    verification: ProgramVerification {
+                        program_id: "unresolved".to_string(),
+                        library_name: program.build.library_name.clone(),
+                        hashes: Err(format!("{:#}", err)),
+                    },
+                    upgrade_authority: "-".to_string(),
+                });
+                continue;
+            }
+        };
+        info!("Resolved {} to {} from {}", role, program_id, reference);
+
+        let repo_url = program
+            .repo_url
+            .as_ref()
+            .or(manifest.repo_url.as_ref())
+            .ok_or_else(|| anyhow!("Set the repo-url of {} or of the manifest", role))?;
+        let commit = program.commit_hash.clone().or(manifest.commit_hash.clone());
+        let key = (repo_url.clone(), commit.clone());
+        if !checkouts.contains_key(&key) {
+            let commit_hash = get_commit_hash(commit.as_deref(), repo_url).await?;
+            let checkout = clone_repo_and_checkout(
+                repo_url,
+                current_dir,
+                &get_basename(repo_url)?,
+                Some(commit_hash),
+            )?;
+            checkouts.insert(key.clone(), checkout);
+        }
+        let (root, _) = &checkouts[&key];
+
+        let verification =
+            verify_program(root, &program_id.to_string(), &program.build, connection);
+        let upgrade_authority = match get_program_upgrade_authority(connection, &program_id) {
+            Ok(Some(authority)) => authority.to_string(),
+            Ok(None) => "immutable".to_string(),
+            Err(err) => format!("unknown ({})", err),
+        };
+        results.push(SuiteVerification {
+            role: role.to_string(),
+            resolved_from: reference.to_string(),
+            verification,
+            upgrade_authority,
+        });
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::{Keypair, Signer};
+
+    #[test]
+    fn test_parse_suite_manifest() {
+        let primary = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let config = Pubkey::new_unique();
+        let manifest = SuiteManifest::parse(&format!(
+            r#"
+repo-url = "https://github.com/acme/stablecoin"
+
+[primary]
+program-id = "{}"
+library-name = "stablecoin"
+
+[companions.transfer-hook]
+transfer-hook-mint = "{}"
+mount-path = "programs/hook"
+
+[companions.oracle-proxy]
+account = "{}"
+offset = 40
+repo-url = "https://github.com/acme/oracle-proxy"
+"#,
+            primary, mint, config
+        ))
+        .unwrap();
+        let programs = manifest.programs();
+        assert_eq!(programs[0].0, "primary");
+        assert_eq!(
+            programs[0].1.reference("primary").unwrap(),
+            ProgramReference::ProgramId(primary)
+        );
+        assert_eq!(
+            manifest.companions["transfer-hook"]
+                .reference("transfer-hook")
+                .unwrap(),
+            ProgramReference::TransferHook { mint }
+        );
+        assert_eq!(
+            manifest.companions["transfer-hook"].build.mount_path,
+            "programs/hook"
+        );
+        assert_eq!(
+            manifest.companions["oracle-proxy"]
+                .reference("oracle-proxy")
+                .unwrap(),
+            ProgramReference::AccountField {
+                account: config,
+                offset: 40
+            }
+        );
+
+        let ambiguous = format!(
+            "[primary]\nprogram-id = \"{}\"\n[companions.hook]\nprogram-id = \"{}\"\ntransfer-hook-mint = \"{}\"\n",
+            primary, primary, mint
+        );
+        assert!(SuiteManifest::parse(&ambiguous).is_err());
+        let unresolved_primary = format!("[primary]\ntransfer-hook-mint = \"{}\"\n", mint);
+        assert!(SuiteManifest::parse(&unresolved_primary).is_err());
+        let discovered = format!(
+            "[primary]\nprogram-id = \"{}\"\n[companions.vault]\nlibrary-name = \"vault\"\n",
+            primary
+        );
+        assert_eq!(
+            SuiteManifest::parse(&discovered).unwrap().companions["vault"]
+                .reference("vault")
+                .unwrap(),
+            ProgramReference::Discovered
+        );
+    }
+
+    #[test]
+    fn test_discover_companions() {
+        // Unlike `Pubkey::new_unique`, a real address is random bytes
+        let companion = Keypair::new().pubkey();
+        let mut data = vec![0; 8];
+        data.extend(companion.to_bytes());
+        data.extend([0; 64]);
+        let addresses: Vec<Pubkey> = embedded_addresses(&data).collect();
+        assert!(addresses.contains(&companion));
+        assert!(!addresses.contains(&Pubkey::default()));
+
+        let other = Pubkey::new_unique();
+        let discovered = BTreeSet::from([companion, other]);
+        assert_eq!(
+            pick_discovered(&discovered, &BTreeSet::from([other]), 1),
+            Ok(companion)
+        );
+        assert!(pick_discovered(&discovered, &BTreeSet::new(), 1).is_err());
+        assert!(pick_discovered(&discovered, &BTreeSet::from([other]), 2).is_err());
+        assert!(pick_discovered(&discovered, &discovered, 1).is_err());
+    }
+
+    #[test]
+    fn test_parse_transfer_hook() {
+        let hook = Pubkey::new_unique();
+        let mut mint = vec![0; TOKEN_2022_ACCOUNT_TYPE_OFFSET];
+        mint.push(TOKEN_2022_MINT_ACCOUNT_TYPE);
+        // An unrelated extension, then the transfer hook
+        mint.extend(18u16.to_le_bytes());
+        mint.extend(2u16.to_le_bytes());
+        mint.extend([0, 0]);
+        mint.extend(TRANSFER_HOOK_EXTENSION_TYPE.to_le_bytes());
+        mint.extend(64u16.to_le_bytes());
+        mint.extend(Pubkey::new_unique().to_bytes());
+        mint.extend(hook.to_bytes());
+        assert_eq!(parse_transfer_hook(&mint).unwrap(), Some(hook));
+
+        let without_hook = mint[..TOKEN_2022_ACCOUNT_TYPE_OFFSET + 7].to_vec();
+        assert_eq!(parse_transfer_hook(&without_hook).unwrap(), None);
+        assert!(parse_transfer_hook(&[0; 82]).is_err());
+    }
+}